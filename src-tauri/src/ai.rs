@@ -0,0 +1,304 @@
+//! Optional summaries and cleanups from an OpenAI-compatible chat endpoint.
+//! Off unless enabled in settings; nothing leaves the machine otherwise.
+//! Responses stream back through `ai-chunk` events as they are generated,
+//! and requests go through `curl` like sync does.
+
+use std::{
+    io::{BufRead, BufReader},
+    path::Path,
+    process::Child,
+    thread,
+};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+use crate::{
+    http,
+    settings::{self, Secret, Settings},
+    StorageState, NOTES_CHANGED_EVENT,
+};
+
+const AI_CHUNK_EVENT: &str = "ai-chunk";
+const AI_FINISHED_EVENT: &str = "ai-finished";
+pub(crate) const DEFAULT_AI_MODEL: &str = "gpt-4o-mini";
+const REQUEST_TIMEOUT_SECS: u32 = 120;
+const SUMMARIZE_PROMPT: &str = "Summarize the user's note in two or three sentences. \
+    Answer in the note's language, with the summary only.";
+const TIDY_PROMPT: &str = "Clean up the user's note: fix spelling, grammar and punctuation, \
+    and tidy its Markdown formatting. Keep its meaning, language, tone, #tags and [[links]]. \
+    Answer with the cleaned-up note only.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AiTask {
+    Summarize,
+    Tidy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct AiChunk {
+    request_id: String,
+    note_id: String,
+    text: String,
+}
+
+/// Either `result` holds the whole response or `error` says why there is none.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct AiFinished {
+    request_id: String,
+    note_id: String,
+    task: AiTask,
+    result: Option<String>,
+    error: Option<String>,
+}
+
+/// Starts summarizing a note and returns the request id its events carry.
+/// With `ai_save_summaries` on, the finished summary is stored on the note.
+#[tauri::command]
+pub(crate) fn summarize_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<String, String> {
+    start(&app, &state, id, AiTask::Summarize)
+}
+
+/// Starts a cleanup of a note's text. The result is only streamed back; the
+/// frontend saves it if the user accepts it.
+#[tauri::command]
+pub(crate) fn tidy_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<String, String> {
+    start(&app, &state, id, AiTask::Tidy)
+}
+
+fn start(
+    app: &AppHandle,
+    state: &StorageState,
+    note_id: String,
+    task: AiTask,
+) -> Result<String, String> {
+    let settings = settings::load_settings(app)?;
+    if !settings.ai_enabled {
+        return Err("AI features are turned off in settings".to_string());
+    }
+    let Some(endpoint) = settings.ai_endpoint_url.clone() else {
+        return Err("No AI endpoint is configured".to_string());
+    };
+
+    let text = {
+        let storage = state.lock(app)?;
+        let notes = storage.notes()?;
+        notes
+            .iter()
+            .find(|note| note.id == note_id && !note.is_trashed())
+            .map(|note| note.text.clone())
+            .ok_or_else(|| "Note not found".to_string())?
+    };
+
+    let request_id = Uuid::new_v4().to_string();
+    let app = app.clone();
+    let id = request_id.clone();
+    thread::spawn(move || {
+        let result = stream_completion(&settings, &endpoint, task, &text, |chunk| {
+            let _ = app.emit(
+                AI_CHUNK_EVENT,
+                AiChunk {
+                    request_id: id.clone(),
+                    note_id: note_id.clone(),
+                    text: chunk.to_string(),
+                },
+            );
+        })
+        .map(|response| response.trim().to_string());
+
+        if let Ok(summary) = &result {
+            if task == AiTask::Summarize && settings.ai_save_summaries {
+                if let Err(error) = save_summary(&app, &note_id, summary) {
+                    eprintln!("Failed to save summary: {error}");
+                }
+            }
+        }
+
+        let (result, error) = match result {
+            Ok(response) => (Some(response), None),
+            Err(error) => (None, Some(error)),
+        };
+        let _ = app.emit(
+            AI_FINISHED_EVENT,
+            AiFinished {
+                request_id: id,
+                note_id,
+                task,
+                result,
+                error,
+            },
+        );
+    });
+    Ok(request_id)
+}
+
+/// Posts a chat completion request with `stream: true` and hands every piece
+/// of generated text to `on_chunk`, returning the whole response.
+fn stream_completion(
+    settings: &Settings,
+    endpoint: &str,
+    task: AiTask,
+    text: &str,
+    mut on_chunk: impl FnMut(&str),
+) -> Result<String, String> {
+    let prompt = match task {
+        AiTask::Summarize => SUMMARIZE_PROMPT,
+        AiTask::Tidy => TIDY_PROMPT,
+    };
+    let body = json!({
+        "model": settings.ai_model,
+        "stream": true,
+        "messages": [
+            { "role": "system", "content": prompt },
+            { "role": "user", "content": text },
+        ],
+    });
+    let url = endpoint_url(endpoint, "chat/completions");
+    let body_file = stage_body(&body)?;
+    let api_key = Secret::AiApiKey.get()?;
+    post(&url, api_key.as_deref(), body_file.path())
+        .and_then(|child| read_stream(child, &mut on_chunk))
+}
+
+/// Posts `body` to an OpenAI-compatible endpoint and returns the decoded
+/// response, for the requests that are not streamed.
+pub(crate) fn post_json(url: &str, api_key: Option<&str>, body: &Value) -> Result<Value, String> {
+    let body_file = stage_body(body)?;
+    let output = post(url, api_key, body_file.path()).and_then(|child| {
+        child
+            .wait_with_output()
+            .map_err(|e| format!("Failed to run curl: {e}"))
+    })?;
+    drop(body_file);
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("AI request failed: {}", stderr.trim()));
+    }
+    let response: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse AI response: {e}"))?;
+    match response["error"]["message"].as_str() {
+        Some(message) => Err(format!("AI endpoint returned an error: {message}")),
+        None => Ok(response),
+    }
+}
+
+/// Accepts both a base URL such as `https://api.openai.com/v1` and the full
+/// URL of the `path` endpoint.
+pub(crate) fn endpoint_url(endpoint: &str, path: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with(path) {
+        endpoint.to_string()
+    } else {
+        format!("{endpoint}/{path}")
+    }
+}
+
+fn stage_body(body: &Value) -> Result<http::StagedFile, String> {
+    http::StagedFile::with_contents("ai-request", body.to_string().as_bytes())
+}
+
+/// Starts a JSON POST whose response arrives on the child's stdout. The API
+/// key goes through a config on stdin so it never shows up in the process
+/// list.
+fn post(url: &str, api_key: Option<&str>, body_file: &Path) -> Result<Child, String> {
+    let mut config = format!(
+        "url = {}\nheader = \"Content-Type: application/json\"\n",
+        http::quote_curl_value(url)?
+    );
+    if let Some(key) = api_key {
+        config.push_str(&format!(
+            "header = {}\n",
+            http::quote_curl_value(&format!("Authorization: Bearer {key}"))?
+        ));
+    }
+
+    let body_arg = format!("@{}", body_file.to_string_lossy());
+    http::spawn_curl(
+        http::WEB_PROTOCOLS,
+        REQUEST_TIMEOUT_SECS,
+        &config,
+        &["--no-buffer", "--data-binary", &body_arg],
+    )
+}
+
+/// Reads server-sent completion events until the stream ends.
+fn read_stream(mut child: Child, on_chunk: &mut impl FnMut(&str)) -> Result<String, String> {
+    let mut response = String::new();
+    // Anything that is not a server-sent event is an error body.
+    let mut other = String::new();
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            let line = line.map_err(|e| format!("Failed to read AI response: {e}"))?;
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                other.push_str(&line);
+                continue;
+            };
+            if data == "[DONE]" {
+                break;
+            }
+            let content = serde_json::from_str::<Value>(data)
+                .ok()
+                .and_then(|event| {
+                    event["choices"][0]["delta"]["content"]
+                        .as_str()
+                        .map(String::from)
+                })
+                .unwrap_or_default();
+            if !content.is_empty() {
+                on_chunk(&content);
+                response.push_str(&content);
+            }
+        }
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run curl: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("AI request failed: {}", stderr.trim()));
+    }
+    if response.is_empty() && !other.trim().is_empty() {
+        let message = serde_json::from_str::<Value>(&other)
+            .ok()
+            .and_then(|body| body["error"]["message"].as_str().map(String::from))
+            .unwrap_or(other);
+        return Err(format!("AI endpoint returned an error: {}", message.trim()));
+    }
+    Ok(response)
+}
+
+fn save_summary(app: &AppHandle, note_id: &str, summary: &str) -> Result<(), String> {
+    let state = app.state::<StorageState>();
+    let storage = state.lock(app)?;
+
+    let mut notes = storage.load_notes()?;
+    let Some(note) = notes.iter_mut().find(|note| note.id == note_id) else {
+        return Ok(());
+    };
+    note.summary = Some(summary.to_string()).filter(|summary| !summary.is_empty());
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let updated = note.clone();
+    storage.save_notes(&notes)?;
+    if !updated.is_trashed() {
+        state.update_search_index(|index| index.upsert(&updated));
+    }
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! Capturing into an existing note, so a burst of quick jots (say, during a
+//! meeting) builds up one note instead of dozens of one-liners.
+
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{daily, insert_note, settings, undo, Note, StorageState, NOTES_CHANGED_EVENT};
+
+pub(crate) const DEFAULT_APPEND_WINDOW_MINUTES: u32 = 10;
+
+/// Where quick captures go.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CaptureTarget {
+    /// Every capture is a note of its own.
+    #[default]
+    NewNote,
+    /// Captures are appended to the latest note while it is still recent.
+    AppendToLatest,
+    /// Captures are appended to today's daily note.
+    AppendToDaily,
+}
+
+/// Adds `text` as a new line of the most recently created note, or starts a
+/// new note once that one has gone untouched for longer than the append
+/// window. Appends can be undone.
+#[tauri::command]
+pub(crate) fn append_to_latest_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    text: String,
+) -> Result<Note, String> {
+    append_or_insert(&app, &state, &text)
+}
+
+/// Saves capture text according to the configured capture target.
+pub(crate) fn capture_text(
+    app: &AppHandle,
+    state: &StorageState,
+    text: &str,
+) -> Result<Note, String> {
+    let target = settings::load_settings(app)
+        .map(|settings| settings.capture_target)
+        .unwrap_or_default();
+    match target {
+        CaptureTarget::NewNote => insert_note(app, state, text),
+        CaptureTarget::AppendToLatest => append_or_insert(app, state, text),
+        CaptureTarget::AppendToDaily => daily::append(app, state, text),
+    }
+}
+
+fn append_or_insert(app: &AppHandle, state: &StorageState, text: &str) -> Result<Note, String> {
+    let addition = text.trim();
+    if addition.is_empty() {
+        return Err("Note text cannot be empty".to_string());
+    }
+    let window_minutes = settings::load_settings(app)?.append_window_minutes;
+    let cutoff = Utc::now() - Duration::minutes(i64::from(window_minutes));
+
+    {
+        let storage = state.lock(app)?;
+        let mut notes = storage.load_notes()?;
+
+        // The latest note by creation, so editing an old note in between
+        // does not redirect captures into it.
+        let latest = notes
+            .iter_mut()
+            .filter(|note| !note.is_trashed())
+            .max_by(|a, b| a.created_at.cmp(&b.created_at))
+            .filter(|note| {
+                note.locked.is_none()
+                    && !note.archived
+                    && !note.read_only
+                    && note
+                        .revised_at()
+                        .is_some_and(|revised_at| revised_at >= cutoff)
+            });
+        if let Some(note) = latest {
+            let before = append_line(note, addition);
+            let appended = note.clone();
+
+            storage.save_notes(&notes)?;
+            state.update_search_index(|index| index.upsert(&appended));
+            undo::record("Append to note", vec![before]);
+
+            let _ = app.emit(NOTES_CHANGED_EVENT, ());
+            return Ok(appended);
+        }
+    }
+
+    insert_note(app, state, addition)
+}
+
+/// Adds `addition` as the new last line of `note`, returning the note as it
+/// was before.
+pub(crate) fn append_line(note: &mut Note, addition: &str) -> Note {
+    let before = note.clone();
+    note.set_text(format!("{}\n{addition}", note.text.trim_end()));
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    before
+}
@@ -0,0 +1,191 @@
+//! Imports notes exported from Apple Notes, either as a folder of `.html`
+//! and `.txt` files (what Notes' own export and most exporter tools write)
+//! or as an `.enex` file. Rich text is downgraded to Markdown.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::NaiveDateTime;
+use tauri::{AppHandle, State};
+
+use crate::{
+    html,
+    import::{self, ImportSummary, ImportedNote},
+    normalize_tags, Note, StorageState,
+};
+
+const EXPORT_EXTENSIONS: &[&str] = &["html", "htm", "txt"];
+
+/// Imports an Apple Notes export at `path`, a folder or an `.enex` file.
+/// Notes already present are skipped, so importing twice is harmless.
+#[tauri::command]
+pub(crate) fn import_apple_notes(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    path: String,
+) -> Result<ImportSummary, String> {
+    let path = PathBuf::from(path.trim());
+    let mut summary = ImportSummary::default();
+    let imported = if path.is_dir() {
+        read_export_folder(&path, &mut summary)?
+    } else if has_extension(&path, &["enex"]) {
+        let raw = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        read_enex(&raw)
+    } else {
+        return Err(format!(
+            "{} is neither a folder nor an .enex file",
+            path.display()
+        ));
+    };
+
+    import::store_imported(&app, &state, imported, summary)
+}
+
+fn read_export_folder(
+    root: &Path,
+    summary: &mut ImportSummary,
+) -> Result<Vec<ImportedNote>, String> {
+    let mut files = Vec::new();
+    collect_export_files(root, &mut files)?;
+    files.sort();
+
+    let mut imported = Vec::new();
+    for file in files {
+        let source = file
+            .strip_prefix(root)
+            .unwrap_or(&file)
+            .display()
+            .to_string();
+        let raw = match fs::read_to_string(&file) {
+            Ok(raw) => raw,
+            Err(error) => {
+                summary.skip(source, format!("Failed to read file: {error}"));
+                continue;
+            }
+        };
+
+        let text = if has_extension(&file, &["txt"]) {
+            raw.trim().to_string()
+        } else {
+            let title = element_text(&raw, "title").into_iter().next();
+            with_title(html::to_markdown(&raw), title.as_deref())
+        };
+        let mut note = Note::new(text);
+        if let Some(modified_at) = import::file_modified_at(&file) {
+            note.created_at = modified_at;
+        }
+        imported.push(ImportedNote { source, note });
+    }
+    Ok(imported)
+}
+
+fn collect_export_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {e}", dir.display()))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect_export_files(&path, files)?;
+        } else if has_extension(&path, EXPORT_EXTENSIONS) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// One note per `<note>` element, keeping its creation and modification
+/// dates and tags.
+fn read_enex(raw: &str) -> Vec<ImportedNote> {
+    element_text(raw, "note")
+        .into_iter()
+        .enumerate()
+        .map(|(position, note_xml)| {
+            let title = element_text(&note_xml, "title").into_iter().next();
+            let content = element_text(&note_xml, "content")
+                .into_iter()
+                .next()
+                .unwrap_or_default();
+            let mut note = Note::new(with_title(html::to_markdown(&content), title.as_deref()));
+            if let Some(created_at) = first_enex_date(&note_xml, "created") {
+                note.created_at = created_at;
+            }
+            note.updated_at = first_enex_date(&note_xml, "updated");
+            note.tags = normalize_tags(element_text(&note_xml, "tag"));
+
+            let source = title
+                .filter(|title| !title.trim().is_empty())
+                .unwrap_or_else(|| format!("Note {}", position + 1));
+            ImportedNote { source, note }
+        })
+        .collect()
+}
+
+/// Notes usually open with their title already; otherwise it becomes a
+/// heading so it is not lost.
+fn with_title(body: String, title: Option<&str>) -> String {
+    let Some(title) = title.map(str::trim).filter(|title| !title.is_empty()) else {
+        return body;
+    };
+    let first_line = body
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim_matches(|c: char| c == '#' || c == '*' || c.is_whitespace());
+    if first_line == title {
+        body
+    } else {
+        format!("# {title}\n\n{body}").trim().to_string()
+    }
+}
+
+/// ENEX dates look like `20240131T174500Z`.
+fn first_enex_date(xml: &str, name: &str) -> Option<String> {
+    let value = element_text(xml, name).into_iter().next()?;
+    NaiveDateTime::parse_from_str(value.trim(), "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|datetime| datetime.and_utc().to_rfc3339())
+        .or_else(|| import::normalize_timestamp(value.trim()))
+}
+
+/// The contents of every `<name>` element, CDATA unwrapped and entities
+/// decoded. Good enough for ENEX and `<title>`, which do not nest.
+fn element_text(xml: &str, name: &str) -> Vec<String> {
+    let open = format!("<{name}");
+    let close = format!("</{name}>");
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        // `<note>` must not match `<notebook>`.
+        if !after.starts_with(['>', ' ', '\t', '\n', '\r']) {
+            rest = after;
+            continue;
+        }
+        let Some(content_start) = after.find('>') else {
+            break;
+        };
+        let content = &after[content_start + 1..];
+        let Some(end) = content.find(&close) else {
+            break;
+        };
+        let value = content[..end].trim();
+        values.push(match value.strip_prefix("<![CDATA[") {
+            Some(cdata) => cdata.trim_end_matches("]]>").to_string(),
+            None => html::decode_entities(value),
+        });
+        rest = &content[end + close.len()..];
+    }
+    values
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extensions.contains(&extension.to_lowercase().as_str()))
+}
@@ -0,0 +1,328 @@
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use image::{codecs::png::PngEncoder, ExtendedColorType, ImageEncoder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, State};
+use uuid::Uuid;
+
+use crate::{
+    find_active_note_mut, insert_prepared_note, ocr, resolve_data_file, shred, Note, StorageState,
+    NOTES_CHANGED_EVENT,
+};
+
+const ATTACHMENTS_DIR_NAME: &str = "attachments";
+const CLIPBOARD_IMAGE_CAPTION: &str = "Pasted image";
+const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "bmp", "tif", "tiff", "webp"];
+
+/// A file linked to a note. The bytes live once in the content-addressed
+/// store, named by their SHA-256, no matter how many notes reference them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct Attachment {
+    id: String,
+    file_name: String,
+    /// Names the blob in the store, so anything but a SHA-256 is refused
+    /// rather than joined to the store's path.
+    #[serde(deserialize_with = "deserialize_hash")]
+    hash: String,
+    size: u64,
+    added_at: String,
+}
+
+impl Attachment {
+    pub(crate) fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    /// Judged by the file name, which is all a pasted or attached file keeps.
+    pub(crate) fn is_image(&self) -> bool {
+        Path::new(&self.file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct AttachmentInfo {
+    #[serde(flatten)]
+    attachment: Attachment,
+    path: String,
+}
+
+#[tauri::command]
+pub(crate) fn attach_file(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    note_id: String,
+    path: String,
+) -> Result<Attachment, String> {
+    let source = PathBuf::from(path.trim());
+    if !source.is_file() {
+        return Err(format!("{} is not a file", source.display()));
+    }
+    let file_name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "attachment".to_string());
+
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &note_id)?;
+
+    let (hash, size) = store_file(&app, &source)?;
+    let attachment = new_attachment(file_name, hash, size);
+    note.attachments.push(attachment.clone());
+    let updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+    if attachment.is_image() {
+        ocr::refresh(&app, &note_id);
+    }
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(attachment)
+}
+
+/// Saves the clipboard image as a PNG attachment on a brand new note.
+#[tauri::command]
+pub(crate) fn create_note_with_clipboard_image(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    text: Option<String>,
+) -> Result<Note, String> {
+    let png = read_clipboard_png()?;
+    let (hash, size) = store_bytes(&app, &png)?;
+
+    let caption = text
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .unwrap_or_else(|| CLIPBOARD_IMAGE_CAPTION.to_string());
+    let file_name = format!("clipboard-{}.png", Utc::now().format("%Y%m%d-%H%M%S"));
+
+    let mut note = Note::new(caption);
+    note.attachments.push(new_attachment(file_name, hash, size));
+    let note = insert_prepared_note(&app, &state, note)?;
+    ocr::refresh(&app, &note.id);
+    Ok(note)
+}
+
+fn read_clipboard_png() -> Result<Vec<u8>, String> {
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("Failed to access clipboard: {e}"))?;
+    let image = clipboard
+        .get_image()
+        .map_err(|_| "Clipboard does not contain an image".to_string())?;
+
+    let width =
+        u32::try_from(image.width).map_err(|_| "Clipboard image is too large".to_string())?;
+    let height =
+        u32::try_from(image.height).map_err(|_| "Clipboard image is too large".to_string())?;
+
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(&image.bytes, width, height, ExtendedColorType::Rgba8)
+        .map_err(|e| format!("Failed to encode clipboard image: {e}"))?;
+    Ok(png)
+}
+
+#[tauri::command]
+pub(crate) fn list_attachments(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    note_id: String,
+) -> Result<Vec<AttachmentInfo>, String> {
+    let storage = state.lock(&app)?;
+
+    let notes = storage.notes()?;
+    let note = notes
+        .iter()
+        .find(|note| note.id == note_id)
+        .ok_or_else(|| "Note not found".to_string())?;
+
+    let store = resolve_store_dir(&app)?;
+    note.attachments
+        .iter()
+        .map(|attachment| {
+            Ok(AttachmentInfo {
+                path: blob_in(&store, &attachment.hash)?.display().to_string(),
+                attachment: attachment.clone(),
+            })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub(crate) fn remove_attachment(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    note_id: String,
+    attachment_id: String,
+) -> Result<(), String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &note_id)?;
+
+    let before = note.attachments.len();
+    note.attachments
+        .retain(|attachment| attachment.id != attachment_id);
+    if note.attachments.len() == before {
+        return Err("Attachment not found".to_string());
+    }
+    let updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+    remove_orphaned_blobs(&app, &notes);
+    if updated.ocr_text.is_some() {
+        ocr::refresh(&app, &note_id);
+    }
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(())
+}
+
+pub(crate) fn new_attachment(file_name: String, hash: String, size: u64) -> Attachment {
+    Attachment {
+        id: Uuid::new_v4().to_string(),
+        file_name,
+        hash,
+        size,
+        added_at: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Copies a file into the store and returns its hash and size.
+pub(crate) fn store_file(app: &AppHandle, source: &Path) -> Result<(String, u64), String> {
+    let mut file =
+        fs::File::open(source).map_err(|e| format!("Failed to open {}: {e}", source.display()))?;
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("Failed to read {}: {e}", source.display()))?;
+    let hash = format!("{:x}", hasher.finalize());
+
+    let target = resolve_store_dir(app)?.join(&hash);
+    if !target.exists() {
+        let temp_path = target.with_extension("tmp");
+        fs::copy(source, &temp_path).map_err(|e| format!("Failed to copy attachment: {e}"))?;
+        fs::rename(&temp_path, &target).map_err(|e| format!("Failed to store attachment: {e}"))?;
+    }
+    Ok((hash, size))
+}
+
+/// Writes in-memory bytes (e.g. a clipboard image) into the store.
+pub(crate) fn store_bytes(app: &AppHandle, bytes: &[u8]) -> Result<(String, u64), String> {
+    let hash = format!("{:x}", Sha256::digest(bytes));
+
+    let target = resolve_store_dir(app)?.join(&hash);
+    if !target.exists() {
+        let temp_path = target.with_extension("tmp");
+        fs::write(&temp_path, bytes).map_err(|e| format!("Failed to write attachment: {e}"))?;
+        fs::rename(&temp_path, &target).map_err(|e| format!("Failed to store attachment: {e}"))?;
+    }
+    Ok((hash, bytes.len() as u64))
+}
+
+/// Hashes of every blob referenced by `notes`, trashed ones included.
+pub(crate) fn referenced_blobs(notes: &[Note]) -> BTreeSet<&str> {
+    notes
+        .iter()
+        .flat_map(|note| note.attachments.iter())
+        .map(|attachment| attachment.hash.as_str())
+        .collect()
+}
+
+pub(crate) fn blob_path(app: &AppHandle, hash: &str) -> Result<PathBuf, String> {
+    blob_in(&resolve_store_dir(app)?, hash)
+}
+
+pub(crate) fn read_blob(app: &AppHandle, hash: &str) -> Result<Vec<u8>, String> {
+    fs::read(blob_path(app, hash)?).map_err(|e| format!("Failed to read attachment {hash}: {e}"))
+}
+
+fn blob_in(store: &Path, hash: &str) -> Result<PathBuf, String> {
+    if !is_blob_hash(hash) {
+        return Err(format!("Invalid attachment hash: {hash}"));
+    }
+    Ok(store.join(hash))
+}
+
+/// A lowercase hex SHA-256, as `store_file` and `store_bytes` name blobs.
+fn is_blob_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn deserialize_hash<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let hash = String::deserialize(deserializer)?;
+    if !is_blob_hash(&hash) {
+        return Err(serde::de::Error::custom(format!(
+            "invalid attachment hash: {hash}"
+        )));
+    }
+    Ok(hash)
+}
+
+/// Deletes blobs that no note (trashed ones included) references anymore.
+/// Best effort: failures are logged because the notes file is already saved.
+pub(crate) fn remove_orphaned_blobs(app: &AppHandle, notes: &[Note]) {
+    let Ok(store) = resolve_store_dir(app) else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(&store) else {
+        return;
+    };
+
+    let referenced = referenced_blobs(notes);
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if !referenced.contains(name) {
+            if let Err(error) = shred::destroy_file(&entry.path()) {
+                eprintln!("Failed to remove orphaned attachment {name}: {error}");
+            }
+        }
+    }
+}
+
+fn resolve_store_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = resolve_data_file(app, ATTACHMENTS_DIR_NAME)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create attachments directory: {e}"))?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_hashes_that_are_not_blob_names() {
+        let hash = format!("{:x}", Sha256::digest(b"blob"));
+        let attachment = |hash: &str| {
+            serde_json::from_value::<Attachment>(serde_json::json!({
+                "id": "a",
+                "file_name": "a.png",
+                "hash": hash,
+                "size": 4,
+                "added_at": "2025-01-01T00:00:00+00:00",
+            }))
+        };
+        assert!(attachment(&hash).is_ok());
+        assert!(attachment("../../notes.json").is_err());
+        assert!(attachment(&hash.to_uppercase()).is_err());
+        assert!(blob_in(Path::new("store"), "../secret").is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use tauri::AppHandle;
+
+use crate::tray;
+
+#[tauri::command]
+pub(crate) fn enable_autostart(app: AppHandle) -> Result<bool, String> {
+    set_autostart(&app, true)
+}
+
+#[tauri::command]
+pub(crate) fn disable_autostart(app: AppHandle) -> Result<bool, String> {
+    set_autostart(&app, false)
+}
+
+#[tauri::command]
+pub(crate) fn is_autostart_enabled(app: AppHandle) -> Result<bool, String> {
+    platform::is_enabled(&app)
+}
+
+/// Registers or removes the login item and returns the resulting state.
+pub(crate) fn set_autostart(app: &AppHandle, enabled: bool) -> Result<bool, String> {
+    if enabled {
+        let executable = std::env::current_exe()
+            .map_err(|e| format!("Failed to resolve the app executable: {e}"))?;
+        platform::enable(app, &executable)?;
+    } else {
+        platform::disable(app)?;
+    }
+
+    let enabled = platform::is_enabled(app)?;
+    tray::refresh(app);
+    Ok(enabled)
+}
+
+fn launch_entry_name(app: &AppHandle) -> String {
+    app.config().identifier.clone()
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use tauri::{AppHandle, Manager};
+
+    use super::launch_entry_name;
+
+    fn launch_agent_path(app: &AppHandle) -> Result<PathBuf, String> {
+        let home = app
+            .path()
+            .home_dir()
+            .map_err(|e| format!("Failed to resolve home directory: {e}"))?;
+        Ok(home
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", launch_entry_name(app))))
+    }
+
+    pub(super) fn enable(app: &AppHandle, executable: &Path) -> Result<(), String> {
+        let path = launch_agent_path(app)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create LaunchAgents directory: {e}"))?;
+        }
+
+        let plist = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{program}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+            label = xml_escape(&launch_entry_name(app)),
+            program = xml_escape(&executable.display().to_string()),
+        );
+
+        fs::write(&path, plist).map_err(|e| format!("Failed to write launch agent: {e}"))
+    }
+
+    pub(super) fn disable(app: &AppHandle) -> Result<(), String> {
+        let path = launch_agent_path(app)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove launch agent: {e}"))?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn is_enabled(app: &AppHandle) -> Result<bool, String> {
+        Ok(launch_agent_path(app)?.exists())
+    }
+
+    fn xml_escape(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use tauri::{AppHandle, Manager};
+
+    use super::launch_entry_name;
+
+    fn desktop_entry_path(app: &AppHandle) -> Result<PathBuf, String> {
+        let config_dir = app
+            .path()
+            .config_dir()
+            .map_err(|e| format!("Failed to resolve config directory: {e}"))?;
+        Ok(config_dir
+            .join("autostart")
+            .join(format!("{}.desktop", launch_entry_name(app))))
+    }
+
+    pub(super) fn enable(app: &AppHandle, executable: &Path) -> Result<(), String> {
+        let path = desktop_entry_path(app)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create autostart directory: {e}"))?;
+        }
+
+        let name = app.package_info().name.clone();
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName={name}\nExec=\"{}\"\nX-GNOME-Autostart-enabled=true\n",
+            executable.display()
+        );
+        fs::write(&path, entry).map_err(|e| format!("Failed to write autostart entry: {e}"))
+    }
+
+    pub(super) fn disable(app: &AppHandle) -> Result<(), String> {
+        let path = desktop_entry_path(app)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove autostart entry: {e}"))?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn is_enabled(app: &AppHandle) -> Result<bool, String> {
+        Ok(desktop_entry_path(app)?.exists())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::{path::Path, process::Command};
+
+    use tauri::AppHandle;
+
+    use super::launch_entry_name;
+
+    const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+    fn reg(args: &[&str]) -> Result<bool, String> {
+        Command::new("reg")
+            .args(args)
+            .output()
+            .map(|output| output.status.success())
+            .map_err(|e| format!("Failed to run reg.exe: {e}"))
+    }
+
+    pub(super) fn enable(app: &AppHandle, executable: &Path) -> Result<(), String> {
+        let command = format!("\"{}\"", executable.display());
+        let name = launch_entry_name(app);
+        if reg(&[
+            "add", RUN_KEY, "/v", &name, "/t", "REG_SZ", "/d", &command, "/f",
+        ])? {
+            Ok(())
+        } else {
+            Err("Failed to register the app in the Run key".to_string())
+        }
+    }
+
+    pub(super) fn disable(app: &AppHandle) -> Result<(), String> {
+        if is_enabled(app)? {
+            reg(&["delete", RUN_KEY, "/v", &launch_entry_name(app), "/f"])?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn is_enabled(app: &AppHandle) -> Result<bool, String> {
+        reg(&["query", RUN_KEY, "/v", &launch_entry_name(app)])
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+mod platform {
+    use std::path::Path;
+
+    use tauri::AppHandle;
+
+    const UNSUPPORTED: &str = "Autostart is not supported on this platform";
+
+    pub(super) fn enable(_app: &AppHandle, _executable: &Path) -> Result<(), String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub(super) fn disable(_app: &AppHandle) -> Result<(), String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub(super) fn is_enabled(_app: &AppHandle) -> Result<bool, String> {
+        Ok(false)
+    }
+}
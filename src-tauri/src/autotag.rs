@@ -0,0 +1,143 @@
+//! Tag suggestions for new notes, learned from how existing notes are
+//! tagged. Every tag gets a TF-IDF profile built from the notes carrying it;
+//! a new note is offered the tags whose profile is closest to its own words,
+//! plus any tag it mentions by name. Only tags already in use are proposed.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::{search, Note, SnapshotCache};
+
+/// Most tags proposed for one note.
+const MAX_SUGGESTED_TAGS: usize = 3;
+/// Similarity below which a tag is not worth proposing.
+const MIN_SCORE: f32 = 0.2;
+/// Tokens shorter than this carry little meaning on their own.
+const MIN_TOKEN_CHARS: usize = 3;
+static TAG_MODEL: SnapshotCache<TagModel> = SnapshotCache::new("Tag model", TagModel::build);
+
+type TermWeights = HashMap<String, f32>;
+
+#[derive(Default)]
+struct TagModel {
+    /// Number of active notes each term appears in.
+    document_frequency: HashMap<String, usize>,
+    documents: usize,
+    /// Tag to the normalized sum of its notes' term weights.
+    profiles: HashMap<String, TermWeights>,
+}
+
+impl TagModel {
+    fn build(notes: &[Note]) -> Self {
+        let active: Vec<(&Note, Vec<String>)> = notes
+            .iter()
+            .filter(|note| !note.is_trashed())
+            .map(|note| (note, terms(&note.text)))
+            .collect();
+
+        let mut model = Self {
+            documents: active.len(),
+            ..Self::default()
+        };
+        for (_, terms) in &active {
+            for term in terms.iter().collect::<HashSet<_>>() {
+                *model.document_frequency.entry(term.clone()).or_default() += 1;
+            }
+        }
+
+        for (note, terms) in &active {
+            if note.tags.is_empty() {
+                continue;
+            }
+            let weights = model.weigh(terms);
+            for tag in &note.tags {
+                let profile = model.profiles.entry(tag.clone()).or_default();
+                for (term, weight) in &weights {
+                    *profile.entry(term.clone()).or_default() += weight;
+                }
+            }
+        }
+        for profile in model.profiles.values_mut() {
+            normalize(profile);
+        }
+        model
+    }
+
+    /// Normalized TF-IDF weights of a note's terms.
+    fn weigh(&self, terms: &[String]) -> TermWeights {
+        let mut weights = TermWeights::new();
+        for term in terms {
+            *weights.entry(term.clone()).or_default() += 1.0;
+        }
+        for (term, weight) in weights.iter_mut() {
+            let frequency = self.document_frequency.get(term).copied().unwrap_or(0);
+            let idf = ((self.documents + 1) as f32 / (frequency + 1) as f32).ln() + 1.0;
+            *weight *= idf;
+        }
+        normalize(&mut weights);
+        weights
+    }
+
+    fn suggest(&self, text: &str, applied: &[String]) -> Vec<String> {
+        let terms = terms(text);
+        let weights = self.weigh(&terms);
+        // Every token, short ones too, so tags like `ux` can be mentioned.
+        let tokens: HashSet<String> = search::tokenize(text).into_iter().collect();
+
+        let mut scored: Vec<(&String, f32)> = self
+            .profiles
+            .iter()
+            .filter(|(tag, _)| !applied.contains(tag))
+            .map(|(tag, profile)| {
+                let similarity: f32 = weights
+                    .iter()
+                    .filter_map(|(term, weight)| profile.get(term).map(|other| weight * other))
+                    .sum();
+                let tag_tokens = search::tokenize(tag);
+                let mentioned =
+                    !tag_tokens.is_empty() && tag_tokens.iter().all(|token| tokens.contains(token));
+                (tag, similarity + if mentioned { 1.0 } else { 0.0 })
+            })
+            .filter(|(_, score)| *score >= MIN_SCORE)
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        scored
+            .into_iter()
+            .take(MAX_SUGGESTED_TAGS)
+            .map(|(tag, _)| tag.clone())
+            .collect()
+    }
+}
+
+/// Existing tags that fit `text`, best first, leaving out those in `applied`.
+pub(crate) fn suggest(
+    notes: &Arc<Vec<Note>>,
+    text: &str,
+    applied: &[String],
+) -> Result<Vec<String>, String> {
+    TAG_MODEL.with(notes, |model| model.suggest(text, applied))
+}
+
+fn terms(text: &str) -> Vec<String> {
+    search::tokenize(text)
+        .into_iter()
+        .filter(|token| {
+            token.chars().count() >= MIN_TOKEN_CHARS && !token.chars().all(|c| c.is_numeric())
+        })
+        .collect()
+}
+
+fn normalize(weights: &mut TermWeights) {
+    let norm = weights
+        .values()
+        .map(|weight| weight * weight)
+        .sum::<f32>()
+        .sqrt();
+    if norm > 0.0 {
+        for weight in weights.values_mut() {
+            *weight /= norm;
+        }
+    }
+}
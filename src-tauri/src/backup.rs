@@ -0,0 +1,504 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    attachments, encryption, load_notes_from_path, notebooks, notebooks::Notebook,
+    resolve_notes_path, schema, settings, settings::Settings, shred, zip, Note, StorageState,
+    NOTES_CHANGED_EVENT,
+};
+
+pub(crate) const DEFAULT_BACKUP_RETENTION: u32 = 10;
+const ROTATING_BACKUPS_DIR_NAME: &str = "backups";
+const ROTATING_BACKUP_PREFIX: &str = "notes-";
+const ROTATING_BACKUP_EXTENSION: &str = ".json";
+/// Copies of notes files taken before a schema migration; never pruned.
+const MIGRATION_BACKUP_PREFIX: &str = "pre-migration-";
+const BACKUP_FORMAT_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const NOTES_ENTRY: &str = "notes.json";
+const NOTEBOOKS_ENTRY: &str = "notebooks.json";
+const SETTINGS_ENTRY: &str = "settings.json";
+const ATTACHMENTS_PREFIX: &str = "attachments/";
+
+/// Mirrors `Settings::backup_retention` so saves don't re-read settings.
+static BACKUP_RETENTION: AtomicU32 = AtomicU32::new(DEFAULT_BACKUP_RETENTION);
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RestoreMode {
+    /// Adds notes from the backup, keeping whichever copy is newer per note.
+    #[default]
+    Merge,
+    /// Discards current notes, notebooks and settings in favor of the backup.
+    Replace,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+struct BackupManifest {
+    format_version: u32,
+    app_version: String,
+    created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct BackupSummary {
+    notes: usize,
+    notebooks: usize,
+    attachments: usize,
+    path: String,
+}
+
+/// A copy of the notes file taken automatically before it was overwritten.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct RotatingBackup {
+    name: String,
+    size: u64,
+    modified_at: Option<String>,
+    /// `None` when the copy itself cannot be parsed.
+    notes: Option<usize>,
+}
+
+/// Lists the automatic backups of the notes file, newest first.
+#[tauri::command]
+pub(crate) fn list_backups(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+) -> Result<Vec<RotatingBackup>, String> {
+    let _guard = state.lock(&app)?;
+
+    let dir = rotating_backups_dir(&resolve_notes_path(&app)?);
+    let backups = rotating_backup_paths(&dir)
+        .into_iter()
+        .rev()
+        .map(|path| {
+            let metadata = fs::metadata(&path).ok();
+            RotatingBackup {
+                name: file_name(&path),
+                size: metadata
+                    .as_ref()
+                    .map(|metadata| metadata.len())
+                    .unwrap_or(0),
+                modified_at: metadata
+                    .and_then(|metadata| metadata.modified().ok())
+                    .map(|modified| DateTime::<Utc>::from(modified).to_rfc3339()),
+                notes: load_notes_from_path(&path).ok().map(|notes| notes.len()),
+            }
+        })
+        .collect();
+    Ok(backups)
+}
+
+/// Replaces the notes file with one of its automatic backups. The current
+/// file is itself backed up first, so a restore can be undone the same way.
+#[tauri::command]
+pub(crate) fn restore_backup(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    name: String,
+) -> Result<usize, String> {
+    let notes = {
+        let storage = state.lock(&app)?;
+
+        let dir = rotating_backups_dir(storage.notes_path());
+        let backup = rotating_backup_paths(&dir)
+            .into_iter()
+            .find(|path| file_name(path) == name)
+            .ok_or_else(|| "Backup not found".to_string())?;
+
+        let notes = load_notes_from_path(&backup)?;
+        storage.save_notes(&notes)?;
+        state.invalidate_search_index();
+        notes
+    };
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(notes.len())
+}
+
+pub(crate) fn load_retention(app: &AppHandle) {
+    if let Ok(settings) = settings::load_settings(app) {
+        set_retention(settings.backup_retention);
+    }
+}
+
+pub(crate) fn set_retention(retention: u32) {
+    BACKUP_RETENTION.store(retention, Ordering::SeqCst);
+}
+
+/// Copies the current notes file into `backups/` before it is overwritten and
+/// prunes copies beyond the configured retention. Failures are logged rather
+/// than returned: a missing backup must never block a save.
+pub(crate) fn rotate_before_write(notes_path: &Path) {
+    let retention = BACKUP_RETENTION.load(Ordering::SeqCst) as usize;
+    if retention == 0 || !notes_path.exists() {
+        return;
+    }
+
+    let dir = rotating_backups_dir(notes_path);
+    if let Err(error) = fs::create_dir_all(&dir) {
+        eprintln!("Failed to create backups directory: {error}");
+        return;
+    }
+
+    let stamp = Utc::now().format("%Y%m%dT%H%M%S%3fZ");
+    let mut target = dir.join(format!(
+        "{ROTATING_BACKUP_PREFIX}{stamp}{ROTATING_BACKUP_EXTENSION}"
+    ));
+    let mut attempt = 1;
+    while target.exists() {
+        attempt += 1;
+        target = dir.join(format!(
+            "{ROTATING_BACKUP_PREFIX}{stamp}-{attempt}{ROTATING_BACKUP_EXTENSION}"
+        ));
+    }
+    if let Err(error) = fs::copy(notes_path, &target) {
+        eprintln!("Failed to back up notes before saving: {error}");
+        return;
+    }
+
+    let backups = rotating_backup_paths(&dir);
+    let excess = backups.len().saturating_sub(retention);
+    for old in &backups[..excess] {
+        if let Err(error) = fs::remove_file(old) {
+            eprintln!("Failed to prune backup {}: {error}", old.display());
+        }
+    }
+}
+
+/// Keeps a copy of a notes file in schema `version` before it is migrated.
+/// Unlike `rotate_before_write`, a failure stops the migration.
+pub(crate) fn keep_before_migration(notes_path: &Path, version: u32) -> Result<(), String> {
+    let dir = rotating_backups_dir(notes_path);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create backups directory: {e}"))?;
+
+    let stamp = Utc::now().format("%Y%m%dT%H%M%S%3fZ");
+    let target = dir.join(format!(
+        "{MIGRATION_BACKUP_PREFIX}v{version}-{stamp}{ROTATING_BACKUP_EXTENSION}"
+    ));
+    fs::copy(notes_path, &target)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to back up notes before migrating them: {e}"))
+}
+
+/// Rewrites the rotating backups that still hold any of `ids` without them,
+/// overwriting the old contents in place.
+pub(crate) fn scrub_notes(notes_path: &Path, ids: &HashSet<&str>) {
+    rewrite_backups(notes_path, |notes| {
+        let before = notes.len();
+        notes.retain(|note| !ids.contains(note.id.as_str()));
+        notes.len() != before
+    });
+}
+
+/// Puts `replacements` over the versions of those notes the backups hold,
+/// e.g. a locked note over its readable text, overwriting the old contents
+/// in place.
+pub(crate) fn replace_notes(notes_path: &Path, replacements: &[Note]) {
+    let by_id: HashMap<&str, &Note> = replacements
+        .iter()
+        .map(|note| (note.id.as_str(), note))
+        .collect();
+    rewrite_backups(notes_path, |notes| {
+        let mut replaced = false;
+        for note in notes.iter_mut() {
+            if let Some(replacement) = by_id.get(note.id.as_str()) {
+                *note = (*replacement).clone();
+                replaced = true;
+            }
+        }
+        replaced
+    });
+}
+
+/// Rewrites each backup `edit` changed, telling by its return value.
+fn rewrite_backups(notes_path: &Path, mut edit: impl FnMut(&mut Vec<Note>) -> bool) {
+    for path in backup_files(notes_path) {
+        let Ok(mut notes) = load_notes_from_path(&path) else {
+            continue;
+        };
+        if !edit(&mut notes) {
+            continue;
+        }
+        let scrubbed = schema::serialize(&notes)
+            .and_then(encryption::seal)
+            .and_then(|payload| {
+                shred::rewrite_in_place(&path, &payload).map_err(|e| e.to_string())
+            });
+        if let Err(error) = scrubbed {
+            eprintln!("Failed to scrub backup {}: {error}", path.display());
+        }
+    }
+}
+
+/// Every copy of the notes file in `backups/`, rotating and pre-migration.
+pub(crate) fn backup_files(notes_path: &Path) -> Vec<PathBuf> {
+    let dir = rotating_backups_dir(notes_path);
+    let mut paths = rotating_backup_paths(&dir);
+    paths.extend(backup_paths(&dir, MIGRATION_BACKUP_PREFIX));
+    paths
+}
+
+fn rotating_backups_dir(notes_path: &Path) -> PathBuf {
+    notes_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(ROTATING_BACKUPS_DIR_NAME)
+}
+
+/// Backup files oldest first; the timestamped names sort chronologically.
+fn rotating_backup_paths(dir: &Path) -> Vec<PathBuf> {
+    backup_paths(dir, ROTATING_BACKUP_PREFIX)
+}
+
+fn backup_paths(dir: &Path, prefix: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = file_name(path);
+            name.starts_with(prefix) && name.ends_with(ROTATING_BACKUP_EXTENSION)
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Bundles notes, notebooks, attachment blobs and settings into one zip.
+#[tauri::command]
+pub(crate) fn export_backup(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    path: String,
+) -> Result<BackupSummary, String> {
+    let target = PathBuf::from(path.trim());
+    if target.as_os_str().is_empty() {
+        return Err("Backup path cannot be empty".to_string());
+    }
+
+    let (notes, notebooks) = {
+        let storage = state.lock(&app)?;
+        (storage.load_notes()?, notebooks::load_notebooks(&app)?)
+    };
+    // Passwords and tokens stay behind: they live in the keychain, never in
+    // `Settings`.
+    let settings = settings::load_settings(&app)?;
+
+    let manifest = BackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        app_version: app.package_info().version.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+    };
+
+    let mut archive = zip::ZipWriter::new();
+    archive.add_file(MANIFEST_ENTRY, &to_json(&manifest)?)?;
+    archive.add_file(NOTES_ENTRY, schema::serialize(&notes)?.as_bytes())?;
+    archive.add_file(NOTEBOOKS_ENTRY, &to_json(&notebooks)?)?;
+    archive.add_file(SETTINGS_ENTRY, &to_json(&settings)?)?;
+
+    let blobs = attachments::referenced_blobs(&notes);
+    for hash in &blobs {
+        let bytes = attachments::read_blob(&app, hash)?;
+        archive.add_file(&format!("{ATTACHMENTS_PREFIX}{hash}"), &bytes)?;
+    }
+
+    let temp_path = target.with_extension("zip.tmp");
+    fs::write(&temp_path, archive.finish()?).map_err(|e| format!("Failed to write backup: {e}"))?;
+    fs::rename(&temp_path, &target).map_err(|e| format!("Failed to finalize backup: {e}"))?;
+
+    Ok(BackupSummary {
+        notes: notes.len(),
+        notebooks: notebooks.len(),
+        attachments: blobs.len(),
+        path: target.display().to_string(),
+    })
+}
+
+/// Restores a backup made by `export_backup`, merging into or replacing the
+/// current data. Replacing also restores the backup's preferences, but
+/// never where the data lives, integrations or anything that reaches the
+/// network (see `restored_preferences`).
+#[tauri::command]
+pub(crate) fn import_backup(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    path: String,
+    mode: Option<RestoreMode>,
+) -> Result<BackupSummary, String> {
+    let mode = mode.unwrap_or_default();
+    let source = PathBuf::from(path.trim());
+    let archive = fs::read(&source).map_err(|e| format!("Failed to read backup: {e}"))?;
+    let mut entries: HashMap<String, Vec<u8>> = zip::read_entries(&archive)?.into_iter().collect();
+
+    let manifest: BackupManifest = from_json(&entries, MANIFEST_ENTRY)?
+        .ok_or_else(|| "Not a Jotin backup: manifest.json is missing".to_string())?;
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(format!(
+            "This backup was made by a newer Jotin ({}); update the app to restore it",
+            manifest.app_version
+        ));
+    }
+
+    // Backups made before the notes schema was versioned hold a bare array.
+    let incoming_notes = match entries.get(NOTES_ENTRY) {
+        Some(bytes) => {
+            schema::parse(&String::from_utf8_lossy(bytes))
+                .map_err(|e| format!("Failed to parse {NOTES_ENTRY}: {e}"))?
+                .notes
+        }
+        None => Vec::new(),
+    };
+    let incoming_notebooks: Vec<Notebook> =
+        from_json(&entries, NOTEBOOKS_ENTRY)?.unwrap_or_default();
+    let incoming_settings: Option<Settings> = from_json(&entries, SETTINGS_ENTRY)?;
+
+    // Blobs first, so restored notes never point at missing files.
+    let mut restored_attachments = 0;
+    for (name, bytes) in entries.drain() {
+        let Some(expected_hash) = name.strip_prefix(ATTACHMENTS_PREFIX) else {
+            continue;
+        };
+        // Checked before anything is written, so a corrupt entry leaves no
+        // stray blob behind.
+        if format!("{:x}", Sha256::digest(&bytes)) != expected_hash {
+            return Err(format!(
+                "Corrupt backup: attachment {expected_hash} does not match"
+            ));
+        }
+        attachments::store_bytes(&app, &bytes)?;
+        restored_attachments += 1;
+    }
+
+    let (notes, notebooks) = {
+        let storage = state.lock(&app)?;
+
+        let notes = match mode {
+            RestoreMode::Replace => incoming_notes,
+            RestoreMode::Merge => merge_notes(storage.load_notes()?, incoming_notes),
+        };
+        let notebooks = match mode {
+            RestoreMode::Replace => incoming_notebooks,
+            RestoreMode::Merge => {
+                notebooks::merge_notebooks(notebooks::load_notebooks(&app)?, incoming_notebooks)
+            }
+        };
+
+        storage.save_notes(&notes)?;
+        notebooks::save_notebooks(&app, &notebooks)?;
+        state.invalidate_search_index();
+        (notes, notebooks)
+    };
+
+    if let (RestoreMode::Replace, Some(incoming)) = (mode, incoming_settings) {
+        let restored = restored_preferences(incoming, settings::load_settings(&app)?);
+        settings::update_settings(app.clone(), state, restored)?;
+    }
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    notebooks::notify_changed(&app);
+    Ok(BackupSummary {
+        notes: notes.len(),
+        notebooks: notebooks.len(),
+        attachments: restored_attachments,
+        path: source.display().to_string(),
+    })
+}
+
+/// The backup's preferences over the current settings. The rest, such as
+/// the data directory, vault, HTTP API, sync targets, AI and mail servers,
+/// the clipboard watcher and offline mode, describe this machine or decide
+/// what leaves it, so an archive never changes them.
+fn restored_preferences(incoming: Settings, current: Settings) -> Settings {
+    Settings {
+        capture_shortcut: incoming.capture_shortcut,
+        clipboard_capture_shortcut: incoming.clipboard_capture_shortcut,
+        main_window_shortcut: incoming.main_window_shortcut,
+        screenshot_capture_shortcut: incoming.screenshot_capture_shortcut,
+        selection_capture_shortcut: incoming.selection_capture_shortcut,
+        voice_capture_shortcut: incoming.voice_capture_shortcut,
+        theme: incoming.theme,
+        capture_always_on_top: incoming.capture_always_on_top,
+        auto_apply_tag_suggestions: incoming.auto_apply_tag_suggestions,
+        record_capture_source: incoming.record_capture_source,
+        record_source_url: incoming.record_source_url,
+        capture_mode: incoming.capture_mode,
+        capture_target: incoming.capture_target,
+        append_window_minutes: incoming.append_window_minutes,
+        capture_placement: incoming.capture_placement,
+        capture_monitor: incoming.capture_monitor,
+        capture_dismiss: incoming.capture_dismiss,
+        default_note_sort: incoming.default_note_sort,
+        tray_recent_action: incoming.tray_recent_action,
+        saved_searches: incoming.saved_searches,
+        backup_retention: incoming.backup_retention,
+        trash_retention_days: incoming.trash_retention_days,
+        undo_window_seconds: incoming.undo_window_seconds,
+        do_not_disturb: incoming.do_not_disturb,
+        pomodoro_work_minutes: incoming.pomodoro_work_minutes,
+        pomodoro_break_minutes: incoming.pomodoro_break_minutes,
+        pomodoro_long_break_minutes: incoming.pomodoro_long_break_minutes,
+        ai_model: incoming.ai_model,
+        ai_save_summaries: incoming.ai_save_summaries,
+        embedding_model: incoming.embedding_model,
+        notion_tags_property: incoming.notion_tags_property,
+        todoist_trash_sent_notes: incoming.todoist_trash_sent_notes,
+        ..current
+    }
+}
+
+/// Keeps every note from both sides; for ids present in both, the copy with
+/// the newer revision wins.
+fn merge_notes(current: Vec<Note>, incoming: Vec<Note>) -> Vec<Note> {
+    let mut merged = current;
+    let positions: HashMap<String, usize> = merged
+        .iter()
+        .enumerate()
+        .map(|(index, note)| (note.id.clone(), index))
+        .collect();
+
+    for note in incoming {
+        match positions.get(&note.id) {
+            Some(&index) if note.revised_at() > merged[index].revised_at() => merged[index] = note,
+            Some(_) => {}
+            None => merged.push(note),
+        }
+    }
+    merged
+}
+
+fn to_json(value: &impl Serialize) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(value).map_err(|e| format!("Failed to serialize backup: {e}"))
+}
+
+fn from_json<T: for<'de> Deserialize<'de>>(
+    entries: &HashMap<String, Vec<u8>>,
+    name: &str,
+) -> Result<Option<T>, String> {
+    entries
+        .get(name)
+        .map(|bytes| {
+            serde_json::from_slice(bytes).map_err(|e| format!("Failed to parse {name}: {e}"))
+        })
+        .transpose()
+}
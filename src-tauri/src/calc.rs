@@ -0,0 +1,383 @@
+//! Quick calculations typed into capture, such as `=12*45+3`. A small
+//! recursive-descent evaluator over numbers, `+ - * / % ^`, parentheses and
+//! a few functions; nothing else can run, so any text is safe to try.
+
+use serde::Serialize;
+
+const MAX_EXPRESSION_CHARS: usize = 512;
+const MAX_NESTING: usize = 64;
+/// Decimal places shown before trailing zeros are trimmed.
+const RESULT_DECIMALS: usize = 10;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct Calculation {
+    expression: String,
+    value: f64,
+    /// The value as it would be appended to the note, e.g. `543` or `0.125`.
+    formatted: String,
+}
+
+/// Evaluates `text`, with or without its leading `=`.
+#[tauri::command]
+pub(crate) fn evaluate_expression(text: String) -> Result<Calculation, String> {
+    let expression = text.trim().trim_start_matches('=').trim().to_string();
+    let value = evaluate(&expression)?;
+    Ok(Calculation {
+        formatted: format_number(value),
+        expression,
+        value,
+    })
+}
+
+pub(crate) fn evaluate(expression: &str) -> Result<f64, String> {
+    if expression.is_empty() {
+        return Err("Nothing to calculate".to_string());
+    }
+    if expression.chars().count() > MAX_EXPRESSION_CHARS {
+        return Err("Expression is too long".to_string());
+    }
+
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+        depth: 0,
+    };
+    let value = parser.expression()?;
+    if parser.position < parser.tokens.len() {
+        return Err("Unexpected input after the expression".to_string());
+    }
+    if !value.is_finite() {
+        return Err("The result is not a finite number".to_string());
+    }
+    Ok(value)
+}
+
+/// Rounds away floating-point noise (`0.1+0.2` gives `0.3`).
+pub(crate) fn format_number(value: f64) -> String {
+    format_rounded(value, RESULT_DECIMALS)
+}
+
+/// `value` with at most `decimals` places and no trailing zeros.
+pub(crate) fn format_rounded(value: f64, decimals: usize) -> String {
+    let formatted = format!("{value:.decimals$}");
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    match formatted {
+        "-0" => "0".to_string(),
+        formatted => formatted.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Operator(char),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    match c {
+                        '0'..='9' | '.' => number.push(c),
+                        // `1_000` groups digits; `,` cannot, as it
+                        // separates function arguments.
+                        '_' => {}
+                        _ => break,
+                    }
+                    chars.next();
+                }
+                let value = number
+                    .parse()
+                    .map_err(|_| format!("Invalid number \"{number}\""))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_alphabetic() => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric()) {
+                    name.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Name(name.to_lowercase()));
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                tokens.push(Token::Operator(c));
+                chars.next();
+            }
+            '×' => {
+                tokens.push(Token::Operator('*'));
+                chars.next();
+            }
+            '÷' => {
+                tokens.push(Token::Operator('/'));
+                chars.next();
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                chars.next();
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                chars.next();
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                chars.next();
+            }
+            c => return Err(format!("Unexpected character \"{c}\"")),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Precedence climbs from `expression` (`+ -`) through `term` (`* / %`),
+/// `unary` signs and `power` (`^`, right-associative) to `primary`.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn expression(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        while let Some(operator) = self.next_operator(&['+', '-']) {
+            let right = self.term()?;
+            value = if operator == '+' {
+                value + right
+            } else {
+                value - right
+            };
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        while let Some(operator) = self.next_operator(&['*', '/', '%']) {
+            let right = self.unary()?;
+            value = match operator {
+                '*' => value * right,
+                _ if right == 0.0 => return Err("Division by zero".to_string()),
+                '/' => value / right,
+                _ => value % right,
+            };
+        }
+        Ok(value)
+    }
+
+    /// Signs bind looser than `^`, so `-2^2` is `-4`.
+    fn unary(&mut self) -> Result<f64, String> {
+        match self.next_operator(&['-', '+']) {
+            Some('-') => self.nested(Self::unary).map(|value| -value),
+            Some(_) => self.nested(Self::unary),
+            None => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.primary()?;
+        if self.next_operator(&['^']).is_some() {
+            let exponent = self.nested(Self::unary)?;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<f64, String> {
+        match self.tokens.get(self.position).cloned() {
+            Some(Token::Number(value)) => {
+                self.position += 1;
+                Ok(value)
+            }
+            Some(Token::Open) => {
+                self.position += 1;
+                let value = self.nested(Self::expression)?;
+                self.expect(&Token::Close)?;
+                Ok(value)
+            }
+            Some(Token::Name(name)) => {
+                self.position += 1;
+                if self.tokens.get(self.position) == Some(&Token::Open) {
+                    self.position += 1;
+                    let arguments = self.arguments()?;
+                    call(&name, &arguments)
+                } else {
+                    constant(&name)
+                }
+            }
+            Some(_) => Err("Unexpected operator".to_string()),
+            None => Err("The expression ends too early".to_string()),
+        }
+    }
+
+    /// Comma-separated arguments up to the closing parenthesis.
+    fn arguments(&mut self) -> Result<Vec<f64>, String> {
+        let mut arguments = Vec::new();
+        if self.tokens.get(self.position) == Some(&Token::Close) {
+            self.position += 1;
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.nested(Self::expression)?);
+            match self.tokens.get(self.position) {
+                Some(Token::Comma) => self.position += 1,
+                Some(Token::Close) => {
+                    self.position += 1;
+                    return Ok(arguments);
+                }
+                _ => return Err("Missing closing parenthesis".to_string()),
+            }
+        }
+    }
+
+    /// Guards recursion so deeply nested input cannot overflow the stack.
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<f64, String>) -> Result<f64, String> {
+        if self.depth >= MAX_NESTING {
+            return Err("Expression is nested too deeply".to_string());
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn next_operator(&mut self, operators: &[char]) -> Option<char> {
+        match self.tokens.get(self.position) {
+            Some(Token::Operator(operator)) if operators.contains(operator) => {
+                self.position += 1;
+                Some(*operator)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), String> {
+        if self.tokens.get(self.position) == Some(token) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err("Missing closing parenthesis".to_string())
+        }
+    }
+}
+
+fn constant(name: &str) -> Result<f64, String> {
+    match name {
+        "pi" | "π" => Ok(std::f64::consts::PI),
+        "e" => Ok(std::f64::consts::E),
+        _ => Err(format!("Unknown name \"{name}\"")),
+    }
+}
+
+fn call(name: &str, arguments: &[f64]) -> Result<f64, String> {
+    let one = |f: fn(f64) -> f64| match arguments {
+        [value] => Ok(f(*value)),
+        _ => Err(format!("{name}() takes one argument")),
+    };
+    match name {
+        "sqrt" => one(f64::sqrt),
+        "abs" => one(f64::abs),
+        "round" => one(f64::round),
+        "floor" => one(f64::floor),
+        "ceil" => one(f64::ceil),
+        "ln" => one(f64::ln),
+        "log" => one(f64::log10),
+        "sin" => one(f64::sin),
+        "cos" => one(f64::cos),
+        "tan" => one(f64::tan),
+        "min" | "max" if arguments.is_empty() => {
+            Err(format!("{name}() takes at least one argument"))
+        }
+        "min" => Ok(arguments.iter().copied().fold(f64::INFINITY, f64::min)),
+        "max" => Ok(arguments.iter().copied().fold(f64::NEG_INFINITY, f64::max)),
+        _ => Err(format!("Unknown function \"{name}\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(expression: &str) -> f64 {
+        evaluate(expression).unwrap()
+    }
+
+    #[test]
+    fn follows_operator_precedence() {
+        assert_eq!(value("12*45+3"), 543.0);
+        assert_eq!(value("2+3*4^2"), 50.0);
+        assert_eq!(value("(2+3)*4"), 20.0);
+        assert_eq!(value("7 % 4"), 3.0);
+        assert_eq!(value("1_000 × 3 ÷ 4"), 750.0);
+    }
+
+    #[test]
+    fn powers_are_right_associative_and_bind_tighter_than_signs() {
+        assert_eq!(value("2^3^2"), 512.0);
+        assert_eq!(value("-2^2"), -4.0);
+        assert_eq!(value("2^-1"), 0.5);
+        assert_eq!(value("--3"), 3.0);
+    }
+
+    #[test]
+    fn knows_functions_and_constants() {
+        assert_eq!(value("sqrt(16) + abs(-2)"), 6.0);
+        assert_eq!(value("max(1, 5, 3) - min(4, 2)"), 3.0);
+        assert_eq!(value("round(PI * 100)"), 314.0);
+        assert_eq!(value("log(1000)"), 3.0);
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert!(evaluate("").is_err());
+        assert!(evaluate("1 +").is_err());
+        assert!(evaluate("(1 + 2").is_err());
+        assert!(evaluate("1 2").is_err());
+        assert!(evaluate("2 $ 3").is_err());
+        assert!(evaluate("foo(1)").is_err());
+        assert!(evaluate("sqrt(1, 2)").is_err());
+        assert!(evaluate("max()").is_err());
+        assert_eq!(evaluate("1/0").unwrap_err(), "Division by zero");
+        assert!(evaluate("10^400").is_err());
+    }
+
+    #[test]
+    fn refuses_deep_nesting_and_long_input() {
+        let nested = format!("{}1{}", "(".repeat(100), ")".repeat(100));
+        assert_eq!(
+            evaluate(&nested).unwrap_err(),
+            "Expression is nested too deeply"
+        );
+        assert_eq!(
+            evaluate(&"1+".repeat(300)).unwrap_err(),
+            "Expression is too long"
+        );
+    }
+
+    #[test]
+    fn formats_without_floating_point_noise() {
+        assert_eq!(format_number(value("0.1+0.2")), "0.3");
+        assert_eq!(format_number(value("1/8")), "0.125");
+        assert_eq!(format_number(value("-0.00000000001")), "0");
+        assert_eq!(format_rounded(2.0 / 3.0, 2), "0.67");
+    }
+
+    #[test]
+    fn strips_the_leading_equals_sign() {
+        let calculation = evaluate_expression(" =2*21 ".to_string()).unwrap();
+        assert_eq!(calculation.expression, "2*21");
+        assert_eq!(calculation.formatted, "42");
+    }
+}
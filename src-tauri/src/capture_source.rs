@@ -0,0 +1,246 @@
+//! Where a quick capture came from: the app and window in front when the
+//! capture shortcut was pressed, kept on the note as `source`. Opt-in
+//! through `record_capture_source`, since window titles can be private.
+//!
+//! Looked up through `osascript` (System Events; window titles need the
+//! Accessibility permission) on macOS, `user32` through PowerShell on
+//! Windows and `xdotool` on X11. Wayland offers no way to ask, so captures
+//! there have no source.
+//!
+//! With `record_source_url` on, a capture over a browser also keeps the
+//! active tab's address as the note's `source_url`. A browser extension can
+//! report it through the HTTP API (`POST /browser-tab`); failing that it is
+//! read through AppleScript (Safari and Chromium browsers) on macOS and
+//! through the address bar's UI Automation value on Windows.
+
+use std::{process::Command, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::settings;
+
+/// The source recorded when the capture window last opened; cleared when it
+/// hides, so notes captured later elsewhere do not inherit it.
+static CURRENT: Mutex<Option<CaptureSource>> = Mutex::new(None);
+/// Like `CURRENT`, for the browser tab's address.
+static CURRENT_URL: Mutex<Option<String>> = Mutex::new(None);
+/// The active tab the browser extension last reported.
+static REPORTED_TAB: Mutex<Option<String>> = Mutex::new(None);
+
+/// Process or app names of browsers, compared case-insensitively.
+const BROWSERS: [&str; 12] = [
+    "arc",
+    "brave",
+    "brave browser",
+    "chrome",
+    "chromium",
+    "firefox",
+    "google chrome",
+    "microsoft edge",
+    "msedge",
+    "opera",
+    "safari",
+    "vivaldi",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct CaptureSource {
+    app: String,
+    #[serde(default)]
+    window_title: Option<String>,
+}
+
+/// Records the foreground app and browser tab as far as the settings allow.
+/// Call before the capture window takes focus.
+pub(crate) fn remember(app: &AppHandle) {
+    let (record_source, record_url) = settings::load_settings(app)
+        .map(|settings| (settings.record_capture_source, settings.record_source_url))
+        .unwrap_or((false, false));
+    let source = if record_source || record_url {
+        foreground()
+    } else {
+        None
+    };
+    let url = source
+        .as_ref()
+        .filter(|source| record_url && is_browser(&source.app))
+        .and_then(browser_url);
+    set(&CURRENT_URL, url);
+    set(&CURRENT, source.filter(|_| record_source));
+}
+
+pub(crate) fn current() -> Option<CaptureSource> {
+    CURRENT.lock().ok().and_then(|current| current.clone())
+}
+
+pub(crate) fn current_url() -> Option<String> {
+    CURRENT_URL.lock().ok().and_then(|current| current.clone())
+}
+
+pub(crate) fn clear() {
+    set(&CURRENT, None);
+    set(&CURRENT_URL, None);
+}
+
+/// Called by the browser extension whenever the active tab changes.
+pub(crate) fn report_browser_tab(url: &str) -> Result<(), String> {
+    let url = url.trim();
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err("Only http and https tabs can be reported".to_string());
+    }
+    set(&REPORTED_TAB, Some(url.to_string()));
+    Ok(())
+}
+
+fn set<T>(slot: &Mutex<Option<T>>, value: Option<T>) {
+    if let Ok(mut slot) = slot.lock() {
+        *slot = value;
+    }
+}
+
+fn is_browser(app: &str) -> bool {
+    BROWSERS.contains(&app.to_lowercase().as_str())
+}
+
+/// Prefers the extension's report, which works for every browser it is
+/// installed in.
+fn browser_url(source: &CaptureSource) -> Option<String> {
+    REPORTED_TAB
+        .lock()
+        .ok()
+        .and_then(|reported| reported.clone())
+        .or_else(|| {
+            let output = active_tab_command(&source.app)?.output().ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            match url.as_str() {
+                "" => None,
+                // Address bars hide the scheme.
+                _ if !url.contains("://") => Some(format!("https://{url}")),
+                _ => Some(url),
+            }
+        })
+}
+
+fn foreground() -> Option<CaptureSource> {
+    let output = foreground_command()?.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines().map(str::trim);
+    let app = lines.next().filter(|app| !app.is_empty())?.to_string();
+    let window_title = lines
+        .next()
+        .filter(|title| !title.is_empty())
+        .map(str::to_string);
+    Some(CaptureSource { app, window_title })
+}
+
+#[cfg(target_os = "macos")]
+const MACOS_SCRIPT: &str = r#"tell application "System Events"
+    set frontApp to first application process whose frontmost is true
+    set appName to name of frontApp
+    set windowTitle to ""
+    try
+        set windowTitle to name of front window of frontApp
+    end try
+end tell
+return appName & linefeed & windowTitle"#;
+
+#[cfg(target_os = "macos")]
+fn foreground_command() -> Option<Command> {
+    let mut command = Command::new("osascript");
+    command.args(["-e", MACOS_SCRIPT]);
+    Some(command)
+}
+
+#[cfg(target_os = "windows")]
+const WINDOWS_SCRIPT: &str = r#"Add-Type @"
+using System;
+using System.Runtime.InteropServices;
+using System.Text;
+public static class Foreground {
+    [DllImport("user32.dll")] public static extern IntPtr GetForegroundWindow();
+    [DllImport("user32.dll", CharSet = CharSet.Unicode)]
+    public static extern int GetWindowText(IntPtr window, StringBuilder text, int max);
+    [DllImport("user32.dll")]
+    public static extern uint GetWindowThreadProcessId(IntPtr window, out uint pid);
+}
+"@
+$window = [Foreground]::GetForegroundWindow()
+$title = New-Object System.Text.StringBuilder 512
+[void][Foreground]::GetWindowText($window, $title, 512)
+$processId = 0
+[void][Foreground]::GetWindowThreadProcessId($window, [ref]$processId)
+(Get-Process -Id $processId).ProcessName
+$title.ToString()"#;
+
+#[cfg(target_os = "windows")]
+fn foreground_command() -> Option<Command> {
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", WINDOWS_SCRIPT]);
+    Some(command)
+}
+
+/// `xdotool` prints the window's title and pid; the app is the process name.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn foreground_command() -> Option<Command> {
+    std::env::var_os("DISPLAY")?;
+    let mut command = Command::new("sh");
+    command.args([
+        "-c",
+        "pid=$(xdotool getactivewindow getwindowpid) && cat /proc/$pid/comm \
+         && xdotool getactivewindow getwindowname",
+    ]);
+    Some(command)
+}
+
+#[cfg(target_os = "macos")]
+fn active_tab_command(browser: &str) -> Option<Command> {
+    let script = match browser {
+        "Safari" => "tell application \"Safari\" to return URL of front document".to_string(),
+        // Firefox has no AppleScript dictionary.
+        "Firefox" => return None,
+        chromium => {
+            format!("tell application \"{chromium}\" to return URL of active tab of front window")
+        }
+    };
+    let mut command = Command::new("osascript");
+    command.args(["-e", &script]);
+    Some(command)
+}
+
+/// Reads the first edit field of the foreground window, which Chromium
+/// browsers and Firefox both expose as the address bar.
+#[cfg(target_os = "windows")]
+const WINDOWS_ACTIVE_TAB_SCRIPT: &str = r#"Add-Type -AssemblyName UIAutomationClient, UIAutomationTypes
+Add-Type @"
+using System;
+using System.Runtime.InteropServices;
+public static class Foreground {
+    [DllImport("user32.dll")] public static extern IntPtr GetForegroundWindow();
+}
+"@
+$automation = [System.Windows.Automation.AutomationElement]
+$window = $automation::FromHandle([Foreground]::GetForegroundWindow())
+$edit = New-Object System.Windows.Automation.PropertyCondition($automation::ControlTypeProperty, [System.Windows.Automation.ControlType]::Edit)
+$bar = $window.FindFirst([System.Windows.Automation.TreeScope]::Descendants, $edit)
+$bar.GetCurrentPattern([System.Windows.Automation.ValuePattern]::Pattern).Current.Value"#;
+
+#[cfg(target_os = "windows")]
+fn active_tab_command(_browser: &str) -> Option<Command> {
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", WINDOWS_ACTIVE_TAB_SCRIPT]);
+    Some(command)
+}
+
+/// Only the browser extension can tell on Linux.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn active_tab_command(_browser: &str) -> Option<Command> {
+    None
+}
@@ -0,0 +1,237 @@
+//! `jotin add`, `jotin list` and `jotin search`: add and query notes from a
+//! shell, sharing the app's storage. The app binary runs them instead of
+//! starting when its first argument is one of them.
+
+use std::{
+    fs,
+    io::{self, IsTerminal, Read},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use crate::{
+    backup, load_notes_from_path, lock_notes_file, save_notes_to_path, schema, search::SearchIndex,
+    settings, sort_for_display, vault::Vault, Note, DEFAULT_SEARCH_LIMIT, NOTES_FILE_NAME,
+};
+
+/// Must match `identifier` in `tauri.conf.json`, which names the app data dir.
+const APP_IDENTIFIER: &str = "com.chainyo.jotin";
+const DEFAULT_LIST_LIMIT: usize = 20;
+const SUBCOMMANDS: &[&str] = &["add", "list", "ls", "search", "find", "-h", "--help"];
+
+const USAGE: &str = "\
+Usage:
+  jotin add [TEXT...]             Save a note (reads stdin when TEXT is omitted or -)
+  jotin list [--limit N]          Show the most recent notes
+  jotin search QUERY [--limit N]  Search notes
+  jotin                           Start the app
+
+Options:
+  --json       Print results as JSON
+  -h, --help   Show this help";
+
+enum Command {
+    Add(Option<String>),
+    List,
+    Search(String),
+}
+
+struct Options {
+    command: Command,
+    limit: Option<usize>,
+    json: bool,
+}
+
+/// Runs the subcommand the process was started with. `None` when the first
+/// argument is not one, so the app starts instead: deep links on Windows
+/// and Linux also arrive as arguments.
+pub fn run() -> Option<ExitCode> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !SUBCOMMANDS.contains(&args.first()?.as_str()) {
+        return None;
+    }
+    attach_console();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return Some(ExitCode::SUCCESS);
+    }
+
+    Some(match parse_args(args).and_then(execute) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("jotin: {error}");
+            ExitCode::FAILURE
+        }
+    })
+}
+
+/// Release builds on Windows are GUI programs without a console, so output
+/// goes to the terminal the command was typed in only once attached to it.
+#[cfg(target_os = "windows")]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // Fails when there is no parent console or one is attached already;
+    // either way there is nothing more to do.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_console() {}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut args = args.into_iter();
+    let subcommand = args.next().unwrap_or_default();
+
+    let mut words = Vec::new();
+    let mut limit = None;
+    let mut json = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--limit" | "-n" => {
+                let value = args.next().ok_or("--limit expects a number")?;
+                let value = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid limit \"{value}\""))?;
+                limit = Some(value);
+            }
+            "--" => words.extend(args.by_ref()),
+            _ => words.push(arg),
+        }
+    }
+
+    let command = match subcommand.as_str() {
+        "add" => match words.as_slice() {
+            [] => Command::Add(None),
+            [dash] if dash == "-" => Command::Add(None),
+            _ => Command::Add(Some(words.join(" "))),
+        },
+        "list" | "ls" => Command::List,
+        "search" | "find" => {
+            if words.is_empty() {
+                return Err("search expects a query".to_string());
+            }
+            Command::Search(words.join(" "))
+        }
+        other => return Err(format!("Unknown command \"{other}\"\n\n{USAGE}")),
+    };
+
+    Ok(Options {
+        command,
+        limit,
+        json,
+    })
+}
+
+fn execute(options: Options) -> Result<(), String> {
+    let notes_path = resolve_notes_path()?;
+    let _lock = lock_notes_file(&notes_path)?;
+
+    match options.command {
+        Command::Add(text) => {
+            let text = match text {
+                Some(text) => text,
+                None => read_stdin()?,
+            };
+            let text = text.trim();
+            if text.is_empty() {
+                return Err("Note text cannot be empty".to_string());
+            }
+
+            let note = Note::new(text.to_string());
+            let mut notes = schema::read_upgrading(&notes_path)?;
+            notes.push(note.clone());
+            save_notes_to_path(&notes_path, &notes)?;
+
+            if options.json {
+                print_json(&note)
+            } else {
+                println!("{}", note.id);
+                Ok(())
+            }
+        }
+        Command::List => {
+            let mut notes = load_notes_from_path(&notes_path)?;
+            notes.retain(|note| !note.is_trashed() && !note.archived);
+            sort_for_display(&mut notes);
+            notes.truncate(options.limit.unwrap_or(DEFAULT_LIST_LIMIT));
+
+            if options.json {
+                return print_json(&notes);
+            }
+            for note in &notes {
+                print_line(note, note.title());
+            }
+            Ok(())
+        }
+        Command::Search(query) => {
+            let mut notes = load_notes_from_path(&notes_path)?;
+            // The index is shared with the app, which also searches archived
+            // notes, so they are left out of the results instead.
+            notes.retain(|note| !note.is_trashed());
+            let mut results = SearchIndex::open(&notes_path, &notes)?.search(&query, usize::MAX);
+            results.retain(|result| !result.note.archived);
+            results.truncate(options.limit.unwrap_or(DEFAULT_SEARCH_LIMIT));
+
+            if options.json {
+                return print_json(&results);
+            }
+            for result in &results {
+                print_line(&result.note, &result.snippet);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Mirrors `resolve_notes_path` without a running app: the Tauri app data dir,
+/// the custom data directory chosen in settings, or the vault's sidecar file.
+/// Notes added there get their Markdown file when the app next loads them.
+fn resolve_notes_path() -> Result<PathBuf, String> {
+    let app_data_dir = dirs::data_dir()
+        .ok_or("Failed to resolve app data dir")?
+        .join(APP_IDENTIFIER);
+    let settings =
+        settings::load_settings_from_path(&app_data_dir.join(settings::SETTINGS_FILE_NAME))?;
+    backup::set_retention(settings.backup_retention);
+    if let Some(vault) = Vault::configured(&settings) {
+        return vault.sidecar_path();
+    }
+
+    let data_dir = settings.data_dir.map(PathBuf::from).unwrap_or(app_data_dir);
+    fs::create_dir_all(&data_dir).map_err(|e| format!("Failed to create data directory: {e}"))?;
+
+    Ok(data_dir.join(NOTES_FILE_NAME))
+}
+
+fn read_stdin() -> Result<String, String> {
+    let mut stdin = io::stdin();
+    if stdin.is_terminal() {
+        return Err("Nothing to add: pass TEXT or pipe it on stdin".to_string());
+    }
+
+    let mut text = String::new();
+    stdin
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Failed to read stdin: {e}"))?;
+    Ok(text)
+}
+
+fn print_line(note: &Note, summary: &str) {
+    let date = note.created_at.get(..10).unwrap_or(&note.created_at);
+    let id = note.id.get(..8).unwrap_or(&note.id);
+    let pin = if note.pinned { "*" } else { " " };
+    println!("{id} {date} {pin} {summary}");
+}
+
+fn print_json(value: &impl serde::Serialize) -> Result<(), String> {
+    let payload = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize output: {e}"))?;
+    println!("{payload}");
+    Ok(())
+}
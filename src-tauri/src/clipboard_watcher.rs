@@ -0,0 +1,161 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use tauri::{AppHandle, Manager};
+
+use crate::{append, insert_note, notifications, settings, tray, StorageState};
+
+const POLL_INTERVAL_MS: u64 = 700;
+static WATCHER_ENABLED: AtomicBool = AtomicBool::new(false);
+static WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+static IGNORED_TEXT: Mutex<Option<String>> = Mutex::new(None);
+
+#[tauri::command]
+pub(crate) fn set_clipboard_watcher(app: AppHandle, enabled: bool) -> Result<bool, String> {
+    settings::modify_settings(&app, |settings| {
+        settings.clipboard_watcher_enabled = enabled;
+        Ok(())
+    })?;
+
+    apply(&app, enabled);
+    Ok(enabled)
+}
+
+#[tauri::command]
+pub(crate) fn is_clipboard_watcher_enabled() -> bool {
+    is_enabled()
+}
+
+pub(crate) fn is_enabled() -> bool {
+    WATCHER_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Flips the running watcher and the tray check mark without persisting.
+pub(crate) fn apply(app: &AppHandle, enabled: bool) {
+    WATCHER_ENABLED.store(enabled, Ordering::SeqCst);
+    tray::refresh(app);
+}
+
+/// Marks text that jotin itself put on the clipboard so it is not re-captured.
+pub(crate) fn ignore_text(text: &str) {
+    if let Ok(mut ignored) = IGNORED_TEXT.lock() {
+        *ignored = Some(text.to_string());
+    }
+}
+
+/// Saves whatever text is on the clipboard as a note right away, confirming
+/// with a notification instead of opening a window.
+pub(crate) fn capture_clipboard(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let text = arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .unwrap_or_default();
+        if text.trim().is_empty() {
+            notifications::show(
+                &app,
+                "Nothing to save",
+                "The clipboard has no text",
+                &[],
+                |_, _| {},
+            );
+            return;
+        }
+
+        // The watcher would otherwise save the same text a second time.
+        ignore_text(&text);
+        let state = app.state::<StorageState>();
+        match append::capture_text(&app, &state, &text) {
+            Ok(note) => notifications::show(
+                &app,
+                "Saved clipboard as a note",
+                note.title(),
+                &[],
+                |_, _| {},
+            ),
+            Err(error) => eprintln!("Failed to save clipboard as a note: {error}"),
+        }
+    });
+}
+
+/// Spawns the polling thread once; it idles while the watcher is disabled.
+pub(crate) fn start(app: &AppHandle) {
+    let enabled = settings::load_settings(app)
+        .map(|settings| settings.clipboard_watcher_enabled)
+        .unwrap_or(false);
+    WATCHER_ENABLED.store(enabled, Ordering::SeqCst);
+
+    if WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    thread::spawn(move || watch(app));
+}
+
+/// Polls the clipboard and captures new text once it has been stable for two
+/// consecutive polls. Whatever is on the clipboard when the watcher is turned
+/// on becomes the baseline and is never captured.
+fn watch(app: AppHandle) {
+    let mut clipboard: Option<arboard::Clipboard> = None;
+    let mut baseline: Option<String> = None;
+    let mut pending: Option<String> = None;
+
+    loop {
+        thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+
+        if !is_enabled() {
+            baseline = None;
+            pending = None;
+            continue;
+        }
+
+        if clipboard.is_none() {
+            clipboard = arboard::Clipboard::new()
+                .map_err(|error| eprintln!("Clipboard watcher cannot access clipboard: {error}"))
+                .ok();
+        }
+        let Some(text) = clipboard
+            .as_mut()
+            .and_then(|clipboard| clipboard.get_text().ok())
+        else {
+            continue;
+        };
+
+        if baseline.is_none() {
+            baseline = Some(text);
+            continue;
+        }
+        if baseline.as_ref() == Some(&text) {
+            pending = None;
+            continue;
+        }
+        if pending.as_ref() != Some(&text) {
+            pending = Some(text);
+            continue;
+        }
+
+        baseline = pending.take();
+        if is_ignored(&text) || text.trim().is_empty() {
+            continue;
+        }
+
+        let state = app.state::<StorageState>();
+        if let Err(error) = insert_note(&app, &state, &text) {
+            eprintln!("Clipboard watcher failed to save note: {error}");
+        }
+    }
+}
+
+fn is_ignored(text: &str) -> bool {
+    IGNORED_TEXT
+        .lock()
+        .map(|ignored| ignored.as_deref() == Some(text))
+        .unwrap_or(false)
+}
@@ -0,0 +1,182 @@
+//! Notes that diverged: edited on two machines between syncs in ways the
+//! merge could not reconcile. The note keeps the newer version and carries
+//! the other one (plus the text both started from) until the user picks
+//! what to keep, so nothing is lost and the UI can show a three-way
+//! comparison.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    crypto::Envelope, ensure_writable, find_active_note_mut, history, locks, undo, Note,
+    StorageState, NOTES_CHANGED_EVENT,
+};
+
+/// The version of a note that lost to the one it is stored on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct NoteConflict {
+    text: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// The text both versions were edited from, when known.
+    #[serde(default)]
+    base_text: Option<String>,
+    /// The other version's sealed text when it is locked; `text` is then
+    /// the placeholder.
+    #[serde(default)]
+    locked: Option<Envelope>,
+    detected_at: String,
+}
+
+impl NoteConflict {
+    pub(crate) fn new(other: &Note, base: Option<&Note>) -> Self {
+        Self {
+            text: other.text.clone(),
+            tags: other.tags.clone(),
+            // The base may still hold the text from before the lock.
+            base_text: base
+                .filter(|_| other.locked.is_none())
+                .map(|base| base.text.clone()),
+            locked: other.locked.clone(),
+            detected_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Whether `other` is the version already kept here.
+    pub(crate) fn holds(&self, other: &Note) -> bool {
+        self.text == other.text && self.tags == other.tags && self.locked == other.locked
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct Conflict {
+    note_id: String,
+    title: String,
+    base_text: Option<String>,
+    current_text: String,
+    current_tags: Vec<String>,
+    other_text: String,
+    other_tags: Vec<String>,
+    detected_at: String,
+}
+
+/// What `resolve_conflict` keeps: `"current"`, `"other"`, `"both"` (the
+/// other version becomes a note of its own) or `{ "merged": "<text>" }`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ConflictChoice {
+    Current,
+    Other,
+    Both,
+    Merged(String),
+}
+
+/// Every active note with an unresolved conflict, oldest conflict first.
+#[tauri::command]
+pub(crate) fn list_conflicts(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+) -> Result<Vec<Conflict>, String> {
+    let storage = state.lock(&app)?;
+    let notes = storage.notes()?;
+
+    let mut conflicts: Vec<Conflict> = notes
+        .iter()
+        .filter(|note| !note.is_trashed())
+        .filter_map(|note| {
+            let conflict = note.conflict.as_ref()?;
+            Some(Conflict {
+                note_id: note.id.clone(),
+                title: note.title().to_string(),
+                base_text: conflict.base_text.clone(),
+                current_text: note.text.clone(),
+                current_tags: note.tags.clone(),
+                other_text: conflict.text.clone(),
+                other_tags: conflict.tags.clone(),
+                detected_at: conflict.detected_at.clone(),
+            })
+        })
+        .collect();
+    conflicts.sort_by(|a, b| a.detected_at.cmp(&b.detected_at));
+    Ok(conflicts)
+}
+
+#[tauri::command]
+pub(crate) fn resolve_conflict(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    choice: ConflictChoice,
+) -> Result<Note, String> {
+    if let ConflictChoice::Merged(text) = &choice {
+        if text.trim().is_empty() {
+            return Err("Note text cannot be empty".to_string());
+        }
+    }
+
+    let storage = state.lock(&app)?;
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+    ensure_writable(note)?;
+    let before = note.clone();
+    let conflict = note
+        .conflict
+        .take()
+        .ok_or_else(|| "Note has no conflict to resolve".to_string())?;
+
+    let mut copy = None;
+    match choice {
+        ConflictChoice::Current => {}
+        ConflictChoice::Other => {
+            if conflict.locked.is_none() {
+                locks::ensure_not_locked(note)?;
+            }
+            note.set_text(conflict.text);
+            note.locked = conflict.locked;
+            note.tags = conflict.tags;
+        }
+        ConflictChoice::Both => {
+            locks::ensure_not_locked(note)?;
+            copy = Some(copy_of(note, conflict.text, conflict.tags));
+        }
+        // Sealed as is, like any edit to a locked note; no history.
+        ConflictChoice::Merged(text) if note.locked.is_some() => {
+            locks::save_text(note, text.trim())?
+        }
+        ConflictChoice::Merged(text) => note.set_text(text.trim().to_string()),
+    }
+    if note.text != before.text {
+        if let Err(error) = history::record_version(&app, &before) {
+            eprintln!("Failed to record note history: {error}");
+        }
+    }
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let resolved = note.clone();
+    notes.extend(copy.clone());
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| {
+        index.upsert(&resolved);
+        if let Some(copy) = &copy {
+            index.upsert(copy);
+        }
+    });
+    undo::record("Resolve conflict", vec![before]);
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(resolved)
+}
+
+/// A separate note holding another version of `note`, in the same notebook.
+pub(crate) fn copy_of(note: &Note, text: String, tags: Vec<String>) -> Note {
+    let mut copy = Note::new(text);
+    copy.created_at = note.created_at.clone();
+    copy.updated_at = Some(Utc::now().to_rfc3339());
+    copy.tags = tags;
+    copy.notebook_id = note.notebook_id.clone();
+    copy.attachments = note.attachments.clone();
+    copy
+}
@@ -0,0 +1,334 @@
+//! Unit and currency conversion for the capture window's `/convert 5 km mi`
+//! helper. Units convert offline from the table below. Currencies use the
+//! European Central Bank's daily rates through Frankfurter; they are only
+//! fetched once `currency_conversion_enabled` is on, then cached in the app
+//! data directory and reused for a day, or for longer while offline or in
+//! offline mode.
+
+use std::{collections::HashMap, fs};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{calc, http, resolve_app_data_file, settings, write_file_atomically};
+
+const RATES_FILE_NAME: &str = "exchange-rates.json";
+const RATES_URL: &str = "https://api.frankfurter.app/latest";
+const RATES_BASE: &str = "EUR";
+const RATES_MAX_AGE_HOURS: i64 = 24;
+const UNIT_DECIMALS: usize = 6;
+const CURRENCY_DECIMALS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quantity {
+    Length,
+    Mass,
+    Volume,
+    Area,
+    Time,
+    Speed,
+    Data,
+    Temperature,
+}
+
+/// Aliases, what they measure and how many base units (metre, gram, litre,
+/// square metre, second, metre per second, byte) one of them is.
+/// Temperatures are handled separately; their factor is unused.
+const UNITS: &[(&[&str], Quantity, f64)] = &[
+    (&["mm", "millimeter", "millimetre"], Quantity::Length, 0.001),
+    (&["cm", "centimeter", "centimetre"], Quantity::Length, 0.01),
+    (
+        &["m", "meter", "metre", "meters", "metres"],
+        Quantity::Length,
+        1.0,
+    ),
+    (
+        &["km", "kilometer", "kilometre", "kilometers", "kilometres"],
+        Quantity::Length,
+        1000.0,
+    ),
+    (&["in", "inch", "inches"], Quantity::Length, 0.0254),
+    (&["ft", "foot", "feet"], Quantity::Length, 0.3048),
+    (&["yd", "yard", "yards"], Quantity::Length, 0.9144),
+    (&["mi", "mile", "miles"], Quantity::Length, 1609.344),
+    (&["nmi", "nautical mile"], Quantity::Length, 1852.0),
+    (&["mg", "milligram"], Quantity::Mass, 0.001),
+    (&["g", "gram", "grams"], Quantity::Mass, 1.0),
+    (&["kg", "kilogram", "kilograms"], Quantity::Mass, 1000.0),
+    (&["t", "tonne", "tonnes"], Quantity::Mass, 1_000_000.0),
+    (&["oz", "ounce", "ounces"], Quantity::Mass, 28.349523125),
+    (&["lb", "lbs", "pound", "pounds"], Quantity::Mass, 453.59237),
+    (&["st", "stone"], Quantity::Mass, 6350.29318),
+    (&["ml", "milliliter", "millilitre"], Quantity::Volume, 0.001),
+    (&["cl", "centiliter", "centilitre"], Quantity::Volume, 0.01),
+    (&["dl", "deciliter", "decilitre"], Quantity::Volume, 0.1),
+    (
+        &["l", "liter", "litre", "liters", "litres"],
+        Quantity::Volume,
+        1.0,
+    ),
+    (&["tsp", "teaspoon"], Quantity::Volume, 0.00492892159375),
+    (&["tbsp", "tablespoon"], Quantity::Volume, 0.01478676478125),
+    (
+        &["floz", "fl oz", "fluid ounce"],
+        Quantity::Volume,
+        0.0295735295625,
+    ),
+    (&["cup", "cups"], Quantity::Volume, 0.2365882365),
+    (&["pt", "pint", "pints"], Quantity::Volume, 0.473176473),
+    (&["qt", "quart", "quarts"], Quantity::Volume, 0.946352946),
+    (&["gal", "gallon", "gallons"], Quantity::Volume, 3.785411784),
+    (&["m2", "sqm", "m²"], Quantity::Area, 1.0),
+    (&["km2", "km²"], Quantity::Area, 1_000_000.0),
+    (&["ft2", "sqft", "ft²"], Quantity::Area, 0.09290304),
+    (&["ha", "hectare", "hectares"], Quantity::Area, 10_000.0),
+    (&["acre", "acres"], Quantity::Area, 4046.8564224),
+    (
+        &["ms", "millisecond", "milliseconds"],
+        Quantity::Time,
+        0.001,
+    ),
+    (&["s", "sec", "second", "seconds"], Quantity::Time, 1.0),
+    (&["min", "minute", "minutes"], Quantity::Time, 60.0),
+    (&["h", "hr", "hour", "hours"], Quantity::Time, 3600.0),
+    (&["d", "day", "days"], Quantity::Time, 86_400.0),
+    (&["wk", "week", "weeks"], Quantity::Time, 604_800.0),
+    (&["m/s", "mps"], Quantity::Speed, 1.0),
+    (&["km/h", "kmh", "kph"], Quantity::Speed, 1000.0 / 3600.0),
+    (&["mph"], Quantity::Speed, 0.44704),
+    (&["kn", "knot", "knots"], Quantity::Speed, 1852.0 / 3600.0),
+    (&["b", "byte", "bytes"], Quantity::Data, 1.0),
+    (&["kb", "kilobyte"], Quantity::Data, 1e3),
+    (&["mb", "megabyte"], Quantity::Data, 1e6),
+    (&["gb", "gigabyte"], Quantity::Data, 1e9),
+    (&["tb", "terabyte"], Quantity::Data, 1e12),
+    (&["kib"], Quantity::Data, 1024.0),
+    (&["mib"], Quantity::Data, 1_048_576.0),
+    (&["gib"], Quantity::Data, 1_073_741_824.0),
+    (&["tib"], Quantity::Data, 1_099_511_627_776.0),
+    (&["c", "°c", "celsius"], Quantity::Temperature, 0.0),
+    (&["f", "°f", "fahrenheit"], Quantity::Temperature, 0.0),
+    (&["k", "kelvin"], Quantity::Temperature, 0.0),
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct Conversion {
+    value: f64,
+    from: String,
+    to: String,
+    result: f64,
+    /// E.g. `3.107 mi`, ready to append to the note.
+    formatted: String,
+    /// Day of the exchange rates used, for currency conversions.
+    rates_date: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+struct CachedRates {
+    fetched_at: String,
+    date: String,
+    /// Units of each currency per euro.
+    rates: HashMap<String, f64>,
+}
+
+#[derive(Deserialize)]
+struct RatesResponse {
+    date: String,
+    rates: HashMap<String, f64>,
+}
+
+/// Converts `value` between two units (`km`, `lb`, `°F`, ...) or, when both
+/// are ISO currency codes, between currencies.
+#[tauri::command]
+pub(crate) fn convert(
+    app: AppHandle,
+    value: f64,
+    from: String,
+    to: String,
+) -> Result<Conversion, String> {
+    if !value.is_finite() {
+        return Err("Value must be a finite number".to_string());
+    }
+    let (from, to) = (from.trim().to_string(), to.trim().to_string());
+
+    let (result, decimals, rates_date) = match (unit(&from), unit(&to)) {
+        (Some(from_unit), Some(to_unit)) => (
+            convert_units(value, from_unit, to_unit)?,
+            UNIT_DECIMALS,
+            None,
+        ),
+        _ if is_currency_code(&from) && is_currency_code(&to) => {
+            let (rate, date) = exchange_rate(&app, &from, &to)?;
+            (value * rate, CURRENCY_DECIMALS, Some(date))
+        }
+        (None, _) => return Err(format!("Unknown unit \"{from}\"")),
+        (_, None) => return Err(format!("Unknown unit \"{to}\"")),
+    };
+
+    let label = if rates_date.is_some() {
+        to.to_uppercase()
+    } else {
+        to.clone()
+    };
+    Ok(Conversion {
+        formatted: format!("{} {label}", calc::format_rounded(result, decimals)),
+        value,
+        from,
+        to,
+        result,
+        rates_date,
+    })
+}
+
+type Unit = (&'static [&'static str], Quantity, f64);
+
+fn unit(name: &str) -> Option<&'static Unit> {
+    let name = name.to_lowercase();
+    UNITS
+        .iter()
+        .find(|(aliases, _, _)| aliases.contains(&name.as_str()))
+}
+
+fn convert_units(value: f64, from: &Unit, to: &Unit) -> Result<f64, String> {
+    let (from_aliases, quantity, from_factor) = *from;
+    let (to_aliases, to_quantity, to_factor) = *to;
+    if quantity != to_quantity {
+        return Err(format!(
+            "Cannot convert {} to {}",
+            from_aliases[0], to_aliases[0]
+        ));
+    }
+    if quantity != Quantity::Temperature {
+        return Ok(value * from_factor / to_factor);
+    }
+
+    let kelvin = match from_aliases[0] {
+        "c" => value + 273.15,
+        "f" => (value - 32.0) * 5.0 / 9.0 + 273.15,
+        _ => value,
+    };
+    Ok(match to_aliases[0] {
+        "c" => kelvin - 273.15,
+        "f" => (kelvin - 273.15) * 9.0 / 5.0 + 32.0,
+        _ => kelvin,
+    })
+}
+
+fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// How many `to` one `from` buys, and the day of the rates.
+fn exchange_rate(app: &AppHandle, from: &str, to: &str) -> Result<(f64, String), String> {
+    let settings = settings::load_settings(app)?;
+    if !settings.currency_conversion_enabled {
+        return Err("Turn on currency conversion in settings first".to_string());
+    }
+    let rates = rates(app, settings.offline_mode)?;
+    let per_euro = |code: &str| {
+        let code = code.to_uppercase();
+        if code == RATES_BASE {
+            return Ok(1.0);
+        }
+        rates
+            .rates
+            .get(&code)
+            .copied()
+            .ok_or_else(|| format!("No exchange rate for {code}"))
+    };
+    Ok((per_euro(to)? / per_euro(from)?, rates.date))
+}
+
+/// The cached rates while fresh; otherwise new ones, falling back to the
+/// stale cache when the fetch fails or is not allowed.
+fn rates(app: &AppHandle, offline: bool) -> Result<CachedRates, String> {
+    let path = resolve_app_data_file(app, RATES_FILE_NAME)?;
+    let cached = fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_json::from_str::<CachedRates>(&raw).ok());
+    let fresh = cached.as_ref().is_some_and(|cached| {
+        DateTime::parse_from_rfc3339(&cached.fetched_at).is_ok_and(|at| {
+            Utc::now() - at.with_timezone(&Utc) < Duration::hours(RATES_MAX_AGE_HOURS)
+        })
+    });
+    if fresh || (offline && cached.is_some()) {
+        return cached.ok_or_else(|| "Exchange rates are missing".to_string());
+    }
+    if offline {
+        return Err("Exchange rates cannot be fetched in offline mode".to_string());
+    }
+
+    match fetch_rates() {
+        Ok(rates) => {
+            let payload = serde_json::to_string_pretty(&rates)
+                .map_err(|e| format!("Failed to serialize exchange rates: {e}"))?;
+            if let Err(error) = write_file_atomically(&path, &payload) {
+                eprintln!("Failed to cache exchange rates: {error}");
+            }
+            Ok(rates)
+        }
+        Err(error) => cached.ok_or(error),
+    }
+}
+
+fn fetch_rates() -> Result<CachedRates, String> {
+    let response = http::send_json("GET", &format!("{RATES_URL}?from={RATES_BASE}"), &[], None)?;
+    if !response.is_success() {
+        return Err(format!(
+            "Exchange rates request failed with status {}",
+            response.status
+        ));
+    }
+    let rates: RatesResponse = serde_json::from_value(response.body)
+        .map_err(|e| format!("Unexpected exchange rates response: {e}"))?;
+    Ok(CachedRates {
+        fetched_at: Utc::now().to_rfc3339(),
+        date: rates.date,
+        rates: rates.rates,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn converted(value: f64, from: &str, to: &str) -> String {
+        let result = convert_units(value, unit(from).unwrap(), unit(to).unwrap()).unwrap();
+        calc::format_rounded(result, UNIT_DECIMALS)
+    }
+
+    #[test]
+    fn converts_within_a_quantity() {
+        assert_eq!(converted(5.0, "km", "mi"), "3.106856");
+        assert_eq!(converted(1.0, "lb", "g"), "453.59237");
+        assert_eq!(converted(2.0, "cups", "ml"), "473.176473");
+        assert_eq!(converted(90.0, "min", "h"), "1.5");
+        assert_eq!(converted(1.0, "GiB", "MB"), "1073.741824");
+        assert_eq!(converted(100.0, "km/h", "mph"), "62.137119");
+    }
+
+    #[test]
+    fn converts_temperatures_through_kelvin() {
+        assert_eq!(converted(100.0, "°C", "F"), "212");
+        assert_eq!(converted(32.0, "fahrenheit", "celsius"), "0");
+        assert_eq!(converted(0.0, "K", "C"), "-273.15");
+    }
+
+    #[test]
+    fn refuses_mixed_quantities() {
+        let error = convert_units(1.0, unit("kg").unwrap(), unit("m").unwrap()).unwrap_err();
+        assert_eq!(error, "Cannot convert kg to m");
+        assert!(unit("parsec").is_none());
+    }
+
+    #[test]
+    fn recognizes_currency_codes() {
+        assert!(is_currency_code("usd"));
+        assert!(is_currency_code("EUR"));
+        assert!(!is_currency_code("US"));
+        assert!(!is_currency_code("U$D"));
+    }
+}
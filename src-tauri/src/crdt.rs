@@ -0,0 +1,230 @@
+//! Character-level merging of note text through a CRDT (a `yrs` text
+//! document) kept on each note as `crdt`. Writers only change `text`; the
+//! document catches up with it when sync needs it, by replaying the
+//! character diff between the document's text and the note's. Two devices
+//! that edited the same document merge by exchanging it, so edits never
+//! conflict, even within one line.
+//!
+//! Documents are built deterministically: a note without one is seeded
+//! from the text both devices last synced, and each catch-up writes under a
+//! client id derived from the document and the edit. Two devices doing the
+//! same thing therefore produce the same operations, and a merge never
+//! duplicates text both sides already had.
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+use similar::{DiffTag, TextDiff};
+use yrs::{
+    updates::{decoder::Decode, encoder::Encode},
+    Doc, GetString, ReadTxn, StateVector, Text, Transact, Update,
+};
+
+use crate::Note;
+
+const TEXT_NAME: &str = "text";
+/// Beyond this the diff settles for a longer edit script rather than keep
+/// the sync waiting.
+const DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+/// Client ids stay within what other Yjs implementations accept.
+const CLIENT_ID_MASK: u64 = (1 << 53) - 1;
+
+/// Merges the texts of `ours` and `theirs` through their documents, caught
+/// up with their texts first. Notes without one start from `base`'s, or a
+/// document seeded with its text. Returns the merged text and document, or
+/// `None` when the two documents do not share any history to merge on.
+pub(crate) fn merge_text(base: &Note, ours: &Note, theirs: &Note) -> Option<(String, String)> {
+    let ours = caught_up(ours, base)?;
+    let theirs = caught_up(theirs, base)?;
+    let (our_clients, their_clients) = (state_vector(&ours)?, state_vector(&theirs)?);
+    if !our_clients
+        .iter()
+        .any(|(client, _)| their_clients.contains_client(client))
+    {
+        return None;
+    }
+
+    let merged = Doc::new();
+    let text = merged.get_or_insert_text(TEXT_NAME);
+    let mut txn = merged.transact_mut();
+    for state in [&ours, &theirs] {
+        txn.apply_update(Update::decode_v1(state).ok()?).ok()?;
+    }
+    let merged_text = text.get_string(&txn);
+    let state = txn.encode_state_as_update_v1(&StateVector::default());
+    Some((merged_text, STANDARD.encode(state)))
+}
+
+/// The note's document with its text replayed on top, as an encoded update.
+fn caught_up(note: &Note, base: &Note) -> Option<Vec<u8>> {
+    let state = match note.crdt.as_deref().or(base.crdt.as_deref()) {
+        Some(encoded) => STANDARD.decode(encoded).ok()?,
+        None => seed(&note.id, &base.text),
+    };
+    catch_up(state, &note.text)
+}
+
+/// A document holding `text` as one insertion, the same on every device.
+fn seed(id: &str, text: &str) -> Vec<u8> {
+    let doc = Doc::with_client_id(client_id(&[id.as_bytes(), text.as_bytes()]));
+    let field = doc.get_or_insert_text(TEXT_NAME);
+    let mut txn = doc.transact_mut();
+    field.insert(&mut txn, 0, text);
+    txn.encode_state_as_update_v1(&StateVector::default())
+}
+
+/// Applies the edits that turn the document's text into `text`.
+fn catch_up(state: Vec<u8>, text: &str) -> Option<Vec<u8>> {
+    let current = {
+        let doc = Doc::new();
+        let field = doc.get_or_insert_text(TEXT_NAME);
+        let mut txn = doc.transact_mut();
+        txn.apply_update(Update::decode_v1(&state).ok()?).ok()?;
+        field.get_string(&txn)
+    };
+    if current == text {
+        return Some(state);
+    }
+
+    let edits = edits(&current, text);
+    let client = client_id(&[
+        &state_vector(&state)?.encode_v1(),
+        format!("{edits:?}").as_bytes(),
+    ]);
+    let doc = Doc::with_client_id(client);
+    let field = doc.get_or_insert_text(TEXT_NAME);
+    let mut txn = doc.transact_mut();
+    txn.apply_update(Update::decode_v1(&state).ok()?).ok()?;
+    for edit in &edits {
+        match edit {
+            Edit::Delete { at, len } => field.remove_range(&mut txn, *at, *len),
+            Edit::Insert { at, text } => field.insert(&mut txn, *at, text),
+        }
+    }
+    Some(txn.encode_state_as_update_v1(&StateVector::default()))
+}
+
+#[derive(Debug, PartialEq)]
+enum Edit {
+    /// Byte offsets, which is how the document counts by default.
+    Delete {
+        at: u32,
+        len: u32,
+    },
+    Insert {
+        at: u32,
+        text: String,
+    },
+}
+
+/// Character diff from `old` to `new`, as edits applied in order.
+fn edits(old: &str, new: &str) -> Vec<Edit> {
+    let diff = TextDiff::configure()
+        .timeout(DIFF_TIMEOUT)
+        .diff_chars(old, new);
+    let (old_chars, new_chars) = (diff.old_slices(), diff.new_slices());
+    let byte_len = |chars: &[&str]| chars.iter().map(|c| c.len()).sum::<usize>() as u32;
+
+    let mut edits = Vec::new();
+    let mut at = 0;
+    for op in diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let removed = byte_len(&old_chars[old_range]);
+        let inserted = new_chars[new_range].concat();
+        match tag {
+            DiffTag::Equal => {
+                at += removed;
+                continue;
+            }
+            DiffTag::Delete | DiffTag::Replace => edits.push(Edit::Delete { at, len: removed }),
+            DiffTag::Insert => {}
+        }
+        if !inserted.is_empty() {
+            let len = inserted.len() as u32;
+            edits.push(Edit::Insert { at, text: inserted });
+            at += len;
+        }
+    }
+    edits
+}
+
+fn state_vector(state: &[u8]) -> Option<StateVector> {
+    let doc = Doc::new();
+    let mut txn = doc.transact_mut();
+    txn.apply_update(Update::decode_v1(state).ok()?).ok()?;
+    Some(txn.state_vector())
+}
+
+fn client_id(parts: &[&[u8]]) -> u64 {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+    let digest = hasher.finalize();
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_le_bytes(bytes) & CLIENT_ID_MASK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(text: &str) -> Note {
+        let mut note = Note::new(text.to_string());
+        note.id = "a".to_string();
+        note
+    }
+
+    #[test]
+    fn edits_within_one_line_merge() {
+        let base = note("the quick fox");
+        let ours = note("the quick brown fox");
+        let theirs = note("the very quick fox");
+
+        let (text, _) = merge_text(&base, &ours, &theirs).unwrap();
+        assert_eq!(text, "the very quick brown fox");
+    }
+
+    #[test]
+    fn the_same_edit_on_both_sides_is_kept_once() {
+        let base = note("shopping: milk");
+        let ours = note("shopping: milk, eggs");
+        let (text, state) = merge_text(&base, &ours, &ours).unwrap();
+        assert_eq!(text, "shopping: milk, eggs");
+
+        // Later edits merge on the shared document.
+        let mut merged = note(&text);
+        merged.crdt = Some(state);
+        let mut ours = merged.clone();
+        ours.text = "shopping: oat milk, eggs".to_string();
+        let mut theirs = merged.clone();
+        theirs.text = "shopping: milk, eggs, bread".to_string();
+        let (text, _) = merge_text(&merged, &ours, &theirs).unwrap();
+        assert_eq!(text, "shopping: oat milk, eggs, bread");
+    }
+
+    #[test]
+    fn documents_without_shared_history_do_not_merge() {
+        let mut ours = note("one");
+        ours.crdt = Some(STANDARD.encode(seed("a", "one")));
+        let mut theirs = note("two");
+        theirs.crdt = Some(STANDARD.encode(seed("a", "two")));
+        assert!(merge_text(&note("base"), &ours, &theirs).is_none());
+    }
+
+    #[test]
+    fn edits_count_bytes() {
+        let old = "café au lait";
+        let new = "café crème";
+        let state = catch_up(seed("a", old), new).unwrap();
+        let doc = Doc::new();
+        let field = doc.get_or_insert_text(TEXT_NAME);
+        let mut txn = doc.transact_mut();
+        txn.apply_update(Update::decode_v1(&state).unwrap())
+            .unwrap();
+        assert_eq!(field.get_string(&txn), new);
+    }
+}
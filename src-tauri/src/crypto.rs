@@ -0,0 +1,260 @@
+//! Passphrase-based authenticated encryption, shared by encrypted storage,
+//! encrypted sync and locked notes. Argon2id stretches the passphrase with
+//! a random salt into a key, and XChaCha20-Poly1305 seals each payload under
+//! a fresh random nonce, so a wrong passphrase or a modified payload fails
+//! to open instead of producing garbage.
+//!
+//! A sealed payload is `nonce | ciphertext`. Each is also bound to a
+//! purpose (e.g. `storage`, or a note's id), so a payload cannot be passed off
+//! as another one sealed under the same key.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+const KDF: &str = "argon2id";
+const CIPHER: &str = "xchacha20poly1305";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+/// Refused when reading parameters from a file, so a crafted header cannot
+/// make unlocking take gigabytes of memory or minutes of work.
+const MAX_MEMORY_KIB: u32 = 1 << 20;
+const MAX_ITERATIONS: u32 = 16;
+const MAX_PARALLELISM: u32 = 8;
+
+/// How a key was derived, stored next to what it sealed so the key can be
+/// derived again from the passphrase.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct KeyParams {
+    kdf: String,
+    cipher: String,
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl KeyParams {
+    /// Argon2's recommended cost under a fresh random salt.
+    fn generate() -> Self {
+        let mut salt = [0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::with_cost(
+            salt,
+            Params::DEFAULT_M_COST,
+            Params::DEFAULT_T_COST,
+            Params::DEFAULT_P_COST,
+        )
+    }
+
+    fn with_cost(salt: [u8; SALT_LEN], memory_kib: u32, iterations: u32, parallelism: u32) -> Self {
+        Self {
+            kdf: KDF.to_string(),
+            cipher: CIPHER.to_string(),
+            salt: STANDARD.encode(salt),
+            memory_kib,
+            iterations,
+            parallelism,
+        }
+    }
+}
+
+/// A key derived from a passphrase; derive once, seal many.
+pub(crate) struct Key {
+    params: KeyParams,
+    cipher: XChaCha20Poly1305,
+}
+
+impl Key {
+    /// Derives a key under a fresh random salt.
+    pub(crate) fn generate(passphrase: &str) -> Result<Self, String> {
+        Self::derive(passphrase, &KeyParams::generate())
+    }
+
+    /// Derives the key `params` describe again.
+    pub(crate) fn derive(passphrase: &str, params: &KeyParams) -> Result<Self, String> {
+        if params.kdf != KDF || params.cipher != CIPHER {
+            return Err(format!(
+                "Unsupported encryption: {} with {}",
+                params.kdf, params.cipher
+            ));
+        }
+        if params.memory_kib > MAX_MEMORY_KIB
+            || params.iterations > MAX_ITERATIONS
+            || params.parallelism > MAX_PARALLELISM
+        {
+            return Err("Encryption parameters are out of range".to_string());
+        }
+        let salt = STANDARD
+            .decode(&params.salt)
+            .map_err(|_| "Encryption salt is damaged".to_string())?;
+
+        let cost = Params::new(
+            params.memory_kib,
+            params.iterations,
+            params.parallelism,
+            Some(KEY_LEN),
+        )
+        .map_err(|e| format!("Invalid encryption parameters: {e}"))?;
+        let mut key = Zeroizing::new([0; KEY_LEN]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, cost)
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| format!("Failed to derive encryption key: {e}"))?;
+        Ok(Self {
+            params: params.clone(),
+            cipher: XChaCha20Poly1305::new(key.as_ref().into()),
+        })
+    }
+
+    pub(crate) fn params(&self) -> &KeyParams {
+        &self.params
+    }
+
+    pub(crate) fn seal(&self, plaintext: &[u8], purpose: &str) -> Result<Vec<u8>, String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: purpose.as_bytes(),
+                },
+            )
+            .map_err(|_| "Failed to encrypt".to_string())?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    pub(crate) fn open(&self, sealed: &[u8], purpose: &str) -> Result<Vec<u8>, String> {
+        if sealed.len() < NONCE_LEN {
+            return Err("Encrypted data is damaged".to_string());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: purpose.as_bytes(),
+                },
+            )
+            .map_err(|_| "Wrong passphrase, or the encrypted data was modified".to_string())
+    }
+}
+
+/// A sealed document as it is stored: the key's parameters and the sealed
+/// payload, `{"encryption": {...}, "ciphertext": "<base64>"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct Envelope {
+    pub encryption: KeyParams,
+    ciphertext: String,
+}
+
+impl Envelope {
+    pub(crate) fn seal(key: &Key, plaintext: &[u8], purpose: &str) -> Result<Self, String> {
+        Ok(Self {
+            encryption: key.params().clone(),
+            ciphertext: STANDARD.encode(key.seal(plaintext, purpose)?),
+        })
+    }
+
+    /// The envelope `raw` holds, or `None` when it is not one, e.g. a plain
+    /// notes file.
+    pub(crate) fn parse(raw: &str) -> Option<Self> {
+        if !raw.trim_start().starts_with('{') {
+            return None;
+        }
+        serde_json::from_str(raw).ok()
+    }
+
+    /// Opens with `key`, which must have been derived with this envelope's
+    /// parameters.
+    pub(crate) fn open(&self, key: &Key, purpose: &str) -> Result<Vec<u8>, String> {
+        if key.params() != &self.encryption {
+            return Err("Encrypted data was sealed with a different key".to_string());
+        }
+        let sealed = STANDARD
+            .decode(&self.ciphertext)
+            .map_err(|_| "Encrypted data is damaged".to_string())?;
+        key.open(&sealed, purpose)
+    }
+
+    pub(crate) fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("Failed to serialize encrypted data: {e}"))
+    }
+}
+
+#[cfg(test)]
+impl Key {
+    /// Cheap parameters; the real cost makes debug-build tests crawl.
+    pub(crate) fn for_tests(passphrase: &str, salt: u8) -> Self {
+        Self::derive(
+            passphrase,
+            &KeyParams::with_cost([salt; SALT_LEN], 64, 1, 1),
+        )
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key(passphrase: &str) -> Key {
+        Key::for_tests(passphrase, 7)
+    }
+
+    #[test]
+    fn round_trips() {
+        let key = test_key("correct horse");
+        let sealed = key.seal(b"hello", "notes").unwrap();
+        assert_ne!(&sealed[NONCE_LEN..], b"hello");
+        assert_eq!(key.open(&sealed, "notes").unwrap(), b"hello");
+        // A fresh nonce each time.
+        assert_ne!(sealed, key.seal(b"hello", "notes").unwrap());
+    }
+
+    #[test]
+    fn refuses_the_wrong_key_purpose_or_a_change() {
+        let key = test_key("correct horse");
+        let mut sealed = key.seal(b"hello", "notes").unwrap();
+
+        assert!(test_key("battery staple").open(&sealed, "notes").is_err());
+        assert!(key.open(&sealed, "sync").is_err());
+        assert!(key.open(&sealed[..NONCE_LEN - 1], "notes").is_err());
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(key.open(&sealed, "notes").is_err());
+    }
+
+    #[test]
+    fn derives_the_same_key_from_the_stored_parameters() {
+        let key = test_key("correct horse");
+        let envelope = Envelope::seal(&key, b"hello", "notes").unwrap();
+        let parsed = Envelope::parse(&envelope.to_json().unwrap()).unwrap();
+
+        let again = Key::derive("correct horse", &parsed.encryption).unwrap();
+        assert_eq!(parsed.open(&again, "notes").unwrap(), b"hello");
+        assert!(Envelope::parse(r#"{"version": 2, "notes": []}"#).is_none());
+        assert!(Envelope::parse("[]").is_none());
+    }
+
+    #[test]
+    fn refuses_costly_parameters() {
+        let params = KeyParams::with_cost([7; SALT_LEN], MAX_MEMORY_KIB + 1, 1, 1);
+        assert!(Key::derive("correct horse", &params).is_err());
+        let params = KeyParams {
+            kdf: "pbkdf2".to_string(),
+            ..KeyParams::with_cost([7; SALT_LEN], 64, 1, 1)
+        };
+        assert!(Key::derive("correct horse", &params).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! Imports notes from a CSV file, such as a spreadsheet export or the
+//! `export_tabular` output. A mapping names the column each field comes
+//! from; rows that do not fit it are reported by row number, and a dry run
+//! previews what would be imported without saving.
+
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::{
+    export::CSV_FORMULA_PREFIXES,
+    import::{self, ImportSummary, ImportedNote},
+    normalize_tags, Note, StorageState,
+};
+
+/// Notes returned by a dry run; the counts still cover every row.
+const PREVIEW_NOTES: usize = 20;
+
+/// Header names of the columns each field is read from, matched without
+/// regard to case. Only `text` is required.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct CsvMapping {
+    text: String,
+    created_at: Option<String>,
+    /// Tags separated by commas or semicolons.
+    tags: Option<String>,
+    /// Defaults to `,`.
+    delimiter: Option<char>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct CsvImport {
+    /// `imported` counts the notes a dry run would import; `skipped` lists
+    /// rows that failed validation or duplicate a note.
+    #[serde(flatten)]
+    summary: ImportSummary,
+    dry_run: bool,
+    /// The first notes a dry run would create.
+    preview: Vec<Note>,
+}
+
+/// Columns resolved from the header.
+struct Columns {
+    text: usize,
+    created_at: Option<usize>,
+    tags: Option<usize>,
+}
+
+#[tauri::command]
+pub(crate) fn import_csv(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    path: String,
+    mapping: CsvMapping,
+    dry_run: Option<bool>,
+) -> Result<CsvImport, String> {
+    let path = PathBuf::from(path.trim());
+    let raw =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let delimiter = mapping.delimiter.unwrap_or(',');
+    if matches!(delimiter, '"' | '\n' | '\r') {
+        return Err("The delimiter cannot be a quote or line break".to_string());
+    }
+
+    let mut records = parse_csv(raw.trim_start_matches('\u{feff}'), delimiter)?.into_iter();
+    let header = records
+        .next()
+        .ok_or_else(|| "The CSV file is empty".to_string())?;
+    let columns = resolve_columns(&header, &mapping)?;
+
+    let mut summary = ImportSummary::default();
+    let mut imported = Vec::new();
+    // Row 1 is the header, as a spreadsheet numbers them.
+    for (index, record) in records.enumerate() {
+        let source = format!("Row {}", index + 2);
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        match to_note(&record, &columns) {
+            Ok(note) => imported.push(ImportedNote { source, note }),
+            Err(reason) => summary.skip(source, reason),
+        }
+    }
+
+    if !dry_run.unwrap_or(false) {
+        let summary = import::store_imported(&app, &state, imported, summary)?;
+        return Ok(CsvImport {
+            summary,
+            dry_run: false,
+            preview: Vec::new(),
+        });
+    }
+    let (summary, mut preview) = import::preview_imported(&app, &state, imported, summary)?;
+    preview.truncate(PREVIEW_NOTES);
+    Ok(CsvImport {
+        summary,
+        dry_run: true,
+        preview,
+    })
+}
+
+fn resolve_columns(header: &[String], mapping: &CsvMapping) -> Result<Columns, String> {
+    let column = |name: &str| {
+        let name = name.trim();
+        header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("No column named \"{name}\" in the header"))
+    };
+    let optional = |name: &Option<String>| {
+        name.as_deref()
+            .filter(|name| !name.trim().is_empty())
+            .map(column)
+            .transpose()
+    };
+    Ok(Columns {
+        text: column(&mapping.text)?,
+        created_at: optional(&mapping.created_at)?,
+        tags: optional(&mapping.tags)?,
+    })
+}
+
+fn to_note(record: &[String], columns: &Columns) -> Result<Note, String> {
+    let field = |index: usize| {
+        record
+            .get(index)
+            .map(|field| unguard(field.trim()))
+            .ok_or_else(|| format!("Expected at least {} columns", index + 1))
+    };
+
+    let text = field(columns.text)?;
+    if text.is_empty() {
+        return Err("The text column is empty".to_string());
+    }
+    let mut note = Note::new(text.to_string());
+    if let Some(index) = columns.created_at {
+        let created_at = field(index)?;
+        if !created_at.is_empty() {
+            note.created_at = import::normalize_timestamp(created_at)
+                .ok_or_else(|| format!("Invalid created_at \"{created_at}\""))?;
+        }
+    }
+    if let Some(index) = columns.tags {
+        let tags = field(index)?
+            .split([',', ';'])
+            .map(str::to_string)
+            .collect();
+        note.tags = normalize_tags(tags);
+    }
+    Ok(note)
+}
+
+/// Drops the `'` that `export_tabular` puts before formula-like fields.
+fn unguard(field: &str) -> &str {
+    field
+        .strip_prefix('\'')
+        .filter(|rest| rest.starts_with(CSV_FORMULA_PREFIXES))
+        .unwrap_or(field)
+}
+
+/// RFC 4180 records: fields may be quoted, with `""` for a quote, and
+/// quoted fields may span lines. Accepts `\n` as well as `\r\n`.
+fn parse_csv(raw: &str, delimiter: char) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut quote_line = 1;
+    let mut chars = raw.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                c => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => {
+                quoted = true;
+                quote_line = line;
+            }
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                line += 1;
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("Unclosed quote starting on line {quote_line}"));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|field| field.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_quoted_fields() {
+        let records = parse_csv("text,tags\n\"Say \"\"hi\"\"\",\"a, b\"\n", ',').unwrap();
+        assert_eq!(
+            records,
+            vec![row(&["text", "tags"]), row(&["Say \"hi\"", "a, b"])]
+        );
+    }
+
+    #[test]
+    fn quoted_fields_span_lines() {
+        let records = parse_csv("\"one\ntwo\",x\r\nlast,y", ',').unwrap();
+        assert_eq!(records, vec![row(&["one\ntwo", "x"]), row(&["last", "y"])]);
+    }
+
+    #[test]
+    fn keeps_empty_fields_and_custom_delimiters() {
+        let records = parse_csv("a;;c\n", ';').unwrap();
+        assert_eq!(records, vec![row(&["a", "", "c"])]);
+    }
+
+    #[test]
+    fn reports_where_an_unclosed_quote_starts() {
+        let error = parse_csv("a\nb\n\"never\nclosed", ',').unwrap_err();
+        assert_eq!(error, "Unclosed quote starting on line 3");
+    }
+
+    #[test]
+    fn drops_the_formula_guard() {
+        assert_eq!(unguard("'=SUM(A1)"), "=SUM(A1)");
+        assert_eq!(unguard("'quoted"), "'quoted");
+    }
+}
@@ -0,0 +1,90 @@
+//! One note per day for quick jots, found again by its `daily` flag and the
+//! local date it was created on.
+
+use chrono::{DateTime, Local, NaiveDate};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    append::append_line, ensure_writable, locks, undo, Note, StorageState, NOTES_CHANGED_EVENT,
+};
+
+/// Today's daily note, created with the date as its title if there is none
+/// yet.
+#[tauri::command]
+pub(crate) fn get_or_create_daily_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+) -> Result<Note, String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let (index, created) = find_or_create(&mut notes, Local::now().date_naive());
+    let note = notes[index].clone();
+
+    if created {
+        storage.save_notes(&notes)?;
+        state.update_search_index(|search| search.upsert(&note));
+        let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    }
+    Ok(note)
+}
+
+/// Adds `text` as a new line of today's daily note, creating the note first
+/// if needed.
+#[tauri::command]
+pub(crate) fn append_to_daily_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    text: String,
+) -> Result<Note, String> {
+    append(&app, &state, &text)
+}
+
+pub(crate) fn append(app: &AppHandle, state: &StorageState, text: &str) -> Result<Note, String> {
+    let addition = text.trim();
+    if addition.is_empty() {
+        return Err("Note text cannot be empty".to_string());
+    }
+
+    let storage = state.lock(app)?;
+
+    let mut notes = storage.load_notes()?;
+    let (index, created) = find_or_create(&mut notes, Local::now().date_naive());
+    ensure_writable(&notes[index])?;
+    locks::ensure_not_locked(&notes[index])?;
+    let before = append_line(&mut notes[index], addition);
+    let appended = notes[index].clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|search| search.upsert(&appended));
+    if !created {
+        undo::record("Append to daily note", vec![before]);
+    }
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(appended)
+}
+
+/// Whether `note` is the active daily note for `date`.
+pub(crate) fn is_daily_note_for(note: &Note, date: NaiveDate) -> bool {
+    note.daily && !note.is_trashed() && local_date(&note.created_at) == Some(date)
+}
+
+/// The calendar day a timestamp falls on in the local time zone.
+pub(crate) fn local_date(timestamp: &str) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Local).date_naive())
+}
+
+/// Index of the daily note for `date`, pushing a new one when missing.
+fn find_or_create(notes: &mut Vec<Note>, date: NaiveDate) -> (usize, bool) {
+    if let Some(index) = notes.iter().position(|note| is_daily_note_for(note, date)) {
+        return (index, false);
+    }
+
+    let mut note = Note::new(format!("# {}", date.format("%Y-%m-%d")));
+    note.daily = true;
+    notes.push(note);
+    (notes.len() - 1, true)
+}
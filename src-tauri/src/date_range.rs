@@ -0,0 +1,180 @@
+//! Creation-date bounds for note queries. Each bound is an RFC 3339
+//! timestamp, a `YYYY-MM-DD` day, a `YYYY-MM` month or a shortcut such as
+//! `today`, `last week` or `last 30 days`. Days and periods are in local
+//! time and cover their whole span: `from: "last week"` starts on last
+//! week's Monday and `to: "last week"` ends with its Sunday.
+
+use chrono::{DateTime, Datelike, Duration, Local, Months, NaiveDate, Utc};
+
+use crate::{recurrence, Note};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct DateRange {
+    from: Option<DateTime<Utc>>,
+    /// Exclusive.
+    until: Option<DateTime<Utc>>,
+}
+
+impl DateRange {
+    /// Empty or missing bounds leave that side open.
+    pub(crate) fn parse(from: Option<&str>, to: Option<&str>) -> Result<Self, String> {
+        let bound = |bound: Option<&str>| {
+            bound
+                .map(str::trim)
+                .filter(|bound| !bound.is_empty())
+                .map(parse_bound)
+                .transpose()
+        };
+        let range = Self {
+            from: bound(from)?.map(|(start, _)| start),
+            until: bound(to)?.map(|(_, end)| end),
+        };
+        if let (Some(from), Some(until)) = (range.from, range.until) {
+            if from > until {
+                return Err("The start date is after the end date".to_string());
+            }
+        }
+        Ok(range)
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.from.is_none() && self.until.is_none()
+    }
+
+    /// Whether the note was created within the range.
+    pub(crate) fn contains(&self, note: &Note) -> bool {
+        if self.is_open() {
+            return true;
+        }
+        let Ok(created_at) = DateTime::parse_from_rfc3339(&note.created_at) else {
+            return false;
+        };
+        self.from.is_none_or(|from| created_at >= from)
+            && self.until.is_none_or(|until| created_at < until)
+    }
+}
+
+/// The span a bound stands for, end exclusive. A timestamp spans itself,
+/// so as `to` it excludes that very instant.
+fn parse_bound(bound: &str) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(bound) {
+        let at = at.with_timezone(&Utc);
+        return Ok((at, at));
+    }
+
+    let (start, end) =
+        period(&bound.to_lowercase()).ok_or_else(|| format!("Unrecognized date \"{bound}\""))?;
+    let midnight = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .and_then(recurrence::to_utc)
+            .ok_or_else(|| format!("Date out of range: \"{bound}\""))
+    };
+    Ok((midnight(start)?, midnight(end)?))
+}
+
+/// First day and the day after the last, in local time.
+fn period(bound: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let today = Local::now().date_naive();
+    let day = |date: NaiveDate| Some((date, date.succ_opt()?));
+    let month_of = |date: NaiveDate| {
+        let start = date.with_day(1)?;
+        Some((start, start.checked_add_months(Months::new(1))?))
+    };
+    let week_start = today - Duration::days(i64::from(today.weekday().num_days_from_monday()));
+    let year_start = today.with_ordinal(1)?;
+
+    match bound.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["today"] => day(today),
+        ["yesterday"] => day(today.pred_opt()?),
+        ["this", "week"] => Some((week_start, week_start + Duration::weeks(1))),
+        ["last", "week"] => Some((week_start - Duration::weeks(1), week_start)),
+        ["this", "month"] => month_of(today),
+        ["last", "month"] => month_of(today.checked_sub_months(Months::new(1))?),
+        ["this", "year"] => Some((year_start, year_start.with_year(year_start.year() + 1)?)),
+        ["last", "year"] => Some((year_start.with_year(year_start.year() - 1)?, year_start)),
+        // Counting today, so `last 7 days` is this day and the six before.
+        ["last" | "past", count, unit @ ("days" | "day" | "weeks" | "week")] => {
+            let count: i64 = count.parse().ok().filter(|count| *count > 0)?;
+            let days = if unit.starts_with("week") {
+                count.checked_mul(7)?
+            } else {
+                count
+            };
+            let end = today.succ_opt()?;
+            Some((end.checked_sub_signed(Duration::try_days(days)?)?, end))
+        }
+        [date] => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(date) => day(date),
+            Err(_) => month_of(NaiveDate::parse_from_str(&format!("{date}-01"), "%Y-%m-%d").ok()?),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn created(at: &str) -> Note {
+        let mut note = Note::new("text".to_string());
+        note.created_at = at.to_string();
+        note
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn timestamps_bound_the_range() {
+        let range =
+            DateRange::parse(Some("2025-06-01T00:00:00Z"), Some("2025-07-01T00:00:00Z")).unwrap();
+        assert!(range.contains(&created("2025-06-01T00:00:00+00:00")));
+        assert!(range.contains(&created("2025-06-30T23:59:59+00:00")));
+        assert!(!range.contains(&created("2025-07-01T00:00:00+00:00")));
+        assert!(!range.contains(&created("2025-05-31T23:59:59+00:00")));
+    }
+
+    #[test]
+    fn missing_bounds_stay_open() {
+        let range = DateRange::parse(None, Some(" ")).unwrap();
+        assert!(range.is_open());
+        assert!(range.contains(&created("not a date")));
+
+        let from = DateRange::parse(Some("2025-06-01T00:00:00Z"), None).unwrap();
+        assert!(from.contains(&created("2030-01-01T00:00:00+00:00")));
+    }
+
+    #[test]
+    fn refuses_reversed_and_unknown_bounds() {
+        assert!(
+            DateRange::parse(Some("2025-07-01T00:00:00Z"), Some("2025-06-01T00:00:00Z")).is_err()
+        );
+        assert!(DateRange::parse(Some("someday"), None).is_err());
+        assert!(DateRange::parse(Some("last 0 days"), None).is_err());
+    }
+
+    #[test]
+    fn days_and_months_cover_their_span() {
+        assert_eq!(
+            period("2025-06-12"),
+            Some((date("2025-06-12"), date("2025-06-13")))
+        );
+        assert_eq!(
+            period("2025-12"),
+            Some((date("2025-12-01"), date("2026-01-01")))
+        );
+    }
+
+    #[test]
+    fn shortcuts_end_with_today() {
+        let tomorrow = Local::now().date_naive().succ_opt().unwrap();
+        let (start, end) = period("last 7 days").unwrap();
+        assert_eq!(end, tomorrow);
+        assert_eq!(end - start, Duration::days(7));
+
+        let (start, end) = period("this week").unwrap();
+        assert_eq!(start.weekday(), chrono::Weekday::Mon);
+        assert_eq!(end - start, Duration::weeks(1));
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Url};
+
+use crate::{show_capture_window, show_main_window, CAPTURE_WINDOW_LABEL, MAIN_WINDOW_LABEL};
+
+const URL_SCHEME: &str = "jotin";
+const DEEP_LINK_EVENT: &str = "deep-link";
+
+/// The last link received before its window's frontend was ready to listen.
+static PENDING_DEEP_LINK: Mutex<Option<DeepLink>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum DeepLink {
+    /// `jotin://new?text=...`
+    NewNote { text: String },
+    /// `jotin://note/<id>`
+    OpenNote { id: String },
+    /// `jotin://search/<id>`, a saved search
+    OpenSavedSearch { id: String },
+}
+
+/// Lets a freshly loaded window pick up the link that launched the app.
+#[tauri::command]
+pub(crate) fn take_pending_deep_link() -> Option<DeepLink> {
+    PENDING_DEEP_LINK.lock().ok()?.take()
+}
+
+/// Handles links passed on the command line, which is how Windows and Linux
+/// hand a URL to a cold-launched app.
+pub(crate) fn handle_launch_args(app: &AppHandle) {
+    let urls = std::env::args()
+        .skip(1)
+        .filter_map(|arg| Url::parse(&arg).ok());
+    for url in urls {
+        handle_url(app, &url);
+    }
+}
+
+pub(crate) fn handle_url(app: &AppHandle, url: &Url) {
+    let Some(link) = parse_deep_link(url) else {
+        if url.scheme() == URL_SCHEME {
+            eprintln!("Ignoring unsupported deep link: {url}");
+        }
+        return;
+    };
+
+    open(app, link);
+}
+
+/// Shows a note in the main window, as `jotin://note/<id>` would.
+pub(crate) fn open_note(app: &AppHandle, id: &str) {
+    open(app, DeepLink::OpenNote { id: id.to_string() });
+}
+
+/// Shows a saved search's results in the main window.
+pub(crate) fn open_saved_search(app: &AppHandle, id: &str) {
+    open(app, DeepLink::OpenSavedSearch { id: id.to_string() });
+}
+
+fn open(app: &AppHandle, link: DeepLink) {
+    if let Ok(mut pending) = PENDING_DEEP_LINK.lock() {
+        *pending = Some(link.clone());
+    }
+
+    match &link {
+        DeepLink::NewNote { .. } => {
+            if let Err(error) = show_capture_window(app) {
+                eprintln!("Failed to open quick capture from deep link: {error}");
+                return;
+            }
+            let _ = app.emit_to(CAPTURE_WINDOW_LABEL, DEEP_LINK_EVENT, &link);
+        }
+        DeepLink::OpenNote { .. } | DeepLink::OpenSavedSearch { .. } => {
+            show_main_window(app);
+            let _ = app.emit_to(MAIN_WINDOW_LABEL, DEEP_LINK_EVENT, &link);
+        }
+    }
+}
+
+fn parse_deep_link(url: &Url) -> Option<DeepLink> {
+    if url.scheme() != URL_SCHEME {
+        return None;
+    }
+
+    match url.host_str()? {
+        "new" => {
+            let text = url
+                .query_pairs()
+                .find(|(key, _)| key == "text")
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default();
+            Some(DeepLink::NewNote { text })
+        }
+        host @ ("note" | "search") => {
+            let id = url.path().trim_matches('/');
+            if id.is_empty() || id.contains('/') {
+                return None;
+            }
+            let id = id.to_string();
+            Some(if host == "note" {
+                DeepLink::OpenNote { id }
+            } else {
+                DeepLink::OpenSavedSearch { id }
+            })
+        }
+        _ => None,
+    }
+}
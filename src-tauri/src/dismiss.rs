@@ -0,0 +1,108 @@
+//! When the quick-capture window is allowed to go away: on focus loss, on
+//! Esc, or only when explicitly closed. Enforced here rather than in the
+//! webview so every close path follows the same rules.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    hide_capture_window,
+    placement::{self, CaptureConfig},
+    settings, CAPTURE_WINDOW_LABEL,
+};
+
+/// Focus bounces while the window is being shown and focused; losing it this
+/// soon after does not count as the user clicking away.
+const REVEAL_GRACE: Duration = Duration::from_millis(300);
+static PINNED: AtomicBool = AtomicBool::new(false);
+static REVEALED_AT: Mutex<Option<Instant>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CaptureDismiss {
+    /// Clicking another window hides capture, and so does Esc.
+    FocusLoss,
+    /// Only Esc (or saving) hides capture.
+    #[default]
+    Escape,
+    /// Capture stays open, even after saving, until explicitly closed.
+    Never,
+}
+
+/// Why the frontend asks to close the capture window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CloseReason {
+    /// A close button, or anything else the user aimed at closing.
+    #[default]
+    Explicit,
+    /// The note was saved.
+    Submitted,
+    Escape,
+}
+
+/// Keeps the capture window open and above other windows until unpinned,
+/// whatever the dismiss setting says. Not persisted.
+#[tauri::command]
+pub(crate) fn pin_capture_window(app: AppHandle, pinned: bool) -> CaptureConfig {
+    PINNED.store(pinned, Ordering::SeqCst);
+    if let Some(window) = app.get_webview_window(CAPTURE_WINDOW_LABEL) {
+        let always_on_top = settings::load_settings(&app)
+            .map(|settings| settings.capture_always_on_top)
+            .unwrap_or(true);
+        let _ = window.set_always_on_top(pinned || always_on_top);
+    }
+    placement::capture_config(&app)
+}
+
+pub(crate) fn is_pinned() -> bool {
+    PINNED.load(Ordering::SeqCst)
+}
+
+/// The dismiss behavior in effect, taking a pin into account.
+pub(crate) fn effective_dismiss(app: &AppHandle) -> CaptureDismiss {
+    if is_pinned() {
+        return CaptureDismiss::Never;
+    }
+    settings::load_settings(app)
+        .map(|settings| settings.capture_dismiss)
+        .unwrap_or_default()
+}
+
+/// Whether a close request for `reason` should hide the window.
+pub(crate) fn allows_close(app: &AppHandle, reason: CloseReason) -> bool {
+    reason == CloseReason::Explicit || effective_dismiss(app) != CaptureDismiss::Never
+}
+
+/// Starts the grace period in which focus loss is ignored.
+pub(crate) fn note_revealed() {
+    if let Ok(mut revealed_at) = REVEALED_AT.lock() {
+        *revealed_at = Some(Instant::now());
+    }
+}
+
+/// Runs when the capture window loses focus.
+pub(crate) fn handle_focus_lost(app: &AppHandle) {
+    if effective_dismiss(app) != CaptureDismiss::FocusLoss {
+        return;
+    }
+    let settling = REVEALED_AT
+        .lock()
+        .map(|revealed_at| revealed_at.is_some_and(|at| at.elapsed() < REVEAL_GRACE))
+        .unwrap_or(false);
+    if settling {
+        return;
+    }
+
+    if let Err(error) = hide_capture_window(app) {
+        eprintln!("{error}");
+    }
+}
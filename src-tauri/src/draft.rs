@@ -0,0 +1,75 @@
+//! What was typed in quick capture but not saved yet, kept on disk so closing
+//! the window by accident (or quitting the app) does not lose it.
+
+use std::{fs, path::PathBuf, sync::Mutex};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{encryption, resolve_app_data_file, write_file_atomically};
+
+const DRAFT_FILE_NAME: &str = "capture_draft.json";
+/// Keystroke saves can arrive concurrently; they share one temp file.
+static DRAFT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+struct CaptureDraft {
+    text: String,
+    saved_at: String,
+}
+
+/// Stores the capture input as typed; blank text clears the draft, e.g. once
+/// the note was saved.
+#[tauri::command]
+pub(crate) fn save_capture_draft(app: AppHandle, text: String) -> Result<(), String> {
+    let path = draft_file(&app)?;
+    let _guard = DRAFT_LOCK
+        .lock()
+        .map_err(|_| "Draft lock was poisoned".to_string())?;
+
+    if text.trim().is_empty() {
+        return match fs::remove_file(&path) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to clear capture draft: {error}"))
+            }
+            _ => Ok(()),
+        };
+    }
+
+    // It would have to be written in the clear.
+    if encryption::is_locked(&app) {
+        return Err(encryption::STORAGE_LOCKED.to_string());
+    }
+    let draft = CaptureDraft {
+        text,
+        saved_at: Utc::now().to_rfc3339(),
+    };
+    let payload = serde_json::to_string(&draft)
+        .map_err(|e| format!("Failed to serialize capture draft: {e}"))?;
+    write_file_atomically(&path, &encryption::seal(payload)?)
+}
+
+/// The unsaved capture text, if there is any.
+#[tauri::command]
+pub(crate) fn load_capture_draft(app: AppHandle) -> Result<Option<String>, String> {
+    let path = draft_file(&app)?;
+    let _guard = DRAFT_LOCK
+        .lock()
+        .map_err(|_| "Draft lock was poisoned".to_string())?;
+
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read capture draft: {e}"))?;
+    let raw = encryption::open(raw)?;
+    let draft = serde_json::from_str::<CaptureDraft>(&raw)
+        .map_err(|e| format!("Failed to parse capture draft: {e}"))?;
+    Ok(Some(draft.text).filter(|text| !text.trim().is_empty()))
+}
+
+pub(crate) fn draft_file(app: &AppHandle) -> Result<PathBuf, String> {
+    resolve_app_data_file(app, DRAFT_FILE_NAME)
+}
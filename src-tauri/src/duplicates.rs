@@ -0,0 +1,277 @@
+//! Duplicate detection for notes that were captured more than once, e.g. the
+//! same clipboard contents saved twice with a small edit in between.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    ensure_writable, history, import::content_hash, locks, normalize_tags, undo, Note,
+    StorageState, NOTES_CHANGED_EVENT,
+};
+
+/// Share of distinct words two notes need in common to count as near
+/// duplicates when no threshold is given.
+const DEFAULT_SIMILARITY: f64 = 0.8;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct DuplicateGroup {
+    /// Oldest first, so the first note is the one a merge keeps.
+    notes: Vec<Note>,
+    /// Every note has the same text up to whitespace.
+    exact: bool,
+    /// Lowest word-overlap score that joined the group, 1.0 for exact groups.
+    similarity: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MergeStrategy {
+    /// Texts in creation order, each distinct text once.
+    #[default]
+    Concatenate,
+    KeepLongest,
+    /// Text of the most recently changed note.
+    KeepNewest,
+}
+
+/// Groups active notes with identical text (ignoring whitespace) or at
+/// least `threshold` word overlap, largest groups first.
+#[tauri::command]
+pub(crate) fn find_duplicate_notes(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    threshold: Option<f64>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    let threshold = threshold.unwrap_or(DEFAULT_SIMILARITY);
+    if !(threshold > 0.0 && threshold <= 1.0) {
+        return Err("Similarity threshold must be between 0 and 1".to_string());
+    }
+
+    let storage = state.lock(&app)?;
+    let notes = storage.notes()?;
+
+    let mut buckets: Vec<Vec<&Note>> = Vec::new();
+    let mut bucket_by_hash = HashMap::new();
+    for note in notes.iter().filter(|note| !note.is_trashed()) {
+        let bucket = *bucket_by_hash
+            .entry(content_hash(&note.text))
+            .or_insert_with(|| {
+                buckets.push(Vec::new());
+                buckets.len() - 1
+            });
+        buckets[bucket].push(note);
+    }
+
+    // Compare one note per bucket, smallest word sets first: Jaccard
+    // similarity cannot exceed the ratio of the set sizes, so the inner loop
+    // stops once the sizes are too far apart.
+    let mut words: Vec<(usize, HashSet<String>)> = buckets
+        .iter()
+        .enumerate()
+        .map(|(bucket, notes)| (bucket, word_set(&notes[0].text)))
+        .filter(|(_, words)| !words.is_empty())
+        .collect();
+    words.sort_by_key(|(_, words)| words.len());
+
+    let mut groups = DisjointSets::new(buckets.len());
+    for (offset, (left, left_words)) in words.iter().enumerate() {
+        for (right, right_words) in &words[offset + 1..] {
+            if (left_words.len() as f64) < threshold * right_words.len() as f64 {
+                break;
+            }
+            let shared = left_words.intersection(right_words).count();
+            let similarity = shared as f64 / (left_words.len() + right_words.len() - shared) as f64;
+            if similarity >= threshold {
+                groups.union(*left, *right, similarity);
+            }
+        }
+    }
+
+    let mut merged: HashMap<usize, (Vec<&Note>, usize)> = HashMap::new();
+    for (bucket, notes) in buckets.iter().enumerate() {
+        let (group, bucket_count) = merged.entry(groups.find(bucket)).or_default();
+        group.extend(notes);
+        *bucket_count += 1;
+    }
+
+    let mut duplicates: Vec<DuplicateGroup> = merged
+        .into_iter()
+        .filter(|(_, (notes, _))| notes.len() > 1)
+        .map(|(root, (mut notes, bucket_count))| {
+            notes.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+            DuplicateGroup {
+                notes: notes.into_iter().cloned().collect(),
+                exact: bucket_count == 1,
+                similarity: groups.similarity[root],
+            }
+        })
+        .collect();
+    duplicates.sort_by(|a, b| {
+        b.notes
+            .len()
+            .cmp(&a.notes.len())
+            .then_with(|| a.notes[0].created_at.cmp(&b.notes[0].created_at))
+    });
+    Ok(duplicates)
+}
+
+/// Folds the given notes into the oldest one, which keeps its id and
+/// `created_at`. Tags and attachments are combined; the other notes move to
+/// the trash, and the whole merge can be undone.
+#[tauri::command]
+pub(crate) fn merge_notes(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    ids: Vec<String>,
+    strategy: Option<MergeStrategy>,
+) -> Result<Note, String> {
+    let mut unique_ids: Vec<String> = Vec::new();
+    for id in ids {
+        if !unique_ids.contains(&id) {
+            unique_ids.push(id);
+        }
+    }
+    if unique_ids.len() < 2 {
+        return Err("Select at least two notes to merge".to_string());
+    }
+
+    let storage = state.lock(&app)?;
+    let mut notes = storage.load_notes()?;
+
+    let mut sources: Vec<Note> = unique_ids
+        .iter()
+        .map(|id| {
+            notes
+                .iter()
+                .find(|note| &note.id == id && !note.is_trashed())
+                .cloned()
+                .ok_or_else(|| "Note not found".to_string())
+        })
+        .collect::<Result<_, _>>()?;
+    sources.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    for source in &sources {
+        ensure_writable(source)?;
+        locks::ensure_not_locked(source)?;
+    }
+
+    let text = merged_text(&sources, strategy.unwrap_or_default());
+    let now = Utc::now().to_rfc3339();
+    let survivor_id = sources[0].id.clone();
+    let mut merged = None;
+
+    for note in notes.iter_mut() {
+        if note.id == survivor_id {
+            if note.text != text {
+                if let Err(error) = history::record_version(&app, note) {
+                    eprintln!("Failed to record note history: {error}");
+                }
+                note.set_text(text.clone());
+            }
+            note.tags = normalize_tags(
+                sources
+                    .iter()
+                    .flat_map(|source| source.tags.iter().cloned())
+                    .collect(),
+            );
+            note.pinned = sources.iter().any(|source| source.pinned);
+            if note.color.is_none() {
+                note.color = sources.iter().find_map(|source| source.color.clone());
+            }
+            for source in &sources[1..] {
+                note.attachments.extend(source.attachments.iter().cloned());
+            }
+            note.updated_at = Some(now.clone());
+            merged = Some(note.clone());
+        } else if unique_ids.contains(&note.id) {
+            note.deleted_at = Some(now.clone());
+        }
+    }
+    let merged = merged.ok_or_else(|| "Note not found".to_string())?;
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| {
+        index.upsert(&merged);
+        for source in &sources[1..] {
+            index.remove(&source.id);
+        }
+    });
+    undo::record("Merge notes", sources);
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(merged)
+}
+
+/// `sources` are sorted oldest first.
+fn merged_text(sources: &[Note], strategy: MergeStrategy) -> String {
+    match strategy {
+        MergeStrategy::Concatenate => {
+            let mut seen = HashSet::new();
+            sources
+                .iter()
+                .filter(|note| seen.insert(content_hash(&note.text)))
+                .map(|note| note.text.trim())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        }
+        MergeStrategy::KeepLongest => sources
+            .iter()
+            .max_by_key(|note| note.text.chars().count())
+            .map(|note| note.text.clone())
+            .unwrap_or_default(),
+        MergeStrategy::KeepNewest => sources
+            .iter()
+            .max_by_key(|note| note.revised_at())
+            .map(|note| note.text.clone())
+            .unwrap_or_default(),
+    }
+}
+
+/// Lowercased words with surrounding punctuation removed.
+fn word_set(text: &str) -> HashSet<String> {
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Union-find over hash buckets, tracking the weakest link in each set.
+struct DisjointSets {
+    parent: Vec<usize>,
+    similarity: Vec<f64>,
+}
+
+impl DisjointSets {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            similarity: vec![1.0; len],
+        }
+    }
+
+    fn find(&mut self, item: usize) -> usize {
+        let mut root = item;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut current = item;
+        while self.parent[current] != root {
+            current = std::mem::replace(&mut self.parent[current], root);
+        }
+        root
+    }
+
+    fn union(&mut self, left: usize, right: usize, similarity: f64) {
+        let left = self.find(left);
+        let right = self.find(right);
+        let weakest = self.similarity[left]
+            .min(self.similarity[right])
+            .min(similarity);
+        self.parent[right] = left;
+        self.similarity[left] = weakest;
+    }
+}
@@ -0,0 +1,191 @@
+//! End-to-end encryption for sync, for backends that keep notes on someone
+//! else's server: S3 always, WebDAV with `sync_encrypted`. Each note is
+//! sealed on its own (see `crypto`) under a key derived from the sync
+//! passphrase and bound to its id, and the remote document holds nothing but
+//! those blobs keyed by note id (random UUIDs) and the key's parameters: no
+//! titles, tags, dates or notebook names. The server still sees how many
+//! notes there are and roughly how long each one is.
+//!
+//! ```json
+//! { "format": "jotin-e2e-1", "encryption": { ... }, "notes": { "<id>": "<base64 blob>" } }
+//! ```
+//!
+//! Notes that did not change keep their blob, so a sync only re-encrypts
+//! what it has to.
+
+use std::collections::{BTreeMap, HashMap};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::{
+    crypto::{Key, KeyParams},
+    Note,
+};
+
+const FORMAT: &str = "jotin-e2e-1";
+/// How every encrypted document starts, which tells it from a plain notes
+/// file without parsing it.
+const FORMAT_PREFIX: &[u8] = b"{\"format\":\"jotin-e2e-";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+struct EncryptedNotes {
+    format: String,
+    encryption: KeyParams,
+    notes: BTreeMap<String, String>,
+}
+
+pub(crate) fn is_encrypted(raw: &[u8]) -> bool {
+    raw.starts_with(FORMAT_PREFIX)
+}
+
+/// Encodes and decodes one remote's notes, keeping the derived key and the
+/// blobs it has seen for the rest of the sync.
+pub(crate) struct Codec {
+    passphrase: Zeroizing<String>,
+    key: Option<Key>,
+    /// Plaintext hash and blob of every note decoded or encoded so far, by id.
+    blobs: HashMap<String, ([u8; 32], String)>,
+}
+
+impl Codec {
+    pub(crate) fn new(passphrase: Zeroizing<String>) -> Self {
+        Self {
+            passphrase,
+            key: None,
+            blobs: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn decode(&mut self, raw: &[u8]) -> Result<Vec<Note>, String> {
+        let document: EncryptedNotes = serde_json::from_slice(raw)
+            .map_err(|e| format!("Failed to parse encrypted remote notes: {e}"))?;
+        if document.format != FORMAT {
+            return Err(format!(
+                "Remote notes use an unknown format: {}",
+                document.format
+            ));
+        }
+        let key = match self.key.take() {
+            Some(key) if *key.params() == document.encryption => key,
+            _ => {
+                // Blobs sealed under another key cannot go into this document.
+                self.blobs.clear();
+                Key::derive(&self.passphrase, &document.encryption)?
+            }
+        };
+
+        let mut notes = Vec::with_capacity(document.notes.len());
+        for (id, blob) in document.notes {
+            let sealed = STANDARD
+                .decode(&blob)
+                .map_err(|_| format!("Remote note {id} is damaged"))?;
+            let plaintext = key.open(&sealed, &id)?;
+            let note: Note = serde_json::from_slice(&plaintext)
+                .map_err(|e| format!("Failed to parse remote note {id}: {e}"))?;
+            self.blobs
+                .insert(id, (Sha256::digest(&plaintext).into(), blob));
+            notes.push(note);
+        }
+        self.key = Some(key);
+        Ok(notes)
+    }
+
+    pub(crate) fn encode(&mut self, notes: &[Note]) -> Result<Vec<u8>, String> {
+        let key = match self.key.take() {
+            Some(key) => key,
+            None => Key::generate(&self.passphrase)?,
+        };
+
+        let mut blobs = BTreeMap::new();
+        for note in notes {
+            let plaintext = Zeroizing::new(
+                serde_json::to_vec(note)
+                    .map_err(|e| format!("Failed to serialize note {}: {e}", note.id))?,
+            );
+            let hash: [u8; 32] = Sha256::digest(plaintext.as_slice()).into();
+            let blob = match self.blobs.get(&note.id) {
+                Some((seen, blob)) if *seen == hash => blob.clone(),
+                _ => {
+                    let blob = STANDARD.encode(key.seal(&plaintext, &note.id)?);
+                    self.blobs.insert(note.id.clone(), (hash, blob.clone()));
+                    blob
+                }
+            };
+            blobs.insert(note.id.clone(), blob);
+        }
+
+        let document = EncryptedNotes {
+            format: FORMAT.to_string(),
+            encryption: key.params().clone(),
+            notes: blobs,
+        };
+        self.key = Some(key);
+        serde_json::to_vec(&document).map_err(|e| format!("Failed to serialize remote notes: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codec(passphrase: &str, salt: u8) -> Codec {
+        Codec {
+            passphrase: Zeroizing::new(passphrase.to_string()),
+            key: Some(Key::for_tests(passphrase, salt)),
+            blobs: HashMap::new(),
+        }
+    }
+
+    fn note(id: &str, text: &str) -> Note {
+        let mut note = Note::new(text.to_string());
+        note.id = id.to_string();
+        note.tags = vec!["private".to_string()];
+        note
+    }
+
+    #[test]
+    fn another_device_decodes_with_the_passphrase() {
+        let encoded = codec("correct horse", 1)
+            .encode(&[note("a", "secret text")])
+            .unwrap();
+        assert!(is_encrypted(&encoded));
+        let raw = String::from_utf8_lossy(&encoded);
+        assert!(!raw.contains("secret") && !raw.contains("private"));
+
+        let notes = codec("correct horse", 2).decode(&encoded).unwrap();
+        assert_eq!(notes[0].text, "secret text");
+        assert!(codec("battery staple", 2).decode(&encoded).is_err());
+    }
+
+    #[test]
+    fn reencrypts_only_changed_notes() {
+        let mut device = codec("correct horse", 1);
+        let (a, mut b) = (note("a", "one"), note("b", "two"));
+        let first = device.encode(&[a.clone(), b.clone()]).unwrap();
+        device.decode(&first).unwrap();
+        b.text = "two, edited".to_string();
+        let second = device.encode(&[a, b]).unwrap();
+
+        let blobs = |raw: &[u8]| serde_json::from_slice::<EncryptedNotes>(raw).unwrap().notes;
+        let (first, second) = (blobs(&first), blobs(&second));
+        assert_eq!(first["a"], second["a"]);
+        assert_ne!(first["b"], second["b"]);
+    }
+
+    #[test]
+    fn refuses_a_blob_moved_to_another_id() {
+        let encoded = codec("correct horse", 1)
+            .encode(&[note("a", "one"), note("b", "two")])
+            .unwrap();
+        let mut document: EncryptedNotes = serde_json::from_slice(&encoded).unwrap();
+        let a = document.notes["a"].clone();
+        document.notes.insert("b".to_string(), a);
+
+        let swapped = serde_json::to_vec(&document).unwrap();
+        assert!(codec("correct horse", 1).decode(&swapped).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+//! Emails a note through the user's SMTP server, for sending a capture to
+//! oneself or a colleague. The message has a plain-text part and an HTML
+//! part rendered from the Markdown. `curl` does the SMTP, as it does HTTP
+//! elsewhere; the password lives in the system keychain, not in settings.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+use crate::{
+    http, markdown,
+    settings::{self, Secret, Settings},
+    Note, StorageState,
+};
+
+pub(crate) const DEFAULT_SMTP_PORT: u16 = 587;
+/// Port of SMTP over TLS from the first byte; other ports use STARTTLS.
+const IMPLICIT_TLS_PORT: u16 = 465;
+const REQUEST_TIMEOUT_SECS: u32 = 60;
+const MAIL_PROTOCOLS: &str = "=smtp,smtps";
+/// Base64 lines in a MIME body must stay under 78 characters.
+const BASE64_LINE_CHARS: usize = 76;
+
+/// Sends the note to `to`, one address or several separated by commas.
+#[tauri::command]
+pub(crate) fn email_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    to: String,
+) -> Result<(), String> {
+    let note = {
+        let storage = state.lock(&app)?;
+        let notes = storage.notes()?;
+        notes
+            .iter()
+            .find(|note| note.id == id && !note.is_trashed())
+            .cloned()
+            .ok_or_else(|| "Note not found".to_string())?
+    };
+
+    let recipients: Vec<&str> = to
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .collect();
+    if recipients.is_empty() {
+        return Err("Enter at least one recipient".to_string());
+    }
+    if let Some(invalid) = recipients.iter().find(|address| !is_address(address)) {
+        return Err(format!("\"{invalid}\" is not an email address"));
+    }
+
+    let settings = settings::load_settings(&app)?;
+    let host = settings
+        .smtp_host
+        .as_deref()
+        .ok_or_else(|| "Set an SMTP server in settings first".to_string())?;
+    let from = settings
+        .smtp_from
+        .as_deref()
+        .or(settings.smtp_username.as_deref())
+        .filter(|address| is_address(address))
+        .ok_or_else(|| "Set the address to send from in settings first".to_string())?;
+
+    let message = build_message(&note, from, &recipients);
+    send(&settings, host, from, &recipients, &message)
+}
+
+fn is_address(address: &str) -> bool {
+    address
+        .split_once('@')
+        .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'))
+        && !address.contains(char::is_whitespace)
+        && !address.contains(['<', '>', '"'])
+}
+
+/// A `multipart/alternative` message: mail clients show the HTML part and
+/// fall back to the plain text.
+fn build_message(note: &Note, from: &str, recipients: &[&str]) -> String {
+    let subject = match note.title() {
+        "" => "Note",
+        title => title,
+    };
+    let boundary = format!("jotin-{}", Uuid::new_v4().simple());
+    let domain = from.rsplit('@').next().unwrap_or("jotin");
+    let html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"></head><body>\n{}\n</body></html>\n",
+        markdown::to_html(&note.text)
+    );
+
+    let mut message = String::new();
+    for (name, value) in [
+        ("From", from.to_string()),
+        ("To", recipients.join(", ")),
+        ("Subject", encode_header(subject)),
+        ("Date", Utc::now().to_rfc2822()),
+        (
+            "Message-ID",
+            format!("<{}@{domain}>", Uuid::new_v4().simple()),
+        ),
+        ("MIME-Version", "1.0".to_string()),
+        (
+            "Content-Type",
+            format!("multipart/alternative; boundary=\"{boundary}\""),
+        ),
+    ] {
+        message.push_str(&format!("{name}: {value}\r\n"));
+    }
+    message.push_str("\r\n");
+
+    for (content_type, body) in [
+        ("text/plain", markdown::to_plain_text(&note.text)),
+        ("text/html", html),
+    ] {
+        message.push_str(&format!(
+            "--{boundary}\r\nContent-Type: {content_type}; charset=utf-8\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+            encode_body(&body)
+        ));
+    }
+    message.push_str(&format!("--{boundary}--\r\n"));
+    message
+}
+
+/// RFC 2047 encoding for headers that are not plain ASCII.
+fn encode_header(value: &str) -> String {
+    let value: String = value.chars().filter(|c| !c.is_control()).collect();
+    if value.is_ascii() {
+        value
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(value))
+    }
+}
+
+fn encode_body(body: &str) -> String {
+    let encoded = STANDARD.encode(body);
+    encoded
+        .as_bytes()
+        .chunks(BASE64_LINE_CHARS)
+        .map(|line| String::from_utf8_lossy(line).to_string())
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// Hands the message to `curl`. The password goes through a config on
+/// stdin so it never shows up in the process list.
+fn send(
+    settings: &Settings,
+    host: &str,
+    from: &str,
+    recipients: &[&str],
+    message: &str,
+) -> Result<(), String> {
+    let scheme = if settings.smtp_port == IMPLICIT_TLS_PORT {
+        "smtps"
+    } else {
+        "smtp"
+    };
+    let mut config = format!(
+        "url = {}\nssl-reqd\nmail-from = {}\n",
+        http::quote_curl_value(&format!("{scheme}://{host}:{}", settings.smtp_port))?,
+        http::quote_curl_value(from)?
+    );
+    for recipient in recipients {
+        config.push_str(&format!(
+            "mail-rcpt = {}\n",
+            http::quote_curl_value(recipient)?
+        ));
+    }
+    if let Some(username) = settings.smtp_username.as_deref() {
+        let password = Secret::SmtpPassword.get()?.unwrap_or_default();
+        config.push_str(&format!(
+            "user = {}\n",
+            http::quote_curl_value(&format!("{username}:{password}"))?
+        ));
+    }
+
+    let message_file = http::StagedFile::with_contents("email", message.as_bytes())?;
+    config.push_str(&format!(
+        "upload-file = {}\n",
+        http::quote_curl_value(&message_file.path().to_string_lossy())?
+    ));
+
+    http::run_curl(MAIL_PROTOCOLS, REQUEST_TIMEOUT_SECS, &config, &[])
+        .map(|_| ())
+        .map_err(|e| format!("Failed to send email: {e}"))
+}
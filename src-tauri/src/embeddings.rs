@@ -0,0 +1,296 @@
+//! Meaning-based search. Notes are embedded by a local embedding server with
+//! an OpenAI-compatible `/embeddings` endpoint (Ollama, llama.cpp's server,
+//! LM Studio), and the vectors are kept next to the settings file, keyed by
+//! a hash of the embedded text so only changed notes are sent again. With
+//! encryption at rest the file is sealed like the notes.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Listener, Manager, State};
+
+use crate::{
+    ai, encryption, markdown, resolve_app_data_file,
+    settings::{self, Settings},
+    write_file_atomically, Note, StorageState, NOTES_CHANGED_EVENT,
+};
+
+const EMBEDDINGS_FILE_NAME: &str = "embeddings.json";
+pub(crate) const DEFAULT_EMBEDDING_MODEL: &str = "nomic-embed-text";
+const DEFAULT_SEMANTIC_RESULTS: usize = 10;
+/// Notes sent per embeddings request.
+const BATCH_SIZE: usize = 32;
+/// Longer notes are embedded by their beginning, which keeps requests within
+/// small models' context.
+const MAX_EMBEDDED_CHARS: usize = 4000;
+/// Saves come in bursts while typing; the index catches up once they settle.
+const REFRESH_DELAY: Duration = Duration::from_secs(3);
+static REFRESH_PENDING: AtomicBool = AtomicBool::new(false);
+/// Serializes background refreshes and searches, which both rewrite the file.
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "snake_case")]
+struct EmbeddingIndex {
+    /// Vectors from different models cannot be compared; switching models
+    /// starts over.
+    model: String,
+    entries: HashMap<String, Embedding>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+struct Embedding {
+    text_hash: String,
+    vector: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct SemanticResult {
+    note: Note,
+    /// Cosine similarity to the query, higher is closer.
+    score: f32,
+}
+
+/// The `k` notes closest in meaning to `query`. Notes changed since the last
+/// refresh are embedded first.
+#[tauri::command]
+pub(crate) fn semantic_search(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    query: String,
+    k: Option<usize>,
+) -> Result<Vec<SemanticResult>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let settings = settings::load_settings(&app)?;
+    let Some(endpoint) = settings.embedding_endpoint_url.as_deref() else {
+        return Err("No embedding endpoint is configured".to_string());
+    };
+
+    let notes = {
+        let storage = state.lock(&app)?;
+        storage.notes()?
+    };
+    let _guard = INDEX_LOCK
+        .lock()
+        .map_err(|_| "Embedding index lock was poisoned".to_string())?;
+    let index = refresh(&app, &settings, endpoint, &notes)?;
+    let query_vector = embed(&settings, endpoint, &[query.to_string()])?
+        .pop()
+        .ok_or_else(|| "Embedding endpoint returned no vector".to_string())?;
+
+    let mut results: Vec<SemanticResult> = notes
+        .iter()
+        .filter(|note| !note.is_trashed())
+        .filter_map(|note| {
+            let embedding = index.entries.get(&note.id)?;
+            Some(SemanticResult {
+                note: note.clone(),
+                score: cosine_similarity(&query_vector, &embedding.vector),
+            })
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.truncate(k.unwrap_or(DEFAULT_SEMANTIC_RESULTS));
+    Ok(results)
+}
+
+/// Keeps the index current: once at startup, then after every change to the
+/// notes, in the background so searches do not wait for it.
+pub(crate) fn setup(app: &AppHandle) {
+    let handle = app.clone();
+    app.listen_any(NOTES_CHANGED_EVENT, move |_| schedule_refresh(&handle));
+    schedule_refresh(app);
+}
+
+fn schedule_refresh(app: &AppHandle) {
+    if REFRESH_PENDING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    thread::spawn(move || {
+        thread::sleep(REFRESH_DELAY);
+        REFRESH_PENDING.store(false, Ordering::SeqCst);
+        if let Err(error) = refresh_in_background(&app) {
+            eprintln!("Failed to refresh note embeddings: {error}");
+        }
+    });
+}
+
+fn refresh_in_background(app: &AppHandle) -> Result<(), String> {
+    let settings = settings::load_settings(app)?;
+    let Some(endpoint) = settings.embedding_endpoint_url.as_deref() else {
+        return Ok(());
+    };
+    let notes = {
+        let state = app.state::<StorageState>();
+        let storage = state.lock(app)?;
+        storage.notes()?
+    };
+    let _guard = INDEX_LOCK
+        .lock()
+        .map_err(|_| "Embedding index lock was poisoned".to_string())?;
+    refresh(app, &settings, endpoint, &notes).map(|_| ())
+}
+
+/// Brings the stored index in line with `notes` and returns it.
+fn refresh(
+    app: &AppHandle,
+    settings: &Settings,
+    endpoint: &str,
+    notes: &[Note],
+) -> Result<EmbeddingIndex, String> {
+    let mut index = load(app);
+    let mut changed = false;
+    if index.model != settings.embedding_model {
+        index = EmbeddingIndex {
+            model: settings.embedding_model.clone(),
+            entries: HashMap::new(),
+        };
+        changed = true;
+    }
+
+    let active: HashMap<&str, &Note> = notes
+        .iter()
+        .filter(|note| !note.is_trashed())
+        .map(|note| (note.id.as_str(), note))
+        .collect();
+    let before = index.entries.len();
+    index
+        .entries
+        .retain(|id, _| active.contains_key(id.as_str()));
+    changed |= index.entries.len() != before;
+
+    let stale: Vec<(String, String, String)> = active
+        .values()
+        .filter_map(|note| {
+            let text = embedded_text(note);
+            let hash = format!("{:x}", Sha256::digest(text.as_bytes()));
+            let current = index
+                .entries
+                .get(&note.id)
+                .is_some_and(|embedding| embedding.text_hash == hash);
+            (!current).then(|| (note.id.clone(), hash, text))
+        })
+        .collect();
+
+    // Saved after every batch, so a failure part way keeps what was done.
+    for batch in stale.chunks(BATCH_SIZE) {
+        let inputs: Vec<String> = batch.iter().map(|(_, _, text)| text.clone()).collect();
+        let vectors = embed(settings, endpoint, &inputs)?;
+        for ((id, hash, _), vector) in batch.iter().zip(vectors) {
+            index.entries.insert(
+                id.clone(),
+                Embedding {
+                    text_hash: hash.clone(),
+                    vector,
+                },
+            );
+        }
+        save(app, &index)?;
+        changed = false;
+    }
+    if changed {
+        save(app, &index)?;
+    }
+    Ok(index)
+}
+
+fn embedded_text(note: &Note) -> String {
+    markdown::to_plain_text(&note.text)
+        .chars()
+        .take(MAX_EMBEDDED_CHARS)
+        .collect()
+}
+
+fn embed(settings: &Settings, endpoint: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let body = json!({ "model": settings.embedding_model, "input": inputs });
+    let response = ai::post_json(&ai::endpoint_url(endpoint, "embeddings"), None, &body)?;
+
+    let mut data: Vec<(u64, Vec<f32>)> = response["data"]
+        .as_array()
+        .ok_or_else(|| "Embedding response has no data".to_string())?
+        .iter()
+        .map(|item| {
+            let vector = item["embedding"]
+                .as_array()
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|value| value.as_f64())
+                        .map(|value| value as f32)
+                        .collect()
+                })
+                .unwrap_or_default();
+            (item["index"].as_u64().unwrap_or_default(), vector)
+        })
+        .collect();
+    if data.len() != inputs.len() {
+        return Err(format!(
+            "Embedding endpoint returned {} vectors for {} inputs",
+            data.len(),
+            inputs.len()
+        ));
+    }
+    data.sort_by_key(|(index, _)| *index);
+    Ok(data.into_iter().map(|(_, vector)| vector).collect())
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+fn load(app: &AppHandle) -> EmbeddingIndex {
+    let Ok(path) = embeddings_file(app) else {
+        return EmbeddingIndex::default();
+    };
+    let Ok(raw) = fs::read_to_string(path) else {
+        return EmbeddingIndex::default();
+    };
+    encryption::open(raw)
+        .and_then(|raw| serde_json::from_str(&raw).map_err(|e| e.to_string()))
+        .inspect_err(|error| eprintln!("Failed to read note embeddings: {error}"))
+        .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, index: &EmbeddingIndex) -> Result<(), String> {
+    // It would have to be written in the clear.
+    if encryption::is_locked(app) {
+        return Err(encryption::STORAGE_LOCKED.to_string());
+    }
+    let path = embeddings_file(app)?;
+    let payload = serde_json::to_string(index)
+        .map_err(|e| format!("Failed to serialize note embeddings: {e}"))?;
+    write_file_atomically(&path, &encryption::seal(payload)?)
+}
+
+pub(crate) fn embeddings_file(app: &AppHandle) -> Result<PathBuf, String> {
+    resolve_app_data_file(app, EMBEDDINGS_FILE_NAME)
+}
@@ -0,0 +1,283 @@
+//! Optional encryption at rest. Once a passphrase is set, the notes file,
+//! its backups, note history, the sync state, the capture draft and the
+//! semantic search embeddings are written sealed (see `crypto`) and read
+//! back only after `unlock_storage`; until then every storage command fails
+//! with `STORAGE_LOCKED`. The passphrase and key are kept in memory only, so
+//! the store locks again when the app quits.
+//!
+//! Attachments, link previews and exports stay as they are, and notes kept
+//! in an Obsidian vault cannot be encrypted.
+
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+use zeroize::Zeroizing;
+
+use crate::{
+    backup,
+    crypto::{Envelope, Key},
+    draft, embeddings, history, resolve_storage, schema, search, shred, sync, StorageState,
+    NOTES_CHANGED_EVENT,
+};
+
+/// Returned by every storage command while the notes are sealed and the
+/// passphrase was not given yet; the UI shows the unlock prompt on it.
+pub(crate) const STORAGE_LOCKED: &str = "Storage is locked";
+const PURPOSE: &str = "storage";
+/// How every sealed file starts, which tells it from a plain one without
+/// parsing the whole file.
+const SEALED_PREFIX: &str = "{\"encryption\":";
+
+struct Unlocked {
+    /// Kept to open files sealed under another salt, such as the notes
+    /// file another device committed through git sync.
+    passphrase: Zeroizing<String>,
+    key: Key,
+}
+
+static UNLOCKED: Mutex<Option<Unlocked>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct EncryptionStatus {
+    encrypted: bool,
+    unlocked: bool,
+}
+
+#[tauri::command]
+pub(crate) fn get_encryption_status(app: AppHandle) -> Result<EncryptionStatus, String> {
+    let (notes_path, _) = resolve_storage(&app)?;
+    Ok(EncryptionStatus {
+        encrypted: is_sealed(&notes_path),
+        unlocked: with_unlocked(|unlocked| unlocked.is_some())?,
+    })
+}
+
+/// Keeps the key for the rest of the session once `passphrase` opens the
+/// notes file.
+#[tauri::command]
+pub(crate) fn unlock_storage(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    passphrase: String,
+) -> Result<(), String> {
+    let storage = state.lock(&app)?;
+    unlock(storage.notes_path(), &passphrase)?;
+    drop(storage);
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(())
+}
+
+/// Encrypts the store under `passphrase`, re-encrypts it under a new one,
+/// or with `None` (or an empty passphrase) decrypts it for good. Changing
+/// or removing a passphrase takes the `current` one, even when unlocked.
+/// Every file is rewritten in place, so no copy under the old key is left
+/// in freed disk blocks.
+#[tauri::command]
+pub(crate) fn set_encryption_passphrase(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    passphrase: Option<String>,
+    current: Option<String>,
+) -> Result<(), String> {
+    let storage = state.lock(&app)?;
+    if storage.vault.is_some() {
+        return Err("Notes kept in a vault cannot be encrypted".to_string());
+    }
+    let notes_path = storage.notes_path().to_path_buf();
+    let encrypted = is_sealed(&notes_path);
+    if encrypted {
+        unlock(&notes_path, current.as_deref().unwrap_or_default())?;
+    }
+    let sealing = match passphrase.filter(|passphrase| !passphrase.is_empty()) {
+        Some(passphrase) => Some(Unlocked {
+            key: Key::generate(&passphrase)?,
+            passphrase: Zeroizing::new(passphrase),
+        }),
+        None if encrypted => None,
+        None => return Ok(()),
+    };
+
+    let notes = storage.load_notes()?;
+    let others: Vec<(PathBuf, String)> = sealed_files(&app, &notes_path)
+        .into_iter()
+        .filter_map(|path| {
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(open);
+            match contents {
+                Ok(contents) => Some((path, contents)),
+                Err(error) => {
+                    eprintln!("Failed to re-encrypt {}: {error}", path.display());
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let now_encrypted = sealing.is_some();
+    with_unlocked(|unlocked| *unlocked = sealing)?;
+
+    let payload = seal(schema::serialize(&notes)?)?;
+    if let Err(error) = shred::rewrite_in_place(&notes_path, &payload) {
+        eprintln!("Failed to overwrite the notes file: {error}");
+    }
+    storage.save_notes(&notes)?;
+    for (path, contents) in others {
+        let rewritten = seal(contents).and_then(|payload| {
+            shred::rewrite_in_place(&path, &payload).map_err(|e| e.to_string())
+        });
+        if let Err(error) = rewritten {
+            eprintln!("Failed to re-encrypt {}: {error}", path.display());
+        }
+    }
+
+    // The stored search index holds every note's text.
+    state.invalidate_search_index();
+    if now_encrypted {
+        search::remove_stored(&notes_path);
+    }
+    drop(storage);
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(())
+}
+
+/// Whether notes are encrypted and unlocked, so whatever derives from them
+/// must stay off disk too.
+pub(crate) fn is_unlocked() -> bool {
+    with_unlocked(|unlocked| unlocked.is_some()).unwrap_or(false)
+}
+
+/// Whether the notes are encrypted and not unlocked yet.
+pub(crate) fn is_locked(app: &AppHandle) -> bool {
+    !is_unlocked() && resolve_storage(app).is_ok_and(|(notes_path, _)| is_sealed(&notes_path))
+}
+
+/// `payload` as it should be written: sealed while the store is unlocked,
+/// as is otherwise.
+pub(crate) fn seal(payload: String) -> Result<String, String> {
+    with_unlocked(|unlocked| seal_with(unlocked.as_ref(), payload))?
+}
+
+/// The contents of a file as read from disk, opened when sealed.
+pub(crate) fn open(raw: String) -> Result<String, String> {
+    with_unlocked(|unlocked| open_with(unlocked.as_ref(), raw))?
+}
+
+fn seal_with(unlocked: Option<&Unlocked>, payload: String) -> Result<String, String> {
+    match unlocked {
+        Some(unlocked) => Envelope::seal(&unlocked.key, payload.as_bytes(), PURPOSE)?.to_json(),
+        None => Ok(payload),
+    }
+}
+
+fn open_with(unlocked: Option<&Unlocked>, raw: String) -> Result<String, String> {
+    if !raw.starts_with(SEALED_PREFIX) {
+        return Ok(raw);
+    }
+    let envelope = Envelope::parse(&raw).ok_or("Encrypted file is damaged")?;
+    let unlocked = unlocked.ok_or(STORAGE_LOCKED)?;
+    let plaintext = if envelope.encryption == *unlocked.key.params() {
+        envelope.open(&unlocked.key, PURPOSE)?
+    } else {
+        let key = Key::derive(&unlocked.passphrase, &envelope.encryption)?;
+        envelope.open(&key, PURPOSE)?
+    };
+    String::from_utf8(plaintext).map_err(|_| "Encrypted file is damaged".to_string())
+}
+
+/// Checks `passphrase` against the notes file and keeps the key.
+fn unlock(notes_path: &Path, passphrase: &str) -> Result<(), String> {
+    let raw =
+        fs::read_to_string(notes_path).map_err(|e| format!("Failed to read notes file: {e}"))?;
+    if !raw.starts_with(SEALED_PREFIX) {
+        return Err("Notes are not encrypted".to_string());
+    }
+    let envelope = Envelope::parse(&raw).ok_or("Encrypted notes file is damaged")?;
+    let key = Key::derive(passphrase, &envelope.encryption)?;
+    envelope
+        .open(&key, PURPOSE)
+        .map_err(|_| "Wrong passphrase".to_string())?;
+
+    with_unlocked(|unlocked| {
+        *unlocked = Some(Unlocked {
+            passphrase: Zeroizing::new(passphrase.to_string()),
+            key,
+        })
+    })
+}
+
+/// Reads only the first bytes, as this runs for status checks.
+fn is_sealed(notes_path: &Path) -> bool {
+    let mut start = [0; SEALED_PREFIX.len()];
+    fs::File::open(notes_path)
+        .and_then(|mut file| file.read_exact(&mut start))
+        .is_ok_and(|()| start == SEALED_PREFIX.as_bytes())
+}
+
+/// Files besides the notes file that hold note text.
+fn sealed_files(app: &AppHandle, notes_path: &Path) -> Vec<PathBuf> {
+    let mut paths = backup::backup_files(notes_path);
+    paths.extend(history::history_files(app));
+    paths.extend(sync::state_file(app).ok().filter(|path| path.exists()));
+    paths.extend(draft::draft_file(app).ok().filter(|path| path.exists()));
+    paths.extend(
+        embeddings::embeddings_file(app)
+            .ok()
+            .filter(|path| path.exists()),
+    );
+    paths
+}
+
+fn with_unlocked<T>(f: impl FnOnce(&mut Option<Unlocked>) -> T) -> Result<T, String> {
+    let mut unlocked = UNLOCKED
+        .lock()
+        .map_err(|_| "Encryption key lock was poisoned".to_string())?;
+    Ok(f(&mut unlocked))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unlocked(passphrase: &str, salt: u8) -> Unlocked {
+        Unlocked {
+            passphrase: Zeroizing::new(passphrase.to_string()),
+            key: Key::for_tests(passphrase, salt),
+        }
+    }
+
+    #[test]
+    fn seals_only_while_unlocked() {
+        let payload = r#"{"version":2,"notes":[]}"#.to_string();
+        assert_eq!(seal_with(None, payload.clone()).unwrap(), payload);
+
+        let unlocked = unlocked("correct horse", 1);
+        let sealed = seal_with(Some(&unlocked), payload.clone()).unwrap();
+        assert!(sealed.starts_with(SEALED_PREFIX));
+        assert!(!sealed.contains("notes"));
+        assert_eq!(open_with(Some(&unlocked), sealed.clone()).unwrap(), payload);
+        assert_eq!(open_with(None, sealed).unwrap_err(), STORAGE_LOCKED);
+        assert_eq!(open_with(None, payload.clone()).unwrap(), payload);
+    }
+
+    #[test]
+    fn opens_files_sealed_under_another_salt() {
+        let other_device = unlocked("correct horse", 2);
+        let sealed = seal_with(Some(&other_device), "[]".to_string()).unwrap();
+
+        assert_eq!(
+            open_with(Some(&unlocked("correct horse", 1)), sealed.clone()).unwrap(),
+            "[]"
+        );
+        assert!(open_with(Some(&unlocked("battery staple", 1)), sealed).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! Self-expiring notes for one-time codes and throwaway reminders. A note
+//! captured with "keep for" a duration gets an `expires_at`, and a
+//! background task moves it to the trash once that time has passed.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::{find_active_note_mut, Note, StorageState, NOTES_CHANGED_EVENT};
+
+const SCHEDULER_TICK: Duration = Duration::from_secs(30);
+static SCHEDULER_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Sets or (with `None`) clears when the note expires, `expires_in` being
+/// a duration such as `30m`, `24h`, `7d` or `2w`.
+#[tauri::command]
+pub(crate) fn set_note_expiry(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    expires_in: Option<String>,
+) -> Result<Note, String> {
+    let expires_at = expires_in.as_deref().map(expires_at).transpose()?;
+
+    let storage = state.lock(&app)?;
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+    note.expires_at = expires_at;
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(updated)
+}
+
+/// The RFC 3339 time `expires_in` from now.
+pub(crate) fn expires_at(expires_in: &str) -> Result<String, String> {
+    let expires_in = expires_in.trim();
+    let invalid = || format!("Invalid expiry \"{expires_in}\"; use e.g. 30m, 24h or 7d");
+    let split = expires_in
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = expires_in.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    let duration = match unit.trim() {
+        "m" | "min" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" => chrono::Duration::try_days(amount),
+        "w" => chrono::Duration::try_weeks(amount),
+        _ => None,
+    }
+    .filter(|duration| *duration > chrono::Duration::zero())
+    .ok_or_else(invalid)?;
+    Ok((Utc::now() + duration).to_rfc3339())
+}
+
+pub(crate) fn start(app: &AppHandle) {
+    if SCHEDULER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    thread::spawn(move || loop {
+        if let Err(error) = trash_expired_notes(&app) {
+            eprintln!("Failed to trash expired notes: {error}");
+        }
+        thread::sleep(SCHEDULER_TICK);
+    });
+}
+
+fn trash_expired_notes(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<StorageState>();
+    let storage = state.lock(app)?;
+
+    let now = Utc::now();
+    if !storage.notes()?.iter().any(|note| is_expired(note, now)) {
+        return Ok(());
+    }
+
+    let mut notes = storage.load_notes()?;
+    let deleted_at = now.to_rfc3339();
+    let mut expired = Vec::new();
+    for note in notes.iter_mut().filter(|note| is_expired(note, now)) {
+        note.deleted_at = Some(deleted_at.clone());
+        expired.push(note.id.clone());
+    }
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| {
+        for id in &expired {
+            index.remove(id);
+        }
+    });
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(())
+}
+
+fn is_expired(note: &Note, now: DateTime<Utc>) -> bool {
+    !note.is_trashed()
+        && note
+            .expires_at
+            .as_deref()
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+            .is_some_and(|at| at <= now)
+}
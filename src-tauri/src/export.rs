@@ -0,0 +1,320 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::{frontmatter, Note, StorageState};
+
+const MAX_FILE_STEM_CHARS: usize = 60;
+pub(crate) const CSV_FORMULA_PREFIXES: [char; 4] = ['=', '+', '-', '@'];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct ExportSummary {
+    exported: usize,
+    directory: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ExportFormat {
+    /// With YAML front matter per file, like `export_markdown`.
+    Markdown,
+    Text,
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Text => "txt",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// One row per note, for spreadsheets and scripts.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TabularFormat {
+    Csv,
+    /// JSON Lines: one object per line.
+    Jsonl,
+}
+
+#[derive(Serialize)]
+struct TabularRow<'a> {
+    id: &'a str,
+    created_at: &'a str,
+    updated_at: Option<&'a str>,
+    tags: &'a [String],
+    text: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct NotesExport {
+    exported: usize,
+    path: String,
+    /// Whether the notes went into one document rather than a file each.
+    single_file: bool,
+}
+
+/// Writes every non-trashed note into `dir` as `<title>.md`.
+///
+/// Files from a previous export into the same folder are overwritten so that
+/// re-exporting refreshes an existing vault instead of duplicating it.
+#[tauri::command]
+pub(crate) fn export_markdown(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    dir: String,
+) -> Result<ExportSummary, String> {
+    let directory = PathBuf::from(dir.trim());
+    if directory.as_os_str().is_empty() {
+        return Err("Export directory cannot be empty".to_string());
+    }
+
+    let notes = {
+        let storage = state.lock(&app)?;
+
+        storage.load_notes()?
+    };
+
+    fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create export directory: {e}"))?;
+
+    let mut used_names: HashSet<String> = HashSet::new();
+    let mut exported = 0;
+    for note in notes.iter().filter(|note| !note.is_trashed()) {
+        let file_name = unique_file_name(note, "md", &mut used_names);
+        fs::write(
+            directory.join(&file_name),
+            frontmatter::render_markdown(note),
+        )
+        .map_err(|e| format!("Failed to write {file_name}: {e}"))?;
+        exported += 1;
+    }
+
+    Ok(ExportSummary {
+        exported,
+        directory: directory.display().to_string(),
+    })
+}
+
+/// Writes the given notes, in the order given, to `path`. An existing
+/// folder, or a path without an extension, gets one file per note; any other
+/// path becomes a single document with all of them.
+#[tauri::command]
+pub(crate) fn export_notes(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    ids: Vec<String>,
+    format: ExportFormat,
+    path: String,
+) -> Result<NotesExport, String> {
+    let path = PathBuf::from(path.trim());
+    if path.as_os_str().is_empty() {
+        return Err("Export path cannot be empty".to_string());
+    }
+    if ids.is_empty() {
+        return Err("Select at least one note to export".to_string());
+    }
+
+    let notes = {
+        let storage = state.lock(&app)?;
+        let notes = storage.notes()?;
+        ids.iter()
+            .map(|id| {
+                notes
+                    .iter()
+                    .find(|note| &note.id == id && !note.is_trashed())
+                    .cloned()
+                    .ok_or_else(|| "Note not found".to_string())
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let single_file = !path.is_dir() && path.extension().is_some();
+    if single_file {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create export directory: {e}"))?;
+        }
+        fs::write(&path, render_document(&notes, format)?)
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    } else {
+        write_files(&path, &notes, format)?;
+    }
+
+    Ok(NotesExport {
+        exported: notes.len(),
+        path: path.display().to_string(),
+        single_file,
+    })
+}
+
+/// Writes every non-trashed note to `path` as CSV or JSON Lines, oldest
+/// first, with its id, dates, tags and text.
+#[tauri::command]
+pub(crate) fn export_tabular(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    path: String,
+    format: TabularFormat,
+) -> Result<NotesExport, String> {
+    let path = PathBuf::from(path.trim());
+    if path.as_os_str().is_empty() {
+        return Err("Export path cannot be empty".to_string());
+    }
+
+    let mut notes = {
+        let storage = state.lock(&app)?;
+        storage.load_notes()?
+    };
+    notes.retain(|note| !note.is_trashed());
+    notes.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+
+    let rows = notes.iter().map(|note| TabularRow {
+        id: &note.id,
+        created_at: &note.created_at,
+        updated_at: note.updated_at.as_deref(),
+        tags: &note.tags,
+        text: &note.text,
+    });
+    let contents = match format {
+        TabularFormat::Csv => {
+            let mut csv = String::from("id,created_at,updated_at,tags,text\r\n");
+            for row in rows {
+                let fields = [
+                    row.id,
+                    row.created_at,
+                    row.updated_at.unwrap_or_default(),
+                    &row.tags.join(", "),
+                    row.text,
+                ];
+                let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                csv.push_str(&fields.join(","));
+                csv.push_str("\r\n");
+            }
+            csv
+        }
+        TabularFormat::Jsonl => {
+            let mut jsonl = String::new();
+            for row in rows {
+                let line = serde_json::to_string(&row)
+                    .map_err(|e| format!("Failed to serialize note: {e}"))?;
+                jsonl.push_str(&line);
+                jsonl.push('\n');
+            }
+            jsonl
+        }
+    };
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create export directory: {e}"))?;
+    }
+    fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+
+    Ok(NotesExport {
+        exported: notes.len(),
+        path: path.display().to_string(),
+        single_file: true,
+    })
+}
+
+/// Quoted when it holds a separator, quote or line break (RFC 4180). A
+/// field a spreadsheet would run as a formula (`=`, `+`, `-`, `@`) gets a
+/// leading `'`, which `import_csv` drops again.
+fn csv_field(field: &str) -> String {
+    let field = if field.starts_with(CSV_FORMULA_PREFIXES) {
+        format!("'{field}")
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+fn write_files(directory: &Path, notes: &[Note], format: ExportFormat) -> Result<(), String> {
+    fs::create_dir_all(directory).map_err(|e| format!("Failed to create export directory: {e}"))?;
+
+    let mut used_names: HashSet<String> = HashSet::new();
+    for note in notes {
+        let contents = match format {
+            ExportFormat::Markdown => frontmatter::render_markdown(note),
+            ExportFormat::Text => format!("{}\n", note.text.trim_end()),
+            ExportFormat::Json => serde_json::to_string_pretty(note)
+                .map_err(|e| format!("Failed to serialize note: {e}"))?,
+        };
+        let file_name = unique_file_name(note, format.extension(), &mut used_names);
+        fs::write(directory.join(&file_name), contents)
+            .map_err(|e| format!("Failed to write {file_name}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// All notes in one file: a JSON array, or the texts separated by a
+/// horizontal rule in Markdown and a blank line in plain text.
+fn render_document(notes: &[Note], format: ExportFormat) -> Result<String, String> {
+    let separator = match format {
+        ExportFormat::Markdown => "\n\n---\n\n",
+        ExportFormat::Text => "\n\n",
+        ExportFormat::Json => {
+            return serde_json::to_string_pretty(notes)
+                .map_err(|e| format!("Failed to serialize notes: {e}"));
+        }
+    };
+    let texts: Vec<&str> = notes.iter().map(|note| note.text.trim()).collect();
+    Ok(format!("{}\n", texts.join(separator)))
+}
+
+fn unique_file_name(note: &Note, extension: &str, used_names: &mut HashSet<String>) -> String {
+    let stem = sanitize_file_stem(note.title()).unwrap_or_else(|| note.id.clone());
+
+    let mut candidate = format!("{stem}.{extension}");
+    let mut counter = 2;
+    while !used_names.insert(candidate.to_lowercase()) {
+        candidate = format!("{stem} ({counter}).{extension}");
+        counter += 1;
+    }
+    candidate
+}
+
+/// Drops characters that are reserved on macOS, Windows or in Obsidian links.
+pub(crate) fn sanitize_file_stem(title: &str) -> Option<String> {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+
+    let stem: String = cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_FILE_STEM_CHARS)
+        .collect();
+    let stem = stem.trim_matches(|c: char| c == '.' || c.is_whitespace());
+
+    (!stem.is_empty()).then(|| stem.to_string())
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use crate::Note;
+
+const FRONTMATTER_DELIMITER: &str = "---";
+
+/// The subset of frontmatter jotin understands, plus the remaining body.
+#[derive(Debug, Default)]
+pub(crate) struct ParsedDocument {
+    pub id: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub tags: Vec<String>,
+    pub body: String,
+}
+
+/// Renders a note as Markdown with a YAML frontmatter header.
+///
+/// Scalars are written as JSON strings, which are valid double-quoted YAML, so
+/// arbitrary tag or id characters never need bespoke escaping.
+pub(crate) fn render_markdown(note: &Note) -> String {
+    let mut output = String::new();
+    output.push_str(FRONTMATTER_DELIMITER);
+    output.push('\n');
+    push_field(&mut output, "id", &quote(&note.id));
+    push_field(&mut output, "created_at", &quote(&note.created_at));
+    push_field(
+        &mut output,
+        "updated_at",
+        &note
+            .updated_at
+            .as_deref()
+            .map(quote)
+            .unwrap_or_else(|| "null".to_string()),
+    );
+    let tags: Vec<String> = note.tags.iter().map(|tag| quote(tag)).collect();
+    push_field(&mut output, "tags", &format!("[{}]", tags.join(", ")));
+    output.push_str(FRONTMATTER_DELIMITER);
+    output.push_str("\n\n");
+    output.push_str(&note.text);
+    output.push('\n');
+    output
+}
+
+fn push_field(output: &mut String, key: &str, value: &str) {
+    output.push_str(key);
+    output.push_str(": ");
+    output.push_str(value);
+    output.push('\n');
+}
+
+pub(crate) fn quote(value: &str) -> String {
+    serde_json::Value::String(value.to_string()).to_string()
+}
+
+/// Splits an optional YAML frontmatter block from a Markdown document.
+///
+/// Only flat `key: value` pairs and string lists (flow `[a, b]`, block `- a`
+/// or comma separated) are understood; anything else is ignored.
+pub(crate) fn parse_markdown(raw: &str) -> ParsedDocument {
+    let raw = raw.strip_prefix('\u{feff}').unwrap_or(raw);
+    let Some((header, body)) = split_frontmatter(raw) else {
+        return ParsedDocument {
+            body: raw.trim().to_string(),
+            ..ParsedDocument::default()
+        };
+    };
+
+    let fields = parse_fields(header);
+    let scalar = |key: &str| {
+        fields
+            .get(key)
+            .and_then(|values| values.first())
+            .filter(|value| !value.is_empty() && value.as_str() != "null")
+            .cloned()
+    };
+
+    ParsedDocument {
+        id: scalar("id"),
+        created_at: scalar("created_at")
+            .or_else(|| scalar("created"))
+            .or_else(|| scalar("date")),
+        updated_at: scalar("updated_at").or_else(|| scalar("updated")),
+        tags: fields.get("tags").cloned().unwrap_or_default(),
+        body: body.trim().to_string(),
+    }
+}
+
+/// The raw frontmatter block and the rest of the document, if the document
+/// opens with one.
+pub(crate) fn split_frontmatter(raw: &str) -> Option<(&str, &str)> {
+    let rest = raw
+        .strip_prefix(FRONTMATTER_DELIMITER)?
+        .strip_prefix('\n')
+        .or_else(|| raw.strip_prefix("---\r\n"))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == FRONTMATTER_DELIMITER {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Replaces the `key: value` lines for `fields` in a raw frontmatter block,
+/// block lists included, and leaves every other line as it was.
+pub(crate) fn upsert_fields(header: &str, fields: &[(&str, String)]) -> String {
+    let mut output = String::new();
+    let mut replacing = false;
+    for line in header.lines() {
+        let trimmed = line.trim();
+        if replacing && (trimmed.starts_with("- ") || trimmed.is_empty()) {
+            continue;
+        }
+        replacing = trimmed.split_once(':').is_some_and(|(key, _)| {
+            let key = key.trim().to_lowercase();
+            fields.iter().any(|(field, _)| *field == key)
+        });
+        if !replacing {
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+    for (key, value) in fields {
+        push_field(&mut output, key, value);
+    }
+    output
+}
+
+fn parse_fields(header: &str) -> HashMap<String, Vec<String>> {
+    let mut fields: HashMap<String, Vec<String>> = HashMap::new();
+    let mut current_key: Option<String> = None;
+
+    for line in header.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(item) = trimmed.strip_prefix("- ") {
+            if let Some(key) = &current_key {
+                fields
+                    .entry(key.clone())
+                    .or_default()
+                    .push(unquote(item.trim()));
+            }
+            continue;
+        }
+
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+        let values = if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            split_list(list)
+        } else if value.is_empty() {
+            Vec::new()
+        } else if key == "tags" && !value.starts_with('"') {
+            split_list(value)
+        } else {
+            vec![unquote(value)]
+        };
+
+        fields.insert(key.clone(), values);
+        current_key = Some(key);
+    }
+
+    fields
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|item| unquote(item.trim()))
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn unquote(value: &str) -> String {
+    if value.starts_with('"') {
+        if let Ok(parsed) = serde_json::from_str::<String>(value) {
+            return parsed;
+        }
+    }
+    value
+        .trim_matches(|c| c == '"' || c == '\'')
+        .trim()
+        .to_string()
+}
@@ -0,0 +1,165 @@
+use serde::Serialize;
+
+use crate::Note;
+
+const SCORE_MATCH: i64 = 16;
+const SCORE_GAP_START: i64 = -3;
+const SCORE_GAP_EXTENSION: i64 = -1;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL_CASE: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+const FIRST_CHAR_MULTIPLIER: i64 = 2;
+/// Added to a title match so it outranks the same match deeper in the text.
+const BONUS_TITLE: i64 = 24;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct FuzzyResult {
+    pub note: Note,
+    pub score: i64,
+    /// Character positions in `note.text` to highlight.
+    pub indices: Vec<usize>,
+}
+
+struct FuzzyMatch {
+    score: i64,
+    indices: Vec<usize>,
+}
+
+/// Scores a note against the query, preferring a match in its title.
+/// Returns `None` when the query is not a subsequence of the text.
+pub(crate) fn match_note(query: &str, note: &Note) -> Option<FuzzyResult> {
+    let query = prepare_query(query)?;
+    let text_match = match_chars(&query, &note.text);
+
+    let title = note.title();
+    let title_match = match_chars(&query, title).map(|mut found| {
+        // `title` borrows from `note.text`, so its byte offset is exact.
+        let byte_offset = title.as_ptr() as usize - note.text.as_ptr() as usize;
+        let char_offset = note.text[..byte_offset].chars().count();
+        found.score += BONUS_TITLE;
+        found
+            .indices
+            .iter_mut()
+            .for_each(|index| *index += char_offset);
+        found
+    });
+
+    let best = match (title_match, text_match) {
+        (Some(title), Some(text)) if text.score > title.score => text,
+        (Some(title), _) => title,
+        (None, text) => text?,
+    };
+    Some(FuzzyResult {
+        note: note.clone(),
+        score: best.score,
+        indices: best.indices,
+    })
+}
+
+/// Query characters without whitespace, lowercased unless the query has an
+/// uppercase letter (smart case).
+fn prepare_query(query: &str) -> Option<(Vec<char>, bool)> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let chars: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if case_sensitive { c } else { fold(c) })
+        .collect();
+    (!chars.is_empty()).then_some((chars, case_sensitive))
+}
+
+/// Finds the query as a subsequence of `text`: a forward pass locates the
+/// first complete match, then a backward pass from its end picks the
+/// tightest window, as skim and fzf do.
+fn match_chars((query, case_sensitive): &(Vec<char>, bool), text: &str) -> Option<FuzzyMatch> {
+    let text: Vec<char> = text.chars().collect();
+    let same = |text_char: char, query_char: char| {
+        let text_char = if *case_sensitive {
+            text_char
+        } else {
+            fold(text_char)
+        };
+        text_char == query_char
+    };
+
+    let mut next = 0;
+    let mut end = None;
+    for (index, &c) in text.iter().enumerate() {
+        if same(c, query[next]) {
+            next += 1;
+            if next == query.len() {
+                end = Some(index);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    let mut indices = Vec::with_capacity(query.len());
+    let mut remaining = query.len();
+    for index in (0..=end).rev() {
+        if same(text[index], query[remaining - 1]) {
+            indices.push(index);
+            remaining -= 1;
+            if remaining == 0 {
+                break;
+            }
+        }
+    }
+    indices.reverse();
+
+    Some(FuzzyMatch {
+        score: score(&text, &indices),
+        indices,
+    })
+}
+
+fn score(text: &[char], indices: &[usize]) -> i64 {
+    let mut total = 0;
+    let mut previous: Option<usize> = None;
+    let mut streak_bonus = 0;
+
+    for (position, &index) in indices.iter().enumerate() {
+        let mut bonus = position_bonus(text, index);
+        match previous {
+            Some(previous) if index == previous + 1 => {
+                // A run keeps the bonus of the boundary it started on.
+                streak_bonus = streak_bonus.max(bonus).max(BONUS_CONSECUTIVE);
+                bonus = streak_bonus;
+            }
+            Some(previous) => {
+                let gap = (index - previous - 1) as i64;
+                total += SCORE_GAP_START + SCORE_GAP_EXTENSION * (gap - 1);
+                streak_bonus = bonus;
+            }
+            None => streak_bonus = bonus,
+        }
+        if position == 0 {
+            bonus *= FIRST_CHAR_MULTIPLIER;
+        }
+
+        total += SCORE_MATCH + bonus;
+        previous = Some(index);
+    }
+    total
+}
+
+/// Start of text or of a word scores highest, then a camelCase hump.
+fn position_bonus(text: &[char], index: usize) -> i64 {
+    let Some(&before) = index.checked_sub(1).and_then(|before| text.get(before)) else {
+        return BONUS_BOUNDARY;
+    };
+    let current = text[index];
+    if !before.is_alphanumeric() && current.is_alphanumeric() {
+        BONUS_BOUNDARY
+    } else if before.is_lowercase() && current.is_uppercase() {
+        BONUS_CAMEL_CASE
+    } else {
+        0
+    }
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
@@ -0,0 +1,135 @@
+//! Shares a note as a GitHub Gist, the quickest way to hand someone a
+//! snippet that was captured. A note that is a single fenced code block is
+//! shared as that code, in a file named after its language, so GitHub
+//! highlights it; anything else is shared as Markdown.
+
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, State};
+
+use crate::{clipboard_watcher, export, http, settings::Secret, Note, StorageState};
+
+const GISTS_API_URL: &str = "https://api.github.com/gists";
+const GITHUB_API_VERSION: &str = "2022-11-28";
+
+/// File extensions for the fence languages snippets are usually written in.
+const LANGUAGE_EXTENSIONS: &[(&str, &str)] = &[
+    ("bash", "sh"),
+    ("c", "c"),
+    ("cpp", "cpp"),
+    ("csharp", "cs"),
+    ("css", "css"),
+    ("go", "go"),
+    ("html", "html"),
+    ("java", "java"),
+    ("javascript", "js"),
+    ("js", "js"),
+    ("json", "json"),
+    ("kotlin", "kt"),
+    ("python", "py"),
+    ("py", "py"),
+    ("ruby", "rb"),
+    ("rust", "rs"),
+    ("sh", "sh"),
+    ("shell", "sh"),
+    ("sql", "sql"),
+    ("swift", "swift"),
+    ("toml", "toml"),
+    ("ts", "ts"),
+    ("typescript", "ts"),
+    ("yaml", "yml"),
+    ("yml", "yml"),
+    ("zsh", "sh"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct SharedGist {
+    id: String,
+    url: String,
+}
+
+/// Creates a gist from the note and puts its URL on the clipboard. Secret
+/// gists are unlisted, not private: anyone with the URL can read them.
+#[tauri::command]
+pub(crate) fn share_as_gist(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    public: bool,
+) -> Result<SharedGist, String> {
+    let note = {
+        let storage = state.lock(&app)?;
+        let notes = storage.notes()?;
+        notes
+            .iter()
+            .find(|note| note.id == id && !note.is_trashed())
+            .cloned()
+            .ok_or_else(|| "Note not found".to_string())?
+    };
+
+    let token = Secret::GithubToken
+        .get()?
+        .ok_or_else(|| "Set a GitHub token with the gist scope in settings first".to_string())?;
+
+    let (file_name, content) = gist_file(&note);
+    let body = json!({
+        "description": note.title(),
+        "public": public,
+        "files": { file_name: { "content": content } },
+    });
+    let headers = [
+        format!("Authorization: Bearer {token}"),
+        format!("X-GitHub-Api-Version: {GITHUB_API_VERSION}"),
+    ];
+    let response = http::send_json("POST", GISTS_API_URL, &headers, Some(&body))?;
+    if !response.is_success() {
+        return Err(match response.body["message"].as_str() {
+            Some(message) => format!("GitHub returned an error: {message}"),
+            None => format!("GitHub request failed with HTTP {}", response.status),
+        });
+    }
+
+    let gist = SharedGist {
+        id: response.body["id"].as_str().unwrap_or_default().to_string(),
+        url: response.body["html_url"]
+            .as_str()
+            .ok_or_else(|| "GitHub did not return the new gist".to_string())?
+            .to_string(),
+    };
+    clipboard_watcher::ignore_text(&gist.url);
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(gist.url.clone()))
+        .map_err(|e| format!("Failed to copy gist URL: {e}"))?;
+    Ok(gist)
+}
+
+/// The file name and content the note is shared as.
+fn gist_file(note: &Note) -> (String, String) {
+    let stem = export::sanitize_file_stem(note.title()).unwrap_or_else(|| "note".to_string());
+    match single_code_block(&note.text) {
+        Some((language, code)) => {
+            let extension = LANGUAGE_EXTENSIONS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(language))
+                .map_or("txt", |(_, extension)| extension);
+            let stem = match language {
+                "" => "snippet".to_string(),
+                language => format!("snippet-{}", language.to_lowercase()),
+            };
+            (format!("{stem}.{extension}"), code)
+        }
+        None => (format!("{stem}.md"), note.text.clone()),
+    }
+}
+
+/// The fence language and code of a note made of one code block and
+/// nothing else.
+fn single_code_block(text: &str) -> Option<(&str, String)> {
+    let text = text.trim();
+    let first_line = text.lines().next()?;
+    let language = first_line.strip_prefix("```")?.trim();
+    let inner = text.strip_prefix(first_line)?.strip_suffix("```")?;
+    let code = inner.trim_matches('\n');
+    (!code.contains("\n```")).then(|| (language, format!("{code}\n")))
+}
@@ -0,0 +1,284 @@
+//! Git sync: keeps the data directory in a repository, committing in the
+//! background after every save, and syncs through `origin` instead of
+//! WebDAV. Only repositories jotin created are managed, and their
+//! `.gitignore` keeps everything but the notes file out of history. Sync (see `sync`) fetches
+//! the remote notes file, merges it note by note, then merges the branch
+//! with the notes file taken as ours and pushes. Everything goes through
+//! the `git` command line, so the user's credential helpers and SSH keys
+//! apply.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use tauri::AppHandle;
+
+use crate::{resolve_notes_path, settings, NOTES_FILE_NAME};
+
+const REMOTE_NAME: &str = "origin";
+const FALLBACK_AUTHOR_NAME: &str = "Jotin";
+const FALLBACK_AUTHOR_EMAIL: &str = "jotin@localhost";
+/// Set in repositories jotin created, so it never takes over one the user
+/// already keeps in the data directory.
+const MANAGED_CONFIG_KEY: &str = "jotin.managed";
+/// What earlier versions wrote to `.gitignore`; it marks the repositories
+/// they created before `MANAGED_CONFIG_KEY` existed.
+const LEGACY_GITIGNORES: &[&str] = &[
+    "*.lock\n*.tmp\nsync.json\n",
+    "*.lock\n*.tmp\nsync.json\nbackups/\n",
+];
+
+static GIT_SYNC_ENABLED: AtomicBool = AtomicBool::new(false);
+static GIT_LOCK: Mutex<()> = Mutex::new(());
+
+/// Turns the data directory into a repository (once) and points `origin` at
+/// the configured remote.
+pub(crate) fn apply(app: &AppHandle) -> Result<(), String> {
+    let settings = settings::load_settings(app)?;
+    GIT_SYNC_ENABLED.store(false, Ordering::SeqCst);
+    if !settings.git_sync_enabled {
+        return Ok(());
+    }
+
+    let dir = data_dir(app)?;
+    prepare_repository(&dir, settings.git_remote_url.as_deref())?;
+    GIT_SYNC_ENABLED.store(true, Ordering::SeqCst);
+    commit_all(&dir, "Enable git sync")?;
+    Ok(())
+}
+
+pub(crate) fn start(app: &AppHandle) {
+    if let Err(error) = apply(app) {
+        eprintln!("Failed to set up git sync: {error}");
+    }
+}
+
+pub(crate) fn is_enabled() -> bool {
+    GIT_SYNC_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Commits the data directory in the background after a save.
+pub(crate) fn record_change(notes_path: &Path) {
+    if !is_enabled() {
+        return;
+    }
+    let Some(dir) = notes_path.parent().map(Path::to_path_buf) else {
+        return;
+    };
+
+    thread::spawn(move || {
+        if let Err(error) = commit_all(&dir, "Update notes") {
+            eprintln!("Failed to commit notes: {error}");
+        }
+    });
+}
+
+pub(crate) fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let notes_path = resolve_notes_path(app)?;
+    notes_path
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| "Failed to resolve notes directory".to_string())
+}
+
+/// Stages everything and commits; returns whether a commit was made.
+pub(crate) fn commit_all(dir: &Path, message: &str) -> Result<bool, String> {
+    let _guard = GIT_LOCK
+        .lock()
+        .map_err(|_| "Git lock was poisoned".to_string())?;
+
+    git(dir, &["add", "--all"])?;
+    if git_succeeds(dir, &["diff", "--cached", "--quiet"]) {
+        return Ok(false);
+    }
+    git(dir, &["commit", "--quiet", "--message", message])?;
+    Ok(true)
+}
+
+/// Fetches the remote branch and returns its notes file, or `None` when the
+/// remote has nothing yet.
+pub(crate) fn fetch_remote_notes(dir: &Path) -> Result<Option<String>, String> {
+    let branch = current_branch(dir)?;
+    let heads = git(dir, &["ls-remote", "--heads", REMOTE_NAME, &branch])?;
+    if heads.trim().is_empty() {
+        return Ok(None);
+    }
+
+    git(dir, &["fetch", "--quiet", REMOTE_NAME, &branch])?;
+    let remote_file = format!("FETCH_HEAD:{NOTES_FILE_NAME}");
+    if !git_succeeds(dir, &["cat-file", "-e", &remote_file]) {
+        return Ok(Some(String::new()));
+    }
+    git(dir, &["show", &remote_file]).map(Some)
+}
+
+/// Merges the fetched branch so its history comes along. The notes file is
+/// rewritten by the caller with a note-level merge, so textual conflicts
+/// there are resolved as ours.
+pub(crate) fn merge_fetched(dir: &Path) -> Result<(), String> {
+    let _guard = GIT_LOCK
+        .lock()
+        .map_err(|_| "Git lock was poisoned".to_string())?;
+
+    let merged = git(
+        dir,
+        &[
+            "merge",
+            "--quiet",
+            "--no-edit",
+            "--allow-unrelated-histories",
+            "--strategy-option",
+            "ours",
+            "FETCH_HEAD",
+        ],
+    );
+    if merged.is_err() {
+        let _ = git(dir, &["merge", "--abort"]);
+    }
+    merged.map(|_| ())
+}
+
+pub(crate) fn push(dir: &Path) -> Result<(), String> {
+    let branch = current_branch(dir)?;
+    git(
+        dir,
+        &["push", "--quiet", "--set-upstream", REMOTE_NAME, &branch],
+    )
+    .map(|_| ())
+}
+
+fn prepare_repository(dir: &Path, remote_url: Option<&str>) -> Result<(), String> {
+    if dir.join(".git").exists() {
+        ensure_managed(dir)?;
+    } else {
+        git(dir, &["init", "--quiet"])?;
+        git(dir, &["config", MANAGED_CONFIG_KEY, "true"])?;
+    }
+
+    // Older repositories committed the whole directory; stop tracking
+    // whatever the allow-list now leaves out.
+    let gitignore = dir.join(".gitignore");
+    if fs::read_to_string(&gitignore).ok().as_deref() != Some(gitignore_contents().as_str()) {
+        fs::write(&gitignore, gitignore_contents())
+            .map_err(|e| format!("Failed to write .gitignore: {e}"))?;
+        git(
+            dir,
+            &["rm", "-r", "--cached", "--quiet", "--ignore-unmatch", "."],
+        )?;
+    }
+
+    if !git_succeeds(dir, &["config", "user.email"]) {
+        git(dir, &["config", "user.name", FALLBACK_AUTHOR_NAME])?;
+        git(dir, &["config", "user.email", FALLBACK_AUTHOR_EMAIL])?;
+    }
+
+    let has_remote = git_succeeds(dir, &["remote", "get-url", REMOTE_NAME]);
+    match (remote_url, has_remote) {
+        (Some(url), true) => git(dir, &["remote", "set-url", REMOTE_NAME, url]).map(|_| ()),
+        (Some(url), false) => git(dir, &["remote", "add", REMOTE_NAME, url]).map(|_| ()),
+        (None, true) => git(dir, &["remote", "remove", REMOTE_NAME]).map(|_| ()),
+        (None, false) => Ok(()),
+    }
+}
+
+/// Refuses a repository jotin did not create: rewriting its `.gitignore`
+/// and untracking files would wreck it. One made by an earlier version is
+/// recognized by its `.gitignore` and marked.
+fn ensure_managed(dir: &Path) -> Result<(), String> {
+    let marked = git(dir, &["config", "--get", MANAGED_CONFIG_KEY])
+        .is_ok_and(|value| value.trim() == "true");
+    if marked {
+        return Ok(());
+    }
+
+    let gitignore = fs::read_to_string(dir.join(".gitignore")).unwrap_or_default();
+    if gitignore != gitignore_contents() && !LEGACY_GITIGNORES.contains(&gitignore.as_str()) {
+        return Err(format!(
+            "{} is already a git repository that Jotin did not create; choose another data directory for git sync",
+            dir.display()
+        ));
+    }
+    git(dir, &["config", MANAGED_CONFIG_KEY, "true"]).map(|_| ())
+}
+
+/// Only the notes file is committed. Everything else in the data directory
+/// stays on this machine: settings hold credentials, and attachments,
+/// history, backups, sync state and lock files are local or per machine.
+fn gitignore_contents() -> String {
+    format!("*\n!.gitignore\n!{NOTES_FILE_NAME}\n")
+}
+
+fn current_branch(dir: &Path) -> Result<String, String> {
+    git(dir, &["symbolic-ref", "--short", "HEAD"]).map(|branch| branch.trim().to_string())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {} failed: {}", args[0], stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn git_succeeds(dir: &Path, args: &[&str]) -> bool {
+    git(dir, args).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("jotin-git-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn leaves_foreign_repositories_alone() {
+        let dir = temp_dir();
+        git(&dir, &["init", "--quiet"]).unwrap();
+        fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+
+        assert!(prepare_repository(&dir, None).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join(".gitignore")).unwrap(),
+            "target/\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn manages_repositories_it_created() {
+        let dir = temp_dir();
+        prepare_repository(&dir, None).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(".gitignore")).unwrap(),
+            gitignore_contents()
+        );
+        prepare_repository(&dir, None).unwrap();
+
+        let legacy = temp_dir();
+        git(&legacy, &["init", "--quiet"]).unwrap();
+        fs::write(legacy.join(".gitignore"), LEGACY_GITIGNORES[1]).unwrap();
+        prepare_repository(&legacy, None).unwrap();
+        assert!(git(&legacy, &["config", "--get", MANAGED_CONFIG_KEY]).is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&legacy).unwrap();
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    encryption, ensure_writable, find_active_note_mut, locks, resolve_data_file, shred,
+    write_file_atomically, Note, StorageState, NOTES_CHANGED_EVENT,
+};
+
+const HISTORY_DIR_NAME: &str = "history";
+const MAX_VERSIONS_PER_NOTE: usize = 50;
+/// Beyond this many line pairs the diff degrades to "all removed, all added"
+/// instead of running a quadratic comparison, and a sync merge gives up.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// A previous text of a note, as it was before an edit replaced it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct NoteVersion {
+    version: u32,
+    text: String,
+    /// When this text was last saved, i.e. when it became the note's text.
+    saved_at: String,
+    /// When it was replaced and moved into history.
+    replaced_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DiffKind {
+    Unchanged,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct DiffLine {
+    kind: DiffKind,
+    text: String,
+}
+
+/// Previous versions of a note, newest first.
+#[tauri::command]
+pub(crate) fn list_note_versions(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<Vec<NoteVersion>, String> {
+    let _guard = state.lock(&app)?;
+
+    let mut versions = load_versions(&app, &id)?;
+    versions.reverse();
+    Ok(versions)
+}
+
+/// Line diff from a stored version to the note's current text.
+#[tauri::command]
+pub(crate) fn diff_note_version(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    version: u32,
+) -> Result<Vec<DiffLine>, String> {
+    let storage = state.lock(&app)?;
+
+    let notes = storage.notes()?;
+    let note = notes
+        .iter()
+        .find(|note| note.id == id)
+        .ok_or_else(|| "Note not found".to_string())?;
+    let versions = load_versions(&app, &id)?;
+    let old = find_version(&versions, version)?;
+
+    Ok(diff_lines(&old.text, &note.text))
+}
+
+/// Brings back an old text. The text being replaced goes into history too,
+/// so restoring is itself reversible.
+#[tauri::command]
+pub(crate) fn restore_note_version(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    version: u32,
+) -> Result<Note, String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+    ensure_writable(note)?;
+    locks::ensure_not_locked(note)?;
+    let restored_text = find_version(&load_versions(&app, &id)?, version)?
+        .text
+        .clone();
+
+    record_version(&app, note)?;
+    note.set_text(restored_text);
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let restored = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&restored));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(restored)
+}
+
+/// Stores the note's current text as a new version, dropping the oldest
+/// beyond the per-note limit. Call before overwriting `note.text`.
+pub(crate) fn record_version(app: &AppHandle, note: &Note) -> Result<(), String> {
+    let mut versions = load_versions(app, &note.id)?;
+    if versions.last().is_some_and(|last| last.text == note.text) {
+        return Ok(());
+    }
+
+    let version = versions.last().map_or(1, |last| last.version + 1);
+    versions.push(NoteVersion {
+        version,
+        text: note.text.clone(),
+        saved_at: note
+            .updated_at
+            .clone()
+            .unwrap_or_else(|| note.created_at.clone()),
+        replaced_at: Utc::now().to_rfc3339(),
+    });
+    let excess = versions.len().saturating_sub(MAX_VERSIONS_PER_NOTE);
+    versions.drain(..excess);
+
+    let payload = serde_json::to_string_pretty(&versions)
+        .map_err(|e| format!("Failed to serialize note history: {e}"))?;
+    let payload = encryption::seal(payload)?;
+    write_file_atomically(&history_path(app, &note.id)?, &payload)
+}
+
+/// Deletes a note's history, e.g. once the note is shredded, or locked, as
+/// keeping old texts would defeat the lock.
+pub(crate) fn forget(app: &AppHandle, id: &str) -> Result<(), String> {
+    let path = history_path(app, id)?;
+    match shred::destroy_file(&path) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to remove note history: {error}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Deletes the history of notes that no longer exist. Best effort, like
+/// attachment cleanup: the notes file is already saved.
+pub(crate) fn remove_orphaned_histories(app: &AppHandle, notes: &[Note]) {
+    let Ok(dir) = resolve_history_dir(app) else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return;
+    };
+
+    let known: HashSet<&str> = notes.iter().map(|note| note.id.as_str()).collect();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        if !known.contains(id) {
+            if let Err(error) = shred::destroy_file(&path) {
+                eprintln!("Failed to remove history for {id}: {error}");
+            }
+        }
+    }
+}
+
+/// Every note's history file.
+pub(crate) fn history_files(app: &AppHandle) -> Vec<PathBuf> {
+    let Ok(entries) = resolve_history_dir(app).and_then(|dir| {
+        fs::read_dir(dir).map_err(|e| format!("Failed to read history directory: {e}"))
+    }) else {
+        return Vec::new();
+    };
+    entries.flatten().map(|entry| entry.path()).collect()
+}
+
+fn find_version(versions: &[NoteVersion], version: u32) -> Result<&NoteVersion, String> {
+    versions
+        .iter()
+        .find(|candidate| candidate.version == version)
+        .ok_or_else(|| "Version not found".to_string())
+}
+
+fn load_versions(app: &AppHandle, id: &str) -> Result<Vec<NoteVersion>, String> {
+    load_versions_from_path(&history_path(app, id)?)
+}
+
+fn load_versions_from_path(path: &Path) -> Result<Vec<NoteVersion>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let raw = fs::read_to_string(path).map_err(|e| format!("Failed to read note history: {e}"))?;
+    let raw = encryption::open(raw)?;
+    serde_json::from_str::<Vec<NoteVersion>>(&raw)
+        .map_err(|e| format!("Failed to parse note history: {e}"))
+}
+
+fn history_path(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+        return Err("Invalid note id".to_string());
+    }
+    Ok(resolve_history_dir(app)?.join(format!("{id}.json")))
+}
+
+fn resolve_history_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = resolve_data_file(app, HISTORY_DIR_NAME)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create history directory: {e}"))?;
+    Ok(dir)
+}
+
+/// Longest-common-subsequence line diff from `old` to `new`.
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let line = |kind, text: &str| DiffLine {
+        kind,
+        text: text.to_string(),
+    };
+    let matches = matching_lines(&old, &new).unwrap_or_else(|| vec![None; old.len()]);
+
+    let mut j = 0;
+    let mut diff = Vec::with_capacity(old.len().max(new.len()));
+    for (text, matched) in old.iter().zip(matches) {
+        let Some(matched) = matched else {
+            diff.push(line(DiffKind::Removed, text));
+            continue;
+        };
+        diff.extend(
+            new[j..matched]
+                .iter()
+                .map(|text| line(DiffKind::Added, text)),
+        );
+        diff.push(line(DiffKind::Unchanged, text));
+        j = matched + 1;
+    }
+    diff.extend(new[j..].iter().map(|text| line(DiffKind::Added, text)));
+    diff
+}
+
+/// For each line of `old`, the line of `new` it is matched to in a longest
+/// common subsequence, if any. `None` when the texts are too long to
+/// compare.
+pub(crate) fn matching_lines(old: &[&str], new: &[&str]) -> Option<Vec<Option<usize>>> {
+    if old.len().saturating_mul(new.len()) > MAX_DIFF_CELLS {
+        return None;
+    }
+
+    // lengths[i][j] = LCS length of old[i..] and new[j..].
+    let width = new.len() + 1;
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut matches = vec![None; old.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            matches[i] = Some(j);
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    Some(matches)
+}
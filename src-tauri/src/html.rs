@@ -0,0 +1,319 @@
+//! Downgrades HTML, as found in exported notes, to the Markdown notes are
+//! written in. Like the Markdown scan it handles what note exports contain
+//! (headings, paragraphs, lists and checklists, emphasis, links, code)
+//! rather than the whole of HTML; unknown tags keep only their text.
+
+/// Elements whose content is never note text.
+const SKIPPED_ELEMENTS: &[&str] = &["head", "script", "style", "title"];
+/// Elements that start a new line of their own.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "div", "en-note", "footer", "header", "section", "table", "tr",
+];
+
+pub(crate) fn to_markdown(html: &str) -> String {
+    let mut converter = Converter::default();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            converter.text(rest);
+            break;
+        };
+        converter.text(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            converter.text(&cdata[..end]);
+            rest = cdata.get(end + 3..).unwrap_or("");
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            converter.text(rest);
+            break;
+        };
+        converter.tag(&rest[1..end]);
+        rest = &rest[end + 1..];
+    }
+    converter.finish()
+}
+
+#[derive(Default)]
+struct Converter {
+    output: String,
+    /// `None` for a bulleted list, the next number for a numbered one.
+    lists: Vec<Option<usize>>,
+    /// Targets of the links currently open.
+    links: Vec<String>,
+    /// Depth inside elements whose content is dropped.
+    skipping: usize,
+    preformatted: bool,
+    quote_depth: usize,
+}
+
+impl Converter {
+    fn text(&mut self, raw: &str) {
+        if self.skipping > 0 || raw.is_empty() {
+            return;
+        }
+        let text = decode_entities(raw);
+        if self.preformatted {
+            self.output.push_str(&text);
+            return;
+        }
+
+        let mut collapsed = String::with_capacity(text.len());
+        for c in text.chars() {
+            if c.is_whitespace() {
+                if !collapsed.ends_with(' ') {
+                    collapsed.push(' ');
+                }
+            } else {
+                collapsed.push(c);
+            }
+        }
+        // Whitespace at the start of a line is indentation in Markdown.
+        if self.output.is_empty() || self.output.ends_with('\n') || self.output.ends_with(' ') {
+            collapsed = collapsed.trim_start().to_string();
+        }
+        self.output.push_str(&collapsed);
+    }
+
+    fn tag(&mut self, tag: &str) {
+        let closing = tag.starts_with('/');
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_start_matches('/').trim_end_matches('/');
+        let (name, attributes) = tag
+            .split_once(|c: char| c.is_whitespace())
+            .unwrap_or((tag, ""));
+        let name = name.to_lowercase();
+
+        if SKIPPED_ELEMENTS.contains(&name.as_str()) {
+            if closing {
+                self.skipping = self.skipping.saturating_sub(1);
+            } else if !self_closing {
+                self.skipping += 1;
+            }
+            return;
+        }
+        if self.skipping > 0 {
+            return;
+        }
+
+        match (name.as_str(), closing) {
+            // Exports write empty lines as `<div><br></div>`.
+            ("br", _) if self.output.ends_with('\n') => self.paragraph(),
+            ("br", _) => self.line_break(),
+            ("p", _) => self.paragraph(),
+            (heading, false) if is_heading(heading) => {
+                self.paragraph();
+                let level = heading[1..].parse::<usize>().unwrap_or(1);
+                self.output.push_str(&"#".repeat(level));
+                self.output.push(' ');
+            }
+            (heading, true) if is_heading(heading) => self.paragraph(),
+            ("b" | "strong", _) => self.output.push_str("**"),
+            ("i" | "em", _) => self.output.push('*'),
+            ("s" | "strike" | "del", _) => self.output.push_str("~~"),
+            ("code" | "tt", _) if !self.preformatted => self.output.push('`'),
+            ("pre", false) => {
+                self.paragraph();
+                self.output.push_str("```\n");
+                self.preformatted = true;
+            }
+            ("pre", true) => {
+                self.preformatted = false;
+                self.line_break();
+                self.output.push_str("```");
+                self.paragraph();
+            }
+            ("blockquote", false) => {
+                self.paragraph();
+                self.quote_depth += 1;
+                self.start_line();
+            }
+            ("blockquote", true) => {
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.paragraph();
+            }
+            ("ul", false) => self.open_list(None),
+            ("ol", false) => self.open_list(Some(1)),
+            ("ul" | "ol", true) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.paragraph();
+                }
+            }
+            ("li", false) => self.list_item(),
+            ("en-todo", false) => {
+                let checked = attribute(attributes, "checked").is_some_and(|value| value == "true");
+                self.output
+                    .push_str(if checked { "- [x] " } else { "- [ ] " });
+            }
+            ("a", false) => {
+                self.links
+                    .push(attribute(attributes, "href").unwrap_or_default());
+                self.output.push('[');
+            }
+            ("a", true) => {
+                let href = self.links.pop().unwrap_or_default();
+                self.output.push_str(&format!("]({href})"));
+            }
+            ("img", _) => {
+                // Inline data URIs would swamp the note; attachments are separate.
+                if let Some(src) =
+                    attribute(attributes, "src").filter(|src| !src.starts_with("data:"))
+                {
+                    let alt = attribute(attributes, "alt").unwrap_or_default();
+                    self.output.push_str(&format!("![{alt}]({src})"));
+                }
+            }
+            ("hr", _) => {
+                self.paragraph();
+                self.output.push_str("---");
+                self.paragraph();
+            }
+            ("td" | "th", true) => self.output.push(' '),
+            (block, _) if BLOCK_ELEMENTS.contains(&block) => self.line_break(),
+            _ => {}
+        }
+    }
+
+    fn open_list(&mut self, numbering: Option<usize>) {
+        if self.lists.is_empty() {
+            self.paragraph();
+        }
+        self.lists.push(numbering);
+    }
+
+    fn list_item(&mut self) {
+        self.line_break();
+        let depth = self.lists.len().saturating_sub(1);
+        self.output.push_str(&"  ".repeat(depth));
+        match self.lists.last_mut() {
+            Some(Some(number)) => {
+                self.output.push_str(&format!("{number}. "));
+                *number += 1;
+            }
+            _ => self.output.push_str("- "),
+        }
+    }
+
+    fn line_break(&mut self) {
+        trim_trailing_spaces(&mut self.output);
+        if !self.output.is_empty() && !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+        self.start_line();
+    }
+
+    fn paragraph(&mut self) {
+        trim_trailing_spaces(&mut self.output);
+        if self.output.is_empty() {
+            return;
+        }
+        while !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+        self.start_line();
+    }
+
+    fn start_line(&mut self) {
+        if self.quote_depth > 0 {
+            self.output.push_str(&"> ".repeat(self.quote_depth));
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut markdown = String::with_capacity(self.output.len());
+        let mut blank_lines = 0;
+        for line in self.output.lines().map(str::trim_end) {
+            let blank = line.trim_start_matches(['>', ' ']).is_empty();
+            blank_lines = if blank { blank_lines + 1 } else { 0 };
+            if blank_lines <= 1 {
+                markdown.push_str(if blank { "" } else { line });
+                markdown.push('\n');
+            }
+        }
+        markdown.trim().to_string()
+    }
+}
+
+fn is_heading(name: &str) -> bool {
+    matches!(name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+}
+
+fn trim_trailing_spaces(output: &mut String) {
+    let trimmed = output.trim_end_matches(' ').len();
+    output.truncate(trimmed);
+}
+
+/// The value of `name` in a tag's attribute list, quoted or not.
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let lowered = attributes.to_ascii_lowercase();
+    let mut search_from = 0;
+    while let Some(found) = lowered[search_from..].find(name) {
+        let start = search_from + found;
+        search_from = start + name.len();
+        let preceded_by_space = lowered[..start]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        let rest = attributes[search_from..].trim_start();
+        let Some(value) = rest.strip_prefix('=').filter(|_| preceded_by_space) else {
+            continue;
+        };
+        let value = value.trim_start();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+            _ => value.split_whitespace().next().unwrap_or_default(),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+pub(crate) fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..=end]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        });
+        match (character, entity) {
+            (Some(character), Some(entity)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
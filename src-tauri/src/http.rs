@@ -0,0 +1,230 @@
+//! Every `curl` the app runs starts here, and JSON requests to web APIs
+//! (Notion, Todoist and the like) are sent from here too. URLs, credentials
+//! and headers travel in a config on stdin, built with `quote_curl_value`,
+//! so tokens never show up in the process list. Request bodies and
+//! responses, which hold note text, go through a `StagedFile` only the user
+//! can read.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::Mutex,
+};
+
+use serde_json::Value;
+use tauri::AppHandle;
+use uuid::Uuid;
+
+use crate::resolve_app_data_file;
+
+const REQUEST_TIMEOUT_SECS: u32 = 60;
+const STAGING_DIR_NAME: &str = "staging";
+
+/// Folder in the app data dir that staged files go to, set at startup. Not
+/// the shared temp dir, where other users could read them, nor the data dir,
+/// which may be a synced folder.
+static STAGING_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+/// What web requests may use, redirects included, so neither a redirect nor
+/// a URL taken from a page can reach `file://`, `ftp://` and the like.
+pub(crate) const WEB_PROTOCOLS: &str = "=http,https";
+
+/// A decoded response; `body` is `Null` when empty and a string when it is
+/// not JSON.
+pub(crate) struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    pub(crate) fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Sends `body` as JSON with `method` and the given `Name: value` headers.
+/// Only transport failures are errors; HTTP errors come back as a status.
+pub(crate) fn send_json(
+    method: &str,
+    url: &str,
+    headers: &[String],
+    body: Option<&Value>,
+) -> Result<Response, String> {
+    let mut config = format!(
+        "url = {}\nrequest = {}\nheader = \"Accept: application/json\"\n",
+        quote_curl_value(url)?,
+        quote_curl_value(method)?
+    );
+    for header in headers {
+        config.push_str(&format!("header = {}\n", quote_curl_value(header)?));
+    }
+
+    let body_file = match body {
+        Some(body) => {
+            let staged = StagedFile::with_contents("request", body.to_string().as_bytes())?;
+            config.push_str("header = \"Content-Type: application/json\"\n");
+            config.push_str(&format!(
+                "data-binary = {}\n",
+                quote_curl_value(&format!("@{}", staged.path().to_string_lossy()))?
+            ));
+            Some(staged)
+        }
+        None => None,
+    };
+
+    let output = run_curl(
+        WEB_PROTOCOLS,
+        REQUEST_TIMEOUT_SECS,
+        &config,
+        &["--location", "--write-out", "\n%{http_code}"],
+    );
+    drop(body_file);
+    let output = output.map_err(|e| format!("Request failed: {e}"))?;
+    let output = String::from_utf8_lossy(&output);
+
+    // The status is written after the body, on a line of its own.
+    let (body, status) = output.rsplit_once('\n').unwrap_or(("", output.as_ref()));
+    let status = status
+        .trim()
+        .parse::<u16>()
+        .map_err(|_| format!("Unexpected curl status output: {status}"))?;
+    let body = match body.trim() {
+        "" => Value::Null,
+        raw => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+    };
+    Ok(Response { status, body })
+}
+
+/// Starts `curl` with `config` on stdin, limited to `protocols` (in curl's
+/// `--proto` syntax) and to `timeout_secs`. `args` come last; stdout and
+/// stderr are piped.
+pub(crate) fn spawn_curl(
+    protocols: &str,
+    timeout_secs: u32,
+    config: &str,
+    args: &[&str],
+) -> Result<Child, String> {
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error"])
+        .args(["--proto", protocols, "--proto-redir", protocols])
+        .args(["--max-time", &timeout_secs.to_string()])
+        .args(["--config", "-"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run curl: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(config.as_bytes())
+            .map_err(|e| format!("Failed to configure curl: {e}"))?;
+    }
+    Ok(child)
+}
+
+/// Runs `spawn_curl` to the end and returns what it wrote to stdout. When
+/// curl itself fails the error is its message, for callers to prefix.
+pub(crate) fn run_curl(
+    protocols: &str,
+    timeout_secs: u32,
+    config: &str,
+    args: &[&str],
+) -> Result<Vec<u8>, String> {
+    let output = spawn_curl(protocols, timeout_secs, config, args)?
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run curl: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(stderr.trim().to_string());
+    }
+    Ok(output.stdout)
+}
+
+/// Quotes a value for a curl config. Line breaks would end the option and
+/// start another one, so control characters are refused rather than escaped.
+pub(crate) fn quote_curl_value(value: &str) -> Result<String, String> {
+    if value.chars().any(char::is_control) {
+        return Err(
+            "Refusing to pass a value with a line break or control character to curl".to_string(),
+        );
+    }
+    Ok(format!(
+        "\"{}\"",
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    ))
+}
+
+/// Also clears what a previous run left behind when it did not get to
+/// remove its files.
+pub(crate) fn load_staging_dir(app: &AppHandle) {
+    match resolve_app_data_file(app, STAGING_DIR_NAME) {
+        Ok(dir) => {
+            let _ = fs::remove_dir_all(&dir);
+            if let Ok(mut staging) = STAGING_DIR.lock() {
+                *staging = Some(dir);
+            }
+        }
+        Err(error) => eprintln!("Failed to resolve the staging directory: {error}"),
+    }
+}
+
+/// A file curl reads a request body from or writes a response to. It is
+/// created before curl runs, so it is private to the user from the start,
+/// and removed when dropped.
+pub(crate) struct StagedFile {
+    path: PathBuf,
+}
+
+impl StagedFile {
+    /// An empty file for curl to write to.
+    pub(crate) fn new(purpose: &str) -> Result<Self, String> {
+        Self::with_contents(purpose, &[])
+    }
+
+    pub(crate) fn with_contents(purpose: &str, contents: &[u8]) -> Result<Self, String> {
+        let dir = STAGING_DIR
+            .lock()
+            .map_err(|_| "Staging directory lock was poisoned".to_string())?
+            .clone()
+            .ok_or_else(|| "Staging directory is not set up".to_string())?;
+        create_private_dir(&dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+
+        let path = dir.join(format!("{purpose}-{}.tmp", Uuid::new_v4().simple()));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(&path)
+            .map_err(|e| format!("Failed to stage {purpose}: {e}"))?;
+        let staged = Self { path };
+        file.write_all(contents)
+            .map_err(|e| format!("Failed to stage {purpose}: {e}"))?;
+        Ok(staged)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// What curl wrote; empty when it wrote nothing.
+    pub(crate) fn read(&self) -> Vec<u8> {
+        fs::read(&self.path).unwrap_or_default()
+    }
+}
+
+impl Drop for StagedFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
@@ -0,0 +1,441 @@
+//! Loopback HTTP API for scripts, launchers and the browser extension:
+//! create, list and search notes, and report the active browser tab. It
+//! listens on `127.0.0.1` only while enabled in settings, and every request
+//! must carry the token kept in the keychain, as `Authorization: Bearer` or
+//! `X-Jotin-Token`.
+//!
+//! Each connection serves one request and closes. Slow or idle clients are
+//! cut off by a deadline for the whole request and a write timeout, and at
+//! most `MAX_CONNECTIONS` are served at once; further ones are closed
+//! unanswered.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Take, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, Url};
+
+use crate::{
+    capture_source, insert_note, list_notes, search_notes,
+    settings::{self, Secret},
+    StorageState,
+};
+
+pub(crate) const DEFAULT_HTTP_API_PORT: u16 = 47_820;
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// Time a client has to send its whole request, trickled or not.
+const REQUEST_DEADLINE: Duration = Duration::from_secs(10);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_CONNECTIONS: usize = 16;
+static CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+static SERVER: Mutex<Option<RunningServer>> = Mutex::new(None);
+/// The token requests must present, read from the keychain by `apply`
+/// rather than for every request.
+static TOKEN: Mutex<Option<String>> = Mutex::new(None);
+
+struct RunningServer {
+    port: u16,
+    stopped: Arc<AtomicBool>,
+}
+
+struct Request {
+    method: String,
+    url: Url,
+    token: Option<String>,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    body: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct CreateNoteBody {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct BrowserTabBody {
+    url: String,
+}
+
+/// Starts, restarts or stops the loopback server to match the saved settings,
+/// and picks up a changed token.
+pub(crate) fn apply(app: &AppHandle) -> Result<(), String> {
+    let settings = settings::load_settings(app)?;
+    let token = Secret::HttpApiToken.get()?;
+    *TOKEN
+        .lock()
+        .map_err(|_| "HTTP API token was poisoned".to_string())? = token;
+    let mut server = SERVER
+        .lock()
+        .map_err(|_| "HTTP API state was poisoned".to_string())?;
+
+    let wanted_port = settings.http_api_enabled.then_some(settings.http_api_port);
+    if server.as_ref().map(|running| running.port) == wanted_port {
+        return Ok(());
+    }
+
+    if let Some(running) = server.take() {
+        running.stopped.store(true, Ordering::SeqCst);
+        // Wake the blocking accept so the listener thread can exit.
+        let _ = TcpStream::connect((Ipv4Addr::LOCALHOST, running.port));
+    }
+
+    let Some(port) = wanted_port else {
+        return Ok(());
+    };
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .map_err(|e| format!("Failed to start HTTP API on port {port}: {e}"))?;
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    let app = app.clone();
+    let thread_stopped = stopped.clone();
+    std::thread::spawn(move || serve(app, listener, thread_stopped));
+
+    *server = Some(RunningServer { port, stopped });
+    Ok(())
+}
+
+fn serve(app: AppHandle, listener: TcpListener, stopped: Arc<AtomicBool>) {
+    for stream in listener.incoming() {
+        if stopped.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(stream) = stream else {
+            continue;
+        };
+        // Dropping the stream closes it.
+        let Some(slot) = ConnectionSlot::take() else {
+            continue;
+        };
+
+        let app = app.clone();
+        std::thread::spawn(move || {
+            if let Err(error) = handle_connection(&app, stream) {
+                eprintln!("HTTP API connection failed: {error}");
+            }
+            drop(slot);
+        });
+    }
+}
+
+/// One of the `MAX_CONNECTIONS` served at once, given back on drop.
+struct ConnectionSlot;
+
+impl ConnectionSlot {
+    fn take() -> Option<Self> {
+        CONNECTIONS
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                (open < MAX_CONNECTIONS).then_some(open + 1)
+            })
+            .ok()
+            .map(|_| Self)
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        CONNECTIONS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Reads from the stream until `deadline`, however the reads are spread.
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "request took too long",
+            ));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        (&mut &*self.stream).read(buf)
+    }
+}
+
+fn handle_connection(app: &AppHandle, stream: TcpStream) -> Result<(), String> {
+    stream
+        .set_write_timeout(Some(WRITE_TIMEOUT))
+        .map_err(|e| format!("Failed to configure connection: {e}"))?;
+
+    let reader = DeadlineReader {
+        stream: &stream,
+        deadline: Instant::now() + REQUEST_DEADLINE,
+    };
+    let response = match read_request(reader) {
+        Ok(request) => route(app, request),
+        Err(error) => Response::error(400, error),
+    };
+    write_response(stream, response)
+}
+
+fn route(app: &AppHandle, request: Request) -> Response {
+    let expected_token = match TOKEN.lock() {
+        Ok(token) => token.clone(),
+        Err(_) => return Response::error(500, "HTTP API token was poisoned".to_string()),
+    };
+    let authorized = match (&request.token, &expected_token) {
+        (Some(given), Some(expected)) => token_matches(given, expected),
+        _ => false,
+    };
+    if !authorized {
+        return Response::error(401, "Missing or invalid token".to_string());
+    }
+
+    let state = app.state::<StorageState>();
+    let result = match (request.method.as_str(), request.url.path()) {
+        ("POST", "/notes") => parse_note_text(&request.body)
+            .and_then(|text| insert_note(app, &state, &text))
+            .map(|note| (201, json!(note))),
+        ("GET", "/notes") => {
+            let notebook_id = query_param(&request.url, "notebook_id");
+            let sort = query_param(&request.url, "sort")
+                .and_then(|sort| serde_json::from_value(json!(sort)).ok());
+            let color = query_param(&request.url, "color");
+            let (from, to) = query_range(&request.url);
+            list_notes(app.clone(), state, notebook_id, sort, color, from, to).map(|mut notes| {
+                if let Some(limit) = query_limit(&request.url) {
+                    notes.truncate(limit);
+                }
+                (200, json!(notes))
+            })
+        }
+        ("GET", "/search") => {
+            let query = query_param(&request.url, "q").unwrap_or_default();
+            let (from, to) = query_range(&request.url);
+            search_notes(
+                app.clone(),
+                state,
+                query,
+                query_limit(&request.url),
+                from,
+                to,
+            )
+            .map(|results| (200, json!(results)))
+        }
+        ("POST", "/browser-tab") => serde_json::from_slice::<BrowserTabBody>(&request.body)
+            .map_err(|e| format!("Invalid JSON body: {e}"))
+            .and_then(|tab| capture_source::report_browser_tab(&tab.url))
+            .map(|()| (200, json!({}))),
+        (_, "/notes" | "/search" | "/browser-tab") => {
+            return Response::error(405, "Method not allowed".to_string());
+        }
+        _ => return Response::error(404, "Not found".to_string()),
+    };
+
+    match result {
+        Ok((status, body)) => Response { status, body },
+        Err(error) => Response::error(422, error),
+    }
+}
+
+/// Accepts either `{"text": "..."}` or a plain-text body.
+fn parse_note_text(body: &[u8]) -> Result<String, String> {
+    let body = std::str::from_utf8(body).map_err(|_| "Body must be UTF-8".to_string())?;
+    if body.trim_start().starts_with('{') {
+        return serde_json::from_str::<CreateNoteBody>(body)
+            .map(|payload| payload.text)
+            .map_err(|e| format!("Invalid JSON body: {e}"));
+    }
+    Ok(body.to_string())
+}
+
+fn query_param(url: &Url, key: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.into_owned())
+}
+
+fn query_limit(url: &Url) -> Option<usize> {
+    query_param(url, "limit").and_then(|limit| limit.parse().ok())
+}
+
+/// `from` and `to`, in any form `DateRange` accepts.
+fn query_range(url: &Url) -> (Option<String>, Option<String>) {
+    (query_param(url, "from"), query_param(url, "to"))
+}
+
+/// Compares in constant time, so response timing does not give the token
+/// away byte by byte; hashing first hides its length as well.
+fn token_matches(given: &str, expected: &str) -> bool {
+    let (given, expected) = (Sha256::digest(given), Sha256::digest(expected));
+    given
+        .iter()
+        .zip(expected.iter())
+        .fold(0, |difference, (a, b)| difference | (a ^ b))
+        == 0
+}
+
+fn read_request(stream: impl Read) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream);
+    // The request line and headers together never buffer more than this,
+    // however long a line a client sends.
+    let mut head = (&mut reader).take(MAX_HEADER_BYTES as u64);
+    let mut request_line = String::new();
+    read_head_line(&mut head, &mut request_line)?;
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err("Malformed request line".to_string());
+    };
+    let url = Url::parse(&format!("http://localhost{target}"))
+        .map_err(|e| format!("Invalid request target: {e}"))?;
+
+    let mut content_length = 0;
+    let mut token = None;
+    loop {
+        let mut line = String::new();
+        let read = read_head_line(&mut head, &mut line)?;
+        let line = line.trim_end();
+        if read == 0 || line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => {
+                content_length = value
+                    .parse::<usize>()
+                    .map_err(|_| "Invalid Content-Length".to_string())?;
+            }
+            "authorization" => {
+                token = value
+                    .strip_prefix("Bearer ")
+                    .map(|token| token.trim().to_string());
+            }
+            "x-jotin-token" if token.is_none() => token = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    if content_length > MAX_BODY_BYTES {
+        return Err("Request body too large".to_string());
+    }
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| format!("Failed to read request body: {e}"))?;
+
+    Ok(Request {
+        method: method.to_ascii_uppercase(),
+        url,
+        token,
+        body,
+    })
+}
+
+/// Reads one line of the request head, failing once the head outgrows
+/// `MAX_HEADER_BYTES` instead of buffering the rest of the line.
+fn read_head_line(head: &mut Take<impl BufRead>, line: &mut String) -> Result<usize, String> {
+    let read = head
+        .read_line(line)
+        .map_err(|e| format!("Failed to read request: {e}"))?;
+    if head.limit() == 0 && !line.ends_with('\n') {
+        return Err("Headers too large".to_string());
+    }
+    Ok(read)
+}
+
+fn write_response(mut stream: TcpStream, response: Response) -> Result<(), String> {
+    let body = response.body.to_string();
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        status_text(response.status),
+        body.len()
+    );
+
+    stream
+        .write_all(head.as_bytes())
+        .and_then(|()| stream.write_all(body.as_bytes()))
+        .map_err(|e| format!("Failed to write response: {e}"))
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        422 => "Unprocessable Entity",
+        _ => "Internal Server Error",
+    }
+}
+
+impl Response {
+    fn error(status: u16, message: String) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connections_beyond_the_cap_are_refused() {
+        let slots: Vec<ConnectionSlot> = (0..MAX_CONNECTIONS)
+            .map_while(|_| ConnectionSlot::take())
+            .collect();
+        assert_eq!(slots.len(), MAX_CONNECTIONS);
+        assert!(ConnectionSlot::take().is_none());
+        drop(slots);
+        assert!(ConnectionSlot::take().is_some());
+    }
+
+    #[test]
+    fn tokens_match_only_exactly() {
+        assert!(token_matches("secret", "secret"));
+        assert!(!token_matches("secreT", "secret"));
+        assert!(!token_matches("secret", "secret2"));
+        assert!(!token_matches("", "secret"));
+    }
+
+    #[test]
+    fn head_lines_stop_at_the_limit() {
+        let raw = format!("GET / HTTP/1.1\r\n{}", "a".repeat(MAX_HEADER_BYTES * 2));
+        let mut head = raw.as_bytes().take(MAX_HEADER_BYTES as u64);
+        let mut line = String::new();
+        assert_eq!(read_head_line(&mut head, &mut line), Ok(16));
+        line.clear();
+        assert_eq!(
+            read_head_line(&mut head, &mut line).unwrap_err(),
+            "Headers too large"
+        );
+        assert!(line.len() < MAX_HEADER_BYTES);
+    }
+
+    #[test]
+    fn head_may_end_with_the_connection() {
+        let mut head = b"Host: x".as_slice().take(MAX_HEADER_BYTES as u64);
+        let mut line = String::new();
+        assert_eq!(read_head_line(&mut head, &mut line), Ok(7));
+        assert_eq!(line, "Host: x");
+    }
+}
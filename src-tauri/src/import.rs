@@ -0,0 +1,217 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, State};
+use uuid::Uuid;
+
+use crate::{frontmatter, normalize_tags, Note, StorageState, NOTES_CHANGED_EVENT};
+
+const IMPORTABLE_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct SkippedFile {
+    path: String,
+    reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct ImportSummary {
+    imported: usize,
+    skipped: Vec<SkippedFile>,
+}
+
+impl ImportSummary {
+    pub(crate) fn skip(&mut self, path: impl Into<String>, reason: impl Into<String>) {
+        self.skipped.push(SkippedFile {
+            path: path.into(),
+            reason: reason.into(),
+        });
+    }
+}
+
+/// A note read by one of the importers, with where it came from for the
+/// summary.
+pub(crate) struct ImportedNote {
+    pub source: String,
+    pub note: Note,
+}
+
+/// Saves what an importer read, skipping notes whose text already exists
+/// (or came earlier in the same import).
+pub(crate) fn store_imported(
+    app: &AppHandle,
+    state: &StorageState,
+    imported: Vec<ImportedNote>,
+    mut summary: ImportSummary,
+) -> Result<ImportSummary, String> {
+    let storage = state.lock(app)?;
+
+    let mut notes = storage.load_notes()?;
+    let new_notes = keep_new(&notes, imported, &mut summary);
+    notes.extend(new_notes);
+
+    if summary.imported > 0 {
+        storage.save_notes(&notes)?;
+        state.invalidate_search_index();
+        let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    }
+    Ok(summary)
+}
+
+/// What `store_imported` would add, without saving anything.
+pub(crate) fn preview_imported(
+    app: &AppHandle,
+    state: &StorageState,
+    imported: Vec<ImportedNote>,
+    mut summary: ImportSummary,
+) -> Result<(ImportSummary, Vec<Note>), String> {
+    let notes = state.lock(app)?.notes()?;
+    let new_notes = keep_new(&notes, imported, &mut summary);
+    Ok((summary, new_notes))
+}
+
+/// Drops empty notes and duplicates, recording them in the summary. A note
+/// whose id is already taken gets a new one.
+fn keep_new(notes: &[Note], imported: Vec<ImportedNote>, summary: &mut ImportSummary) -> Vec<Note> {
+    let mut known_hashes: HashSet<[u8; 32]> =
+        notes.iter().map(|note| content_hash(&note.text)).collect();
+    let mut known_ids: HashSet<String> = notes.iter().map(|note| note.id.clone()).collect();
+    let mut new_notes = Vec::new();
+    for ImportedNote { source, mut note } in imported {
+        if note.text.trim().is_empty() {
+            summary.skip(source, "Note is empty");
+            continue;
+        }
+        if !known_hashes.insert(content_hash(&note.text)) {
+            summary.skip(source, "Duplicate of an existing note");
+            continue;
+        }
+        if !known_ids.insert(note.id.clone()) {
+            note.id = Uuid::new_v4().to_string();
+            known_ids.insert(note.id.clone());
+        }
+        new_notes.push(note);
+        summary.imported += 1;
+    }
+    new_notes
+}
+
+/// Recursively imports Markdown and plain-text files, one note per file.
+///
+/// Files whose text already exists as a note (or earlier in the same import)
+/// are skipped, so importing the same folder twice is harmless.
+#[tauri::command]
+pub(crate) fn import_from_directory(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    path: String,
+) -> Result<ImportSummary, String> {
+    let root = PathBuf::from(path.trim());
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+
+    let mut files = Vec::new();
+    collect_importable_files(&root, &mut files)?;
+    files.sort();
+
+    let mut summary = ImportSummary::default();
+    let mut imported = Vec::new();
+    for file in files {
+        let source = file
+            .strip_prefix(&root)
+            .unwrap_or(&file)
+            .display()
+            .to_string();
+        let raw = match fs::read_to_string(&file) {
+            Ok(raw) => raw,
+            Err(error) => {
+                summary.skip(source, format!("Failed to read file: {error}"));
+                continue;
+            }
+        };
+
+        let document = frontmatter::parse_markdown(&raw);
+        let mut note = Note::new(document.body);
+        if let Some(id) = document.id.filter(|id| Uuid::parse_str(id).is_ok()) {
+            note.id = id;
+        }
+        note.created_at = document
+            .created_at
+            .and_then(|value| normalize_timestamp(&value))
+            .or_else(|| file_modified_at(&file))
+            .unwrap_or(note.created_at);
+        note.updated_at = document
+            .updated_at
+            .and_then(|value| normalize_timestamp(&value));
+        note.tags = normalize_tags(document.tags);
+        imported.push(ImportedNote { source, note });
+    }
+
+    store_imported(&app, &state, imported, summary)
+}
+
+fn collect_importable_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {e}", dir.display()))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+        // Symlinks are skipped rather than followed, so a link back up the
+        // tree cannot loop and a link elsewhere cannot pull in other files.
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if is_hidden || file_type.is_symlink() {
+            continue;
+        }
+
+        if file_type.is_dir() {
+            collect_importable_files(&path, files)?;
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                IMPORTABLE_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+            })
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// SHA-256 of the whitespace-normalized text, used to spot duplicate
+/// content.
+pub(crate) fn content_hash(text: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for word in text.split_whitespace() {
+        hasher.update(word.as_bytes());
+        hasher.update(b" ");
+    }
+    hasher.finalize().into()
+}
+
+pub(crate) fn normalize_timestamp(value: &str) -> Option<String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.to_rfc3339());
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc().to_rfc3339())
+}
+
+pub(crate) fn file_modified_at(path: &Path) -> Option<String> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(DateTime::<Utc>::from(modified).to_rfc3339())
+}
@@ -0,0 +1,94 @@
+//! Calendar-style browsing by the local day notes were created on.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use chrono::{Months, NaiveDate};
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::{daily::local_date, Note, SnapshotCache, StorageState};
+
+static DAY_INDEX: SnapshotCache<DayIndex> = SnapshotCache::new("Day index", DayIndex::build);
+
+/// Day to the positions of its active notes in the snapshot, oldest first.
+#[derive(Default)]
+struct DayIndex(BTreeMap<NaiveDate, Vec<usize>>);
+
+impl DayIndex {
+    fn build(notes: &[Note]) -> Self {
+        let mut by_day: BTreeMap<NaiveDate, Vec<usize>> = BTreeMap::new();
+        for (position, note) in notes.iter().enumerate() {
+            if note.is_trashed() {
+                continue;
+            }
+            if let Some(day) = local_date(&note.created_at) {
+                by_day.entry(day).or_default().push(position);
+            }
+        }
+        for positions in by_day.values_mut() {
+            positions.sort_by(|a, b| notes[*a].created_at.cmp(&notes[*b].created_at));
+        }
+        Self(by_day)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct DaySummary {
+    /// `YYYY-MM-DD`.
+    date: String,
+    count: usize,
+}
+
+/// Active notes created on `date` (`YYYY-MM-DD`, local time), oldest first.
+#[tauri::command]
+pub(crate) fn list_notes_by_day(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    date: String,
+) -> Result<Vec<Note>, String> {
+    let day = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date \"{}\", expected YYYY-MM-DD", date.trim()))?;
+
+    let storage = state.lock(&app)?;
+    let notes = storage.notes()?;
+
+    let positions = with_index(&notes, |index| index.0.get(&day).cloned())?;
+    Ok(positions
+        .unwrap_or_default()
+        .into_iter()
+        .map(|position| notes[position].clone())
+        .collect())
+}
+
+/// The days of `month` (`YYYY-MM`) that have notes, with how many.
+#[tauri::command]
+pub(crate) fn list_days_with_notes(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    month: String,
+) -> Result<Vec<DaySummary>, String> {
+    let first = NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid month \"{}\", expected YYYY-MM", month.trim()))?;
+    let next = first
+        .checked_add_months(Months::new(1))
+        .ok_or_else(|| "Month is out of range".to_string())?;
+
+    let storage = state.lock(&app)?;
+    let notes = storage.notes()?;
+
+    with_index(&notes, |index| {
+        index
+            .0
+            .range(first..next)
+            .map(|(day, positions)| DaySummary {
+                date: day.format("%Y-%m-%d").to_string(),
+                count: positions.len(),
+            })
+            .collect()
+    })
+}
+
+fn with_index<T>(notes: &Arc<Vec<Note>>, read: impl FnOnce(&DayIndex) -> T) -> Result<T, String> {
+    DAY_INDEX.with(notes, read)
+}
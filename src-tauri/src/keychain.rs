@@ -0,0 +1,181 @@
+//! Secrets kept in the OS credential store rather than in `settings.json`:
+//! the macOS keychain through `security`, the Secret Service through
+//! `secret-tool` on Linux and the Windows credential vault through
+//! PowerShell. Secrets are always passed on stdin, never as arguments.
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+/// Must match `identifier` in `tauri.conf.json`.
+const SERVICE: &str = "com.chainyo.jotin";
+
+/// Stores `secret` for `account`, replacing any previous one.
+pub(crate) fn set_secret(account: &str, secret: &str) -> Result<(), String> {
+    let output = store(account, secret)?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(format!(
+        "Failed to save to the system keychain: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+/// The secret stored for `account`, or `None` when there is none.
+pub(crate) fn get_secret(account: &str) -> Result<Option<String>, String> {
+    let output = lookup(account)?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let secret = String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches(['\r', '\n'])
+        .to_string();
+    Ok(Some(secret).filter(|secret| !secret.is_empty()))
+}
+
+pub(crate) fn delete_secret(account: &str) -> Result<(), String> {
+    // Deleting what is not there fails; either way it is gone.
+    clear(account).map(drop)
+}
+
+#[cfg(target_os = "macos")]
+fn store(account: &str, secret: &str) -> Result<Output, String> {
+    // `security -i` reads commands from stdin, which keeps the secret out
+    // of the process list. A line break would start another command.
+    if secret.chars().any(char::is_control) {
+        return Err("A secret cannot contain line breaks or control characters".to_string());
+    }
+    let command = format!(
+        "add-generic-password -U -s {} -a {} -w {}\n",
+        quote(SERVICE),
+        quote(account),
+        quote(secret)
+    );
+    run("security", &["-i"], Some(&command))
+}
+
+#[cfg(target_os = "macos")]
+fn lookup(account: &str) -> Result<Output, String> {
+    run(
+        "security",
+        &["find-generic-password", "-s", SERVICE, "-a", account, "-w"],
+        None,
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn clear(account: &str) -> Result<Output, String> {
+    run(
+        "security",
+        &["delete-generic-password", "-s", SERVICE, "-a", account],
+        None,
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(target_os = "windows")]
+const VAULT_SCRIPT_PREAMBLE: &str = "[void][Windows.Security.Credentials.PasswordVault,\
+    Windows.Security.Credentials,ContentType=WindowsRuntime]; \
+    $vault = New-Object Windows.Security.Credentials.PasswordVault; ";
+
+#[cfg(target_os = "windows")]
+fn store(account: &str, secret: &str) -> Result<Output, String> {
+    let script = format!(
+        "{VAULT_SCRIPT_PREAMBLE}$vault.Add((New-Object Windows.Security.Credentials.PasswordCredential(\
+        $env:JOTIN_SERVICE, $env:JOTIN_ACCOUNT, [Console]::In.ReadToEnd())))"
+    );
+    run_powershell(&script, account, Some(secret))
+}
+
+#[cfg(target_os = "windows")]
+fn lookup(account: &str) -> Result<Output, String> {
+    let script = format!(
+        "{VAULT_SCRIPT_PREAMBLE}$credential = $vault.Retrieve($env:JOTIN_SERVICE, $env:JOTIN_ACCOUNT); \
+        $credential.RetrievePassword(); [Console]::Out.Write($credential.Password)"
+    );
+    run_powershell(&script, account, None)
+}
+
+#[cfg(target_os = "windows")]
+fn clear(account: &str) -> Result<Output, String> {
+    let script = format!(
+        "{VAULT_SCRIPT_PREAMBLE}$vault.Remove($vault.Retrieve($env:JOTIN_SERVICE, $env:JOTIN_ACCOUNT))"
+    );
+    run_powershell(&script, account, None)
+}
+
+#[cfg(target_os = "windows")]
+fn run_powershell(script: &str, account: &str, stdin: Option<&str>) -> Result<Output, String> {
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-Command", script])
+        .env("JOTIN_SERVICE", SERVICE)
+        .env("JOTIN_ACCOUNT", account);
+    run_command(command, "powershell", stdin)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn store(account: &str, secret: &str) -> Result<Output, String> {
+    let label = format!("jotin {account}");
+    run(
+        "secret-tool",
+        &[
+            "store", "--label", &label, "service", SERVICE, "account", account,
+        ],
+        Some(secret),
+    )
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn lookup(account: &str) -> Result<Output, String> {
+    run(
+        "secret-tool",
+        &["lookup", "service", SERVICE, "account", account],
+        None,
+    )
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn clear(account: &str) -> Result<Output, String> {
+    run(
+        "secret-tool",
+        &["clear", "service", SERVICE, "account", account],
+        None,
+    )
+}
+
+#[cfg(not(target_os = "windows"))]
+fn run(program: &str, args: &[&str], stdin: Option<&str>) -> Result<Output, String> {
+    let mut command = Command::new(program);
+    command.args(args);
+    run_command(command, program, stdin)
+}
+
+fn run_command(mut command: Command, program: &str, stdin: Option<&str>) -> Result<Output, String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                format!("Storing secrets needs {program}, which was not found")
+            }
+            _ => format!("Failed to run {program}: {e}"),
+        })?;
+    if let Some(mut pipe) = child.stdin.take() {
+        if let Some(input) = stdin {
+            pipe.write_all(input.as_bytes())
+                .map_err(|e| format!("Failed to write to {program}: {e}"))?;
+        }
+    }
+    child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run {program}: {e}"))
+}
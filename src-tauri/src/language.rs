@@ -0,0 +1,102 @@
+//! Guesses the language a note is written in from its most common words.
+//! Function words ("the", "les", "und") give away a language after only a
+//! sentence or two. Covers the languages notes are most often written in;
+//! anything else, or too short to tell, stays undetected.
+
+use std::collections::HashSet;
+
+use crate::search;
+
+/// Fewer stop word hits than this are not evidence of anything.
+const MIN_HITS: usize = 2;
+/// Only the beginning is read; a language does not change halfway through.
+const MAX_SCANNED_WORDS: usize = 400;
+
+/// ISO 639-1 codes with their most frequent words. A word shared by several
+/// languages counts for each of them; the distinctive ones decide.
+const STOP_WORDS: [(&str, &[&str]); 7] = [
+    (
+        "en",
+        &[
+            "the", "and", "is", "are", "was", "were", "of", "to", "with", "that", "this", "for",
+            "not", "you", "it", "have", "has", "be", "on", "at", "from", "but", "or", "what",
+            "which", "will", "would", "should", "can", "my", "we", "they",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "et", "est", "des", "du", "une", "un", "pour", "pas", "que", "qui",
+            "dans", "sur", "avec", "ce", "cette", "sont", "mais", "ou", "je", "nous", "vous", "il",
+            "elle", "au", "aux", "ne", "plus", "faire", "être",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ist", "nicht", "ein", "eine", "mit", "für", "auf", "den",
+            "dem", "sich", "auch", "zu", "von", "ich", "wir", "sie", "es", "aber", "oder", "wie",
+            "noch", "nach", "bei", "sind", "wird", "kann", "haben", "werden",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "los", "las", "y", "es", "una", "del", "por", "para", "con", "que", "no", "se",
+            "su", "al", "lo", "como", "más", "pero", "está", "son", "muy", "también", "yo", "hay",
+            "este", "esta", "cuando", "ser", "todo", "hacer", "tiene",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "gli", "della", "di", "che", "è", "per", "non", "con", "sono", "una", "del",
+            "alla", "anche", "come", "più", "ma", "questo", "questa", "nel", "ho", "essere", "io",
+            "lo", "ci", "fare", "molto", "tutto", "quando", "dei", "delle", "sul",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "os", "as", "e", "é", "um", "uma", "do", "da", "dos", "das", "não", "com", "para",
+            "em", "que", "no", "na", "se", "mais", "mas", "como", "foi", "ao", "eu", "você",
+            "isso", "este", "ser", "tem", "muito", "também",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "is", "van", "niet", "dat", "met", "voor", "op", "zijn",
+            "ik", "je", "wij", "ze", "maar", "ook", "nog", "bij", "naar", "wat", "heeft", "hebben",
+            "worden", "wordt", "kan", "dit", "deze", "er", "om", "als",
+        ],
+    ),
+];
+
+/// The ISO 639-1 code of `text`'s language, or `None` when it is unclear.
+pub(crate) fn detect(text: &str) -> Option<String> {
+    let words: Vec<String> = search::tokenize(text)
+        .into_iter()
+        .take(MAX_SCANNED_WORDS)
+        .collect();
+
+    let mut scores: Vec<(&str, usize)> = STOP_WORDS
+        .iter()
+        .map(|(language, stop_words)| {
+            let stop_words: HashSet<&str> = stop_words.iter().copied().collect();
+            let hits = words
+                .iter()
+                .filter(|word| stop_words.contains(word.as_str()))
+                .count();
+            (*language, hits)
+        })
+        .collect();
+    scores.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+
+    match scores.as_slice() {
+        [(language, best), (_, runner_up), ..] if *best >= MIN_HITS && best > runner_up => {
+            Some(language.to_string())
+        }
+        _ => None,
+    }
+}
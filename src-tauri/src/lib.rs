@@ -1,20 +1,109 @@
+mod ai;
+mod append;
+mod apple_notes;
+mod attachments;
+mod autostart;
+mod autotag;
+mod backup;
+mod calc;
+mod capture_source;
+pub mod cli;
+mod clipboard_watcher;
+mod conflicts;
+mod convert;
+mod crdt;
+mod crypto;
+mod csv_import;
+mod daily;
+mod date_range;
+mod deep_link;
+mod dismiss;
+mod draft;
+mod duplicates;
+mod e2e;
+mod email;
+mod embeddings;
+mod encryption;
+mod expiry;
+mod export;
+mod frontmatter;
+mod fuzzy;
+mod gist;
+mod git_sync;
+mod history;
+mod html;
+mod http;
+mod http_api;
+mod import;
+mod journal;
+mod keychain;
+mod language;
+mod links;
+mod locks;
+mod markdown;
+mod merge;
+mod notebooks;
+mod notes_watcher;
+mod notifications;
+mod notion;
+mod ocr;
+mod placement;
+mod pomodoro;
+mod print;
+mod publish;
+mod recurrence;
+mod reminders;
+mod s3;
+mod saved_searches;
+mod schema;
+mod screenshot;
+mod search;
+mod selection;
+mod settings;
+mod shortcuts;
+mod shred;
+mod simplenote;
+mod snapshots;
+mod speech;
+mod sync;
+mod templates;
+mod theme;
+mod timers;
+mod todoist;
+mod tray;
+mod undo;
+mod unfurl;
+mod vault;
+mod voice;
+mod window_state;
+mod zip;
+
 use std::{
+    collections::{BTreeMap, HashMap},
     fs,
     path::{Path, PathBuf},
-    sync::Mutex,
-    time::{Duration, Instant},
+    sync::{Arc, Mutex, MutexGuard, RwLock},
+    time::{Duration, SystemTime},
 };
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use tauri::{
-    menu::{MenuBuilder, MenuItemBuilder},
-    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Emitter, Manager, State, WebviewUrl, WebviewWindowBuilder, WindowEvent,
-};
-use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewUrl, WebviewWindowBuilder, WindowEvent};
 use uuid::Uuid;
 
+use attachments::Attachment;
+use capture_source::CaptureSource;
+use conflicts::NoteConflict;
+use date_range::DateRange;
+use fuzzy::FuzzyResult;
+use markdown::NoteMetadata;
+use search::{SearchIndex, SearchResult};
+use settings::Settings;
+use snapshots::LinkSnapshot;
+use timers::TimeEntry;
+use unfurl::LinkPreview;
+use vault::Vault;
+
 const MAIN_WINDOW_LABEL: &str = "main";
 const CAPTURE_WINDOW_LABEL: &str = "capture";
 const NOTES_FILE_NAME: &str = "notes.json";
@@ -22,9 +111,11 @@ const NOTES_CHANGED_EVENT: &str = "notes-changed";
 const CAPTURE_OPENED_EVENT: &str = "capture-opened";
 const CAPTURE_WINDOW_WIDTH: f64 = 900.0;
 const CAPTURE_WINDOW_HEIGHT: f64 = 76.0;
-const DOUBLE_SHORTCUT_WINDOW_MS: u64 = 450;
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+const DEFAULT_PAGE_SIZE: usize = 50;
+const DEFAULT_TAG_SUGGESTIONS: usize = 10;
+const MAX_PAGE_SIZE: usize = 500;
 const APP_ICON: tauri::image::Image<'_> = tauri::include_image!("./icons/32x32.png");
-static LAST_SHORTCUT_PRESS: Mutex<Option<Instant>> = Mutex::new(None);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -33,74 +124,1076 @@ struct Note {
     text: String,
     created_at: String,
     updated_at: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    deleted_at: Option<String>,
+    #[serde(default)]
+    pinned: bool,
+    /// Label color, `#rgb` or `#rrggbb` like notebook colors.
+    #[serde(default)]
+    color: Option<String>,
+    #[serde(default = "notebooks::default_notebook_id")]
+    notebook_id: String,
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+    /// Place in the user's manual order; `None` until notes are reordered.
+    #[serde(default)]
+    position: Option<u32>,
+    /// The journal note for the day it was created on.
+    #[serde(default)]
+    daily: bool,
+    /// Done but kept: left out of the default lists, unlike the trash
+    /// never purged.
+    #[serde(default)]
+    archived: bool,
+    /// When the note moves itself to the trash, for throwaway captures.
+    #[serde(default)]
+    expires_at: Option<String>,
+    /// Protects reference material: editing and deleting are refused until
+    /// it is turned off. Unrelated to `locked`, which hides the text.
+    #[serde(default)]
+    read_only: bool,
+    /// App and window quick capture was opened over, when recorded.
+    #[serde(default)]
+    source: Option<CaptureSource>,
+    /// Address of the browser tab quick capture was opened over, when
+    /// recorded.
+    #[serde(default)]
+    source_url: Option<String>,
+    /// Set while the note's timer runs; see `timers`.
+    #[serde(default)]
+    timer_started_at: Option<String>,
+    /// Time tracked on the note, one entry per stopped timer.
+    #[serde(default)]
+    time_entries: Vec<TimeEntry>,
+    /// Titles and favicons of the note's links, filled in the background.
+    #[serde(default)]
+    link_previews: Vec<LinkPreview>,
+    /// Whether the note's linked pages are saved as offline snapshots.
+    #[serde(default)]
+    snapshot_links: bool,
+    /// The snapshot attachment of each saved link; see `snapshots`.
+    #[serde(default)]
+    link_snapshots: Vec<LinkSnapshot>,
+    /// Text recognized in the image attachments, searchable like `text`.
+    #[serde(default)]
+    ocr_text: Option<String>,
+    /// Written by `summarize_note` when summaries are kept.
+    #[serde(default)]
+    summary: Option<String>,
+    /// The note's Markdown file, relative to the vault, with vault storage.
+    #[serde(default)]
+    vault_path: Option<String>,
+    /// Public URL from the last `publish_note`.
+    #[serde(default)]
+    published_url: Option<String>,
+    /// Sealed text of a locked note, whose `text` is then a placeholder.
+    #[serde(default)]
+    locked: Option<crypto::Envelope>,
+    /// The text's edit history as a base64 `yrs` document, which sync
+    /// merges on; see `crdt`. Lags behind `text` until the next merge.
+    #[serde(default)]
+    crdt: Option<String>,
+    /// The other version, while the note has an unresolved sync conflict.
+    #[serde(default)]
+    conflict: Option<NoteConflict>,
+    /// Derived from `text` by `refresh_metadata` whenever the text changes.
+    #[serde(default)]
+    metadata: NoteMetadata,
+}
+
+impl Note {
+    fn new(text: String) -> Self {
+        let metadata = markdown::extract(&text);
+        Self {
+            id: Uuid::new_v4().to_string(),
+            text,
+            created_at: Utc::now().to_rfc3339(),
+            updated_at: None,
+            tags: Vec::new(),
+            deleted_at: None,
+            pinned: false,
+            color: None,
+            notebook_id: notebooks::default_notebook_id(),
+            attachments: Vec::new(),
+            position: None,
+            daily: false,
+            archived: false,
+            expires_at: None,
+            read_only: false,
+            source: None,
+            source_url: None,
+            timer_started_at: None,
+            time_entries: Vec::new(),
+            link_previews: Vec::new(),
+            snapshot_links: false,
+            link_snapshots: Vec::new(),
+            ocr_text: None,
+            summary: None,
+            vault_path: None,
+            published_url: None,
+            locked: None,
+            crdt: None,
+            conflict: None,
+            metadata,
+        }
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.text = text;
+        self.refresh_metadata();
+    }
+
+    fn refresh_metadata(&mut self) {
+        self.metadata = markdown::extract(&self.text);
+    }
+
+    fn is_trashed(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// Latest moment the note is known to have changed: created, edited or trashed.
+    fn revised_at(&self) -> Option<DateTime<Utc>> {
+        [
+            Some(self.created_at.as_str()),
+            self.updated_at.as_deref(),
+            self.deleted_at.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .max()
+    }
+
+    /// First non-empty line, without Markdown heading markers.
+    fn title(&self) -> &str {
+        self.text
+            .lines()
+            .map(|line| line.trim().trim_start_matches('#').trim())
+            .find(|line| !line.is_empty())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "snake_case")]
+struct NoteFilter {
+    notebook_id: Option<String>,
+    tag: Option<String>,
+    pinned: Option<bool>,
+    /// Archived notes instead of the others; unset means not archived.
+    archived: Option<bool>,
+    color: Option<String>,
+    /// Case-insensitive substring of the note text.
+    text: Option<String>,
+    /// Creation date bounds; see `DateRange::parse`.
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum NoteSort {
+    /// Pinned first, then newest.
+    #[default]
+    Display,
+    Newest,
+    Oldest,
+    RecentlyUpdated,
+    Title,
+    /// The order set with `reorder_notes`; notes never placed come first,
+    /// newest first, so fresh captures stay visible.
+    Manual,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CopyFormat {
+    /// The note text as written.
+    #[default]
+    Markdown,
+    Text,
+    Html,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct NotePage {
+    total: usize,
+    offset: usize,
+    notes: Vec<Note>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct TagSummary {
+    name: String,
+    count: usize,
 }
 
 #[derive(Default)]
 struct StorageState {
     write_lock: Mutex<()>,
+    /// The notes file as last loaded or saved, so commands work in memory and
+    /// only go back to disk when another process has changed the file.
+    notes: RwLock<Option<CachedNotes>>,
+    search_index: Mutex<Option<SearchIndex>>,
+}
+
+struct CachedNotes {
+    path: PathBuf,
+    stamp: Option<FileStamp>,
+    notes: Arc<Vec<Note>>,
+}
+
+/// Something built from one snapshot of the in-memory notes, such as an
+/// index, and reused until a save or reload replaces that snapshot.
+struct SnapshotCache<T> {
+    /// Names the cache in the poisoned lock error.
+    name: &'static str,
+    build: fn(&[Note]) -> T,
+    cached: Mutex<Option<(Arc<Vec<Note>>, T)>>,
+}
+
+impl<T> SnapshotCache<T> {
+    const fn new(name: &'static str, build: fn(&[Note]) -> T) -> Self {
+        Self {
+            name,
+            build,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Reads what was built from `notes`, building it first when the cached
+    /// one came from another snapshot.
+    fn with<R>(&self, notes: &Arc<Vec<Note>>, read: impl FnOnce(&T) -> R) -> Result<R, String> {
+        let mut cached = self
+            .cached
+            .lock()
+            .map_err(|_| format!("{} lock was poisoned", self.name))?;
+        if cached
+            .as_ref()
+            .is_some_and(|(snapshot, _)| !Arc::ptr_eq(snapshot, notes))
+        {
+            *cached = None;
+        }
+        let (_, built) = cached.get_or_insert_with(|| (Arc::clone(notes), (self.build)(notes)));
+        Ok(read(built))
+    }
+}
+
+/// Cheap fingerprint of the notes file: modification time and length.
+type FileStamp = (SystemTime, u64);
+
+/// Held for a whole read-modify-write of the notes file.
+struct StorageGuard<'a> {
+    state: &'a StorageState,
+    notes_path: PathBuf,
+    /// Set when the notes live in an Obsidian vault; `notes_path` is then
+    /// the vault's sidecar file.
+    vault: Option<Vault>,
+    stamp_at_lock: Option<FileStamp>,
+    _write_lock: MutexGuard<'a, ()>,
+    _file_lock: fs::File,
+}
+
+impl StorageGuard<'_> {
+    fn notes_path(&self) -> &Path {
+        &self.notes_path
+    }
+
+    /// Shared view of the current notes, loading them from disk only once.
+    fn notes(&self) -> Result<Arc<Vec<Note>>, String> {
+        let cached = self
+            .state
+            .notes
+            .read()
+            .map_err(|_| "Notes cache was poisoned".to_string())?
+            .as_ref()
+            .map(|cached| Arc::clone(&cached.notes));
+        if let Some(notes) = cached {
+            return Ok(notes);
+        }
+
+        // The file may come from an older version or another tool, so its
+        // derived metadata cannot be trusted.
+        let mut notes = match &self.vault {
+            Some(vault) => vault.load(&self.notes_path)?,
+            None => schema::read_upgrading(&self.notes_path)?,
+        };
+        notes.iter_mut().for_each(Note::refresh_metadata);
+        let notes = Arc::new(notes);
+        self.cache(self.stamp(), Arc::clone(&notes));
+        Ok(notes)
+    }
+
+    /// Owned copy of the current notes for a command to modify and save.
+    fn load_notes(&self) -> Result<Vec<Note>, String> {
+        self.notes().map(Arc::unwrap_or_clone)
+    }
+
+    /// Writes the notes to disk and makes them the new in-memory copy.
+    fn save_notes(&self, notes: &[Note]) -> Result<(), String> {
+        let Some(vault) = &self.vault else {
+            save_notes_to_path(&self.notes_path, notes)?;
+            self.cache(self.stamp(), Arc::new(notes.to_vec()));
+            return Ok(());
+        };
+
+        // Only the files of notes that changed since the last load are written.
+        let previous = self
+            .state
+            .notes
+            .read()
+            .map_err(|_| "Notes cache was poisoned".to_string())?
+            .as_ref()
+            .map(|cached| Arc::clone(&cached.notes))
+            .unwrap_or_default();
+        let saved = vault.save(&self.notes_path, notes, &previous)?;
+        self.cache(self.stamp(), Arc::new(saved));
+        Ok(())
+    }
+
+    fn stamp(&self) -> Option<FileStamp> {
+        storage_stamp(&self.notes_path, self.vault.as_ref())
+    }
+
+    fn cache(&self, stamp: Option<FileStamp>, notes: Arc<Vec<Note>>) {
+        if let Ok(mut cached) = self.state.notes.write() {
+            *cached = Some(CachedNotes {
+                path: self.notes_path.clone(),
+                stamp,
+                notes,
+            });
+        }
+    }
+}
+
+impl Drop for StorageGuard<'_> {
+    fn drop(&mut self) {
+        if self.stamp() != self.stamp_at_lock {
+            git_sync::record_change(&self.notes_path);
+        }
+    }
 }
 
+impl StorageState {
+    /// Serializes storage access within the app and, through an advisory lock
+    /// file, against other processes such as the `jotin` CLI.
+    fn lock(&self, app: &AppHandle) -> Result<StorageGuard<'_>, String> {
+        let write_lock = self
+            .write_lock
+            .lock()
+            .map_err(|_| "Storage lock was poisoned".to_string())?;
+        let (notes_path, vault) = resolve_storage(app)?;
+        let file_lock = lock_notes_file(&notes_path)?;
+
+        // Another process (e.g. the CLI, or Obsidian for a vault) may have
+        // written since we last looked. Our own saves update the cached
+        // stamp, so they never count.
+        let stamp = storage_stamp(&notes_path, vault.as_ref());
+        let mut cached = self
+            .notes
+            .write()
+            .map_err(|_| "Notes cache was poisoned".to_string())?;
+        let unchanged = cached
+            .as_ref()
+            .is_some_and(|cached| cached.path == notes_path && cached.stamp == stamp);
+        if !unchanged {
+            // Only tell windows to reload if they could have seen the old notes.
+            let was_loaded = cached.take().is_some();
+            self.invalidate_search_index();
+            if was_loaded {
+                let _ = app.emit(NOTES_CHANGED_EVENT, ());
+            }
+        }
+        drop(cached);
+
+        Ok(StorageGuard {
+            state: self,
+            notes_path,
+            vault,
+            stamp_at_lock: stamp,
+            _write_lock: write_lock,
+            _file_lock: file_lock,
+        })
+    }
+
+    /// Drops the search index so the next query reopens it, re-indexing the
+    /// notes that changed on disk.
+    fn invalidate_search_index(&self) {
+        if let Ok(mut index) = self.search_index.lock() {
+            *index = None;
+        }
+    }
+
+    /// Applies an incremental change to the search index, if it has been built.
+    fn update_search_index(&self, apply: impl FnOnce(&mut SearchIndex)) {
+        if let Ok(mut index) = self.search_index.lock() {
+            if let Some(index) = index.as_mut() {
+                apply(index);
+            }
+        }
+    }
+}
+
+/// A new note, plus the tags proposed for it that the UI may offer to add.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct CreatedNote {
+    #[serde(flatten)]
+    note: Note,
+    suggested_tags: Vec<String>,
+}
+
+/// With `auto_apply_tag_suggestions` on, suggested tags are added right away
+/// and none are returned. `expires_in` (e.g. `24h`, `7d`) makes the note
+/// move itself to the trash after that long.
 #[tauri::command]
-fn create_note(app: AppHandle, state: State<'_, StorageState>, text: String) -> Result<Note, String> {
-    let note_text = text.trim();
+fn create_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    text: String,
+    expires_in: Option<String>,
+) -> Result<CreatedNote, String> {
+    let (note_text, tags) = split_trailing_tags(text.trim());
     if note_text.is_empty() {
         return Err("Note text cannot be empty".to_string());
     }
+    let expires_at = expires_in.as_deref().map(expiry::expires_at).transpose()?;
 
-    let _guard = state
-        .write_lock
-        .lock()
-        .map_err(|_| "Storage lock was poisoned".to_string())?;
-
-    let path = resolve_notes_path(&app)?;
-    let mut notes = load_notes_from_path(&path)?;
+    let mut suggested_tags = {
+        let storage = state.lock(&app)?;
+        let notes = storage.notes()?;
+        autotag::suggest(&notes, note_text, &tags)?
+    };
+    let mut note = Note::new(note_text.to_string());
+    note.tags = tags;
+    note.expires_at = expires_at;
+    note.source = capture_source::current();
+    note.source_url = capture_source::current_url();
+    if settings::load_settings(&app)?.auto_apply_tag_suggestions {
+        note.tags.append(&mut suggested_tags);
+    }
+    let note = insert_prepared_note(&app, &state, note)?;
+    Ok(CreatedNote {
+        note,
+        suggested_tags,
+    })
+}
 
-    let note = Note {
-        id: Uuid::new_v4().to_string(),
-        text: note_text.to_string(),
-        created_at: Utc::now().to_rfc3339(),
-        updated_at: None,
+#[tauri::command]
+fn list_notes(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    notebook_id: Option<String>,
+    sort: Option<NoteSort>,
+    color: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<Note>, String> {
+    let range = DateRange::parse(from.as_deref(), to.as_deref())?;
+    let sort = match sort {
+        Some(sort) => sort,
+        None => settings::load_settings(&app)?.default_note_sort,
     };
+    let color = color
+        .as_deref()
+        .map(notebooks::normalize_color)
+        .transpose()?;
+    let color = color.as_deref();
 
-    notes.push(note.clone());
-    save_notes_to_path(&path, &notes)?;
+    let storage = state.lock(&app)?;
+
+    let notes = storage.notes()?;
+    let mut matching: Vec<&Note> = notes
+        .iter()
+        .filter(|note| {
+            !note.is_trashed()
+                && !note.archived
+                && notebook_id
+                    .as_deref()
+                    .is_none_or(|notebook_id| note.notebook_id == notebook_id)
+                && color.is_none_or(|color| note.color.as_deref() == Some(color))
+                && range.contains(note)
+        })
+        .collect();
+    sort_notes(&mut matching, sort);
+    let mut matching: Vec<Note> = matching.into_iter().cloned().collect();
+    locks::reveal(&mut matching);
+    Ok(matching)
+}
+
+/// Stores a manual order: the given notes take positions in sequence, and
+/// notes left out keep theirs after them.
+#[tauri::command]
+fn reorder_notes(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    ids: Vec<String>,
+) -> Result<(), String> {
+    let storage = state.lock(&app)?;
+
+    let placed = u32::try_from(ids.len()).map_err(|_| "Too many notes to reorder".to_string())?;
+    let order: HashMap<&str, u32> = ids.iter().map(String::as_str).zip(0..).collect();
+
+    let mut notes = storage.load_notes()?;
+    for note in notes.iter_mut() {
+        note.position = match order.get(note.id.as_str()) {
+            Some(position) => Some(*position),
+            None => note.position.map(|old| old.saturating_add(placed)),
+        };
+    }
+
+    storage.save_notes(&notes)?;
 
     let _ = app.emit(NOTES_CHANGED_EVENT, ());
-    Ok(note)
+    Ok(())
 }
 
 #[tauri::command]
-fn list_notes(app: AppHandle, state: State<'_, StorageState>) -> Result<Vec<Note>, String> {
-    let _guard = state
-        .write_lock
-        .lock()
-        .map_err(|_| "Storage lock was poisoned".to_string())?;
+fn delete_note(app: AppHandle, state: State<'_, StorageState>, id: String) -> Result<(), String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+    ensure_writable(note)?;
+    let before = note.clone();
+    note.deleted_at = Some(Utc::now().to_rfc3339());
 
-    let path = resolve_notes_path(&app)?;
-    let mut notes = load_notes_from_path(&path)?;
-    notes.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.remove(&id));
+    undo::record("Delete note", vec![before]);
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(())
+}
+
+/// Moves several notes to the trash in one write. Ids that are missing,
+/// already trashed or read-only are skipped; returns how many notes were
+/// deleted.
+#[tauri::command]
+fn delete_notes(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    ids: Vec<String>,
+) -> Result<usize, String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let deleted_at = Utc::now().to_rfc3339();
+    let mut befores = Vec::new();
+    for note in notes.iter_mut() {
+        if !note.is_trashed() && !note.read_only && ids.contains(&note.id) {
+            befores.push(note.clone());
+            note.deleted_at = Some(deleted_at.clone());
+        }
+    }
+    if befores.is_empty() {
+        return Ok(0);
+    }
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| {
+        for before in &befores {
+            index.remove(&before.id);
+        }
+    });
+    let deleted = befores.len();
+    undo::record("Delete notes", befores);
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(deleted)
+}
+
+#[tauri::command]
+fn list_trash(app: AppHandle, state: State<'_, StorageState>) -> Result<Vec<Note>, String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    notes.retain(Note::is_trashed);
+    notes.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
     Ok(notes)
 }
 
+/// Archived notes, most recently archived (or edited) first.
 #[tauri::command]
-fn delete_note(app: AppHandle, state: State<'_, StorageState>, id: String) -> Result<(), String> {
-    let _guard = state
-        .write_lock
+fn list_archive(app: AppHandle, state: State<'_, StorageState>) -> Result<Vec<Note>, String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    notes.retain(|note| note.archived && !note.is_trashed());
+    notes.sort_by_key(|note| std::cmp::Reverse(note.revised_at()));
+    Ok(notes)
+}
+
+#[tauri::command]
+fn archive_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<Note, String> {
+    set_archived(&app, &state, &id, true)
+}
+
+#[tauri::command]
+fn unarchive_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<Note, String> {
+    set_archived(&app, &state, &id, false)
+}
+
+fn set_archived(
+    app: &AppHandle,
+    state: &StorageState,
+    id: &str,
+    archived: bool,
+) -> Result<Note, String> {
+    let storage = state.lock(app)?;
+
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, id)?;
+    if note.archived != archived {
+        note.archived = archived;
+        note.updated_at = Some(Utc::now().to_rfc3339());
+    }
+    let updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(updated)
+}
+
+#[tauri::command]
+fn restore_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<Note, String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let note = notes
+        .iter_mut()
+        .find(|note| note.id == id && note.is_trashed())
+        .ok_or_else(|| "Note not found in trash".to_string())?;
+
+    note.deleted_at = None;
+    // Restoring an expired note keeps it.
+    note.expires_at = None;
+    let restored = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&restored));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(restored)
+}
+
+#[tauri::command]
+fn purge_trash(app: AppHandle, state: State<'_, StorageState>) -> Result<usize, String> {
+    let storage = state.lock(&app)?;
+
+    let (purged, notes): (Vec<Note>, Vec<Note>) = storage
+        .load_notes()?
+        .into_iter()
+        .partition(|note| note.is_trashed());
+
+    if !purged.is_empty() {
+        shred::purge(&app, &storage, &notes, &purged)?;
+        let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    }
+    Ok(purged.len())
+}
+
+#[tauri::command]
+fn set_trash_retention_days(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    days: Option<u32>,
+) -> Result<Settings, String> {
+    let settings = settings::modify_settings(&app, |settings| {
+        settings.trash_retention_days = days.filter(|days| *days > 0);
+        Ok(())
+    })?;
+
+    purge_expired_trash(&app, &state)?;
+    Ok(settings)
+}
+
+#[tauri::command]
+fn toggle_pin(app: AppHandle, state: State<'_, StorageState>, id: String) -> Result<Note, String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+    note.pinned = !note.pinned;
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(updated)
+}
+
+/// Turns read-only on or off; the toggle itself is always allowed.
+#[tauri::command]
+fn toggle_read_only(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<Note, String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+    note.read_only = !note.read_only;
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(updated)
+}
+
+#[tauri::command]
+fn set_note_color(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    color: Option<String>,
+) -> Result<Note, String> {
+    let color = color
+        .as_deref()
+        .map(notebooks::normalize_color)
+        .transpose()?;
+
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+
+    note.color = color;
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(updated)
+}
+
+#[tauri::command]
+fn set_note_tags(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    tags: Vec<String>,
+) -> Result<Note, String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+
+    note.tags = normalize_tags(tags);
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(updated)
+}
+
+#[tauri::command]
+fn list_tags(app: AppHandle, state: State<'_, StorageState>) -> Result<Vec<TagSummary>, String> {
+    let storage = state.lock(&app)?;
+
+    let notes = storage.notes()?;
+
+    Ok(tag_summaries(&notes))
+}
+
+/// Existing tags starting with `prefix`, most used first, for autocomplete.
+#[tauri::command]
+fn suggest_tags(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    prefix: String,
+    limit: Option<usize>,
+) -> Result<Vec<TagSummary>, String> {
+    let prefix = normalize_tag(&prefix).unwrap_or_default();
+
+    let storage = state.lock(&app)?;
+
+    let notes = storage.notes()?;
+
+    let mut suggestions = tag_summaries(&notes);
+    suggestions.retain(|tag| tag.name.starts_with(&prefix));
+    // Stable, so equally used tags stay in name order.
+    suggestions.sort_by_key(|tag| std::cmp::Reverse(tag.count));
+    suggestions.truncate(limit.unwrap_or(DEFAULT_TAG_SUGGESTIONS));
+    Ok(suggestions)
+}
+
+/// Every tag on an active note with how many notes carry it, by name.
+fn tag_summaries(notes: &[Note]) -> Vec<TagSummary> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for tag in notes
+        .iter()
+        .filter(|note| !note.is_trashed() && !note.archived)
+        .flat_map(|note| note.tags.iter())
+    {
+        *counts.entry(tag.clone()).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|(name, count)| TagSummary { name, count })
+        .collect()
+}
+
+#[tauri::command]
+fn list_notes_by_tag(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    tag: String,
+) -> Result<Vec<Note>, String> {
+    let Some(tag) = normalize_tag(&tag) else {
+        return Err("Tag cannot be empty".to_string());
+    };
+
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    notes.retain(|note| !note.is_trashed() && !note.archived && note.tags.contains(&tag));
+    sort_for_display(&mut notes);
+    Ok(notes)
+}
+
+/// Returns one page of active notes plus the total matching count, so the UI
+/// never has to receive the whole collection at once.
+#[tauri::command]
+fn list_notes_paged(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    filter: Option<NoteFilter>,
+    sort: Option<NoteSort>,
+) -> Result<NotePage, String> {
+    let sort = match sort {
+        Some(sort) => sort,
+        None => settings::load_settings(&app)?.default_note_sort,
+    };
+    let filter = filter.unwrap_or_default();
+    let range = DateRange::parse(filter.from.as_deref(), filter.to.as_deref())?;
+    let tag = filter.tag.as_deref().and_then(normalize_tag);
+    let color = filter
+        .color
+        .as_deref()
+        .map(notebooks::normalize_color)
+        .transpose()?;
+    let text = filter
+        .text
+        .as_deref()
+        .map(|text| text.trim().to_lowercase())
+        .filter(|text| !text.is_empty());
+
+    let storage = state.lock(&app)?;
+
+    let all_notes = storage.notes()?;
+    let mut matching: Vec<&Note> = all_notes
+        .iter()
+        .filter(|note| !note.is_trashed())
+        .filter(|note| {
+            filter
+                .notebook_id
+                .as_deref()
+                .is_none_or(|notebook_id| note.notebook_id == notebook_id)
+        })
+        .filter(|note| tag.as_ref().is_none_or(|tag| note.tags.contains(tag)))
+        .filter(|note| filter.pinned.is_none_or(|pinned| note.pinned == pinned))
+        .filter(|note| note.archived == filter.archived.unwrap_or(false))
+        .filter(|note| color.is_none() || note.color == color)
+        .filter(|note| {
+            text.as_deref()
+                .is_none_or(|text| note.text.to_lowercase().contains(text))
+        })
+        .filter(|note| range.contains(note))
+        .collect();
+
+    sort_notes(&mut matching, sort);
+
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let total = matching.len();
+    let mut notes: Vec<Note> = matching
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .cloned()
+        .collect();
+    locks::reveal(&mut notes);
+    Ok(NotePage {
+        total,
+        offset,
+        notes,
+    })
+}
+
+#[tauri::command]
+fn search_notes(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    query: String,
+    limit: Option<usize>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<SearchResult>, String> {
+    let range = DateRange::parse(from.as_deref(), to.as_deref())?;
+    let storage = state.lock(&app)?;
+
+    let mut index = state
+        .search_index
         .lock()
-        .map_err(|_| "Storage lock was poisoned".to_string())?;
+        .map_err(|_| "Search index lock was poisoned".to_string())?;
+
+    if index.is_none() {
+        let mut notes = storage.load_notes()?;
+        notes.retain(|note| !note.is_trashed());
+        // Encrypted notes must not end up in the clear on disk.
+        *index = Some(if encryption::is_unlocked() {
+            SearchIndex::build(&notes)?
+        } else {
+            SearchIndex::open(storage.notes_path(), &notes)?
+        });
+    }
+
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    let mut results = index
+        .as_ref()
+        .map(|index| index.search(&query, if range.is_open() { limit } else { usize::MAX }))
+        .unwrap_or_default();
+    results.retain(|result| range.contains(&result.note));
+    results.truncate(limit);
+    for result in results
+        .iter_mut()
+        .filter(|result| result.note.locked.is_some())
+    {
+        locks::reveal(std::slice::from_mut(&mut result.note));
+        // Whatever matched was the placeholder.
+        result.matches.clear();
+    }
+    Ok(results)
+}
+
+/// Regular-expression search for structured snippets such as ticket IDs or
+/// IP addresses; see `search::search_regex` for the flags.
+#[tauri::command]
+fn search_notes_regex(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    pattern: String,
+    flags: Option<String>,
+    limit: Option<usize>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<SearchResult>, String> {
+    let range = DateRange::parse(from.as_deref(), to.as_deref())?;
+    let storage = state.lock(&app)?;
 
-    let path = resolve_notes_path(&app)?;
-    let mut notes = load_notes_from_path(&path)?;
-    let before = notes.len();
-    notes.retain(|note| note.id != id);
+    let mut notes = storage.load_notes()?;
+    notes.retain(|note| !note.is_trashed() && range.contains(note));
+    search::search_regex(
+        &notes,
+        &pattern,
+        flags.as_deref().unwrap_or_default(),
+        limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+    )
+}
+
+/// Approximate, launcher-style matching: the query's characters must appear
+/// in order, and results carry the positions to highlight.
+#[tauri::command]
+fn fuzzy_search_notes(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    query: String,
+    limit: Option<usize>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<FuzzyResult>, String> {
+    let range = DateRange::parse(from.as_deref(), to.as_deref())?;
+    let storage = state.lock(&app)?;
 
-    if notes.len() == before {
-        return Err("Note not found".to_string());
+    let notes = storage.notes()?;
+    let mut results: Vec<FuzzyResult> = notes
+        .iter()
+        .filter(|note| !note.is_trashed() && range.contains(note))
+        .filter_map(|note| fuzzy::match_note(&query, note))
+        .collect();
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| b.note.created_at.cmp(&a.note.created_at))
+    });
+    results.truncate(limit.unwrap_or(DEFAULT_SEARCH_LIMIT));
+    Ok(results)
+}
+
+#[tauri::command]
+fn update_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    text: String,
+) -> Result<Note, String> {
+    let note_text = text.trim();
+    if note_text.is_empty() {
+        return Err("Note text cannot be empty".to_string());
     }
 
-    save_notes_to_path(&path, &notes)?;
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+    ensure_writable(note)?;
+
+    if note.locked.is_some() {
+        // Sealed as is; keeping history would leave the text readable.
+        locks::save_text(note, note_text)?;
+    } else {
+        if note.text != note_text {
+            if let Err(error) = history::record_version(&app, note) {
+                eprintln!("Failed to record note history: {error}");
+            }
+        }
+        note.set_text(note_text.to_string());
+    }
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let mut updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+    unfurl::refresh(&app, &updated);
+    snapshots::queue(&app, &updated);
+    locks::reveal(std::slice::from_mut(&mut updated));
+
     let _ = app.emit(NOTES_CHANGED_EVENT, ());
-    Ok(())
+    Ok(updated)
 }
 
 #[tauri::command]
@@ -108,21 +1201,225 @@ fn open_quick_capture(app: AppHandle) -> Result<(), String> {
     show_capture_window(&app)
 }
 
+/// Hides quick capture unless the dismiss setting (or a pin) keeps it open
+/// for this kind of request. Returns whether it was hidden.
 #[tauri::command]
-fn close_quick_capture(app: AppHandle) -> Result<(), String> {
-    hide_capture_window(&app)
+fn close_quick_capture(
+    app: AppHandle,
+    reason: Option<dismiss::CloseReason>,
+) -> Result<bool, String> {
+    if !dismiss::allows_close(&app, reason.unwrap_or_default()) {
+        return Ok(false);
+    }
+    hide_capture_window(&app)?;
+    Ok(true)
 }
 
+/// Puts a note on the clipboard as written, as plain text without Markdown
+/// syntax, or as rendered HTML with the plain text as fallback flavor.
 #[tauri::command]
-fn copy_note_text(text: String) -> Result<(), String> {
-    let mut clipboard = arboard::Clipboard::new()
-        .map_err(|e| format!("Failed to access clipboard: {e}"))?;
-    clipboard
-        .set_text(text)
-        .map_err(|e| format!("Failed to copy note: {e}"))
+fn copy_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    format: Option<CopyFormat>,
+) -> Result<(), String> {
+    let text = {
+        let storage = state.lock(&app)?;
+        let notes = storage.notes()?;
+        notes
+            .iter()
+            .find(|note| note.id == id && !note.is_trashed())
+            .map(|note| note.text.clone())
+            .ok_or_else(|| "Note not found".to_string())?
+    };
+
+    let format = format.unwrap_or_default();
+    let plain = match format {
+        CopyFormat::Markdown => text.clone(),
+        CopyFormat::Text | CopyFormat::Html => markdown::to_plain_text(&text),
+    };
+    clipboard_watcher::ignore_text(&plain);
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("Failed to access clipboard: {e}"))?;
+    match format {
+        CopyFormat::Html => clipboard.set_html(markdown::to_html(&text), Some(plain)),
+        CopyFormat::Markdown | CopyFormat::Text => clipboard.set_text(plain),
+    }
+    .map_err(|e| format!("Failed to copy note: {e}"))
+}
+
+/// Persists a new note from raw capture text; shared by every capture path.
+fn insert_note(app: &AppHandle, state: &StorageState, text: &str) -> Result<Note, String> {
+    let note_text = text.trim();
+    if note_text.is_empty() {
+        return Err("Note text cannot be empty".to_string());
+    }
+
+    insert_prepared_note(app, state, Note::new(note_text.to_string()))
+}
+
+/// Appends an already-built note (e.g. one carrying attachments) to storage.
+fn insert_prepared_note(app: &AppHandle, state: &StorageState, note: Note) -> Result<Note, String> {
+    let storage = state.lock(app)?;
+
+    let mut notes = storage.load_notes()?;
+
+    notes.push(note.clone());
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&note));
+    unfurl::refresh(app, &note);
+    snapshots::queue(app, &note);
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(note)
+}
+
+fn ensure_writable(note: &Note) -> Result<(), String> {
+    if note.read_only {
+        return Err("Note is read-only".to_string());
+    }
+    Ok(())
+}
+
+fn find_active_note_mut<'a>(notes: &'a mut [Note], id: &str) -> Result<&'a mut Note, String> {
+    notes
+        .iter_mut()
+        .find(|note| note.id == id && !note.is_trashed())
+        .ok_or_else(|| "Note not found".to_string())
+}
+
+/// Permanently removes trashed notes older than the configured retention.
+fn purge_expired_trash(app: &AppHandle, state: &StorageState) -> Result<(), String> {
+    let Some(days) = settings::load_settings(app)?.trash_retention_days else {
+        return Ok(());
+    };
+    let cutoff = Utc::now() - chrono::Duration::days(i64::from(days));
+
+    let storage = state.lock(app)?;
+
+    let (notes, purged): (Vec<Note>, Vec<Note>) =
+        storage.load_notes()?.into_iter().partition(|note| {
+            note.deleted_at
+                .as_deref()
+                .and_then(|deleted_at| DateTime::parse_from_rfc3339(deleted_at).ok())
+                .is_none_or(|deleted_at| deleted_at > cutoff)
+        });
+
+    if !purged.is_empty() {
+        shred::purge(app, &storage, &notes, &purged)?;
+        let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    }
+    Ok(())
+}
+
+fn sort_notes(notes: &mut [&Note], sort: NoteSort) {
+    match sort {
+        NoteSort::Display => notes.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then_with(|| b.created_at.cmp(&a.created_at))
+        }),
+        NoteSort::Newest => notes.sort_by(|a, b| b.created_at.cmp(&a.created_at)),
+        NoteSort::Oldest => notes.sort_by(|a, b| a.created_at.cmp(&b.created_at)),
+        NoteSort::RecentlyUpdated => notes.sort_by_key(|note| std::cmp::Reverse(note.revised_at())),
+        NoteSort::Title => notes.sort_by_cached_key(|note| note.title().to_lowercase()),
+        NoteSort::Manual => notes.sort_by(|a, b| match (a.position, b.position) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => std::cmp::Ordering::Greater,
+            (None, Some(_)) => std::cmp::Ordering::Less,
+            (None, None) => b.created_at.cmp(&a.created_at),
+        }),
+    }
+}
+
+/// Pinned notes first, then newest first within each group.
+fn sort_for_display(notes: &mut [Note]) {
+    notes.sort_by(|a, b| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then_with(|| b.created_at.cmp(&a.created_at))
+    });
+}
+
+/// Tags are stored lowercase without a leading `#` so lookups are exact.
+fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').trim().to_lowercase();
+    (!tag.is_empty()).then_some(tag)
+}
+
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags.iter().filter_map(|tag| normalize_tag(tag)) {
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Splits `#tag` tokens off the end of capture text, so `Buy milk #errands`
+/// is saved as `Buy milk` tagged `errands`. Issue numbers like `#42` stay in
+/// the text, and text made only of tags is kept whole.
+fn split_trailing_tags(text: &str) -> (&str, Vec<String>) {
+    let mut body = text.trim_end();
+    let mut tags = Vec::new();
+    while !body.is_empty() {
+        let start = body
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(index, c)| index + c.len_utf8());
+        let Some(tag) = body[start..]
+            .strip_prefix('#')
+            .filter(|tag| is_inline_tag(tag))
+        else {
+            break;
+        };
+        tags.push(tag.to_string());
+        body = body[..start].trim_end();
+    }
+
+    tags.reverse();
+    if body.is_empty() {
+        return (text, normalize_tags(tags));
+    }
+    (body, normalize_tags(tags))
+}
+
+fn is_inline_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/'))
+        && !tag.chars().all(|c| c.is_ascii_digit())
 }
 
 fn resolve_notes_path(app: &AppHandle) -> Result<PathBuf, String> {
+    resolve_storage(app).map(|(notes_path, _)| notes_path)
+}
+
+/// The notes file and, with vault storage, the vault it belongs to.
+fn resolve_storage(app: &AppHandle) -> Result<(PathBuf, Option<Vault>), String> {
+    match Vault::configured(&settings::load_settings(app)?) {
+        Some(vault) => Ok((vault.sidecar_path()?, Some(vault))),
+        None => Ok((resolve_data_file(app, NOTES_FILE_NAME)?, None)),
+    }
+}
+
+/// Resolves a note data file, honoring the user's custom data directory.
+fn resolve_data_file(app: &AppHandle, file_name: &str) -> Result<PathBuf, String> {
+    let Some(data_dir) = settings::load_settings(app)?.data_dir else {
+        return resolve_app_data_file(app, file_name);
+    };
+
+    let data_dir = PathBuf::from(data_dir);
+    fs::create_dir_all(&data_dir).map_err(|e| format!("Failed to create data directory: {e}"))?;
+
+    Ok(data_dir.join(file_name))
+}
+
+fn resolve_app_data_file(app: &AppHandle, file_name: &str) -> Result<PathBuf, String> {
     let app_data_dir = app
         .path()
         .app_data_dir()
@@ -131,28 +1428,55 @@ fn resolve_notes_path(app: &AppHandle) -> Result<PathBuf, String> {
     fs::create_dir_all(&app_data_dir)
         .map_err(|e| format!("Failed to create app data directory: {e}"))?;
 
-    Ok(app_data_dir.join(NOTES_FILE_NAME))
+    Ok(app_data_dir.join(file_name))
 }
 
-fn load_notes_from_path(path: &Path) -> Result<Vec<Note>, String> {
-    if !path.exists() {
-        return Ok(Vec::new());
-    }
+/// Takes an exclusive advisory lock next to the notes file; released on drop.
+fn lock_notes_file(notes_path: &Path) -> Result<fs::File, String> {
+    let lock_path = notes_path.with_extension("json.lock");
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|e| format!("Failed to open storage lock file: {e}"))?;
+    file.lock()
+        .map_err(|e| format!("Failed to lock storage: {e}"))?;
+    Ok(file)
+}
+
+fn notes_stamp(notes_path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(notes_path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
 
-    let raw = fs::read_to_string(path).map_err(|e| format!("Failed to read notes file: {e}"))?;
-    if raw.trim().is_empty() {
-        return Ok(Vec::new());
+/// The notes file's stamp, or the whole vault's with vault storage.
+fn storage_stamp(notes_path: &Path, vault: Option<&Vault>) -> Option<FileStamp> {
+    match vault {
+        Some(vault) => vault.stamp(notes_path),
+        None => notes_stamp(notes_path),
     }
+}
 
-    serde_json::from_str::<Vec<Note>>(&raw).map_err(|e| format!("Failed to parse notes file: {e}"))
+/// Reads a notes file in any schema version without upgrading it on disk.
+/// Callers that save what they read go through `schema::read_upgrading`
+/// instead, so the original is backed up before it is rewritten.
+fn load_notes_from_path(path: &Path) -> Result<Vec<Note>, String> {
+    schema::read(path).map(|file| file.notes)
 }
 
 fn save_notes_to_path(path: &Path, notes: &[Note]) -> Result<(), String> {
-    let payload = serde_json::to_string_pretty(notes)
-        .map_err(|e| format!("Failed to serialize notes payload: {e}"))?;
+    let payload = encryption::seal(schema::serialize(notes)?)?;
+
+    backup::rotate_before_write(path);
+    write_file_atomically(path, &payload)
+}
 
+/// Writes through a temp file and renames it into place, falling back to a
+/// copy when the rename fails, so readers never observe a half-written file.
+fn write_file_atomically(path: &Path, payload: &str) -> Result<(), String> {
     let temp_path = path.with_extension("json.tmp");
-    fs::write(&temp_path, payload).map_err(|e| format!("Failed to write temp notes file: {e}"))?;
+    fs::write(&temp_path, payload).map_err(|e| format!("Failed to write temp file: {e}"))?;
 
     match fs::rename(&temp_path, path) {
         Ok(()) => Ok(()),
@@ -160,16 +1484,18 @@ fn save_notes_to_path(path: &Path, notes: &[Note]) -> Result<(), String> {
             Ok(_) => {
                 if let Err(cleanup_error) = fs::remove_file(&temp_path) {
                     eprintln!(
-                        "Saved notes via copy fallback, but failed to remove temp file: {cleanup_error}"
+                        "Saved {} via copy fallback, but failed to remove temp file: {cleanup_error}",
+                        path.display()
                     );
                 }
                 Ok(())
             }
             Err(copy_error) => Err(format!(
-                "Failed to finalize notes file. rename error: {rename_error}; copy error: {copy_error}"
+                "Failed to finalize {}. rename error: {rename_error}; copy error: {copy_error}",
+                path.display()
             )),
         },
-            }
+    }
 }
 
 fn app_icon_image() -> Option<tauri::image::Image<'static>> {
@@ -187,8 +1513,25 @@ fn show_main_window(app: &AppHandle) {
     }
 }
 
+/// Hides the main window when it is already in front, otherwise brings it up.
+fn toggle_main_window(app: &AppHandle) {
+    let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) else {
+        return;
+    };
+    let visible = window.is_visible().unwrap_or(false);
+    let minimized = window.is_minimized().unwrap_or(false);
+    let focused = window.is_focused().unwrap_or(false);
+    if visible && !minimized && focused {
+        let _ = window.hide();
+    } else {
+        show_main_window(app);
+    }
+}
+
 fn hide_capture_window(app: &AppHandle) -> Result<(), String> {
     if let Some(window) = app.get_webview_window(CAPTURE_WINDOW_LABEL) {
+        placement::remember_capture_position(app, &window);
+        capture_source::clear();
         window
             .hide()
             .map_err(|e| format!("Failed to hide capture window: {e}"))?;
@@ -196,59 +1539,12 @@ fn hide_capture_window(app: &AppHandle) -> Result<(), String> {
     Ok(())
 }
 
-fn position_capture_window_near_cursor(app: &AppHandle, window: &tauri::WebviewWindow) {
-    let Ok(cursor) = app.cursor_position() else {
-        let _ = window.center();
-        return;
-    };
-
-    let size = window.outer_size().ok();
-    let window_width = size
-        .map(|s| f64::from(s.width))
-        .unwrap_or(CAPTURE_WINDOW_WIDTH);
-    let window_height = size
-        .map(|s| f64::from(s.height))
-        .unwrap_or(CAPTURE_WINDOW_HEIGHT);
-
-    let mut x = cursor.x + 14.0;
-    let mut y = cursor.y + 14.0;
-
-    if let Ok(monitors) = app.available_monitors() {
-        if let Some(monitor) = monitors.iter().find(|monitor| {
-            let area = monitor.work_area();
-            let left = f64::from(area.position.x);
-            let top = f64::from(area.position.y);
-            let right = left + f64::from(area.size.width);
-            let bottom = top + f64::from(area.size.height);
-
-            cursor.x >= left && cursor.x < right && cursor.y >= top && cursor.y < bottom
-        }) {
-            let area = monitor.work_area();
-            let left = f64::from(area.position.x);
-            let top = f64::from(area.position.y);
-            let right = left + f64::from(area.size.width);
-            let bottom = top + f64::from(area.size.height);
-
-            let max_x = right - window_width;
-            let max_y = bottom - window_height;
-
-            x = if max_x < left {
-                left
-            } else {
-                x.clamp(left, max_x)
-            };
-            y = if max_y < top { top } else { y.clamp(top, max_y) };
-        }
-    }
-
-    let _ = window.set_position(tauri::PhysicalPosition::new(
-        x.round() as i32,
-        y.round() as i32,
-    ));
-}
-
 fn reveal_capture_window(app: &AppHandle, window: &tauri::WebviewWindow, reset_input: bool) {
-    position_capture_window_near_cursor(app, window);
+    if reset_input {
+        placement::reset_capture_window_size(app, window);
+    }
+    placement::position_capture_window(app, window);
+    dismiss::note_revealed();
 
     if let Err(error) = window.show() {
         eprintln!("Failed to show capture window: {error}");
@@ -259,20 +1555,21 @@ fn reveal_capture_window(app: &AppHandle, window: &tauri::WebviewWindow, reset_i
     if let Err(error) = window.set_focus() {
         eprintln!("Failed to focus capture window: {error}");
     }
+    let config = placement::capture_config(app);
     if reset_input {
-        let _ = window.emit(CAPTURE_OPENED_EVENT, ());
+        let _ = window.emit(CAPTURE_OPENED_EVENT, &config);
     }
 
     let app_handle = app.clone();
     std::thread::spawn(move || {
         std::thread::sleep(Duration::from_millis(90));
         if let Some(retry_window) = app_handle.get_webview_window(CAPTURE_WINDOW_LABEL) {
-            position_capture_window_near_cursor(&app_handle, &retry_window);
+            placement::position_capture_window(&app_handle, &retry_window);
             let _ = retry_window.show();
             let _ = retry_window.unminimize();
             let _ = retry_window.set_focus();
             if reset_input {
-                let _ = retry_window.emit(CAPTURE_OPENED_EVENT, ());
+                let _ = retry_window.emit(CAPTURE_OPENED_EVENT, &config);
             }
         }
     });
@@ -282,13 +1579,19 @@ fn show_capture_window(app: &AppHandle) -> Result<(), String> {
     let window = if let Some(window) = app.get_webview_window(CAPTURE_WINDOW_LABEL) {
         window
     } else {
+        let always_on_top = settings::load_settings(app)
+            .map(|settings| settings.capture_always_on_top)
+            .unwrap_or(true);
+
+        let (width, height) = placement::initial_capture_window_size(app);
+
         let created = match WebviewWindowBuilder::new(
             app,
             CAPTURE_WINDOW_LABEL,
             WebviewUrl::App("index.html#/capture".into()),
         )
         .title("Quick Capture")
-        .inner_size(CAPTURE_WINDOW_WIDTH, CAPTURE_WINDOW_HEIGHT)
+        .inner_size(width, height)
         .resizable(false)
         .maximizable(false)
         .minimizable(false)
@@ -297,7 +1600,8 @@ fn show_capture_window(app: &AppHandle) -> Result<(), String> {
         .shadow(false)
         .background_color(tauri::utils::config::Color(0, 0, 0, 0))
         .skip_taskbar(true)
-        .always_on_top(true)
+        .always_on_top(always_on_top)
+        .theme(theme::window_theme(app))
         .visible(false)
         .build()
         {
@@ -313,11 +1617,14 @@ fn show_capture_window(app: &AppHandle) -> Result<(), String> {
         }
 
         let capture = created.clone();
-        created.on_window_event(move |event| {
-            if let WindowEvent::CloseRequested { api, .. } = event {
+        created.on_window_event(move |event| match event {
+            WindowEvent::CloseRequested { api, .. } => {
                 api.prevent_close();
+                placement::remember_capture_position(capture.app_handle(), &capture);
                 let _ = capture.hide();
             }
+            WindowEvent::Focused(false) => dismiss::handle_focus_lost(capture.app_handle()),
+            _ => {}
         });
 
         created
@@ -327,101 +1634,24 @@ fn show_capture_window(app: &AppHandle) -> Result<(), String> {
     Ok(())
 }
 
-fn setup_tray(app: &AppHandle) -> Result<(), tauri::Error> {
-    let new_note = MenuItemBuilder::with_id("new_note", "New Note").build(app)?;
-    let open_notes = MenuItemBuilder::with_id("open_notes", "Open Notes").build(app)?;
-    let quit = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
-
-    let menu = MenuBuilder::new(app)
-        .item(&new_note)
-        .item(&open_notes)
-        .separator()
-        .item(&quit)
-        .build()?;
-
-    let mut tray = TrayIconBuilder::with_id("jotin-tray")
-        .menu(&menu)
-        .show_menu_on_left_click(false)
-        .on_menu_event(|app, event| match event.id.as_ref() {
-            "new_note" => {
-                if let Err(error) = show_capture_window(app) {
-                    eprintln!("{error}");
-                }
-            }
-            "open_notes" => show_main_window(app),
-            "quit" => app.exit(0),
-            _ => {}
-        })
-        .on_tray_icon_event(|tray, event| {
-            if let TrayIconEvent::Click {
-                button: MouseButton::Left,
-                button_state: MouseButtonState::Up,
-                ..
-            } = event
-            {
-                show_main_window(tray.app_handle());
-            }
-        });
-
-    if let Some(icon) = app_icon_image() {
-        tray = tray.icon(icon);
-    }
-
-    tray.build(app)?;
-
-    Ok(())
-}
-
-fn setup_global_shortcut(app: &AppHandle) -> tauri::Result<()> {
-    let shortcut = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::ALT), Code::KeyN);
-
-    app.global_shortcut()
-        .on_shortcut(shortcut, move |app, _shortcut, event| {
-            if event.state() == ShortcutState::Pressed {
-                let now = Instant::now();
-                let is_double_press = LAST_SHORTCUT_PRESS
-                    .lock()
-                    .map(|mut last_press| {
-                        let was_recent = last_press
-                            .map(|previous| {
-                                now.duration_since(previous)
-                                    <= Duration::from_millis(DOUBLE_SHORTCUT_WINDOW_MS)
-                            })
-                            .unwrap_or(false);
-
-                        if was_recent {
-                            *last_press = None;
-                        } else {
-                            *last_press = Some(now);
-                        }
-
-                        was_recent
-                    })
-                    .unwrap_or(false);
-
-                if is_double_press {
-                    show_main_window(app);
-                    let _ = hide_capture_window(app);
-                } else if let Err(error) = show_capture_window(app) {
-                    eprintln!("{error}");
-                }
-            }
-        })
-        .map_err(|e| {
-            tauri::Error::from(std::io::Error::other(format!(
-                "Failed to register global shortcut: {e}"
-            )))
-        })
-}
-
 fn setup_main_window_behavior(app: &AppHandle) {
     if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
+        window_state::restore(&window);
+
         let main_window = window.clone();
-        window.on_window_event(move |event| {
-            if let WindowEvent::CloseRequested { api, .. } = event {
+        window.on_window_event(move |event| match event {
+            WindowEvent::CloseRequested { api, .. } => {
                 api.prevent_close();
                 let _ = main_window.hide();
             }
+            // Every window sees the change; the main window always exists.
+            WindowEvent::ThemeChanged(theme) => {
+                theme::handle_os_theme_changed(main_window.app_handle(), *theme)
+            }
+            WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+                window_state::schedule_save(&main_window)
+            }
+            _ => {}
         });
     }
 }
@@ -433,19 +1663,182 @@ pub fn run() {
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .manage(StorageState::default())
         .setup(|app| {
-            setup_tray(app.handle())?;
+            if let Err(error) = settings::migrate_secrets(app.handle()) {
+                eprintln!("Failed to move secrets to the keychain: {error}");
+            }
+            backup::load_retention(app.handle());
+            http::load_staging_dir(app.handle());
+            notifications::load_do_not_disturb(app.handle());
+            clipboard_watcher::start(app.handle());
+            notes_watcher::start(app.handle());
+            reminders::start(app.handle());
+            expiry::start(app.handle());
+            git_sync::start(app.handle());
+            sync::start(app.handle());
+            tray::setup(app.handle())?;
             setup_main_window_behavior(app.handle());
-            setup_global_shortcut(app.handle())?;
+            theme::load(app.handle());
+            shortcuts::setup_global_shortcut(app.handle())?;
+            templates::setup_shortcuts(app.handle());
+            embeddings::setup(app.handle());
+            if let Err(error) = purge_expired_trash(app.handle(), &app.state::<StorageState>()) {
+                eprintln!("Failed to purge expired trash: {error}");
+            }
+            if let Err(error) = http_api::apply(app.handle()) {
+                eprintln!("{error}");
+            }
+            deep_link::handle_launch_args(app.handle());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             create_note,
             list_notes,
+            list_notes_paged,
+            reorder_notes,
+            update_note,
             delete_note,
+            delete_notes,
+            search_notes,
+            search_notes_regex,
+            saved_searches::list_saved_searches,
+            saved_searches::create_saved_search,
+            saved_searches::update_saved_search,
+            saved_searches::delete_saved_search,
+            saved_searches::run_saved_search,
+            fuzzy_search_notes,
+            toggle_pin,
+            toggle_read_only,
+            set_note_tags,
+            set_note_color,
+            list_tags,
+            suggest_tags,
+            list_notes_by_tag,
+            list_archive,
+            archive_note,
+            unarchive_note,
+            expiry::set_note_expiry,
+            timers::start_timer,
+            timers::stop_timer,
+            pomodoro::get_pomodoro,
+            pomodoro::start_pomodoro,
+            pomodoro::pause_pomodoro,
+            pomodoro::reset_pomodoro,
+            list_trash,
+            restore_note,
+            purge_trash,
+            set_trash_retention_days,
+            notebooks::list_notebooks,
+            notebooks::create_notebook,
+            notebooks::rename_notebook,
+            notebooks::set_notebook_color,
+            notebooks::delete_notebook,
+            notebooks::move_note_to_notebook,
+            export::export_markdown,
+            export::export_notes,
+            export::export_tabular,
+            import::import_from_directory,
+            apple_notes::import_apple_notes,
+            simplenote::import_simplenote,
+            csv_import::import_csv,
+            backup::export_backup,
+            backup::import_backup,
+            backup::list_backups,
+            backup::restore_backup,
+            history::list_note_versions,
+            history::diff_note_version,
+            history::restore_note_version,
+            undo::undo_last_operation,
+            reminders::set_reminder,
+            reminders::list_reminders,
+            reminders::cancel_reminder,
+            reminders::snooze_reminder,
+            links::get_backlinks,
+            links::resolve_wikilink,
+            duplicates::find_duplicate_notes,
+            duplicates::merge_notes,
+            attachments::attach_file,
+            attachments::create_note_with_clipboard_image,
+            screenshot::capture_screenshot_note,
+            screenshot::take_pending_screenshot,
+            attachments::list_attachments,
+            attachments::remove_attachment,
+            snapshots::set_link_snapshots,
+            shortcuts::set_capture_shortcut,
+            shortcuts::set_clipboard_capture_shortcut,
+            shortcuts::set_main_window_shortcut,
+            shortcuts::set_screenshot_capture_shortcut,
+            shortcuts::set_selection_capture_shortcut,
+            shortcuts::set_voice_capture_shortcut,
+            settings::get_settings,
+            settings::update_settings,
+            settings::set_secret,
+            settings::has_secret,
+            settings::reveal_http_api_token,
+            settings::set_capture_always_on_top,
+            autostart::enable_autostart,
+            autostart::disable_autostart,
+            autostart::is_autostart_enabled,
+            clipboard_watcher::set_clipboard_watcher,
+            clipboard_watcher::is_clipboard_watcher_enabled,
+            notifications::set_do_not_disturb,
+            deep_link::take_pending_deep_link,
+            sync::sync_now,
+            sync::get_sync_status,
             open_quick_capture,
             close_quick_capture,
-            copy_note_text
+            placement::resize_capture_window,
+            placement::get_capture_config,
+            append::append_to_latest_note,
+            daily::get_or_create_daily_note,
+            daily::append_to_daily_note,
+            journal::list_notes_by_day,
+            journal::list_days_with_notes,
+            print::print_note,
+            templates::list_templates,
+            templates::create_template,
+            templates::update_template,
+            templates::delete_template,
+            templates::create_note_from_template,
+            draft::save_capture_draft,
+            draft::load_capture_draft,
+            dismiss::pin_capture_window,
+            theme::get_theme,
+            theme::set_theme,
+            voice::start_voice_note,
+            voice::stop_voice_note,
+            voice::cancel_voice_note,
+            speech::speak_note,
+            speech::stop_speaking,
+            speech::get_speaking_note,
+            calc::evaluate_expression,
+            convert::convert,
+            ai::summarize_note,
+            ai::tidy_note,
+            embeddings::semantic_search,
+            notion::send_note_to_notion,
+            notion::export_notes_to_notion,
+            todoist::send_to_todoist,
+            email::email_note,
+            gist::share_as_gist,
+            publish::publish_note,
+            conflicts::list_conflicts,
+            conflicts::resolve_conflict,
+            locks::lock_note,
+            locks::unlock_note,
+            shred::shred_note,
+            encryption::get_encryption_status,
+            encryption::unlock_storage,
+            encryption::set_encryption_passphrase,
+            copy_note
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, _event| {
+            #[cfg(target_os = "macos")]
+            if let tauri::RunEvent::Opened { urls } = _event {
+                for url in &urls {
+                    deep_link::handle_url(_app, url);
+                }
+            }
+        });
 }
@@ -0,0 +1,107 @@
+use std::{collections::HashMap, sync::Arc};
+
+use tauri::{AppHandle, State};
+
+use crate::{sort_for_display, Note, SnapshotCache, StorageState};
+
+static LINK_INDEX: SnapshotCache<LinkIndex> = SnapshotCache::new("Link index", LinkIndex::build);
+
+#[derive(Default)]
+struct LinkIndex {
+    /// Normalized title to the ids of active notes with it, newest first.
+    by_title: HashMap<String, Vec<String>>,
+    /// Note id to the ids of active notes linking to it.
+    backlinks: HashMap<String, Vec<String>>,
+}
+
+impl LinkIndex {
+    fn build(notes: &[Note]) -> Self {
+        let mut active: Vec<&Note> = notes.iter().filter(|note| !note.is_trashed()).collect();
+        active.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+        let mut index = Self::default();
+        for note in &active {
+            let title = normalize_title(note.title());
+            if !title.is_empty() {
+                index
+                    .by_title
+                    .entry(title)
+                    .or_default()
+                    .push(note.id.clone());
+            }
+        }
+
+        for note in &active {
+            for title in &note.metadata.wikilinks {
+                let Some(target) = index.resolve(title) else {
+                    continue;
+                };
+                if target == note.id {
+                    continue;
+                }
+                let sources = index.backlinks.entry(target.to_string()).or_default();
+                if !sources.contains(&note.id) {
+                    sources.push(note.id.clone());
+                }
+            }
+        }
+        index
+    }
+
+    fn resolve(&self, title: &str) -> Option<&str> {
+        self.by_title
+            .get(&normalize_title(title))
+            .and_then(|ids| ids.first())
+            .map(String::as_str)
+    }
+}
+
+/// Active notes containing a `[[link]]` to the given note.
+#[tauri::command]
+pub(crate) fn get_backlinks(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    note_id: String,
+) -> Result<Vec<Note>, String> {
+    let storage = state.lock(&app)?;
+    let notes = storage.notes()?;
+
+    let sources = with_index(&notes, |index| {
+        index.backlinks.get(&note_id).cloned().unwrap_or_default()
+    })?;
+    let mut backlinks: Vec<Note> = notes
+        .iter()
+        .filter(|note| sources.contains(&note.id))
+        .cloned()
+        .collect();
+    sort_for_display(&mut backlinks);
+    Ok(backlinks)
+}
+
+/// The note a `[[title]]` points to: the newest active note with that title,
+/// compared case-insensitively.
+#[tauri::command]
+pub(crate) fn resolve_wikilink(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    title: String,
+) -> Result<Option<Note>, String> {
+    let storage = state.lock(&app)?;
+    let notes = storage.notes()?;
+
+    let target = with_index(&notes, |index| index.resolve(&title).map(str::to_string))?;
+    Ok(target.and_then(|id| notes.iter().find(|note| note.id == id).cloned()))
+}
+
+fn with_index<T>(notes: &Arc<Vec<Note>>, read: impl FnOnce(&LinkIndex) -> T) -> Result<T, String> {
+    LINK_INDEX.with(notes, read)
+}
+
+/// Titles match regardless of case and runs of whitespace.
+fn normalize_title(title: &str) -> String {
+    title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
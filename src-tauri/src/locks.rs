@@ -0,0 +1,180 @@
+//! Password-protected notes. Locking seals the note's text under its own
+//! passphrase (see `crypto`) and leaves a placeholder in its place, so
+//! lists, search, exports and the Markdown files never show the content.
+//! Unlocking keeps the derived key in memory until the app quits or the
+//! note is locked again; meanwhile the note reads and saves as plain text.
+//!
+//! Locking also scrubs the old text from the note's history, the backups,
+//! the sync merge base and the undo stack, as purging does (see `shred`).
+//! Copies already synced or committed by git sync are out of reach.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use chrono::Utc;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    crypto::{Envelope, Key},
+    find_active_note_mut, shred, Note, StorageState, NOTES_CHANGED_EVENT,
+};
+
+const LOCKED_NOTE_PLACEHOLDER: &str = "🔒 Locked note";
+
+/// Keys of the notes unlocked this session, by note id.
+static UNLOCKED: Mutex<Option<HashMap<String, Key>>> = Mutex::new(None);
+
+/// Seals the note's text under `passphrase`. Locking a note that is
+/// unlocked for the session locks it again instead.
+#[tauri::command]
+pub(crate) fn lock_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    passphrase: String,
+) -> Result<Note, String> {
+    if passphrase.is_empty() {
+        return Err("Passphrase cannot be empty".to_string());
+    }
+
+    let storage = state.lock(&app)?;
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+
+    if let Some(sealed) = &note.locked {
+        open_text(&Key::derive(&passphrase, &sealed.encryption)?, sealed, &id)?;
+        with_unlocked(|unlocked| unlocked.remove(&id))?;
+        return Ok(note.clone());
+    }
+
+    if note.conflict.is_some() {
+        return Err("Resolve the note's sync conflict first".to_string());
+    }
+
+    let key = Key::generate(&passphrase)?;
+    note.locked = Some(Envelope::seal(&key, note.text.as_bytes(), &id)?);
+    note.set_text(LOCKED_NOTE_PLACEHOLDER.to_string());
+    // Derived from the content, so they would give it away.
+    note.summary = None;
+    note.ocr_text = None;
+    note.link_previews.clear();
+    note.crdt = None;
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let locked = note.clone();
+
+    shred::scrub_locked(&app, &storage, &notes, std::slice::from_ref(&locked))?;
+    state.update_search_index(|index| index.upsert(&locked));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(locked)
+}
+
+/// Returns the note with its text for the rest of the session; nothing is
+/// written unsealed.
+#[tauri::command]
+pub(crate) fn unlock_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    passphrase: String,
+) -> Result<Note, String> {
+    unlock(&app, &state, &id, &passphrase)
+}
+
+pub(crate) fn unlock(
+    app: &AppHandle,
+    state: &StorageState,
+    id: &str,
+    passphrase: &str,
+) -> Result<Note, String> {
+    let storage = state.lock(app)?;
+    let notes = storage.notes()?;
+    let mut note = notes
+        .iter()
+        .find(|note| note.id == id && !note.is_trashed())
+        .cloned()
+        .ok_or_else(|| "Note not found".to_string())?;
+    let sealed = note
+        .locked
+        .as_ref()
+        .ok_or_else(|| "Note is not locked".to_string())?;
+
+    let key = Key::derive(passphrase, &sealed.encryption)?;
+    let text = open_text(&key, sealed, id)?;
+    note.set_text(text);
+    with_unlocked(|unlocked| unlocked.insert(id.to_string(), key))?;
+    Ok(note)
+}
+
+/// Puts the text back into notes unlocked this session. Others keep the
+/// placeholder.
+pub(crate) fn reveal(notes: &mut [Note]) {
+    let _ = with_unlocked(|unlocked| {
+        for note in notes {
+            let (Some(key), Some(sealed)) = (unlocked.get(&note.id), &note.locked) else {
+                continue;
+            };
+            if let Ok(text) = open_text(key, sealed, &note.id) {
+                note.set_text(text);
+            }
+        }
+    });
+}
+
+/// Seals a new text into a locked note, which must be unlocked this session.
+pub(crate) fn save_text(note: &mut Note, text: &str) -> Result<(), String> {
+    let sealed = with_unlocked(|unlocked| {
+        let key = unlocked
+            .get(&note.id)
+            .ok_or_else(|| "Unlock the note first".to_string())?;
+        Envelope::seal(key, text.as_bytes(), &note.id)
+    })??;
+    note.locked = Some(sealed);
+    Ok(())
+}
+
+/// Rejects edits to a locked note that cannot go through `save_text`, such
+/// as appending to its placeholder.
+pub(crate) fn ensure_not_locked(note: &Note) -> Result<(), String> {
+    match note.locked {
+        Some(_) => Err("Note is locked".to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Opens a note's sealed text; the note's id is its purpose, so a sealed
+/// text copied into another note does not open.
+fn open_text(key: &Key, sealed: &Envelope, id: &str) -> Result<String, String> {
+    String::from_utf8(sealed.open(key, id)?).map_err(|_| "Locked note is damaged".to_string())
+}
+
+fn with_unlocked<T>(f: impl FnOnce(&mut HashMap<String, Key>) -> T) -> Result<T, String> {
+    let mut unlocked = UNLOCKED
+        .lock()
+        .map_err(|_| "Unlocked notes lock was poisoned".to_string())?;
+    Ok(f(unlocked.get_or_insert_with(HashMap::new)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_text_only_opens_in_its_own_note() {
+        let key = Key::for_tests("correct horse", 1);
+        let sealed = Envelope::seal(&key, b"door code 4711", "a").unwrap();
+
+        assert_eq!(open_text(&key, &sealed, "a").unwrap(), "door code 4711");
+        assert!(open_text(&key, &sealed, "b").is_err());
+        let again = Key::derive("correct horse", &sealed.encryption).unwrap();
+        assert_eq!(open_text(&again, &sealed, "a").unwrap(), "door code 4711");
+    }
+
+    #[test]
+    fn locked_notes_refuse_plain_edits() {
+        let mut note = Note::new("text".to_string());
+        assert!(ensure_not_locked(&note).is_ok());
+        let key = Key::for_tests("correct horse", 1);
+        note.locked = Some(Envelope::seal(&key, b"text", &note.id).unwrap());
+        assert!(ensure_not_locked(&note).is_err());
+    }
+}
@@ -1,6 +1,12 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-fn main() {
-    jotin_lib::run()
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    if let Some(code) = jotin_lib::cli::run() {
+        return code;
+    }
+    jotin_lib::run();
+    ExitCode::SUCCESS
 }
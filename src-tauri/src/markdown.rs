@@ -0,0 +1,574 @@
+//! A light, line-based Markdown scan for list previews and rich copies. It
+//! recognises what notes actually contain (ATX headings, lists and task
+//! items, quotes, fenced code, emphasis and links) rather than implementing
+//! CommonMark.
+
+use serde::{Deserialize, Serialize};
+
+use crate::language;
+
+/// Average silent reading speed used for `reading_minutes`.
+const WORDS_PER_MINUTE: usize = 200;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "snake_case")]
+pub(crate) struct NoteMetadata {
+    pub headings: Vec<Heading>,
+    /// Link targets in order of appearance, without duplicates.
+    pub links: Vec<String>,
+    pub tasks_total: usize,
+    pub tasks_done: usize,
+    pub code_blocks: usize,
+    /// Info-string languages of fenced code blocks, without duplicates.
+    pub code_languages: Vec<String>,
+    /// Titles referenced as `[[title]]`, without aliases or heading anchors.
+    pub wikilinks: Vec<String>,
+    /// ISO 639-1 code of the language the prose is written in, if clear.
+    pub language: Option<String>,
+    /// Counted on the plain text, so Markdown syntax is left out.
+    pub words: usize,
+    pub characters: usize,
+    /// Estimated, rounded up; 0 only for a note without words.
+    pub reading_minutes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct Heading {
+    pub level: u8,
+    pub text: String,
+}
+
+pub(crate) fn extract(text: &str) -> NoteMetadata {
+    let mut metadata = NoteMetadata::default();
+    let mut fence: Option<(char, usize)> = None;
+
+    for line in text.lines() {
+        let trimmed = strip_indent(line);
+
+        if let Some((marker, length)) = fence {
+            if closes_fence(trimmed, marker, length) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some((marker, length, info)) = opens_fence(trimmed) {
+            fence = Some((marker, length));
+            metadata.code_blocks += 1;
+            if let Some(language) = info.split_whitespace().next() {
+                push_unique(&mut metadata.code_languages, language.to_lowercase());
+            }
+            continue;
+        }
+
+        if let Some(heading) = parse_heading(trimmed) {
+            metadata.headings.push(heading);
+        } else if let Some(done) = parse_task(trimmed) {
+            metadata.tasks_total += 1;
+            metadata.tasks_done += usize::from(done);
+        }
+        collect_links(trimmed, &mut metadata.links);
+        collect_wikilinks(trimmed, &mut metadata.wikilinks);
+    }
+    let plain = to_plain_text(text);
+    metadata.words = plain.split_whitespace().count();
+    metadata.characters = plain.chars().filter(|c| *c != '\n').count();
+    metadata.reading_minutes = metadata.words.div_ceil(WORDS_PER_MINUTE);
+    metadata.language = language::detect(&plain);
+    metadata
+}
+
+/// Up to three leading spaces keep a line's block meaning; more make it an
+/// indented code line, which we still scan like text.
+fn strip_indent(line: &str) -> &str {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent <= 3 {
+        &line[indent..]
+    } else {
+        line
+    }
+}
+
+fn opens_fence(line: &str) -> Option<(char, usize, &str)> {
+    let marker = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let length = line.len() - line.trim_start_matches(marker).len();
+    if length < 3 {
+        return None;
+    }
+    let info = line[length..].trim();
+    if marker == '`' && info.contains('`') {
+        return None;
+    }
+    Some((marker, length, info))
+}
+
+fn closes_fence(line: &str, marker: char, length: usize) -> bool {
+    let run = line.len() - line.trim_start_matches(marker).len();
+    run >= length && line[run..].trim().is_empty()
+}
+
+fn parse_heading(line: &str) -> Option<Heading> {
+    let level = line.len() - line.trim_start_matches('#').len();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    let text = rest.trim().trim_end_matches('#').trim_end();
+    Some(Heading {
+        level: level as u8,
+        text: text.to_string(),
+    })
+}
+
+/// `- [ ] todo`, `* [x] done`, `1. [ ] numbered`; returns whether checked.
+fn parse_task(line: &str) -> Option<bool> {
+    let (_, item) = parse_list_item(line)?;
+    parse_task_box(item).map(|(checked, _)| checked)
+}
+
+/// A bullet or numbered list item: whether it is numbered, and its content.
+fn parse_list_item(line: &str) -> Option<(bool, &str)> {
+    let (ordered, after_marker) = if let Some(rest) = line.strip_prefix(['-', '*', '+']) {
+        (false, rest)
+    } else {
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 || digits > 9 {
+            return None;
+        }
+        (true, line[digits..].strip_prefix(['.', ')'])?)
+    };
+
+    let item = after_marker.strip_prefix([' ', '\t'])?.trim_start();
+    Some((ordered, item))
+}
+
+/// The `[ ]` or `[x]` opening a task item, and the text after it.
+fn parse_task_box(item: &str) -> Option<(bool, &str)> {
+    let checked = match item.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let rest = &item[3..];
+    (rest.is_empty() || rest.starts_with([' ', '\t'])).then_some((checked, rest.trim_start()))
+}
+
+/// `---`, `***` or `___`, optionally spaced out.
+fn is_thematic_break(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3
+        && ['-', '*', '_']
+            .into_iter()
+            .any(|mark| marks.chars().all(|c| c == mark))
+}
+
+/// Inline `[text](target)` links, `<scheme://...>` autolinks and bare
+/// http(s) URLs, ignoring anything inside inline code spans.
+fn collect_links(line: &str, links: &mut Vec<String>) {
+    for (index, segment) in line.split('`').enumerate() {
+        if index % 2 == 1 {
+            continue;
+        }
+
+        let mut rest = segment;
+        while let Some(start) = rest.find("](") {
+            let after = &rest[start + 2..];
+            let Some(end) = after.find(')') else {
+                break;
+            };
+            let target = after[..end].split_whitespace().next().unwrap_or_default();
+            let target = target.trim_start_matches('<').trim_end_matches('>');
+            if !target.is_empty() {
+                push_unique(links, target.to_string());
+            }
+            rest = &after[end + 1..];
+        }
+
+        for word in segment.split(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+            let word = word.trim_start_matches('<').trim_end_matches('>');
+            if word.starts_with("http://") || word.starts_with("https://") {
+                let url = word.trim_end_matches(['.', ',', ';', ':', '!', '?', '"', '\'', ']']);
+                push_unique(links, url.to_string());
+            }
+        }
+    }
+}
+
+/// `[[Title]]`, `[[Title|shown text]]` and `[[Title#Heading]]` all refer
+/// to the note titled `Title`.
+fn collect_wikilinks(line: &str, wikilinks: &mut Vec<String>) {
+    for (index, segment) in line.split('`').enumerate() {
+        if index % 2 == 1 {
+            continue;
+        }
+
+        let mut rest = segment;
+        while let Some(start) = rest.find("[[") {
+            let after = &rest[start + 2..];
+            let Some(end) = after.find("]]") else {
+                break;
+            };
+            let target = after[..end].split(['|', '#']).next().unwrap_or_default();
+            let target = target.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !target.is_empty() {
+                push_unique(wikilinks, target);
+            }
+            rest = &after[end + 2..];
+        }
+    }
+}
+
+/// An HTML fragment for pasting into rich-text editors such as mail clients
+/// and chat apps.
+pub(crate) fn to_html(text: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+    let mut list: Option<&str> = None;
+    let mut fence: Option<(char, usize, String)> = None;
+    let mut code = String::new();
+
+    for line in text.lines() {
+        let trimmed = strip_indent(line);
+
+        if let Some((marker, length, language)) = &fence {
+            if closes_fence(trimmed, *marker, *length) {
+                push_code_block(&mut html, language, &code);
+                code.clear();
+                fence = None;
+            } else {
+                code.push_str(line);
+                code.push('\n');
+            }
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix('>') {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+            quote.push(rest.strip_prefix(' ').unwrap_or(rest));
+            continue;
+        }
+        flush_quote(&mut html, &mut quote);
+
+        // Nested items are flattened into the surrounding list.
+        let item_line = if list.is_some() {
+            line.trim_start()
+        } else {
+            trimmed
+        };
+        if trimmed.trim().is_empty() {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+        } else if let Some((marker, length, info)) = opens_fence(trimmed) {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+            let language = info.split_whitespace().next().unwrap_or_default();
+            fence = Some((marker, length, language.to_string()));
+        } else if let Some(heading) = parse_heading(trimmed) {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+            html.push_str(&format!(
+                "<h{level}>{}</h{level}>\n",
+                render_inline(&heading.text, true),
+                level = heading.level
+            ));
+        } else if is_thematic_break(trimmed) {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+            html.push_str("<hr>\n");
+        } else if let Some((ordered, item)) = parse_list_item(item_line) {
+            flush_paragraph(&mut html, &mut paragraph);
+            let tag = if ordered { "ol" } else { "ul" };
+            if list != Some(tag) {
+                close_list(&mut html, &mut list);
+                html.push_str(&format!("<{tag}>\n"));
+                list = Some(tag);
+            }
+            html.push_str(&format!("<li>{}</li>\n", render_list_item(item, true)));
+        } else {
+            close_list(&mut html, &mut list);
+            paragraph.push(trimmed.trim());
+        }
+    }
+
+    if let Some((_, _, language)) = fence {
+        // An unclosed fence runs to the end of the note.
+        push_code_block(&mut html, &language, &code);
+    }
+    flush_quote(&mut html, &mut quote);
+    flush_paragraph(&mut html, &mut paragraph);
+    close_list(&mut html, &mut list);
+    html
+}
+
+/// The note as readable text: line structure and list markers stay, while
+/// heading markers, quote markers, code fences and inline syntax go.
+pub(crate) fn to_plain_text(text: &str) -> String {
+    let mut lines = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
+
+    for line in text.lines() {
+        let trimmed = strip_indent(line);
+
+        if let Some((marker, length)) = fence {
+            if closes_fence(trimmed, marker, length) {
+                fence = None;
+            } else {
+                lines.push(line.to_string());
+            }
+            continue;
+        }
+        if let Some((marker, length, _)) = opens_fence(trimmed) {
+            fence = Some((marker, length));
+            continue;
+        }
+
+        let mut content = trimmed;
+        while let Some(rest) = content.strip_prefix('>') {
+            content = rest.strip_prefix(' ').unwrap_or(rest);
+        }
+
+        if let Some(heading) = parse_heading(content) {
+            lines.push(render_inline(&heading.text, false));
+        } else if is_thematic_break(content) {
+            lines.push(content.trim().to_string());
+        } else if let Some((_, item)) = parse_list_item(content) {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let marker = content[..content.len() - item.len()].trim_end();
+            lines.push(format!(
+                "{indent}{marker} {}",
+                render_list_item(item, false)
+            ));
+        } else {
+            lines.push(render_inline(content, false));
+        }
+    }
+    lines.join("\n")
+}
+
+fn push_code_block(html: &mut String, language: &str, code: &str) {
+    let class = if language.is_empty() {
+        String::new()
+    } else {
+        format!(" class=\"language-{}\"", escape_html(language))
+    };
+    html.push_str(&format!(
+        "<pre><code{class}>{}</code></pre>\n",
+        escape_html(code)
+    ));
+}
+
+fn flush_quote(html: &mut String, quote: &mut Vec<&str>) {
+    if quote.is_empty() {
+        return;
+    }
+    html.push_str(&format!(
+        "<blockquote>\n{}</blockquote>\n",
+        to_html(&quote.join("\n"))
+    ));
+    quote.clear();
+}
+
+fn flush_paragraph(html: &mut String, paragraph: &mut Vec<&str>) {
+    if paragraph.is_empty() {
+        return;
+    }
+    let lines: Vec<String> = paragraph
+        .iter()
+        .map(|line| render_inline(line, true))
+        .collect();
+    html.push_str(&format!("<p>{}</p>\n", lines.join("<br>\n")));
+    paragraph.clear();
+}
+
+fn close_list(html: &mut String, list: &mut Option<&str>) {
+    if let Some(tag) = list.take() {
+        html.push_str(&format!("</{tag}>\n"));
+    }
+}
+
+/// Task boxes become ballot box characters, which survive pasting where
+/// form controls would be stripped.
+fn render_list_item(item: &str, html: bool) -> String {
+    match parse_task_box(item) {
+        Some((checked, rest)) => {
+            let ballot = if checked { '\u{2611}' } else { '\u{2610}' };
+            format!("{ballot} {}", render_inline(rest, html))
+        }
+        None => render_inline(item, html),
+    }
+}
+
+/// Code spans, links, images, `[[wikilinks]]`, autolinks, strong, emphasis
+/// and strikethrough, as HTML or with the syntax removed.
+fn render_inline(text: &str, html: bool) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    let mut after_word = false;
+
+    while let Some(c) = rest.chars().next() {
+        let consumed = if let Some(escaped) = rest
+            .strip_prefix('\\')
+            .and_then(|after| after.chars().next())
+            .filter(|next| next.is_ascii_punctuation())
+        {
+            push_text(&mut out, &escaped.to_string(), html);
+            1 + escaped.len_utf8()
+        } else if let Some(end) = rest.strip_prefix('`').and_then(|after| after.find('`')) {
+            let code = &rest[1..1 + end];
+            if html {
+                out.push_str(&format!("<code>{}</code>", escape_html(code)));
+            } else {
+                out.push_str(code);
+            }
+            end + 2
+        } else if let Some(end) = rest.strip_prefix("[[").and_then(|after| after.find("]]")) {
+            let inner = &rest[2..2 + end];
+            let shown = match inner.split_once('|') {
+                Some((_, alias)) => alias,
+                None => inner.split('#').next().unwrap_or_default(),
+            };
+            push_text(&mut out, shown.trim(), html);
+            end + 4
+        } else if let Some((label, target, length)) =
+            rest.strip_prefix('!').and_then(parse_inline_link)
+        {
+            if html {
+                out.push_str(&format!(
+                    "<img src=\"{}\" alt=\"{}\">",
+                    escape_html(target),
+                    escape_html(label)
+                ));
+            } else {
+                out.push_str(label);
+            }
+            length + 1
+        } else if let Some((label, target, length)) = parse_inline_link(rest) {
+            let label = render_inline(label, html);
+            if html {
+                out.push_str(&format!("<a href=\"{}\">{label}</a>", escape_html(target)));
+            } else if label == target {
+                out.push_str(target);
+            } else {
+                out.push_str(&format!("{label} ({target})"));
+            }
+            length
+        } else if let Some(url) = parse_autolink(rest) {
+            if html {
+                out.push_str(&format!("<a href=\"{0}\">{0}</a>", escape_html(url)));
+            } else {
+                out.push_str(url);
+            }
+            url.len() + 2
+        } else if let Some((marker, tag, inner)) = parse_emphasis(rest, after_word) {
+            let inner_text = render_inline(inner, html);
+            if html {
+                out.push_str(&format!("<{tag}>{inner_text}</{tag}>"));
+            } else {
+                out.push_str(&inner_text);
+            }
+            inner.len() + 2 * marker.len()
+        } else {
+            push_text(&mut out, &c.to_string(), html);
+            c.len_utf8()
+        };
+
+        after_word = rest[..consumed]
+            .chars()
+            .last()
+            .is_some_and(char::is_alphanumeric);
+        rest = &rest[consumed..];
+    }
+    out
+}
+
+/// `[label](target "title")`: the label, the target and the bytes consumed.
+fn parse_inline_link(text: &str) -> Option<(&str, &str, usize)> {
+    let after = text.strip_prefix('[')?;
+    let label_end = after.find("](")?;
+    let label = &after[..label_end];
+    if label.contains(']') {
+        return None;
+    }
+    let destination = &after[label_end + 2..];
+    let end = destination.find(')')?;
+    let target = destination[..end]
+        .split_whitespace()
+        .next()
+        .unwrap_or_default();
+    let target = target.trim_start_matches('<').trim_end_matches('>');
+    Some((label, target, 1 + label_end + 2 + end + 1))
+}
+
+/// `<https://example.com>`: the URL between the brackets.
+fn parse_autolink(text: &str) -> Option<&str> {
+    let after = text.strip_prefix('<')?;
+    let url = &after[..after.find('>')?];
+    (url.contains("://") && !url.contains(char::is_whitespace)).then_some(url)
+}
+
+/// `**strong**`, `__strong__`, `~~struck~~`, `*em*` and `_em_`: the marker,
+/// the HTML tag and the text inside. Underscores inside words stay literal.
+fn parse_emphasis(text: &str, after_word: bool) -> Option<(&'static str, &'static str, &str)> {
+    let (marker, tag) = [
+        ("**", "strong"),
+        ("__", "strong"),
+        ("~~", "del"),
+        ("*", "em"),
+        ("_", "em"),
+    ]
+    .into_iter()
+    .find(|(marker, _)| text.starts_with(marker))?;
+    if marker.starts_with('_') && after_word {
+        return None;
+    }
+
+    let after = &text[marker.len()..];
+    let end = after.find(marker)?;
+    let inner = &after[..end];
+    let closes_word = after[end + marker.len()..]
+        .chars()
+        .next()
+        .is_some_and(char::is_alphanumeric);
+    if inner.is_empty()
+        || inner.starts_with(char::is_whitespace)
+        || inner.ends_with(char::is_whitespace)
+        || (marker.starts_with('_') && closes_word)
+    {
+        return None;
+    }
+    Some((marker, tag, inner))
+}
+
+fn push_text(out: &mut String, text: &str, html: bool) {
+    if html {
+        out.push_str(&escape_html(text));
+    } else {
+        out.push_str(text);
+    }
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn push_unique(values: &mut Vec<String>, value: String) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
@@ -0,0 +1,234 @@
+//! Three-way merging of a note edited on two devices between syncs, against
+//! the version both had at the last sync. Texts merge character by character
+//! through their CRDT documents (see `crdt`), and edits to different tags
+//! or fields all survive instead of the newer copy clobbering the older one.
+//! Texts whose documents share no history fall back to a line-level merge,
+//! where edits to the same lines still conflict.
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::{crdt, history::matching_lines, Note};
+
+/// Fields merged on their own terms (or derived) rather than as values.
+const SPECIAL_FIELDS: &[&str] = &["id", "text", "crdt", "tags", "metadata", "updated_at"];
+
+/// Merges `ours` and `theirs`, both edited since `base`. Fields both sides
+/// changed take the newer revision's value. `None` when the texts cannot be
+/// merged: the line-level fallback found edits to the same lines, or either
+/// side is locked and their sealed texts differ.
+pub(crate) fn merge_note(base: &Note, ours: &Note, theirs: &Note) -> Option<Note> {
+    let (text, document) = if ours.locked.is_some() || theirs.locked.is_some() {
+        // A locked note's text is a placeholder; the sealed text can only
+        // be told apart, not merged.
+        if ours.locked != theirs.locked {
+            return None;
+        }
+        (ours.text.clone(), None)
+    } else {
+        match crdt::merge_text(base, ours, theirs) {
+            Some((text, document)) => (text, Some(document)),
+            None => (merge_text(&base.text, &ours.text, &theirs.text)?, None),
+        }
+    };
+    let (newer, older) = if theirs.revised_at() > ours.revised_at() {
+        (theirs, ours)
+    } else {
+        (ours, theirs)
+    };
+
+    let base_value = serde_json::to_value(base).ok()?;
+    let mut merged = serde_json::to_value(newer).ok()?;
+    let older_value = serde_json::to_value(older).ok()?;
+    if let (Some(merged), Some(older), Some(base)) = (
+        merged.as_object_mut(),
+        older_value.as_object(),
+        base_value.as_object(),
+    ) {
+        for (field, value) in older {
+            if SPECIAL_FIELDS.contains(&field.as_str()) {
+                continue;
+            }
+            let base_field = base.get(field).unwrap_or(&Value::Null);
+            let newer_field = merged.get(field).unwrap_or(&Value::Null);
+            // Only the older side changed it, so its change stands.
+            if newer_field == base_field && value != base_field {
+                merged.insert(field.clone(), value.clone());
+            }
+        }
+    }
+
+    let mut note: Note = serde_json::from_value(merged).ok()?;
+    note.tags = merge_tags(&base.tags, &ours.tags, &theirs.tags);
+    note.set_text(text);
+    note.crdt = document;
+    Some(note)
+}
+
+/// A tag is kept when both sides have it or one side added it.
+fn merge_tags(base: &[String], ours: &[String], theirs: &[String]) -> Vec<String> {
+    let base: HashSet<&String> = base.iter().collect();
+    let theirs_set: HashSet<&String> = theirs.iter().collect();
+    let ours_set: HashSet<&String> = ours.iter().collect();
+
+    let mut tags: Vec<String> = ours
+        .iter()
+        .filter(|tag| theirs_set.contains(tag) || !base.contains(tag))
+        .cloned()
+        .collect();
+    tags.extend(
+        theirs
+            .iter()
+            .filter(|tag| !ours_set.contains(tag) && !base.contains(tag))
+            .cloned(),
+    );
+    tags
+}
+
+/// Line-based diff3: takes each side's change to a stretch of lines the
+/// other left alone. `None` when both changed the same stretch differently,
+/// or the texts are too long for `matching_lines` to compare.
+pub(crate) fn merge_text(base: &str, ours: &str, theirs: &str) -> Option<String> {
+    if ours == theirs || theirs == base {
+        return Some(ours.to_string());
+    }
+    if ours == base {
+        return Some(theirs.to_string());
+    }
+
+    let base_lines: Vec<&str> = base.lines().collect();
+    let our_lines: Vec<&str> = ours.lines().collect();
+    let their_lines: Vec<&str> = theirs.lines().collect();
+    let ours_at = matching_lines(&base_lines, &our_lines)?;
+    let theirs_at = matching_lines(&base_lines, &their_lines)?;
+
+    let mut merged: Vec<&str> = Vec::new();
+    let (mut b, mut o, mut t) = (0, 0, 0);
+    loop {
+        // The next base line both sides kept where the walk expects it.
+        let stable = (b..base_lines.len())
+            .find(|&line| matches!((ours_at[line], theirs_at[line]), (Some(x), Some(y)) if x >= o && y >= t));
+
+        let (base_end, our_end, their_end) = match stable {
+            Some(line) => (line, ours_at[line]?, theirs_at[line]?),
+            None => (base_lines.len(), our_lines.len(), their_lines.len()),
+        };
+        let base_chunk = &base_lines[b..base_end];
+        let our_chunk = &our_lines[o..our_end];
+        let their_chunk = &their_lines[t..their_end];
+        if our_chunk == base_chunk || our_chunk == their_chunk {
+            merged.extend(their_chunk);
+        } else if their_chunk == base_chunk {
+            merged.extend(our_chunk);
+        } else {
+            return None;
+        }
+
+        let Some(line) = stable else {
+            break;
+        };
+        merged.push(base_lines[line]);
+        (b, o, t) = (line + 1, our_end + 1, their_end + 1);
+    }
+
+    let mut text = merged.join("\n");
+    if ours.ends_with('\n') || theirs.ends_with('\n') {
+        text.push('\n');
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{Envelope, Key};
+
+    fn note(text: &str, tags: &[&str], updated_at: &str) -> Note {
+        let mut note = Note::new(text.to_string());
+        note.id = "a".to_string();
+        note.created_at = "2025-01-01T00:00:00+00:00".to_string();
+        note.updated_at = Some(updated_at.to_string());
+        note.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        note
+    }
+
+    #[test]
+    fn one_sided_edits_win() {
+        assert_eq!(merge_text("a", "a", "b").as_deref(), Some("b"));
+        assert_eq!(merge_text("a", "b", "a").as_deref(), Some("b"));
+        assert_eq!(merge_text("a", "b", "b").as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn edits_to_different_lines_merge() {
+        let base = "one\ntwo\nthree\nfour";
+        let ours = "ONE\ntwo\nthree\nfour";
+        let theirs = "one\ntwo\nthree\nfour\nfive";
+        assert_eq!(
+            merge_text(base, ours, theirs).as_deref(),
+            Some("ONE\ntwo\nthree\nfour\nfive")
+        );
+    }
+
+    #[test]
+    fn deletions_and_insertions_merge() {
+        let base = "one\ntwo\nthree";
+        let ours = "one\nthree";
+        let theirs = "zero\none\ntwo\nthree";
+        assert_eq!(
+            merge_text(base, ours, theirs).as_deref(),
+            Some("zero\none\nthree")
+        );
+    }
+
+    #[test]
+    fn edits_to_the_same_line_conflict() {
+        assert_eq!(merge_text("one\ntwo", "uno\ntwo", "eins\ntwo"), None);
+    }
+
+    #[test]
+    fn keeps_a_trailing_line_break() {
+        assert_eq!(
+            merge_text("a\nb\nc\n", "A\nb\nc\n", "a\nb\nC\n").as_deref(),
+            Some("A\nb\nC\n")
+        );
+    }
+
+    #[test]
+    fn merges_tags_and_fields() {
+        let base = note("text", &["keep", "drop"], "2025-01-02T00:00:00+00:00");
+        let mut ours = note(
+            "text",
+            &["keep", "drop", "ours"],
+            "2025-01-03T00:00:00+00:00",
+        );
+        ours.pinned = true;
+        let mut theirs = note("text", &["keep", "theirs"], "2025-01-04T00:00:00+00:00");
+        theirs.color = Some("#f00".to_string());
+
+        let merged = merge_note(&base, &ours, &theirs).unwrap();
+        assert_eq!(merged.tags, vec!["keep", "ours", "theirs"]);
+        // Each side changed a field the other left alone.
+        assert!(merged.pinned);
+        assert_eq!(merged.color.as_deref(), Some("#f00"));
+        assert_eq!(merged.updated_at, theirs.updated_at);
+    }
+
+    #[test]
+    fn locked_texts_only_merge_when_unchanged() {
+        let key = Key::for_tests("correct horse", 1);
+        let base = note("secret", &[], "2025-01-02T00:00:00+00:00");
+        let mut ours = note("🔒", &[], "2025-01-03T00:00:00+00:00");
+        ours.locked = Some(Envelope::seal(&key, b"secret, ours", "a").unwrap());
+        let mut theirs = note("🔒", &["theirs"], "2025-01-04T00:00:00+00:00");
+        theirs.locked = Some(Envelope::seal(&key, b"secret, theirs", "a").unwrap());
+        assert!(merge_note(&base, &ours, &theirs).is_none());
+
+        theirs.locked = ours.locked.clone();
+        let merged = merge_note(&base, &ours, &theirs).unwrap();
+        assert_eq!(merged.text, "🔒");
+        assert_eq!(merged.locked, ours.locked);
+        assert_eq!(merged.tags, vec!["theirs"]);
+    }
+}
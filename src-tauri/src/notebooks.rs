@@ -0,0 +1,281 @@
+use std::{fs, path::Path};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+use uuid::Uuid;
+
+use crate::{
+    find_active_note_mut, resolve_data_file, write_file_atomically, Note, StorageState,
+    NOTES_CHANGED_EVENT,
+};
+
+const NOTEBOOKS_FILE_NAME: &str = "notebooks.json";
+const NOTEBOOKS_CHANGED_EVENT: &str = "notebooks-changed";
+pub(crate) const INBOX_NOTEBOOK_ID: &str = "inbox";
+const INBOX_NOTEBOOK_NAME: &str = "Inbox";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct Notebook {
+    id: String,
+    name: String,
+    color: Option<String>,
+    created_at: String,
+}
+
+/// Notes written before notebooks existed land in the Inbox on load.
+pub(crate) fn default_notebook_id() -> String {
+    INBOX_NOTEBOOK_ID.to_string()
+}
+
+#[tauri::command]
+pub(crate) fn list_notebooks(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+) -> Result<Vec<Notebook>, String> {
+    let _guard = state.lock(&app)?;
+
+    load_notebooks(&app)
+}
+
+#[tauri::command]
+pub(crate) fn create_notebook(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    name: String,
+    color: Option<String>,
+) -> Result<Notebook, String> {
+    let name = validate_notebook_name(&name)?;
+    let color = color.as_deref().map(normalize_color).transpose()?;
+
+    let _guard = state.lock(&app)?;
+
+    let mut notebooks = load_notebooks(&app)?;
+    ensure_unique_name(&notebooks, &name, None)?;
+
+    let notebook = Notebook {
+        id: Uuid::new_v4().to_string(),
+        name,
+        color,
+        created_at: Utc::now().to_rfc3339(),
+    };
+
+    notebooks.push(notebook.clone());
+    save_notebooks(&app, &notebooks)?;
+
+    let _ = app.emit(NOTEBOOKS_CHANGED_EVENT, ());
+    Ok(notebook)
+}
+
+#[tauri::command]
+pub(crate) fn rename_notebook(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    name: String,
+) -> Result<Notebook, String> {
+    let name = validate_notebook_name(&name)?;
+
+    let _guard = state.lock(&app)?;
+
+    let mut notebooks = load_notebooks(&app)?;
+    ensure_unique_name(&notebooks, &name, Some(&id))?;
+    let notebook = find_notebook_mut(&mut notebooks, &id)?;
+    notebook.name = name;
+    let renamed = notebook.clone();
+
+    save_notebooks(&app, &notebooks)?;
+    let _ = app.emit(NOTEBOOKS_CHANGED_EVENT, ());
+    Ok(renamed)
+}
+
+#[tauri::command]
+pub(crate) fn set_notebook_color(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    color: Option<String>,
+) -> Result<Notebook, String> {
+    let color = color.as_deref().map(normalize_color).transpose()?;
+
+    let _guard = state.lock(&app)?;
+
+    let mut notebooks = load_notebooks(&app)?;
+    let notebook = find_notebook_mut(&mut notebooks, &id)?;
+    notebook.color = color;
+    let updated = notebook.clone();
+
+    save_notebooks(&app, &notebooks)?;
+    let _ = app.emit(NOTEBOOKS_CHANGED_EVENT, ());
+    Ok(updated)
+}
+
+/// Deletes a notebook and moves its notes back into the Inbox.
+#[tauri::command]
+pub(crate) fn delete_notebook(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<(), String> {
+    if id == INBOX_NOTEBOOK_ID {
+        return Err("The Inbox notebook cannot be deleted".to_string());
+    }
+
+    let storage = state.lock(&app)?;
+
+    let mut notebooks = load_notebooks(&app)?;
+    let before = notebooks.len();
+    notebooks.retain(|notebook| notebook.id != id);
+    if notebooks.len() == before {
+        return Err("Notebook not found".to_string());
+    }
+
+    let mut notes = storage.load_notes()?;
+    let mut moved = false;
+    for note in notes.iter_mut().filter(|note| note.notebook_id == id) {
+        note.notebook_id = default_notebook_id();
+        moved = true;
+    }
+
+    if moved {
+        storage.save_notes(&notes)?;
+        state.invalidate_search_index();
+        let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    }
+    save_notebooks(&app, &notebooks)?;
+
+    let _ = app.emit(NOTEBOOKS_CHANGED_EVENT, ());
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn move_note_to_notebook(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    notebook_id: String,
+) -> Result<Note, String> {
+    let storage = state.lock(&app)?;
+
+    let notebooks = load_notebooks(&app)?;
+    if !notebooks.iter().any(|notebook| notebook.id == notebook_id) {
+        return Err("Notebook not found".to_string());
+    }
+
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+    note.notebook_id = notebook_id;
+    let moved = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&moved));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(moved)
+}
+
+/// Accepts `#rgb` or `#rrggbb` and stores it lowercase.
+pub(crate) fn normalize_color(color: &str) -> Result<String, String> {
+    let color = color.trim().to_lowercase();
+    let hex = color.strip_prefix('#').unwrap_or(&color);
+    if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "Invalid color \"{color}\", expected #rgb or #rrggbb"
+        ));
+    }
+    Ok(format!("#{hex}"))
+}
+
+fn validate_notebook_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Notebook name cannot be empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+fn ensure_unique_name(
+    notebooks: &[Notebook],
+    name: &str,
+    except_id: Option<&str>,
+) -> Result<(), String> {
+    let taken = notebooks.iter().any(|notebook| {
+        Some(notebook.id.as_str()) != except_id && notebook.name.eq_ignore_ascii_case(name)
+    });
+    if taken {
+        return Err(format!("A notebook named \"{name}\" already exists"));
+    }
+    Ok(())
+}
+
+fn find_notebook_mut<'a>(
+    notebooks: &'a mut [Notebook],
+    id: &str,
+) -> Result<&'a mut Notebook, String> {
+    notebooks
+        .iter_mut()
+        .find(|notebook| notebook.id == id)
+        .ok_or_else(|| "Notebook not found".to_string())
+}
+
+/// Adds incoming notebooks whose id is not known yet; existing ones keep
+/// their current name and color.
+pub(crate) fn merge_notebooks(current: Vec<Notebook>, incoming: Vec<Notebook>) -> Vec<Notebook> {
+    let mut merged = current;
+    for notebook in incoming {
+        if !merged.iter().any(|existing| existing.id == notebook.id) {
+            merged.push(notebook);
+        }
+    }
+    merged
+}
+
+pub(crate) fn notify_changed(app: &AppHandle) {
+    let _ = app.emit(NOTEBOOKS_CHANGED_EVENT, ());
+}
+
+fn inbox_notebook() -> Notebook {
+    Notebook {
+        id: default_notebook_id(),
+        name: INBOX_NOTEBOOK_NAME.to_string(),
+        color: None,
+        created_at: Utc::now().to_rfc3339(),
+    }
+}
+
+pub(crate) fn load_notebooks(app: &AppHandle) -> Result<Vec<Notebook>, String> {
+    let path = resolve_data_file(app, NOTEBOOKS_FILE_NAME)?;
+    let mut notebooks = load_notebooks_from_path(&path)?;
+
+    if !notebooks
+        .iter()
+        .any(|notebook| notebook.id == INBOX_NOTEBOOK_ID)
+    {
+        notebooks.insert(0, inbox_notebook());
+    }
+    Ok(notebooks)
+}
+
+fn load_notebooks_from_path(path: &Path) -> Result<Vec<Notebook>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let raw =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read notebooks file: {e}"))?;
+    if raw.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    serde_json::from_str::<Vec<Notebook>>(&raw)
+        .map_err(|e| format!("Failed to parse notebooks file: {e}"))
+}
+
+pub(crate) fn save_notebooks(app: &AppHandle, notebooks: &[Notebook]) -> Result<(), String> {
+    let path = resolve_data_file(app, NOTEBOOKS_FILE_NAME)?;
+    let payload = serde_json::to_string_pretty(notebooks)
+        .map_err(|e| format!("Failed to serialize notebooks payload: {e}"))?;
+
+    write_file_atomically(&path, &payload)
+}
@@ -0,0 +1,51 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+use tauri::{AppHandle, Manager};
+
+use crate::{resolve_storage, storage_stamp, StorageState};
+
+const POLL_INTERVAL_MS: u64 = 1000;
+static WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Spawns the polling thread once. It notices when another program (a sync
+/// client, an editor, the `jotin` CLI, or Obsidian in a vault) rewrites the
+/// notes while the app is running.
+pub(crate) fn start(app: &AppHandle) {
+    if WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    thread::spawn(move || watch(app));
+}
+
+/// Compares the file's stamp between polls. Taking the storage lock on a
+/// change is enough: the lock tells our own saves apart from outside writes,
+/// drops the stale in-memory notes and emits `notes-changed` for the latter.
+fn watch(app: AppHandle) {
+    let mut last_seen = resolve_storage(&app)
+        .ok()
+        .and_then(|(path, vault)| storage_stamp(&path, vault.as_ref()));
+
+    loop {
+        thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+
+        let Ok((path, vault)) = resolve_storage(&app) else {
+            continue;
+        };
+        let stamp = storage_stamp(&path, vault.as_ref());
+        if stamp == last_seen {
+            continue;
+        }
+        last_seen = stamp;
+
+        let state = app.state::<StorageState>();
+        if let Err(error) = state.lock(&app).map(drop) {
+            eprintln!("Notes watcher failed to check the notes file: {error}");
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Native notifications through each platform's own tooling, in the same
+//! spirit as sync shelling out to `curl` and `git`.
+
+use std::{
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use tauri::AppHandle;
+
+use crate::{settings, tray};
+
+const APP_NAME: &str = "Jotin";
+static DO_NOT_DISTURB: AtomicBool = AtomicBool::new(false);
+
+/// A button on the notification: identifier and label.
+pub(crate) type Action = (&'static str, &'static str);
+
+/// Silences native notifications until turned off again; reminders still
+/// reach an open window.
+#[tauri::command]
+pub(crate) fn set_do_not_disturb(app: AppHandle, enabled: bool) -> Result<bool, String> {
+    settings::modify_settings(&app, |settings| {
+        settings.do_not_disturb = enabled;
+        Ok(())
+    })?;
+
+    apply(&app, enabled);
+    Ok(enabled)
+}
+
+pub(crate) fn is_do_not_disturb() -> bool {
+    DO_NOT_DISTURB.load(Ordering::SeqCst)
+}
+
+/// Picks up the persisted do-not-disturb setting at startup.
+pub(crate) fn load_do_not_disturb(app: &AppHandle) {
+    let enabled = settings::load_settings(app)
+        .map(|settings| settings.do_not_disturb)
+        .unwrap_or(false);
+    DO_NOT_DISTURB.store(enabled, Ordering::SeqCst);
+}
+
+/// Flips do not disturb and the tray check mark without persisting.
+pub(crate) fn apply(app: &AppHandle, enabled: bool) {
+    DO_NOT_DISTURB.store(enabled, Ordering::SeqCst);
+    tray::refresh(app);
+}
+
+/// Shows a notification in the background, unless do not disturb is on. Where the platform reports
+/// interaction (libnotify actions), `on_action` runs with the identifier of
+/// the action the user picked; elsewhere the actions are not shown.
+pub(crate) fn show(
+    app: &AppHandle,
+    title: &str,
+    body: &str,
+    actions: &'static [Action],
+    on_action: impl FnOnce(&AppHandle, &str) + Send + 'static,
+) {
+    if is_do_not_disturb() {
+        return;
+    }
+
+    let app = app.clone();
+    let title = title.to_string();
+    let body = body.to_string();
+
+    thread::spawn(move || match deliver(&title, &body, actions) {
+        Ok(Some(action)) => on_action(&app, &action),
+        Ok(None) => {}
+        Err(error) => eprintln!("Failed to show notification: {error}"),
+    });
+}
+
+/// Blocks until the notification is dismissed where the platform supports
+/// waiting; returns the chosen action, if any.
+#[cfg(target_os = "linux")]
+fn deliver(title: &str, body: &str, actions: &[Action]) -> Result<Option<String>, String> {
+    if !actions.is_empty() {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name", APP_NAME, "--wait"]);
+        for (id, label) in actions {
+            command.arg(format!("--action={id}={label}"));
+        }
+        let output = command
+            .args([title, body])
+            .output()
+            .map_err(|e| format!("Failed to run notify-send: {e}"))?;
+        if output.status.success() {
+            let action = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            return Ok((!action.is_empty()).then_some(action));
+        }
+        // Older libnotify has no actions; fall through to a plain notification.
+    }
+
+    run(Command::new("notify-send")
+        .args(["--app-name", APP_NAME])
+        .args([title, body]))
+    .map(|()| None)
+}
+
+#[cfg(target_os = "macos")]
+fn deliver(title: &str, body: &str, _actions: &[Action]) -> Result<Option<String>, String> {
+    let script = format!(
+        "display notification {} with title {}",
+        apple_script_string(body),
+        apple_script_string(title)
+    );
+    run(Command::new("osascript").args(["-e", script.as_str()])).map(|()| None)
+}
+
+#[cfg(target_os = "windows")]
+fn deliver(title: &str, body: &str, _actions: &[Action]) -> Result<Option<String>, String> {
+    let script = format!(
+        "$xml = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+         $text = $xml.GetElementsByTagName('text'); \
+         $text.Item(0).AppendChild($xml.CreateTextNode({})) | Out-Null; \
+         $text.Item(1).AppendChild($xml.CreateTextNode({})) | Out-Null; \
+         [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier({}).Show([Windows.UI.Notifications.ToastNotification]::new($xml))",
+        powershell_string(title),
+        powershell_string(body),
+        powershell_string(APP_NAME)
+    );
+    run(Command::new("powershell").args([
+        "-NoProfile",
+        "-NonInteractive",
+        "-Command",
+        script.as_str(),
+    ]))
+    .map(|()| None)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn deliver(_title: &str, _body: &str, _actions: &[Action]) -> Result<Option<String>, String> {
+    Err("Notifications are not supported on this platform".to_string())
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn run(command: &mut Command) -> Result<(), String> {
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run notifier: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Notifier failed: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn apple_script_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(target_os = "windows")]
+fn powershell_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
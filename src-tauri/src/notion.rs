@@ -0,0 +1,358 @@
+//! Sends notes to a Notion database through its API, for people who use
+//! jotin as an inbox and Notion as the archive. Each note becomes a page:
+//! its title the page title, its tags the database's multi-select property,
+//! its Markdown the page's blocks. Which notes were sent is remembered in
+//! `notion_pages.json` next to the notes, so the bulk export only sends new
+//! ones.
+
+use std::{collections::HashMap, fs, path::Path, thread, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, State};
+
+use crate::{
+    http, resolve_data_file,
+    settings::{self, Secret, Settings},
+    write_file_atomically, Note, StorageState,
+};
+
+const NOTION_API_URL: &str = "https://api.notion.com/v1";
+const NOTION_VERSION: &str = "2022-06-28";
+const PAGES_FILE_NAME: &str = "notion_pages.json";
+pub(crate) const DEFAULT_NOTION_TAGS_PROPERTY: &str = "Tags";
+/// Notion's limits: characters per rich text item, blocks per request and
+/// characters per select option.
+const MAX_TEXT_CHARS: usize = 2000;
+const MAX_BLOCKS_PER_REQUEST: usize = 100;
+const MAX_OPTION_CHARS: usize = 100;
+/// Notion allows about three requests a second.
+const BULK_EXPORT_DELAY: Duration = Duration::from_millis(400);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct NotionPage {
+    note_id: String,
+    page_id: String,
+    url: String,
+    sent_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct FailedExport {
+    note_id: String,
+    error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct NotionExportSummary {
+    exported: Vec<NotionPage>,
+    /// Notes that were already sent.
+    skipped: usize,
+    failed: Vec<FailedExport>,
+}
+
+/// The database's schema, as far as pages need it.
+struct Database {
+    id: String,
+    title_property: String,
+    tags_property: String,
+}
+
+/// Creates a Notion page for the note and returns it. Sending a note again
+/// creates another page.
+#[tauri::command]
+pub(crate) fn send_note_to_notion(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<NotionPage, String> {
+    let note = {
+        let storage = state.lock(&app)?;
+        let notes = storage.notes()?;
+        notes
+            .iter()
+            .find(|note| note.id == id)
+            .cloned()
+            .ok_or_else(|| "Note not found".to_string())?
+    };
+
+    let settings = settings::load_settings(&app)?;
+    let token = token()?;
+    let database = open_database(&settings, &token)?;
+    let page = create_page(&token, &database, &note)?;
+    record_pages(&app, std::slice::from_ref(&page))?;
+    Ok(page)
+}
+
+/// Sends every note not in the trash that has not been sent yet. A failure
+/// is reported per note and does not stop the export.
+#[tauri::command]
+pub(crate) fn export_notes_to_notion(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+) -> Result<NotionExportSummary, String> {
+    let notes = {
+        let storage = state.lock(&app)?;
+        storage.notes()?
+    };
+
+    let settings = settings::load_settings(&app)?;
+    let token = token()?;
+    let database = open_database(&settings, &token)?;
+    let sent = load_pages(&resolve_data_file(&app, PAGES_FILE_NAME)?)?;
+
+    let mut summary = NotionExportSummary::default();
+    for note in notes.iter().filter(|note| !note.is_trashed()) {
+        if sent.contains_key(&note.id) {
+            summary.skipped += 1;
+            continue;
+        }
+        if !summary.exported.is_empty() || !summary.failed.is_empty() {
+            thread::sleep(BULK_EXPORT_DELAY);
+        }
+        match create_page(&token, &database, note) {
+            Ok(page) => summary.exported.push(page),
+            Err(error) => summary.failed.push(FailedExport {
+                note_id: note.id.clone(),
+                error,
+            }),
+        }
+    }
+
+    record_pages(&app, &summary.exported)?;
+    Ok(summary)
+}
+
+fn token() -> Result<String, String> {
+    Secret::NotionToken
+        .get()?
+        .ok_or_else(|| "Set a Notion integration token in settings first".to_string())
+}
+
+/// Reads the database's title property and makes sure the tags property
+/// exists as a multi-select, adding it when missing.
+fn open_database(settings: &Settings, token: &str) -> Result<Database, String> {
+    let id = settings
+        .notion_database_id
+        .as_deref()
+        .and_then(database_id)
+        .ok_or_else(|| "Set the Notion database to export to in settings first".to_string())?;
+    let schema = request(token, "GET", &format!("databases/{id}"), None)?;
+    let properties = schema["properties"]
+        .as_object()
+        .ok_or_else(|| "Notion returned a database without properties".to_string())?;
+
+    let title_property = properties
+        .iter()
+        .find(|(_, property)| property["type"] == "title")
+        .map(|(name, _)| name.clone())
+        .ok_or_else(|| "The Notion database has no title property".to_string())?;
+    let tags_property = settings.notion_tags_property.clone();
+    match properties
+        .get(&tags_property)
+        .map(|property| &property["type"])
+    {
+        Some(kind) if kind == "multi_select" => {}
+        Some(_) => {
+            return Err(format!(
+                "The Notion property \"{tags_property}\" is not a multi-select"
+            ))
+        }
+        None => {
+            let body = json!({ "properties": { &tags_property: { "multi_select": {} } } });
+            request(token, "PATCH", &format!("databases/{id}"), Some(&body))?;
+        }
+    }
+
+    Ok(Database {
+        id,
+        title_property,
+        tags_property,
+    })
+}
+
+fn create_page(token: &str, database: &Database, note: &Note) -> Result<NotionPage, String> {
+    let title = match note.title() {
+        "" => "Untitled",
+        title => title,
+    };
+    // Notion rejects commas in select options.
+    let tags: Vec<Value> = note
+        .tags
+        .iter()
+        .map(|tag| json!({ "name": truncate(&tag.replace(',', " "), MAX_OPTION_CHARS) }))
+        .collect();
+
+    let blocks = blocks(&note.text);
+    let mut batches = blocks.chunks(MAX_BLOCKS_PER_REQUEST);
+    let body = json!({
+        "parent": { "database_id": database.id },
+        "properties": {
+            &database.title_property: { "title": rich_text(title) },
+            &database.tags_property: { "multi_select": tags },
+        },
+        "children": batches.next().unwrap_or_default(),
+    });
+    let page = request(token, "POST", "pages", Some(&body))?;
+    let page_id = page["id"]
+        .as_str()
+        .ok_or_else(|| "Notion did not return the new page".to_string())?
+        .to_string();
+
+    for batch in batches {
+        let body = json!({ "children": batch });
+        request(
+            token,
+            "PATCH",
+            &format!("blocks/{page_id}/children"),
+            Some(&body),
+        )?;
+    }
+
+    Ok(NotionPage {
+        note_id: note.id.clone(),
+        url: page["url"].as_str().unwrap_or_default().to_string(),
+        page_id,
+        sent_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+fn request(token: &str, method: &str, path: &str, body: Option<&Value>) -> Result<Value, String> {
+    let headers = [
+        format!("Authorization: Bearer {token}"),
+        format!("Notion-Version: {NOTION_VERSION}"),
+    ];
+    let response = http::send_json(method, &format!("{NOTION_API_URL}/{path}"), &headers, body)?;
+    if response.is_success() {
+        return Ok(response.body);
+    }
+    match response.body["message"].as_str() {
+        Some(message) => Err(format!("Notion returned an error: {message}")),
+        None => Err(format!(
+            "Notion request failed with HTTP {}",
+            response.status
+        )),
+    }
+}
+
+/// Accepts the bare id as well as the database's URL, whose last 32 hex
+/// digits are the id.
+fn database_id(value: &str) -> Option<String> {
+    let path = value.split(['?', '#']).next().unwrap_or_default();
+    let hex: String = path
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| *c != '-')
+        .collect();
+    let id = hex.get(hex.len().checked_sub(32)?..)?;
+    id.chars()
+        .all(|c| c.is_ascii_hexdigit())
+        .then(|| id.to_lowercase())
+}
+
+/// The note's Markdown as Notion blocks: headings, to-dos, list items, code
+/// and quotes keep their kind, everything else becomes a paragraph.
+fn blocks(text: &str) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(language) = trimmed.strip_prefix("```") {
+            let code: Vec<&str> = lines
+                .by_ref()
+                .take_while(|line| !line.trim_start().starts_with("```"))
+                .collect();
+            let language = match language.trim() {
+                "" => "plain text",
+                language => language,
+            };
+            blocks.push(json!({
+                "type": "code",
+                "code": { "rich_text": rich_text(&code.join("\n")), "language": language },
+            }));
+            continue;
+        }
+
+        let (kind, content, checked) = if let Some(rest) = trimmed.strip_prefix("### ") {
+            ("heading_3", rest, None)
+        } else if let Some(rest) = trimmed.strip_prefix("## ") {
+            ("heading_2", rest, None)
+        } else if let Some(rest) = trimmed.strip_prefix("# ") {
+            ("heading_1", rest, None)
+        } else if let Some(rest) = trimmed.strip_prefix("- [ ] ") {
+            ("to_do", rest, Some(false))
+        } else if let Some(rest) = trimmed
+            .strip_prefix("- [x] ")
+            .or_else(|| trimmed.strip_prefix("- [X] "))
+        {
+            ("to_do", rest, Some(true))
+        } else if let Some(rest) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            ("bulleted_list_item", rest, None)
+        } else if let Some(rest) = numbered_item(trimmed) {
+            ("numbered_list_item", rest, None)
+        } else if let Some(rest) = trimmed.strip_prefix("> ") {
+            ("quote", rest, None)
+        } else {
+            ("paragraph", trimmed, None)
+        };
+
+        let mut block = json!({ "rich_text": rich_text(content) });
+        if let Some(checked) = checked {
+            block["checked"] = json!(checked);
+        }
+        blocks.push(json!({ "type": kind, kind: block }));
+    }
+    blocks
+}
+
+fn numbered_item(line: &str) -> Option<&str> {
+    let (number, rest) = line.split_once(". ")?;
+    (!number.is_empty() && number.chars().all(|c| c.is_ascii_digit())).then_some(rest)
+}
+
+/// Plain rich text, split where Notion's per-item limit requires.
+fn rich_text(text: &str) -> Vec<Value> {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(MAX_TEXT_CHARS)
+        .map(|chunk| json!({ "type": "text", "text": { "content": chunk.iter().collect::<String>() } }))
+        .collect()
+}
+
+fn truncate(value: &str, max_chars: usize) -> String {
+    value.trim().chars().take(max_chars).collect()
+}
+
+fn load_pages(path: &Path) -> Result<HashMap<String, NotionPage>, String> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let raw = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read Notion export records: {e}"))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Failed to parse Notion export records: {e}"))
+}
+
+fn record_pages(app: &AppHandle, pages: &[NotionPage]) -> Result<(), String> {
+    if pages.is_empty() {
+        return Ok(());
+    }
+    let path = resolve_data_file(app, PAGES_FILE_NAME)?;
+    let mut sent = load_pages(&path)?;
+    for page in pages {
+        sent.insert(page.note_id.clone(), page.clone());
+    }
+    let payload = serde_json::to_string_pretty(&sent)
+        .map_err(|e| format!("Failed to serialize Notion export records: {e}"))?;
+    write_file_atomically(&path, &payload)
+}
@@ -0,0 +1,127 @@
+//! Text recognition for image attachments through the `tesseract` command,
+//! in the same spirit as sync shelling out to `curl` and `git`. Runs in the
+//! background; nothing happens when tesseract is not installed.
+
+use std::{
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{attachments, Note, StorageState, NOTES_CHANGED_EVENT};
+
+const OCR_FINISHED_EVENT: &str = "ocr-finished";
+/// A missing tesseract is reported once per run, not once per image.
+static REPORTED_MISSING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct OcrFinished {
+    note_id: String,
+    found_text: bool,
+}
+
+/// Recognizes the text of every image attached to a note and stores it as
+/// the note's `ocr_text`, replacing what was there.
+pub(crate) fn refresh(app: &AppHandle, note_id: &str) {
+    let app = app.clone();
+    let note_id = note_id.to_string();
+    thread::spawn(move || {
+        if let Err(error) = recognize_note(&app, &note_id) {
+            eprintln!("Failed to recognize attachment text: {error}");
+        }
+    });
+}
+
+fn recognize_note(app: &AppHandle, note_id: &str) -> Result<(), String> {
+    let state = app.state::<StorageState>();
+    let hashes: Vec<String> = {
+        let storage = state.lock(app)?;
+        let notes = storage.notes()?;
+        // A locked note keeps no searchable text.
+        let Some(note) = notes
+            .iter()
+            .find(|note| note.id == note_id && note.locked.is_none())
+        else {
+            return Ok(());
+        };
+        image_hashes(note)
+    };
+
+    let mut texts = Vec::new();
+    for hash in &hashes {
+        let path = attachments::blob_path(app, hash)?;
+        if let Some(text) = recognize(&path.to_string_lossy())? {
+            texts.push(text);
+        }
+    }
+    let ocr_text = Some(texts.join("\n\n")).filter(|text| !text.is_empty());
+
+    let storage = state.lock(app)?;
+    let mut notes = storage.load_notes()?;
+    let Some(note) = notes
+        .iter_mut()
+        .find(|note| note.id == note_id && note.locked.is_none())
+    else {
+        return Ok(());
+    };
+    // Attachments changed while recognizing; the refresh they triggered
+    // will store the up-to-date text.
+    if image_hashes(note) != hashes {
+        return Ok(());
+    }
+    let finished = OcrFinished {
+        note_id: note.id.clone(),
+        found_text: ocr_text.is_some(),
+    };
+    if note.ocr_text != ocr_text {
+        note.ocr_text = ocr_text;
+        let updated = note.clone();
+
+        storage.save_notes(&notes)?;
+        state.update_search_index(|index| index.upsert(&updated));
+        let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    }
+
+    let _ = app.emit(OCR_FINISHED_EVENT, finished);
+    Ok(())
+}
+
+fn image_hashes(note: &Note) -> Vec<String> {
+    note.attachments
+        .iter()
+        .filter(|attachment| attachment.is_image())
+        .map(|attachment| attachment.hash().to_string())
+        .collect()
+}
+
+/// The recognized text, or `None` for an image without any.
+fn recognize(path: &str) -> Result<Option<String>, String> {
+    let output = match Command::new("tesseract").args([path, "stdout"]).output() {
+        Ok(output) => output,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            if !REPORTED_MISSING.swap(true, Ordering::SeqCst) {
+                eprintln!("tesseract is not installed; image text will not be searchable");
+            }
+            return Ok(None);
+        }
+        Err(error) => return Err(format!("Failed to run tesseract: {error}")),
+    };
+    if !output.status.success() {
+        return Err(format!(
+            "tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let text = text
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(Some(text.trim().to_string()).filter(|text| !text.is_empty()))
+}
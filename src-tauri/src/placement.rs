@@ -0,0 +1,449 @@
+//! Where the quick-capture window appears when it opens, and how tall it is.
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, LogicalSize, Manager, Monitor, PhysicalPosition, PhysicalRect, WebviewWindow,
+};
+
+use crate::{
+    append::CaptureTarget,
+    dismiss::{self, CaptureDismiss},
+    settings, CAPTURE_WINDOW_HEIGHT, CAPTURE_WINDOW_LABEL, CAPTURE_WINDOW_WIDTH,
+};
+
+/// Gap between the cursor and the window's top-left corner.
+const CURSOR_OFFSET: f64 = 14.0;
+/// Share of the work area's height above a top-placed window.
+const TOP_PLACEMENT_RATIO: f64 = 0.2;
+/// Tallest the capture window grows, in logical pixels, before its content
+/// has to scroll.
+const MAX_CAPTURE_WINDOW_HEIGHT: f64 = 480.0;
+/// Opening height in multi-line mode, room for about five lines.
+const MULTI_LINE_CAPTURE_WINDOW_HEIGHT: f64 = 200.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CapturePlacement {
+    /// Just below and right of the mouse cursor.
+    #[default]
+    NearCursor,
+    /// Centered on the monitor under the cursor.
+    Centered,
+    /// Where the window was when it was last closed.
+    Remembered,
+    /// Horizontally centered near the top of the monitor, Spotlight style.
+    Top,
+}
+
+/// How the capture input treats Enter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CaptureMode {
+    /// Enter saves the note; the window grows only as long lines wrap.
+    #[default]
+    SingleLine,
+    /// Enter starts a new line and Ctrl+Enter (Cmd+Enter on macOS) saves.
+    MultiLine,
+}
+
+impl CaptureMode {
+    /// Logical height the capture window opens at.
+    fn window_height(self) -> f64 {
+        match self {
+            Self::SingleLine => CAPTURE_WINDOW_HEIGHT,
+            Self::MultiLine => MULTI_LINE_CAPTURE_WINDOW_HEIGHT,
+        }
+    }
+}
+
+/// Sent with `capture-opened` so the input matches the configured mode.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct CaptureConfig {
+    mode: CaptureMode,
+    target: CaptureTarget,
+    enter_submits: bool,
+    /// The key combination that saves, for the hint next to the input.
+    submit_shortcut: &'static str,
+    /// What hides the window, counting a pin as `never`.
+    dismiss: CaptureDismiss,
+    pinned: bool,
+}
+
+/// Which monitor the capture window opens on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CaptureMonitor {
+    /// The monitor under the mouse cursor.
+    #[default]
+    Cursor,
+    /// The monitor of the window that has keyboard focus, for setups where
+    /// the mouse rests on another screen than the one being typed on.
+    FocusedWindow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct WindowPosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Moves the capture window into place for the configured placement,
+/// keeping it inside the work area of the monitor it lands on.
+pub(crate) fn position_capture_window(app: &AppHandle, window: &WebviewWindow) {
+    let settings = settings::load_settings(app).unwrap_or_default();
+    let size = window_size(window);
+    let cursor = app
+        .cursor_position()
+        .ok()
+        .map(|cursor| (cursor.x, cursor.y));
+
+    let remembered = settings
+        .capture_position
+        .filter(|_| settings.capture_placement == CapturePlacement::Remembered)
+        .map(|position| (f64::from(position.x), f64::from(position.y)))
+        .and_then(|position| Some((position, monitor_at(app, position)?)));
+    if let Some((position, monitor)) = remembered {
+        move_window(window, clamp_to(monitor.work_area(), position, size));
+        return;
+    }
+
+    let anchor = match settings.capture_monitor {
+        CaptureMonitor::Cursor => cursor,
+        CaptureMonitor::FocusedWindow => focused_window_center(app, window).or(cursor),
+    };
+    let Some(anchor) = anchor else {
+        let _ = window.center();
+        return;
+    };
+    let Some(monitor) = monitor_at(app, anchor) else {
+        move_window(window, anchor);
+        return;
+    };
+    let area = monitor.work_area();
+    let (left, top, width, height) = bounds(area);
+
+    let cursor_here = cursor.filter(|cursor| contains(area, *cursor));
+    let position = match (settings.capture_placement, cursor_here) {
+        (CapturePlacement::NearCursor, Some((x, y))) => (x + CURSOR_OFFSET, y + CURSOR_OFFSET),
+        // A remembered placement without a usable saved position (first
+        // launch, or its monitor was unplugged) opens centered, and so does
+        // near-cursor when the cursor is on another monitor.
+        (
+            CapturePlacement::NearCursor
+            | CapturePlacement::Centered
+            | CapturePlacement::Remembered,
+            _,
+        ) => (left + (width - size.0) / 2.0, top + (height - size.1) / 2.0),
+        (CapturePlacement::Top, _) => (
+            left + (width - size.0) / 2.0,
+            top + height * TOP_PLACEMENT_RATIO,
+        ),
+    };
+    move_window(window, clamp_to(area, position, size));
+}
+
+/// Grows or shrinks the capture window to `height` logical pixels, between
+/// the opening height for the capture mode and what fits on its monitor,
+/// moving it up if it would run off the bottom. Returns the height applied.
+#[tauri::command]
+pub(crate) fn resize_capture_window(app: AppHandle, height: f64) -> Result<f64, String> {
+    let window = app
+        .get_webview_window(CAPTURE_WINDOW_LABEL)
+        .ok_or_else(|| "Capture window is not open".to_string())?;
+    let scale = window
+        .scale_factor()
+        .map_err(|e| format!("Failed to read capture window scale: {e}"))?;
+    let monitor = window.current_monitor().ok().flatten();
+    let min_height = capture_mode(&app).window_height();
+
+    let max_height = monitor
+        .as_ref()
+        .map(|monitor| f64::from(monitor.work_area().size.height) / scale)
+        .map_or(MAX_CAPTURE_WINDOW_HEIGHT, |fits| {
+            fits.min(MAX_CAPTURE_WINDOW_HEIGHT)
+        })
+        .max(CAPTURE_WINDOW_HEIGHT);
+    let min_height = min_height.min(max_height);
+    let height = if height.is_finite() {
+        height.clamp(min_height, max_height)
+    } else {
+        min_height
+    };
+
+    let width = window
+        .inner_size()
+        .map(|size| size.to_logical::<f64>(scale).width)
+        .unwrap_or(CAPTURE_WINDOW_WIDTH);
+    window
+        .set_size(LogicalSize::new(width, height))
+        .map_err(|e| format!("Failed to resize capture window: {e}"))?;
+
+    if let (Some(monitor), Ok(position)) = (monitor, window.outer_position()) {
+        let position = (f64::from(position.x), f64::from(position.y));
+        let size = (width * scale, height * scale);
+        move_window(&window, clamp_to(monitor.work_area(), position, size));
+    }
+    Ok(height)
+}
+
+/// Back to the opening size for the capture mode, for a fresh capture.
+pub(crate) fn reset_capture_window_size(app: &AppHandle, window: &WebviewWindow) {
+    let _ = window.set_size(LogicalSize::new(
+        CAPTURE_WINDOW_WIDTH,
+        capture_mode(app).window_height(),
+    ));
+}
+
+/// Logical size a newly created capture window starts at.
+pub(crate) fn initial_capture_window_size(app: &AppHandle) -> (f64, f64) {
+    (CAPTURE_WINDOW_WIDTH, capture_mode(app).window_height())
+}
+
+/// How the capture input should handle keys, for when it loads after the
+/// first `capture-opened` was already sent.
+#[tauri::command]
+pub(crate) fn get_capture_config(app: AppHandle) -> CaptureConfig {
+    capture_config(&app)
+}
+
+pub(crate) fn capture_config(app: &AppHandle) -> CaptureConfig {
+    let settings = settings::load_settings(app).unwrap_or_default();
+    let mode = settings.capture_mode;
+    let submit_shortcut = match mode {
+        CaptureMode::SingleLine => "Enter",
+        CaptureMode::MultiLine if cfg!(target_os = "macos") => "Cmd+Enter",
+        CaptureMode::MultiLine => "Ctrl+Enter",
+    };
+    CaptureConfig {
+        mode,
+        target: settings.capture_target,
+        enter_submits: mode == CaptureMode::SingleLine,
+        submit_shortcut,
+        dismiss: dismiss::effective_dismiss(app),
+        pinned: dismiss::is_pinned(),
+    }
+}
+
+fn capture_mode(app: &AppHandle) -> CaptureMode {
+    settings::load_settings(app)
+        .map(|settings| settings.capture_mode)
+        .unwrap_or_default()
+}
+
+/// Saves where the user left the capture window, when that is where it
+/// should reopen.
+pub(crate) fn remember_capture_position(app: &AppHandle, window: &WebviewWindow) {
+    let remembered = settings::load_settings(app)
+        .is_ok_and(|settings| settings.capture_placement == CapturePlacement::Remembered);
+    if !remembered {
+        return;
+    }
+    let Ok(position) = window.outer_position() else {
+        return;
+    };
+    let position = WindowPosition {
+        x: position.x,
+        y: position.y,
+    };
+
+    let result = settings::modify_settings(app, |settings| {
+        settings.capture_position = Some(position);
+        Ok(())
+    });
+    if let Err(error) = result {
+        eprintln!("Failed to remember capture window position: {error}");
+    }
+}
+
+fn window_size(window: &WebviewWindow) -> (f64, f64) {
+    window
+        .outer_size()
+        .map(|size| (f64::from(size.width), f64::from(size.height)))
+        .unwrap_or((CAPTURE_WINDOW_WIDTH, CAPTURE_WINDOW_HEIGHT))
+}
+
+/// Center of the focused window, in physical pixels. Once the capture window
+/// itself has focus (the delayed second placement pass), it stays on its
+/// current monitor.
+fn focused_window_center(app: &AppHandle, window: &WebviewWindow) -> Option<(f64, f64)> {
+    if window.is_focused().unwrap_or(false) {
+        let monitor = window.current_monitor().ok()??;
+        let (left, top, width, height) = bounds(monitor.work_area());
+        return Some((left + width / 2.0, top + height / 2.0));
+    }
+
+    match focus::foreground_window_rect(app) {
+        Ok((x, y, width, height)) => Some((x + width / 2.0, y + height / 2.0)),
+        Err(error) => {
+            eprintln!("Falling back to the cursor monitor: {error}");
+            None
+        }
+    }
+}
+
+pub(crate) fn monitor_at(app: &AppHandle, point: (f64, f64)) -> Option<Monitor> {
+    app.available_monitors()
+        .ok()?
+        .into_iter()
+        .find(|monitor| contains(monitor.work_area(), point))
+}
+
+fn contains(area: &PhysicalRect<i32, u32>, (x, y): (f64, f64)) -> bool {
+    let (left, top, width, height) = bounds(area);
+    x >= left && x < left + width && y >= top && y < top + height
+}
+
+fn bounds(area: &PhysicalRect<i32, u32>) -> (f64, f64, f64, f64) {
+    (
+        f64::from(area.position.x),
+        f64::from(area.position.y),
+        f64::from(area.size.width),
+        f64::from(area.size.height),
+    )
+}
+
+/// The closest position that keeps a window of `size` inside `area`,
+/// pinned to the top-left corner when it is larger than the area.
+pub(crate) fn clamp_to(
+    area: &PhysicalRect<i32, u32>,
+    (x, y): (f64, f64),
+    (width, height): (f64, f64),
+) -> (f64, f64) {
+    let (left, top, area_width, area_height) = bounds(area);
+    let max_x = left + area_width - width;
+    let max_y = top + area_height - height;
+    (x.min(max_x).max(left), y.min(max_y).max(top))
+}
+
+fn move_window(window: &WebviewWindow, (x, y): (f64, f64)) {
+    let _ = window.set_position(PhysicalPosition::new(x.round() as i32, y.round() as i32));
+}
+
+/// The frontmost window of any app, asked of each platform's own tooling.
+#[cfg(target_os = "linux")]
+mod focus {
+    use std::process::Command;
+
+    use tauri::AppHandle;
+
+    /// Needs `xdotool`, so X11 (or XWayland) windows only.
+    pub(super) fn foreground_window_rect(_app: &AppHandle) -> Result<(f64, f64, f64, f64), String> {
+        let output = Command::new("xdotool")
+            .args(["getactivewindow", "getwindowgeometry", "--shell"])
+            .output()
+            .map_err(|e| format!("Failed to run xdotool: {e}"))?;
+        if !output.status.success() {
+            return Err("xdotool could not find the active window".to_string());
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let field = |name: &str| {
+            stdout
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .ok_or_else(|| format!("xdotool did not report {name}"))
+        };
+        Ok((field("X")?, field("Y")?, field("WIDTH")?, field("HEIGHT")?))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod focus {
+    use std::process::Command;
+
+    use tauri::AppHandle;
+
+    const SCRIPT: &str = "tell application \"System Events\" to tell front window of \
+        (first application process whose frontmost is true) to get {position, size}";
+
+    /// Needs the Accessibility permission. System Events reports points, so
+    /// the rectangle is scaled by the monitor it lies on.
+    pub(super) fn foreground_window_rect(app: &AppHandle) -> Result<(f64, f64, f64, f64), String> {
+        let output = Command::new("osascript")
+            .args(["-e", SCRIPT])
+            .output()
+            .map_err(|e| format!("Failed to run osascript: {e}"))?;
+        if !output.status.success() {
+            return Err("System Events could not report the front window".to_string());
+        }
+
+        let values: Vec<f64> = String::from_utf8_lossy(&output.stdout)
+            .split(',')
+            .filter_map(|value| value.trim().parse().ok())
+            .collect();
+        let [x, y, width, height] = values[..] else {
+            return Err("Unexpected front window bounds".to_string());
+        };
+
+        let monitors = app
+            .available_monitors()
+            .map_err(|e| format!("Failed to list monitors: {e}"))?;
+        let scale = monitors
+            .iter()
+            .find(|monitor| {
+                let scale = monitor.scale_factor();
+                let position = monitor.position();
+                let size = monitor.size();
+                let left = f64::from(position.x) / scale;
+                let top = f64::from(position.y) / scale;
+                x >= left
+                    && x < left + f64::from(size.width) / scale
+                    && y >= top
+                    && y < top + f64::from(size.height) / scale
+            })
+            .map_or(1.0, |monitor| monitor.scale_factor());
+        Ok((x * scale, y * scale, width * scale, height * scale))
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod focus {
+    use std::process::Command;
+
+    use tauri::AppHandle;
+
+    const SCRIPT: &str = r#"Add-Type @"
+using System;
+using System.Runtime.InteropServices;
+public struct JotinRect { public int Left; public int Top; public int Right; public int Bottom; }
+public static class JotinForeground {
+    [DllImport("user32.dll")] public static extern IntPtr GetForegroundWindow();
+    [DllImport("user32.dll")] public static extern bool GetWindowRect(IntPtr window, out JotinRect rect);
+}
+"@
+$rect = New-Object JotinRect
+[void][JotinForeground]::GetWindowRect([JotinForeground]::GetForegroundWindow(), [ref]$rect)
+"$($rect.Left),$($rect.Top),$($rect.Right),$($rect.Bottom)""#;
+
+    pub(super) fn foreground_window_rect(_app: &AppHandle) -> Result<(f64, f64, f64, f64), String> {
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+            .output()
+            .map_err(|e| format!("Failed to run powershell: {e}"))?;
+        if !output.status.success() {
+            return Err("Could not read the foreground window".to_string());
+        }
+
+        let values: Vec<f64> = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .split(',')
+            .filter_map(|value| value.trim().parse().ok())
+            .collect();
+        let [left, top, right, bottom] = values[..] else {
+            return Err("Unexpected foreground window bounds".to_string());
+        };
+        Ok((left, top, right - left, bottom - top))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod focus {
+    use tauri::AppHandle;
+
+    pub(super) fn foreground_window_rect(_app: &AppHandle) -> Result<(f64, f64, f64, f64), String> {
+        Err("Finding the focused window is not supported on this platform".to_string())
+    }
+}
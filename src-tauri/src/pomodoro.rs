@@ -0,0 +1,269 @@
+//! A pomodoro timer: focus intervals separated by short breaks, with a long
+//! break after every fourth. Each interval ends with a notification and the
+//! next one starts on its own; finished focus intervals are logged to the
+//! daily note. The tray shows the countdown.
+//!
+//! The timer lives in memory only, so quitting the app resets it.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{daily, notifications, settings, tray, StorageState};
+
+pub(crate) const DEFAULT_POMODORO_WORK_MINUTES: u32 = 25;
+pub(crate) const DEFAULT_POMODORO_BREAK_MINUTES: u32 = 5;
+pub(crate) const DEFAULT_POMODORO_LONG_BREAK_MINUTES: u32 = 15;
+const POMODORO_CHANGED_EVENT: &str = "pomodoro-changed";
+const LONG_BREAK_EVERY: u32 = 4;
+const TICK: Duration = Duration::from_secs(1);
+static TICKER_STARTED: AtomicBool = AtomicBool::new(false);
+static POMODORO: Mutex<Pomodoro> = Mutex::new(Pomodoro {
+    phase: PomodoroPhase::Work,
+    remaining: None,
+    resumed_at: None,
+    completed: 0,
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PomodoroPhase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+struct Pomodoro {
+    phase: PomodoroPhase,
+    /// Time left when last paused; `None` means the whole interval.
+    remaining: Option<Duration>,
+    /// Set while the countdown runs.
+    resumed_at: Option<Instant>,
+    /// Focus intervals finished since the last reset.
+    completed: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct PomodoroStatus {
+    phase: PomodoroPhase,
+    running: bool,
+    remaining_seconds: u64,
+    completed: u32,
+}
+
+#[tauri::command]
+pub(crate) fn get_pomodoro(app: AppHandle) -> Result<PomodoroStatus, String> {
+    let lengths = Lengths::load(&app);
+    with_pomodoro(|pomodoro| pomodoro.status(&lengths))
+}
+
+/// Starts the current interval, or resumes it when paused.
+#[tauri::command]
+pub(crate) fn start_pomodoro(app: AppHandle) -> Result<PomodoroStatus, String> {
+    start_ticker(&app);
+    change(&app, |pomodoro, _| {
+        pomodoro.resumed_at.get_or_insert_with(Instant::now);
+    })
+}
+
+#[tauri::command]
+pub(crate) fn pause_pomodoro(app: AppHandle) -> Result<PomodoroStatus, String> {
+    change(&app, |pomodoro, lengths| {
+        if pomodoro.resumed_at.is_some() {
+            pomodoro.remaining = Some(pomodoro.left(lengths));
+            pomodoro.resumed_at = None;
+        }
+    })
+}
+
+/// Stops the timer and goes back to a fresh focus interval.
+#[tauri::command]
+pub(crate) fn reset_pomodoro(app: AppHandle) -> Result<PomodoroStatus, String> {
+    change(&app, |pomodoro, _| {
+        *pomodoro = Pomodoro {
+            phase: PomodoroPhase::Work,
+            remaining: None,
+            resumed_at: None,
+            completed: 0,
+        };
+    })
+}
+
+/// `🍅 24:59` while focusing, `☕ 04:12` on a break, `None` when stopped.
+pub(crate) fn tray_label(app: &AppHandle) -> Option<String> {
+    let lengths = Lengths::load(app);
+    let status = with_pomodoro(|pomodoro| pomodoro.status(&lengths)).ok()?;
+    if !status.running && status.remaining_seconds == lengths.of(status.phase).as_secs() {
+        return None;
+    }
+    let icon = match status.phase {
+        PomodoroPhase::Work => '\u{1F345}',
+        PomodoroPhase::ShortBreak | PomodoroPhase::LongBreak => '\u{2615}',
+    };
+    let paused = if status.running { "" } else { " (paused)" };
+    Some(format!(
+        "{icon} {:02}:{:02}{paused}",
+        status.remaining_seconds / 60,
+        status.remaining_seconds % 60
+    ))
+}
+
+impl Pomodoro {
+    fn left(&self, lengths: &Lengths) -> Duration {
+        let remaining = self.remaining.unwrap_or_else(|| lengths.of(self.phase));
+        match self.resumed_at {
+            Some(resumed_at) => remaining.saturating_sub(resumed_at.elapsed()),
+            None => remaining,
+        }
+    }
+
+    fn status(&self, lengths: &Lengths) -> PomodoroStatus {
+        PomodoroStatus {
+            phase: self.phase,
+            running: self.resumed_at.is_some(),
+            remaining_seconds: self.left(lengths).as_secs(),
+            completed: self.completed,
+        }
+    }
+
+    /// Moves on to the next interval, already running. Returns the phase
+    /// that just ended.
+    fn advance(&mut self) -> PomodoroPhase {
+        let ended = self.phase;
+        self.phase = match ended {
+            PomodoroPhase::Work => {
+                self.completed += 1;
+                if self.completed.is_multiple_of(LONG_BREAK_EVERY) {
+                    PomodoroPhase::LongBreak
+                } else {
+                    PomodoroPhase::ShortBreak
+                }
+            }
+            PomodoroPhase::ShortBreak | PomodoroPhase::LongBreak => PomodoroPhase::Work,
+        };
+        self.remaining = None;
+        self.resumed_at = Some(Instant::now());
+        ended
+    }
+}
+
+/// Interval lengths from the settings, read fresh so changes apply to the
+/// next interval.
+struct Lengths {
+    work: Duration,
+    short_break: Duration,
+    long_break: Duration,
+}
+
+impl Lengths {
+    fn load(app: &AppHandle) -> Self {
+        let settings = settings::load_settings(app).unwrap_or_default();
+        let minutes = |minutes: u32| Duration::from_secs(u64::from(minutes.max(1)) * 60);
+        Self {
+            work: minutes(settings.pomodoro_work_minutes),
+            short_break: minutes(settings.pomodoro_break_minutes),
+            long_break: minutes(settings.pomodoro_long_break_minutes),
+        }
+    }
+
+    fn of(&self, phase: PomodoroPhase) -> Duration {
+        match phase {
+            PomodoroPhase::Work => self.work,
+            PomodoroPhase::ShortBreak => self.short_break,
+            PomodoroPhase::LongBreak => self.long_break,
+        }
+    }
+}
+
+fn change(
+    app: &AppHandle,
+    f: impl FnOnce(&mut Pomodoro, &Lengths),
+) -> Result<PomodoroStatus, String> {
+    let lengths = Lengths::load(app);
+    let status = with_pomodoro(|pomodoro| {
+        f(pomodoro, &lengths);
+        pomodoro.status(&lengths)
+    })?;
+    announce(app, &status);
+    Ok(status)
+}
+
+fn announce(app: &AppHandle, status: &PomodoroStatus) {
+    let _ = app.emit(POMODORO_CHANGED_EVENT, status);
+    tray::update_tooltip(app);
+}
+
+fn start_ticker(app: &AppHandle) {
+    if TICKER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    thread::spawn(move || loop {
+        thread::sleep(TICK);
+        tick(&app);
+    });
+}
+
+fn tick(app: &AppHandle) {
+    let lengths = Lengths::load(app);
+    let Ok((ended, status)) = with_pomodoro(|pomodoro| {
+        if pomodoro.resumed_at.is_none() {
+            return (None, None);
+        }
+        let ended = pomodoro
+            .left(&lengths)
+            .is_zero()
+            .then(|| pomodoro.advance());
+        (ended, Some(pomodoro.status(&lengths)))
+    }) else {
+        return;
+    };
+    let Some(status) = status else {
+        return;
+    };
+
+    match ended {
+        Some(PomodoroPhase::Work) => {
+            let state = app.state::<StorageState>();
+            let entry = format!(
+                "\u{1F345} Pomodoro #{} finished at {}",
+                status.completed,
+                Local::now().format("%H:%M")
+            );
+            if let Err(error) = daily::append(app, &state, &entry) {
+                eprintln!("Failed to log the pomodoro: {error}");
+            }
+            let body = match status.phase {
+                PomodoroPhase::LongBreak => "Time for a long break",
+                _ => "Time for a short break",
+            };
+            notifications::show(app, "Pomodoro finished", body, &[], |_, _| {});
+        }
+        Some(_) => notifications::show(app, "Break over", "Back to focus", &[], |_, _| {}),
+        None => {}
+    }
+    // Timer events are only needed when an interval ends; the tray counts
+    // down every second.
+    if ended.is_some() {
+        let _ = app.emit(POMODORO_CHANGED_EVENT, &status);
+    }
+    tray::update_tooltip(app);
+}
+
+fn with_pomodoro<T>(f: impl FnOnce(&mut Pomodoro) -> T) -> Result<T, String> {
+    let mut pomodoro = POMODORO
+        .lock()
+        .map_err(|_| "Pomodoro lock was poisoned".to_string())?;
+    Ok(f(&mut pomodoro))
+}
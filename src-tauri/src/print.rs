@@ -0,0 +1,91 @@
+//! Printing through a hidden webview: the note is rendered to HTML here,
+//! written into that webview and handed to the OS print dialog.
+
+use std::sync::Mutex;
+
+use tauri::{
+    webview::PageLoadEvent, AppHandle, Manager, State, Url, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder,
+};
+
+use crate::{markdown, StorageState};
+
+const PRINT_WINDOW_LABEL: &str = "print";
+const PRINT_STYLE: &str = "body { font: 12pt/1.5 -apple-system, 'Segoe UI', sans-serif; \
+    margin: 2cm; color: #000; } pre, code { font-family: ui-monospace, monospace; } \
+    pre { white-space: pre-wrap; } blockquote { border-left: 3px solid #999; margin-left: 0; \
+    padding-left: 1em; color: #333; } img { max-width: 100%; }";
+/// The document to print once the print webview has finished loading.
+static PENDING_DOCUMENT: Mutex<Option<String>> = Mutex::new(None);
+
+/// Opens the print dialog for a note, rendered like its Markdown preview.
+#[tauri::command]
+pub(crate) fn print_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<(), String> {
+    let document = {
+        let storage = state.lock(&app)?;
+        let notes = storage.notes()?;
+        let note = notes
+            .iter()
+            .find(|note| note.id == id && !note.is_trashed())
+            .ok_or_else(|| "Note not found".to_string())?;
+        render_document(note.title(), &note.text)
+    };
+
+    if let Some(window) = app.get_webview_window(PRINT_WINDOW_LABEL) {
+        return print_document(&window, &document);
+    }
+
+    if let Ok(mut pending) = PENDING_DOCUMENT.lock() {
+        *pending = Some(document);
+    }
+    let blank = Url::parse("about:blank").map_err(|e| format!("Failed to open print view: {e}"))?;
+    WebviewWindowBuilder::new(&app, PRINT_WINDOW_LABEL, WebviewUrl::External(blank))
+        .title("Print")
+        .visible(false)
+        .skip_taskbar(true)
+        .on_page_load(|window, payload| {
+            if payload.event() != PageLoadEvent::Finished {
+                return;
+            }
+            let document = PENDING_DOCUMENT
+                .lock()
+                .ok()
+                .and_then(|mut pending| pending.take());
+            if let Some(document) = document {
+                if let Err(error) = print_document(&window, &document) {
+                    eprintln!("{error}");
+                }
+            }
+        })
+        .build()
+        .map_err(|e| format!("Failed to open print view: {e}"))?;
+    Ok(())
+}
+
+/// Replaces the print webview's page, then prints it. Both go through the
+/// webview's queue in order, so the dialog sees the new page.
+fn print_document(window: &WebviewWindow, document: &str) -> Result<(), String> {
+    let document = serde_json::to_string(document)
+        .map_err(|e| format!("Failed to prepare note for printing: {e}"))?;
+    window
+        .eval(format!(
+            "document.open();document.write({document});document.close();"
+        ))
+        .map_err(|e| format!("Failed to render note for printing: {e}"))?;
+    window
+        .print()
+        .map_err(|e| format!("Failed to open print dialog: {e}"))
+}
+
+fn render_document(title: &str, text: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title>\
+         <style>{PRINT_STYLE}</style></head><body>{}</body></html>",
+        markdown::escape_html(title),
+        markdown::to_html(text)
+    )
+}
@@ -0,0 +1,80 @@
+//! Publishes a note to a self-hosted endpoint, such as a pastebin, so it
+//! can be shared by URL. The endpoint receives the note's Markdown and
+//! rendered HTML in one JSON POST and answers with the public URL, which is
+//! kept on the note.
+//!
+//! Request body: `{ "id", "title", "markdown", "html", "tags" }`, sent with
+//! `Authorization: Bearer <publish_token>` when a token is set. Response: a
+//! JSON object with a `url` field, or the URL as plain text.
+
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    find_active_note_mut, http, markdown,
+    settings::{self, Secret},
+    Note, StorageState, NOTES_CHANGED_EVENT,
+};
+
+/// Uploads the note and returns it with `published_url` set. Publishing
+/// again sends the same id, so endpoints may update the earlier copy.
+#[tauri::command]
+pub(crate) fn publish_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<Note, String> {
+    let note = {
+        let storage = state.lock(&app)?;
+        let notes = storage.notes()?;
+        notes
+            .iter()
+            .find(|note| note.id == id && !note.is_trashed())
+            .cloned()
+            .ok_or_else(|| "Note not found".to_string())?
+    };
+
+    let settings = settings::load_settings(&app)?;
+    let url = settings
+        .publish_url
+        .as_deref()
+        .ok_or_else(|| "Set a publish endpoint in settings first".to_string())?;
+    let headers: Vec<String> = Secret::PublishToken
+        .get()?
+        .iter()
+        .map(|token| format!("Authorization: Bearer {token}"))
+        .collect();
+    let body = json!({
+        "id": note.id,
+        "title": note.title(),
+        "markdown": note.text,
+        "html": markdown::to_html(&note.text),
+        "tags": note.tags,
+    });
+
+    let response = http::send_json("POST", url, &headers, Some(&body))?;
+    if !response.is_success() {
+        return Err(format!(
+            "Publish endpoint failed with HTTP {}",
+            response.status
+        ));
+    }
+    let published_url = match &response.body {
+        Value::String(text) => Some(text.trim()),
+        body => body["url"].as_str(),
+    }
+    .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+    .ok_or_else(|| "Publish endpoint did not return a URL".to_string())?
+    .to_string();
+
+    let storage = state.lock(&app)?;
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+    note.published_url = Some(published_url);
+    let note = note.clone();
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&note));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(note)
+}
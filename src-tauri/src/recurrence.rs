@@ -0,0 +1,332 @@
+//! The subset of iCalendar RRULE that reminders need: `FREQ` (daily,
+//! weekly, monthly, yearly), `INTERVAL`, `BYDAY` for weekly rules, `COUNT`
+//! and `UNTIL`. Occurrences are stepped in local time so a daily 9:00
+//! reminder stays at 9:00 across daylight saving changes.
+
+use chrono::{
+    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+
+/// Upper bound on steps when catching up past long gaps, e.g. a daily rule
+/// after the app was closed for months.
+const MAX_STEPS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    weekdays: Vec<Weekday>,
+    count: Option<u32>,
+    until: Option<DateTime<Utc>>,
+}
+
+impl Rule {
+    /// Parses `FREQ=WEEKLY;BYDAY=MO,WE` style rules, with or without an
+    /// `RRULE:` prefix. `daily`, `weekly`, `monthly` and `yearly` are
+    /// accepted as shorthands.
+    pub(crate) fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim().to_uppercase();
+        let value = value.strip_prefix("RRULE:").unwrap_or(&value);
+        let value = match value {
+            "DAILY" | "WEEKLY" | "MONTHLY" | "YEARLY" => format!("FREQ={value}"),
+            _ => value.to_string(),
+        };
+
+        let mut frequency = None;
+        let mut rule = Self {
+            frequency: Frequency::Daily,
+            interval: 1,
+            weekdays: Vec::new(),
+            count: None,
+            until: None,
+        };
+        for part in value.split(';').filter(|part| !part.is_empty()) {
+            let (key, field) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid recurrence part \"{part}\""))?;
+            match key {
+                "FREQ" => {
+                    frequency = Some(match field {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("Unsupported recurrence frequency \"{field}\"")),
+                    });
+                }
+                "INTERVAL" => {
+                    rule.interval = field
+                        .parse()
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| format!("Invalid recurrence interval \"{field}\""))?;
+                }
+                "BYDAY" => {
+                    rule.weekdays = field
+                        .split(',')
+                        .map(parse_weekday)
+                        .collect::<Result<_, _>>()?;
+                }
+                "COUNT" => {
+                    rule.count = Some(
+                        field
+                            .parse()
+                            .ok()
+                            .filter(|count| *count > 0)
+                            .ok_or_else(|| format!("Invalid recurrence count \"{field}\""))?,
+                    );
+                }
+                "UNTIL" => rule.until = Some(parse_until(field)?),
+                _ => return Err(format!("Unsupported recurrence part \"{key}\"")),
+            }
+        }
+
+        rule.frequency = frequency.ok_or_else(|| "Recurrence needs a FREQ".to_string())?;
+        if !rule.weekdays.is_empty() && rule.frequency != Frequency::Weekly {
+            return Err("BYDAY is only supported for weekly recurrence".to_string());
+        }
+        Ok(rule)
+    }
+
+    /// The first occurrence after both `previous` and `now`, given that
+    /// `fired` occurrences have already happened. `None` once the rule is
+    /// exhausted. Missed occurrences are skipped rather than replayed.
+    /// `anchor` is the first occurrence, which monthly and yearly rules
+    /// count from.
+    pub(crate) fn next_after(
+        &self,
+        anchor: DateTime<Utc>,
+        previous: DateTime<Utc>,
+        now: DateTime<Utc>,
+        fired: u32,
+    ) -> Option<DateTime<Utc>> {
+        let anchor = anchor.with_timezone(&Local).naive_local();
+        let previous = previous.with_timezone(&Local).naive_local();
+        let mut candidates = self
+            .occurrences(anchor, previous)
+            .skip_while(|candidate| *candidate <= previous);
+        for fired in (fired..).take(MAX_STEPS) {
+            if self.count.is_some_and(|count| fired >= count) {
+                return None;
+            }
+            let at = to_utc(candidates.next()?)?;
+            if self.until.is_some_and(|until| at > until) {
+                return None;
+            }
+            if at > now {
+                return Some(at);
+            }
+        }
+        None
+    }
+
+    /// Occurrences in order, some of them possibly not after `previous`.
+    /// Monthly and yearly ones are each `interval * n` months from the
+    /// anchor, clamped to the month's length on their own: a rule on Jan 31
+    /// fires Feb 28 and then Mar 31, not the 28th for good. Daily and weekly
+    /// ones never clamp, so they just step on from `previous`.
+    fn occurrences(
+        &self,
+        anchor: NaiveDateTime,
+        previous: NaiveDateTime,
+    ) -> impl Iterator<Item = NaiveDateTime> + '_ {
+        let months = match self.frequency {
+            Frequency::Monthly => Some(self.interval),
+            Frequency::Yearly => Some(self.interval.saturating_mul(12)),
+            Frequency::Daily | Frequency::Weekly => None,
+        };
+        let mut current = previous;
+        (1u32..).map_while(move |n| {
+            current = match months {
+                Some(months) => anchor.checked_add_months(Months::new(months.checked_mul(n)?))?,
+                None => self.step(current)?,
+            };
+            Some(current)
+        })
+    }
+
+    /// The next daily or weekly occurrence after `from`.
+    fn step(&self, from: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self.frequency {
+            Frequency::Daily => from.checked_add_signed(Duration::days(i64::from(self.interval))),
+            Frequency::Weekly if self.weekdays.is_empty() => {
+                from.checked_add_signed(Duration::weeks(i64::from(self.interval)))
+            }
+            Frequency::Weekly => {
+                // Next listed weekday, only in weeks that are a multiple of
+                // the interval away from the previous occurrence's week.
+                let start_week = week_start(from.date());
+                (1..=7 * i64::from(self.interval) + 7)
+                    .filter_map(|days| from.checked_add_signed(Duration::days(days)))
+                    .find(|candidate| {
+                        let weeks = (week_start(candidate.date()) - start_week).num_weeks();
+                        weeks % i64::from(self.interval) == 0
+                            && self.weekdays.contains(&candidate.weekday())
+                    })
+            }
+            // Counted from the anchor instead; see `occurrences`.
+            Frequency::Monthly | Frequency::Yearly => None,
+        }
+    }
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+}
+
+/// Local wall-clock time to UTC. Times skipped by a daylight saving jump
+/// move forward an hour.
+pub(crate) fn to_utc(local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            Local
+                .from_local_datetime(&(local + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|at| at.with_timezone(&Utc))
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday, String> {
+    match value.trim() {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        other => Err(format!("Invalid recurrence weekday \"{other}\"")),
+    }
+}
+
+/// `UNTIL` as an RRULE date (`20261231`), date-time (`20261231T090000Z`)
+/// or RFC 3339.
+fn parse_until(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    if let Ok(at) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ") {
+        return Ok(at.and_utc());
+    }
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .and_then(to_utc)
+        .ok_or_else(|| format!("Invalid recurrence end \"{value}\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(value: &str) -> DateTime<Utc> {
+        to_utc(NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()).unwrap()
+    }
+
+    /// The occurrences following `anchor`, each fed back as `previous` the
+    /// way reminders do.
+    fn schedule(rule: &str, anchor: &str, steps: usize) -> Vec<DateTime<Utc>> {
+        let rule = Rule::parse(rule).unwrap();
+        let anchor = local(anchor);
+        let mut previous = anchor;
+        let mut occurrences = Vec::new();
+        for fired in 1..=steps as u32 {
+            let Some(next) = rule.next_after(anchor, previous, previous, fired) else {
+                break;
+            };
+            occurrences.push(next);
+            previous = next;
+        }
+        occurrences
+    }
+
+    #[test]
+    fn parses_shorthands_and_full_rules() {
+        assert_eq!(
+            Rule::parse("daily").unwrap(),
+            Rule::parse("RRULE:FREQ=DAILY").unwrap()
+        );
+        let rule = Rule::parse("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR;COUNT=4").unwrap();
+        assert_eq!(rule.interval, 2);
+        assert_eq!(rule.weekdays, vec![Weekday::Mon, Weekday::Fri]);
+        assert_eq!(rule.count, Some(4));
+    }
+
+    #[test]
+    fn refuses_unsupported_rules() {
+        assert!(Rule::parse("INTERVAL=2").is_err());
+        assert!(Rule::parse("FREQ=HOURLY").is_err());
+        assert!(Rule::parse("FREQ=DAILY;INTERVAL=0").is_err());
+        assert!(Rule::parse("FREQ=MONTHLY;BYDAY=MO").is_err());
+        assert!(Rule::parse("FREQ=DAILY;BYMONTH=1").is_err());
+    }
+
+    #[test]
+    fn monthly_rules_clamp_each_month_on_its_own() {
+        assert_eq!(
+            schedule("monthly", "2025-01-31 09:00", 4),
+            vec![
+                local("2025-02-28 09:00"),
+                local("2025-03-31 09:00"),
+                local("2025-04-30 09:00"),
+                local("2025-05-31 09:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn yearly_rules_come_back_to_leap_days() {
+        assert_eq!(
+            schedule("yearly", "2024-02-29 09:00", 4),
+            vec![
+                local("2025-02-28 09:00"),
+                local("2026-02-28 09:00"),
+                local("2027-02-28 09:00"),
+                local("2028-02-29 09:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn weekly_rules_follow_their_weekdays_and_interval() {
+        // 2025-06-02 is a Monday.
+        assert_eq!(
+            schedule("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR", "2025-06-02 09:00", 3),
+            vec![
+                local("2025-06-06 09:00"),
+                local("2025-06-16 09:00"),
+                local("2025-06-20 09:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn missed_occurrences_are_skipped() {
+        let rule = Rule::parse("daily").unwrap();
+        let start = local("2025-06-01 09:00");
+        let next = rule.next_after(start, start, local("2025-06-04 12:00"), 1);
+        assert_eq!(next, Some(local("2025-06-05 09:00")));
+    }
+
+    #[test]
+    fn count_and_until_end_the_rule() {
+        assert_eq!(
+            schedule("FREQ=DAILY;COUNT=3", "2025-06-01 09:00", 5).len(),
+            2
+        );
+        assert_eq!(
+            schedule("FREQ=DAILY;UNTIL=20250603", "2025-06-01 09:00", 5),
+            vec![local("2025-06-02 09:00"), local("2025-06-03 09:00")]
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! Reminders that fire a notification for a note at a set time, once or on
+//! a recurrence rule (a subset of RRULE). They are kept in `reminders.json`
+//! and checked by a background scheduler every `SCHEDULER_TICK`; a fired
+//! notification opens the note or snoozes it for `DEFAULT_SNOOZE_MINUTES`.
+//! Reminders of notes deleted meanwhile are dropped when they come due.
+
+use std::{
+    collections::VecDeque,
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+use crate::{
+    deep_link, notifications, notifications::Action, recurrence::Rule, resolve_data_file,
+    write_file_atomically, StorageState, MAIN_WINDOW_LABEL,
+};
+
+const REMINDERS_FILE_NAME: &str = "reminders.json";
+const REMINDER_DUE_EVENT: &str = "reminder-due";
+const SCHEDULER_TICK: Duration = Duration::from_secs(10);
+const DEFAULT_SNOOZE_MINUTES: u32 = 10;
+const MAX_RECENTLY_FIRED: usize = 20;
+/// `default` is what libnotify reports for a click on the notification body.
+const NOTIFICATION_ACTIONS: &[Action] = &[("default", "Open note"), ("snooze", "Snooze 10 min")];
+static REMINDERS_LOCK: Mutex<()> = Mutex::new(());
+static SCHEDULER_STARTED: AtomicBool = AtomicBool::new(false);
+/// Reminders that just fired, so they can still be snoozed.
+static RECENTLY_FIRED: Mutex<VecDeque<Reminder>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct Reminder {
+    id: String,
+    note_id: String,
+    remind_at: String,
+    created_at: String,
+    /// RRULE subset, e.g. `FREQ=WEEKLY;BYDAY=MO,FR`; `None` fires once.
+    #[serde(default)]
+    recurrence: Option<String>,
+    /// How many times a recurring reminder has fired, for `COUNT`.
+    #[serde(default)]
+    occurrences: u32,
+    /// The first occurrence, which monthly and yearly rules count from.
+    /// Missing on reminders set by older versions; `remind_at` stands in.
+    #[serde(default)]
+    starts_at: Option<String>,
+}
+
+/// Schedules a notification for a note at an RFC 3339 time in the future,
+/// optionally repeating by a recurrence rule.
+#[tauri::command]
+pub(crate) fn set_reminder(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    note_id: String,
+    remind_at: String,
+    recurrence: Option<String>,
+) -> Result<Reminder, String> {
+    let remind_at = parse_remind_at(&remind_at)?;
+    if remind_at <= Utc::now() {
+        return Err("Reminder time must be in the future".to_string());
+    }
+    let recurrence = recurrence
+        .map(|rule| rule.trim().to_string())
+        .filter(|rule| !rule.is_empty());
+    if let Some(rule) = recurrence.as_deref() {
+        Rule::parse(rule)?;
+    }
+
+    {
+        let storage = state.lock(&app)?;
+        let notes = storage.notes()?;
+        if !notes
+            .iter()
+            .any(|note| note.id == note_id && !note.is_trashed())
+        {
+            return Err("Note not found".to_string());
+        }
+    }
+
+    let reminder = Reminder {
+        id: Uuid::new_v4().to_string(),
+        note_id,
+        remind_at: remind_at.to_rfc3339(),
+        created_at: Utc::now().to_rfc3339(),
+        recurrence,
+        occurrences: 0,
+        starts_at: Some(remind_at.to_rfc3339()),
+    };
+    modify_reminders(&app, |reminders| {
+        reminders.push(reminder.clone());
+        Ok(())
+    })?;
+    Ok(reminder)
+}
+
+/// Fires a reminder that just went off again in a few minutes. A recurring
+/// reminder keeps its schedule; the snooze is a separate one-time reminder.
+#[tauri::command]
+pub(crate) fn snooze_reminder(
+    app: AppHandle,
+    id: String,
+    minutes: Option<u32>,
+) -> Result<Reminder, String> {
+    let fired = RECENTLY_FIRED
+        .lock()
+        .map_err(|_| "Reminders lock was poisoned".to_string())?
+        .iter()
+        .find(|reminder| reminder.id == id)
+        .cloned()
+        .ok_or_else(|| "Reminder not found".to_string())?;
+
+    snooze(&app, &fired, minutes.unwrap_or(DEFAULT_SNOOZE_MINUTES))
+}
+
+/// Pending reminders, soonest first.
+#[tauri::command]
+pub(crate) fn list_reminders(app: AppHandle) -> Result<Vec<Reminder>, String> {
+    let _guard = REMINDERS_LOCK
+        .lock()
+        .map_err(|_| "Reminders lock was poisoned".to_string())?;
+
+    let mut reminders = load_reminders(&app)?;
+    reminders.sort_by_key(reminder_time);
+    Ok(reminders)
+}
+
+#[tauri::command]
+pub(crate) fn cancel_reminder(app: AppHandle, id: String) -> Result<(), String> {
+    modify_reminders(&app, |reminders| {
+        let before = reminders.len();
+        reminders.retain(|reminder| reminder.id != id);
+        if reminders.len() == before {
+            return Err("Reminder not found".to_string());
+        }
+        Ok(())
+    })
+}
+
+/// Spawns the scheduler once. Reminders that came due while the app was
+/// closed fire on the first tick after launch; a recurring one then moves to
+/// its next future occurrence instead of replaying the ones it missed.
+pub(crate) fn start(app: &AppHandle) {
+    if SCHEDULER_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    thread::spawn(move || loop {
+        if let Err(error) = fire_due_reminders(&app) {
+            eprintln!("Failed to check reminders: {error}");
+        }
+        thread::sleep(SCHEDULER_TICK);
+    });
+}
+
+fn fire_due_reminders(app: &AppHandle) -> Result<(), String> {
+    let due = {
+        let _guard = REMINDERS_LOCK
+            .lock()
+            .map_err(|_| "Reminders lock was poisoned".to_string())?;
+
+        let now = Utc::now();
+        let (due, mut pending): (Vec<Reminder>, Vec<Reminder>) = load_reminders(app)?
+            .into_iter()
+            .partition(|reminder| reminder_time(reminder).is_none_or(|at| at <= now));
+        if due.is_empty() {
+            return Ok(());
+        }
+        pending.extend(
+            due.iter()
+                .filter_map(|reminder| next_occurrence(reminder, now)),
+        );
+        save_reminders(app, &pending)?;
+        due
+    };
+
+    let state = app.state::<StorageState>();
+    let notes = state.lock(app)?.notes()?;
+    for reminder in due {
+        // Reminders of notes deleted since are dropped silently.
+        let Some(note) = notes
+            .iter()
+            .find(|note| note.id == reminder.note_id && !note.is_trashed())
+        else {
+            continue;
+        };
+
+        let _ = app.emit_to(MAIN_WINDOW_LABEL, REMINDER_DUE_EVENT, &reminder);
+        remember_fired(&reminder);
+        notifications::show(
+            app,
+            "Reminder",
+            note.title(),
+            NOTIFICATION_ACTIONS,
+            move |app, action| match action {
+                "default" => deep_link::open_note(app, &reminder.note_id),
+                "snooze" => {
+                    if let Err(error) = snooze(app, &reminder, DEFAULT_SNOOZE_MINUTES) {
+                        eprintln!("Failed to snooze reminder: {error}");
+                    }
+                }
+                _ => {}
+            },
+        );
+    }
+    Ok(())
+}
+
+/// The same reminder moved to its next occurrence, if it recurs and the rule
+/// is not exhausted.
+fn next_occurrence(reminder: &Reminder, now: DateTime<Utc>) -> Option<Reminder> {
+    let rule = Rule::parse(reminder.recurrence.as_deref()?)
+        .map_err(|error| eprintln!("Dropping reminder with a bad recurrence: {error}"))
+        .ok()?;
+    let occurrences = reminder.occurrences + 1;
+    let previous = reminder_time(reminder)?;
+    let anchor = reminder
+        .starts_at
+        .as_deref()
+        .and_then(|starts_at| parse_remind_at(starts_at).ok())
+        .unwrap_or(previous);
+    let next = rule.next_after(anchor, previous, now, occurrences)?;
+
+    Some(Reminder {
+        remind_at: next.to_rfc3339(),
+        occurrences,
+        ..reminder.clone()
+    })
+}
+
+fn snooze(app: &AppHandle, fired: &Reminder, minutes: u32) -> Result<Reminder, String> {
+    let now = Utc::now();
+    let snoozed = Reminder {
+        id: Uuid::new_v4().to_string(),
+        note_id: fired.note_id.clone(),
+        remind_at: (now + chrono::Duration::minutes(i64::from(minutes.max(1)))).to_rfc3339(),
+        created_at: now.to_rfc3339(),
+        recurrence: None,
+        occurrences: 0,
+        starts_at: None,
+    };
+    modify_reminders(app, |reminders| {
+        reminders.push(snoozed.clone());
+        Ok(())
+    })?;
+    Ok(snoozed)
+}
+
+fn remember_fired(reminder: &Reminder) {
+    let Ok(mut fired) = RECENTLY_FIRED.lock() else {
+        return;
+    };
+
+    fired.push_back(reminder.clone());
+    while fired.len() > MAX_RECENTLY_FIRED {
+        fired.pop_front();
+    }
+}
+
+fn parse_remind_at(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value.trim())
+        .map(|at| at.with_timezone(&Utc))
+        .map_err(|e| format!("Invalid reminder time: {e}"))
+}
+
+fn reminder_time(reminder: &Reminder) -> Option<DateTime<Utc>> {
+    parse_remind_at(&reminder.remind_at).ok()
+}
+
+fn modify_reminders(
+    app: &AppHandle,
+    modify: impl FnOnce(&mut Vec<Reminder>) -> Result<(), String>,
+) -> Result<(), String> {
+    let _guard = REMINDERS_LOCK
+        .lock()
+        .map_err(|_| "Reminders lock was poisoned".to_string())?;
+
+    let mut reminders = load_reminders(app)?;
+    modify(&mut reminders)?;
+    save_reminders(app, &reminders)
+}
+
+fn save_reminders(app: &AppHandle, reminders: &[Reminder]) -> Result<(), String> {
+    let payload = serde_json::to_string_pretty(reminders)
+        .map_err(|e| format!("Failed to serialize reminders: {e}"))?;
+    write_file_atomically(&resolve_reminders_path(app)?, &payload)
+}
+
+fn load_reminders(app: &AppHandle) -> Result<Vec<Reminder>, String> {
+    let path = resolve_reminders_path(app)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let raw = fs::read_to_string(&path).map_err(|e| format!("Failed to read reminders: {e}"))?;
+    if raw.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    serde_json::from_str::<Vec<Reminder>>(&raw)
+        .map_err(|e| format!("Failed to parse reminders: {e}"))
+}
+
+fn resolve_reminders_path(app: &AppHandle) -> Result<PathBuf, String> {
+    resolve_data_file(app, REMINDERS_FILE_NAME)
+}
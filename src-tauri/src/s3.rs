@@ -0,0 +1,184 @@
+//! The S3 API as sync needs it (list, download, upload and delete objects),
+//! for AWS, MinIO, Backblaze B2 and other compatible stores. Requests use
+//! path-style URLs so any endpoint works, and `curl --aws-sigv4` signs
+//! them; the credentials travel in a config on stdin.
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    html, http,
+    settings::{Secret, Settings},
+};
+
+pub(crate) const DEFAULT_S3_REGION: &str = "us-east-1";
+pub(crate) const DEFAULT_S3_PREFIX: &str = "jotin";
+const SNAPSHOTS_FOLDER: &str = "snapshots";
+const SNAPSHOT_EXTENSION: &str = ".json";
+const REQUEST_TIMEOUT_SECS: u32 = 120;
+
+pub(crate) struct Bucket {
+    endpoint: String,
+    name: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    prefix: String,
+}
+
+impl Bucket {
+    pub(crate) fn is_configured(settings: &Settings) -> bool {
+        settings.s3_endpoint.is_some() && settings.s3_bucket.is_some()
+    }
+
+    /// The bucket the settings point at, if S3 sync is set up, with its
+    /// secret key read from the keychain.
+    pub(crate) fn configured(settings: &Settings) -> Result<Option<Self>, String> {
+        let (Some(endpoint), Some(name)) = (&settings.s3_endpoint, &settings.s3_bucket) else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            endpoint: endpoint.clone(),
+            name: name.clone(),
+            region: settings.s3_region.clone(),
+            access_key_id: settings.s3_access_key_id.clone().unwrap_or_default(),
+            secret_access_key: Secret::S3SecretAccessKey.get()?.unwrap_or_default(),
+            prefix: settings.s3_prefix.clone(),
+        }))
+    }
+
+    /// Keys of the stored snapshots, oldest first. Keys start with the
+    /// upload time, so their order is the order they were made in.
+    pub(crate) fn list_snapshots(&self) -> Result<Vec<String>, String> {
+        let folder = self.key(SNAPSHOTS_FOLDER) + "/";
+        let url = format!(
+            "{}?list-type=2&prefix={}",
+            self.bucket_url(),
+            encode(&folder)
+        );
+        let (status, body) = self.request(&url, &[])?;
+        check_status(status, "list snapshots")?;
+
+        let mut keys: Vec<String> = element_values(&String::from_utf8_lossy(&body), "Key")
+            .into_iter()
+            .filter(|key| key.ends_with(SNAPSHOT_EXTENSION))
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// A key for a new snapshot, sorting after every existing one.
+    pub(crate) fn new_snapshot_key(&self) -> String {
+        self.key(&format!(
+            "{SNAPSHOTS_FOLDER}/{}-{}{SNAPSHOT_EXTENSION}",
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            &Uuid::new_v4().simple().to_string()[..8]
+        ))
+    }
+
+    pub(crate) fn download(&self, key: &str) -> Result<Vec<u8>, String> {
+        let (status, body) = self.request(&self.object_url(key), &[])?;
+        check_status(status, "download a snapshot")?;
+        Ok(body)
+    }
+
+    pub(crate) fn upload(&self, key: &str, data: &[u8]) -> Result<(), String> {
+        let upload_file = http::StagedFile::with_contents("s3-upload", data)?;
+        let upload_arg = upload_file.path().to_string_lossy().to_string();
+        let (status, _) = self.request(&self.object_url(key), &["--upload-file", &upload_arg])?;
+        check_status(status, "upload a snapshot")
+    }
+
+    pub(crate) fn delete(&self, key: &str) -> Result<(), String> {
+        let (status, _) = self.request(&self.object_url(key), &["--request", "DELETE"])?;
+        check_status(status, "delete an old snapshot")
+    }
+
+    /// Endpoint, bucket and prefix, which together tell buckets apart.
+    pub(crate) fn location(&self) -> String {
+        format!("{}/{}", self.bucket_url(), self.prefix)
+    }
+
+    fn key(&self, name: &str) -> String {
+        match self.prefix.as_str() {
+            "" => name.to_string(),
+            prefix => format!("{prefix}/{name}"),
+        }
+    }
+
+    fn bucket_url(&self) -> String {
+        format!("{}/{}", self.endpoint.trim_end_matches('/'), self.name)
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        let path: Vec<String> = key.split('/').map(encode).collect();
+        format!("{}/{}", self.bucket_url(), path.join("/"))
+    }
+
+    /// Runs a signed request and returns the HTTP status and body.
+    fn request(&self, url: &str, args: &[&str]) -> Result<(u16, Vec<u8>), String> {
+        let config = format!(
+            "url = {}\nuser = {}\naws-sigv4 = {}\n",
+            http::quote_curl_value(url)?,
+            http::quote_curl_value(&format!(
+                "{}:{}",
+                self.access_key_id, self.secret_access_key
+            ))?,
+            http::quote_curl_value(&format!("aws:amz:{}:s3", self.region))?
+        );
+        let body_file = http::StagedFile::new("s3-response")?;
+        let body_arg = body_file.path().to_string_lossy().to_string();
+
+        let mut curl_args = vec!["--write-out", "%{http_code}", "--output", &body_arg];
+        curl_args.extend(args);
+        let output = http::run_curl(
+            http::WEB_PROTOCOLS,
+            REQUEST_TIMEOUT_SECS,
+            &config,
+            &curl_args,
+        );
+        let body = body_file.read();
+        drop(body_file);
+
+        let output = output.map_err(|e| format!("S3 request failed: {e}"))?;
+        let code = String::from_utf8_lossy(&output);
+        let status = code
+            .trim()
+            .parse::<u16>()
+            .map_err(|_| format!("Unexpected curl status output: {code}"))?;
+        Ok((status, body))
+    }
+}
+
+fn check_status(status: u16, action: &str) -> Result<(), String> {
+    match status {
+        200..=299 => Ok(()),
+        401 | 403 => Err("S3 rejected the access key".to_string()),
+        404 => Err(format!("S3 bucket not found while trying to {action}")),
+        status => Err(format!("Failed to {action}: S3 answered HTTP {status}")),
+    }
+}
+
+/// Percent-encodes everything but unreserved characters, as signing needs.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// The text of every `<name>` element in an S3 XML response.
+fn element_values(xml: &str, name: &str) -> Vec<String> {
+    let open = format!("<{name}>");
+    let close = format!("</{name}>");
+    xml.split(open.as_str())
+        .skip(1)
+        .filter_map(|rest| rest.split_once(close.as_str()))
+        .map(|(value, _)| html::decode_entities(value))
+        .collect()
+}
@@ -0,0 +1,208 @@
+//! Saved searches: a text query plus tag, notebook and date filters, kept in
+//! the settings under a name so a smart list is one click away. Pinned ones
+//! are listed in the tray menu and open the main window on their results.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+use crate::{
+    normalize_tags, search, search_notes, settings, tray, DateRange, SearchResult, StorageState,
+    DEFAULT_SEARCH_LIMIT,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct SavedSearch {
+    id: String,
+    #[serde(flatten)]
+    fields: SavedSearchFields,
+    created_at: String,
+}
+
+/// What the frontend sends to create or edit a saved search.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "snake_case")]
+pub(crate) struct SavedSearchFields {
+    name: String,
+    /// Ranked like `search_notes`; empty matches every note.
+    query: String,
+    /// Notes must have all of these.
+    tags: Vec<String>,
+    notebook_id: Option<String>,
+    /// Bounds on when notes were created, as `DateRange` parses them; a
+    /// shortcut like `this week` moves along with the calendar.
+    from: Option<String>,
+    to: Option<String>,
+    /// Lists the search in the tray menu.
+    pinned: bool,
+}
+
+impl SavedSearch {
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.fields.name
+    }
+
+    pub(crate) fn is_pinned(&self) -> bool {
+        self.fields.pinned
+    }
+}
+
+#[tauri::command]
+pub(crate) fn list_saved_searches(app: AppHandle) -> Result<Vec<SavedSearch>, String> {
+    Ok(settings::load_settings(&app)?.saved_searches)
+}
+
+#[tauri::command]
+pub(crate) fn create_saved_search(
+    app: AppHandle,
+    search: SavedSearchFields,
+) -> Result<SavedSearch, String> {
+    let saved = SavedSearch {
+        id: Uuid::new_v4().to_string(),
+        fields: validate(search)?,
+        created_at: Utc::now().to_rfc3339(),
+    };
+
+    settings::modify_settings(&app, |settings| {
+        ensure_unique_name(&settings.saved_searches, &saved.fields.name, None)?;
+        settings.saved_searches.push(saved.clone());
+        Ok(())
+    })?;
+    tray::refresh(&app);
+    Ok(saved)
+}
+
+#[tauri::command]
+pub(crate) fn update_saved_search(
+    app: AppHandle,
+    id: String,
+    search: SavedSearchFields,
+) -> Result<SavedSearch, String> {
+    let fields = validate(search)?;
+
+    let mut updated = None;
+    settings::modify_settings(&app, |settings| {
+        ensure_unique_name(&settings.saved_searches, &fields.name, Some(&id))?;
+        let saved = find_mut(&mut settings.saved_searches, &id)?;
+        saved.fields = fields;
+        updated = Some(saved.clone());
+        Ok(())
+    })?;
+    tray::refresh(&app);
+    updated.ok_or_else(|| format!("Saved search not found: {id}"))
+}
+
+#[tauri::command]
+pub(crate) fn delete_saved_search(app: AppHandle, id: String) -> Result<(), String> {
+    settings::modify_settings(&app, |settings| {
+        find_mut(&mut settings.saved_searches, &id)?;
+        settings.saved_searches.retain(|saved| saved.id != id);
+        Ok(())
+    })?;
+    tray::refresh(&app);
+    Ok(())
+}
+
+/// Notes matching the saved search: ranked by the query when it has one,
+/// otherwise newest first.
+#[tauri::command]
+pub(crate) fn run_saved_search(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    limit: Option<usize>,
+) -> Result<Vec<SearchResult>, String> {
+    let saved = settings::load_settings(&app)?
+        .saved_searches
+        .into_iter()
+        .find(|saved| saved.id == id)
+        .ok_or_else(|| format!("Saved search not found: {id}"))?;
+    let fields = saved.fields;
+
+    let mut results = if fields.query.is_empty() {
+        let range = DateRange::parse(fields.from.as_deref(), fields.to.as_deref())?;
+        let storage = state.lock(&app)?;
+        let mut notes: Vec<_> = storage
+            .notes()?
+            .iter()
+            .filter(|note| !note.is_trashed() && range.contains(note))
+            .map(search::unranked)
+            .collect();
+        notes.sort_by(|a, b| b.note.created_at.cmp(&a.note.created_at));
+        notes
+    } else {
+        search_notes(
+            app.clone(),
+            state,
+            fields.query,
+            Some(usize::MAX),
+            fields.from,
+            fields.to,
+        )?
+    };
+
+    results.retain(|result| {
+        let note = &result.note;
+        fields.tags.iter().all(|tag| note.tags.contains(tag))
+            && fields
+                .notebook_id
+                .as_ref()
+                .is_none_or(|notebook_id| note.notebook_id == *notebook_id)
+    });
+    results.truncate(limit.unwrap_or(DEFAULT_SEARCH_LIMIT));
+    Ok(results)
+}
+
+fn validate(fields: SavedSearchFields) -> Result<SavedSearchFields, String> {
+    let name = fields.name.trim().to_string();
+    if name.is_empty() {
+        return Err("Saved search name cannot be empty".to_string());
+    }
+    let bound = |bound: Option<String>| {
+        bound
+            .map(|bound| bound.trim().to_string())
+            .filter(|bound| !bound.is_empty())
+    };
+    let (from, to) = (bound(fields.from), bound(fields.to));
+    DateRange::parse(from.as_deref(), to.as_deref())?;
+
+    Ok(SavedSearchFields {
+        name,
+        query: fields.query.trim().to_string(),
+        tags: normalize_tags(fields.tags),
+        notebook_id: fields
+            .notebook_id
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty()),
+        from,
+        to,
+        pinned: fields.pinned,
+    })
+}
+
+fn ensure_unique_name(
+    searches: &[SavedSearch],
+    name: &str,
+    except_id: Option<&str>,
+) -> Result<(), String> {
+    let taken = searches.iter().any(|saved| {
+        Some(saved.id.as_str()) != except_id && saved.fields.name.eq_ignore_ascii_case(name)
+    });
+    if taken {
+        return Err(format!("A saved search named \"{name}\" already exists"));
+    }
+    Ok(())
+}
+
+fn find_mut<'a>(searches: &'a mut [SavedSearch], id: &str) -> Result<&'a mut SavedSearch, String> {
+    searches
+        .iter_mut()
+        .find(|saved| saved.id == id)
+        .ok_or_else(|| format!("Saved search not found: {id}"))
+}
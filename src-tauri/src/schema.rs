@@ -0,0 +1,175 @@
+//! The notes file format. Notes are wrapped in a versioned envelope,
+//! `{"version": 2, "notes": [...]}`; version 1 is the bare array earlier
+//! releases wrote. Older files are upgraded on load, one migration per
+//! version, after a copy of the original is kept in `backups/`. A file from
+//! a newer release is refused rather than loaded and saved without the
+//! fields this one does not know.
+//!
+//! Adding a field with a `#[serde(default)]` needs no new version. Renaming
+//! or reshaping one does: bump `CURRENT_VERSION` and append a migration.
+
+use std::{fs, path::Path};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{backup, encryption, save_notes_to_path, Note};
+
+pub(crate) const CURRENT_VERSION: u32 = 2;
+
+/// `MIGRATIONS[n]` turns the notes array of version `n + 1` into that of
+/// version `n + 2`. They work on raw JSON, so they can read fields `Note`
+/// no longer has.
+const MIGRATIONS: [fn(Value) -> Result<Value, String>; CURRENT_VERSION as usize - 1] =
+    [envelope_only];
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    notes: &'a [Note],
+}
+
+pub(crate) struct NotesFile {
+    pub notes: Vec<Note>,
+    /// The version the file was written in, before migrating.
+    pub version: u32,
+}
+
+/// Reads a notes file of any known version; a missing or empty file has no
+/// notes.
+pub(crate) fn read(path: &Path) -> Result<NotesFile, String> {
+    if !path.exists() {
+        return Ok(NotesFile {
+            notes: Vec::new(),
+            version: CURRENT_VERSION,
+        });
+    }
+    let raw = fs::read_to_string(path).map_err(|e| format!("Failed to read notes file: {e}"))?;
+    let raw = encryption::open(raw)?;
+    parse(&raw).map_err(|e| format!("Failed to parse notes file: {e}"))
+}
+
+/// Like `read`, but rewrites an older file in the current version once its
+/// original is backed up.
+pub(crate) fn read_upgrading(path: &Path) -> Result<Vec<Note>, String> {
+    let file = read(path)?;
+    if file.version < CURRENT_VERSION {
+        backup::keep_before_migration(path, file.version)?;
+        save_notes_to_path(path, &file.notes)?;
+    }
+    Ok(file.notes)
+}
+
+pub(crate) fn parse(raw: &str) -> Result<NotesFile, String> {
+    if raw.trim().is_empty() {
+        return Ok(NotesFile {
+            notes: Vec::new(),
+            version: CURRENT_VERSION,
+        });
+    }
+    let document: Value = serde_json::from_str(raw).map_err(|e| e.to_string())?;
+    let (version, mut notes) = match document {
+        Value::Array(_) => (1, document),
+        Value::Object(mut envelope) => {
+            let version = envelope
+                .get("version")
+                .and_then(Value::as_u64)
+                .and_then(|version| u32::try_from(version).ok())
+                .filter(|version| *version >= 1)
+                .ok_or_else(|| "missing or invalid version".to_string())?;
+            let notes = envelope
+                .remove("notes")
+                .ok_or_else(|| "missing notes".to_string())?;
+            (version, notes)
+        }
+        _ => return Err("expected a list of notes".to_string()),
+    };
+    if version > CURRENT_VERSION {
+        return Err(format!(
+            "written by a newer version of Jotin (format {version}); update the app to open it"
+        ));
+    }
+
+    for migrate in &MIGRATIONS[version as usize - 1..] {
+        notes = migrate(notes)?;
+    }
+    let notes = serde_json::from_value(notes).map_err(|e| e.to_string())?;
+    Ok(NotesFile { notes, version })
+}
+
+/// The notes in the current version's envelope.
+pub(crate) fn serialize(notes: &[Note]) -> Result<String, String> {
+    serde_json::to_string_pretty(&Envelope {
+        version: CURRENT_VERSION,
+        notes,
+    })
+    .map_err(|e| format!("Failed to serialize notes payload: {e}"))
+}
+
+/// The notes as a file of `version` would hold them. Only version 1, the
+/// bare array, is written besides the current one, so a remote that older
+/// releases still sync with stays readable to them; this holds while
+/// 1 → 2 is `envelope_only`.
+pub(crate) fn serialize_as(notes: &[Note], version: u32) -> Result<String, String> {
+    if version != 1 {
+        return serialize(notes);
+    }
+    serde_json::to_string_pretty(notes)
+        .map_err(|e| format!("Failed to serialize notes payload: {e}"))
+}
+
+/// 1 → 2 only introduced the envelope; the notes are unchanged.
+fn envelope_only(notes: Value) -> Result<Value, String> {
+    Ok(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = r#"{"id": "a", "text": "Hello", "created_at": "2025-01-01T00:00:00+00:00", "updated_at": null}"#;
+
+    #[test]
+    fn reads_the_bare_array_as_version_1() {
+        let file = parse(&format!("[{NOTE}]")).unwrap();
+        assert_eq!(file.version, 1);
+        assert_eq!(file.notes.len(), 1);
+        assert_eq!(file.notes[0].text, "Hello");
+    }
+
+    #[test]
+    fn reads_the_current_envelope() {
+        let file = parse(&format!(r#"{{"version": 2, "notes": [{NOTE}]}}"#)).unwrap();
+        assert_eq!(file.version, CURRENT_VERSION);
+        assert_eq!(file.notes[0].id, "a");
+    }
+
+    #[test]
+    fn empty_file_has_no_notes() {
+        let file = parse("  \n").unwrap();
+        assert!(file.notes.is_empty());
+        assert_eq!(file.version, CURRENT_VERSION);
+    }
+
+    #[test]
+    fn refuses_newer_and_invalid_versions() {
+        let newer = parse(r#"{"version": 99, "notes": []}"#).err().unwrap();
+        assert!(newer.contains("newer version"));
+        assert!(parse(r#"{"version": 0, "notes": []}"#).is_err());
+        assert!(parse(r#"{"notes": []}"#).is_err());
+        assert!(parse(r#"{"version": 2}"#).is_err());
+        assert!(parse("42").is_err());
+    }
+
+    #[test]
+    fn serialized_notes_read_back() {
+        let notes = parse(&format!("[{NOTE}]")).unwrap().notes;
+        let current = parse(&serialize(&notes).unwrap()).unwrap();
+        assert_eq!(current.version, CURRENT_VERSION);
+        assert_eq!(current.notes[0].text, "Hello");
+
+        let bare = parse(&serialize_as(&notes, 1).unwrap()).unwrap();
+        assert_eq!(bare.version, 1);
+        assert_eq!(bare.notes[0].text, "Hello");
+    }
+}
@@ -0,0 +1,202 @@
+//! Screenshot notes. The OS tool grabs the whole screen or a region the
+//! user drags out, the PNG becomes an attachment on a new note, and quick
+//! capture opens on that note so a caption can be typed straight away.
+//!
+//! macOS uses `screencapture`. Linux tries `grim` (with `slurp` for regions)
+//! on Wayland, then `maim`, `scrot` and `gnome-screenshot`. Windows takes
+//! full-screen shots through PowerShell; it has no region tool that writes
+//! a file, so region shots are full-screen there too.
+
+use std::{fs, io, path::Path, process::Command, sync::Mutex, thread, time::Duration};
+
+use chrono::Utc;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+use crate::{
+    attachments, hide_capture_window, insert_prepared_note, notifications, ocr,
+    show_capture_window, Note, StorageState, CAPTURE_WINDOW_LABEL,
+};
+
+const SCREENSHOT_CAPTION: &str = "Screenshot";
+const SCREENSHOT_CAPTURED_EVENT: &str = "screenshot-captured";
+/// Gives quick capture time to disappear before the screen is grabbed.
+const HIDE_DELAY: Duration = Duration::from_millis(200);
+
+/// The last screenshot note taken before quick capture was ready to listen.
+static PENDING_SCREENSHOT: Mutex<Option<Note>> = Mutex::new(None);
+
+/// Takes a screenshot of a region (the default) or the whole screen in the
+/// background. The new note arrives in quick capture through the
+/// `screenshot-captured` event.
+#[tauri::command]
+pub(crate) fn capture_screenshot_note(app: AppHandle, region: Option<bool>) {
+    start(&app, region.unwrap_or(true));
+}
+
+/// Lets a freshly loaded capture window pick up the note to caption.
+#[tauri::command]
+pub(crate) fn take_pending_screenshot() -> Option<Note> {
+    PENDING_SCREENSHOT.lock().ok()?.take()
+}
+
+/// Region capture for the global shortcut.
+pub(crate) fn capture_region(app: &AppHandle) {
+    start(app, true);
+}
+
+fn start(app: &AppHandle, region: bool) {
+    let app = app.clone();
+    thread::spawn(move || {
+        // Keep quick capture out of the shot.
+        let _ = hide_capture_window(&app);
+        thread::sleep(HIDE_DELAY);
+        match capture(&app, region) {
+            Ok(Some(note)) => open_for_caption(&app, note),
+            Ok(None) => {}
+            Err(error) => {
+                eprintln!("Failed to take a screenshot: {error}");
+                notifications::show(&app, "Screenshot failed", &error, &[], |_, _| {});
+            }
+        }
+    });
+}
+
+/// `None` when the user cancelled the region selection.
+fn capture(app: &AppHandle, region: bool) -> Result<Option<Note>, String> {
+    let path = std::env::temp_dir().join(format!("jotin-screenshot-{}.png", Uuid::new_v4()));
+    let taken = take_screenshot(&path, region);
+    let png = match taken.and_then(|()| read_png(&path)) {
+        Ok(png) => png,
+        Err(error) => {
+            let _ = fs::remove_file(&path);
+            return Err(error);
+        }
+    };
+    let _ = fs::remove_file(&path);
+    let Some(png) = png else {
+        return Ok(None);
+    };
+
+    let (hash, size) = attachments::store_bytes(app, &png)?;
+    let file_name = format!("screenshot-{}.png", Utc::now().format("%Y%m%d-%H%M%S"));
+    let mut note = Note::new(SCREENSHOT_CAPTION.to_string());
+    note.attachments
+        .push(attachments::new_attachment(file_name, hash, size));
+
+    let state = app.state::<StorageState>();
+    let note = insert_prepared_note(app, &state, note)?;
+    ocr::refresh(app, &note.id);
+    Ok(Some(note))
+}
+
+fn open_for_caption(app: &AppHandle, note: Note) {
+    if let Ok(mut pending) = PENDING_SCREENSHOT.lock() {
+        *pending = Some(note.clone());
+    }
+    if let Err(error) = show_capture_window(app) {
+        eprintln!("Failed to open quick capture for the screenshot: {error}");
+        return;
+    }
+    let _ = app.emit_to(CAPTURE_WINDOW_LABEL, SCREENSHOT_CAPTURED_EVENT, &note);
+}
+
+/// Tools leave no file, or an empty one, when the selection is cancelled.
+fn read_png(path: &Path) -> Result<Option<Vec<u8>>, String> {
+    match fs::read(path) {
+        Ok(png) if png.is_empty() => Ok(None),
+        Ok(png) => Ok(Some(png)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(format!("Failed to read the screenshot: {error}")),
+    }
+}
+
+/// Runs the first available tool. A non-zero exit after the tool started
+/// is treated as a cancelled selection.
+fn take_screenshot(path: &Path, region: bool) -> Result<(), String> {
+    for mut command in screenshot_commands(path, region) {
+        match command.status() {
+            Ok(_) => return Ok(()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(format!("Failed to start the screenshot tool: {error}")),
+        }
+    }
+    Err("No screenshot tool found".to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn screenshot_commands(path: &Path, region: bool) -> Vec<Command> {
+    let mut command = Command::new("screencapture");
+    command.arg("-x");
+    if region {
+        command.arg("-i");
+    }
+    command.arg(path);
+    vec![command]
+}
+
+#[cfg(target_os = "windows")]
+const WINDOWS_SCREENSHOT: &str = "Add-Type -AssemblyName System.Windows.Forms, System.Drawing; \
+    $bounds = [System.Windows.Forms.SystemInformation]::VirtualScreen; \
+    $bitmap = New-Object System.Drawing.Bitmap $bounds.Width, $bounds.Height; \
+    $graphics = [System.Drawing.Graphics]::FromImage($bitmap); \
+    $graphics.CopyFromScreen($bounds.Left, $bounds.Top, 0, 0, $bitmap.Size); \
+    $bitmap.Save($env:JOTIN_SCREENSHOT, [System.Drawing.Imaging.ImageFormat]::Png)";
+
+#[cfg(target_os = "windows")]
+fn screenshot_commands(path: &Path, _region: bool) -> Vec<Command> {
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-Command", WINDOWS_SCREENSHOT])
+        .env("JOTIN_SCREENSHOT", path);
+    vec![command]
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn screenshot_commands(path: &Path, region: bool) -> Vec<Command> {
+    let mut commands = Vec::new();
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        let mut grim = Command::new("sh");
+        let script = if region {
+            "command -v slurp >/dev/null && geometry=$(slurp) && grim -g \"$geometry\" \"$0\""
+        } else {
+            "grim \"$0\""
+        };
+        // `sh` finds the tools itself, so check grim exists before using it.
+        if which("grim") {
+            grim.args(["-c", script]).arg(path);
+            commands.push(grim);
+        }
+    }
+
+    let mut maim = Command::new("maim");
+    if region {
+        maim.arg("-s");
+    }
+    maim.arg(path);
+    commands.push(maim);
+
+    let mut scrot = Command::new("scrot");
+    if region {
+        scrot.arg("-s");
+    }
+    scrot.arg("-o").arg(path);
+    commands.push(scrot);
+
+    let mut gnome = Command::new("gnome-screenshot");
+    if region {
+        gnome.arg("-a");
+    }
+    gnome.arg("-f").arg(path);
+    commands.push(gnome);
+    commands
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn which(tool: &str) -> bool {
+    Command::new("sh")
+        .args(["-c", "command -v \"$0\" >/dev/null"])
+        .arg(tool)
+        .status()
+        .is_ok_and(|status| status.success())
+}
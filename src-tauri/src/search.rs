@@ -0,0 +1,564 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use regex::{Regex, RegexBuilder};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{shred, Note};
+
+const INDEX_FILE_NAME: &str = "search-index.sqlite";
+/// Bumped when the tables change; an index with another version is rebuilt.
+const INDEX_VERSION: i32 = 1;
+/// How long to wait for the CLI or another window writing the index.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const SNIPPET_RADIUS: usize = 60;
+const MAX_PATTERN_CHARS: usize = 1000;
+/// Bounds the compiled program and its lazy DFA, so a pattern such as
+/// `(a{100}){100}` is rejected instead of exhausting memory.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+const REGEX_DFA_SIZE_LIMIT: usize = 2 << 20;
+const MAX_NESTING: u32 = 64;
+/// Matches counted per note; ranking only needs to know there are many.
+const MAX_REGEX_MATCHES: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct SearchResult {
+    pub note: Note,
+    pub score: f32,
+    pub snippet: String,
+    /// Where the query terms occur in `note.text`.
+    pub matches: Vec<MatchRange>,
+    pub match_count: usize,
+    /// Where they occur in `snippet`, which collapses whitespace.
+    pub snippet_matches: Vec<MatchRange>,
+}
+
+/// Character offsets, end exclusive, like the `indices` of fuzzy results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Full-text index over note text and OCR text, kept in SQLite FTS5 and
+/// ranked with its BM25.
+///
+/// It lives next to the notes file, so it survives restarts and `jotin
+/// search` shares it. Each row keeps a fingerprint of its note: opening the
+/// index re-indexes only the notes that changed since, and the storage
+/// commands keep it in sync after that.
+pub(crate) struct SearchIndex {
+    connection: Connection,
+}
+
+impl SearchIndex {
+    /// Opens the index stored beside `notes_path` and brings it in line with
+    /// `notes`. An unreadable index is only a cache, so it is rebuilt, and
+    /// when the file cannot be used at all the index is kept in memory.
+    pub(crate) fn open(notes_path: &Path, notes: &[Note]) -> Result<Self, String> {
+        let path = index_path(notes_path);
+        Self::open_file(&path, notes)
+            .or_else(|error| {
+                eprintln!("Rebuilding search index: {error}");
+                let _ = fs::remove_file(&path);
+                Self::open_file(&path, notes)
+            })
+            .or_else(|error| {
+                eprintln!("Keeping search index in memory: {error}");
+                Self::build(notes)
+            })
+    }
+
+    /// An index held in memory only.
+    pub(crate) fn build(notes: &[Note]) -> Result<Self, String> {
+        let connection = Connection::open_in_memory().map_err(index_error)?;
+        Self::prepare(connection, notes)
+    }
+
+    fn open_file(path: &Path, notes: &[Note]) -> Result<Self, String> {
+        let connection = Connection::open(path).map_err(index_error)?;
+        connection.busy_timeout(BUSY_TIMEOUT).map_err(index_error)?;
+        // Zeroes replaced and deleted rows, so the text of shredded or
+        // locked notes does not linger in free pages.
+        connection
+            .pragma_update(None, "secure_delete", true)
+            .map_err(index_error)?;
+        Self::prepare(connection, notes)
+    }
+
+    fn prepare(connection: Connection, notes: &[Note]) -> Result<Self, String> {
+        let version: i32 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(index_error)?;
+        if version != INDEX_VERSION {
+            connection
+                .execute_batch(&format!(
+                    "DROP TABLE IF EXISTS documents;
+                     DROP TABLE IF EXISTS terms;
+                     CREATE TABLE documents (
+                         rowid INTEGER PRIMARY KEY,
+                         id TEXT NOT NULL UNIQUE,
+                         created_at TEXT NOT NULL,
+                         fingerprint BLOB NOT NULL,
+                         note TEXT NOT NULL
+                     );
+                     CREATE VIRTUAL TABLE terms USING fts5(
+                         text, ocr_text, tokenize = 'unicode61 remove_diacritics 0'
+                     );
+                     PRAGMA user_version = {INDEX_VERSION};"
+                ))
+                .map_err(index_error)?;
+        }
+
+        let mut index = Self { connection };
+        index.sync(notes)?;
+        Ok(index)
+    }
+
+    /// Re-indexes the notes whose fingerprint changed and drops the ones
+    /// that are gone.
+    fn sync(&mut self, notes: &[Note]) -> Result<(), String> {
+        let mut stored: HashMap<String, Vec<u8>> = self
+            .connection
+            .prepare("SELECT id, fingerprint FROM documents")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect()
+            })
+            .map_err(index_error)?;
+
+        let transaction = self.connection.transaction().map_err(index_error)?;
+        for note in notes {
+            let document = Document::new(note)?;
+            if stored.remove(&note.id).as_deref() != Some(document.fingerprint.as_slice()) {
+                document.put(&transaction).map_err(index_error)?;
+            }
+        }
+        for id in stored.keys() {
+            delete(&transaction, id).map_err(index_error)?;
+        }
+        transaction.commit().map_err(index_error)
+    }
+
+    pub(crate) fn upsert(&mut self, note: &Note) {
+        let updated = Document::new(note).and_then(|document| {
+            let transaction = self.connection.transaction().map_err(index_error)?;
+            document.put(&transaction).map_err(index_error)?;
+            transaction.commit().map_err(index_error)
+        });
+        if let Err(error) = updated {
+            eprintln!("{error}");
+        }
+    }
+
+    pub(crate) fn remove(&mut self, id: &str) {
+        let removed = self.connection.transaction().and_then(|transaction| {
+            delete(&transaction, id)?;
+            transaction.commit()
+        });
+        if let Err(error) = removed {
+            eprintln!("{}", index_error(error));
+        }
+    }
+
+    /// Ranks notes containing every query term. The last term also matches as
+    /// a prefix so results stay useful while the user is still typing.
+    pub(crate) fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        let query_terms = tokenize(query);
+        if query_terms.is_empty() {
+            return Vec::new();
+        }
+        self.query(&query_terms, limit).unwrap_or_else(|error| {
+            eprintln!("{}", index_error(error));
+            Vec::new()
+        })
+    }
+
+    fn query(&self, query_terms: &[String], limit: usize) -> rusqlite::Result<Vec<SearchResult>> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT documents.note, bm25(terms) FROM terms
+             JOIN documents ON documents.rowid = terms.rowid
+             WHERE terms MATCH ?1
+             ORDER BY bm25(terms), documents.created_at DESC
+             LIMIT ?2",
+        )?;
+        let rows = statement.query_map(
+            params![
+                match_expression(query_terms),
+                i64::try_from(limit).unwrap_or(i64::MAX)
+            ],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)),
+        )?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            let (note, rank) = row?;
+            let Ok(note) = serde_json::from_str::<Note>(&note) else {
+                continue;
+            };
+            let matches = match_ranges(&note.text, query_terms);
+            let snippet = build_snippet(&note.text, query_terms);
+            results.push(SearchResult {
+                // FTS5 ranks better matches lower.
+                score: -rank as f32,
+                match_count: matches.len(),
+                matches,
+                snippet_matches: match_ranges(&snippet, query_terms),
+                snippet,
+                note,
+            });
+        }
+        Ok(results)
+    }
+}
+
+/// A note as stored in the index, with the fingerprint that tells whether
+/// it needs indexing again.
+struct Document<'a> {
+    note: &'a Note,
+    json: String,
+    fingerprint: Vec<u8>,
+}
+
+impl<'a> Document<'a> {
+    fn new(note: &'a Note) -> Result<Self, String> {
+        let json = serde_json::to_string(note)
+            .map_err(|e| format!("Failed to serialize note for search: {e}"))?;
+        let fingerprint = Sha256::digest(json.as_bytes()).to_vec();
+        Ok(Self {
+            note,
+            json,
+            fingerprint,
+        })
+    }
+
+    fn put(&self, connection: &Connection) -> rusqlite::Result<()> {
+        delete(connection, &self.note.id)?;
+        connection.execute(
+            "INSERT INTO documents (id, created_at, fingerprint, note) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                self.note.id,
+                self.note.created_at,
+                self.fingerprint,
+                self.json
+            ],
+        )?;
+        connection.execute(
+            "INSERT INTO terms (rowid, text, ocr_text) VALUES (?1, ?2, ?3)",
+            params![
+                connection.last_insert_rowid(),
+                self.note.text,
+                self.note.ocr_text.as_deref().unwrap_or_default()
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn delete(connection: &Connection, id: &str) -> rusqlite::Result<()> {
+    let rowid: Option<i64> = connection
+        .query_row("SELECT rowid FROM documents WHERE id = ?1", [id], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    if let Some(rowid) = rowid {
+        connection.execute("DELETE FROM terms WHERE rowid = ?1", [rowid])?;
+        connection.execute("DELETE FROM documents WHERE rowid = ?1", [rowid])?;
+    }
+    Ok(())
+}
+
+/// Deletes the stored index, e.g. once notes are encrypted and it would
+/// hold them in the clear.
+pub(crate) fn remove_stored(notes_path: &Path) {
+    let path = index_path(notes_path);
+    match shred::destroy_file(&path) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+            eprintln!("Failed to remove the search index: {error}");
+        }
+        _ => {}
+    }
+}
+
+fn index_path(notes_path: &Path) -> PathBuf {
+    notes_path.with_file_name(INDEX_FILE_NAME)
+}
+
+fn index_error(error: rusqlite::Error) -> String {
+    format!("Search index failed: {error}")
+}
+
+/// Every term quoted, so words such as `NOT` stay words, and the last one
+/// as a prefix.
+fn match_expression(query_terms: &[String]) -> String {
+    let last = query_terms.len() - 1;
+    query_terms
+        .iter()
+        .enumerate()
+        .map(|(position, term)| {
+            let quoted = format!("\"{}\"", term.replace('"', "\"\""));
+            if position == last {
+                quoted + "*"
+            } else {
+                quoted
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A result for a note listed without a query, e.g. by a saved search that
+/// only filters.
+pub(crate) fn unranked(note: &Note) -> SearchResult {
+    SearchResult {
+        note: note.clone(),
+        score: 0.0,
+        snippet: snippet_around(&note.text, 0),
+        matches: Vec::new(),
+        match_count: 0,
+        snippet_matches: Vec::new(),
+    }
+}
+
+/// Notes matching a regular expression, most matches first. `flags` holds
+/// any of `i` (ignore case), `m` (`^`/`$` match at line breaks), `s` (`.`
+/// matches a line break) and `x` (ignore whitespace and `#` comments).
+pub(crate) fn search_regex(
+    notes: &[Note],
+    pattern: &str,
+    flags: &str,
+    limit: usize,
+) -> Result<Vec<SearchResult>, String> {
+    let regex = compile_regex(pattern, flags)?;
+    let mut results: Vec<SearchResult> = notes
+        .iter()
+        .filter_map(|note| {
+            let matches = regex_ranges(&regex, &note.text);
+            let first = matches.first()?.start;
+            let snippet = snippet_around(&note.text, first);
+            Some(SearchResult {
+                note: note.clone(),
+                score: matches.len() as f32,
+                match_count: matches.len(),
+                matches,
+                snippet_matches: regex_ranges(&regex, &snippet),
+                snippet,
+            })
+        })
+        .collect();
+
+    results.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| b.note.created_at.cmp(&a.note.created_at))
+    });
+    results.truncate(limit);
+    Ok(results)
+}
+
+fn compile_regex(pattern: &str, flags: &str) -> Result<Regex, String> {
+    if pattern.is_empty() {
+        return Err("Pattern is empty".to_string());
+    }
+    if pattern.chars().count() > MAX_PATTERN_CHARS {
+        return Err("Pattern is too long".to_string());
+    }
+
+    let mut builder = RegexBuilder::new(pattern);
+    builder
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_DFA_SIZE_LIMIT)
+        .nest_limit(MAX_NESTING);
+    for flag in flags.chars() {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            'm' => builder.multi_line(true),
+            's' => builder.dot_matches_new_line(true),
+            'x' => builder.ignore_whitespace(true),
+            flag => return Err(format!("Unknown regex flag \"{flag}\"")),
+        };
+    }
+    builder.build().map_err(|e| match e {
+        regex::Error::CompiledTooBig(_) => "Pattern is too complex".to_string(),
+        e => format!("Invalid pattern: {e}"),
+    })
+}
+
+/// Non-empty matches as character ranges.
+fn regex_ranges(regex: &Regex, text: &str) -> Vec<MatchRange> {
+    let mut ranges = Vec::new();
+    let mut counted = (0, 0);
+    for found in regex
+        .find_iter(text)
+        .filter(|found| !found.is_empty())
+        .take(MAX_REGEX_MATCHES)
+    {
+        // Counts chars incrementally; matches arrive in order.
+        let start = counted.1 + text[counted.0..found.start()].chars().count();
+        let end = start + found.as_str().chars().count();
+        counted = (found.end(), end);
+        ranges.push(MatchRange { start, end });
+    }
+    ranges
+}
+
+pub(crate) fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The tokens of `text` that the query matches, the way `search` matches
+/// them: whole terms, and the last term also as a prefix, of which only the
+/// typed part is highlighted.
+fn match_ranges(text: &str, query_terms: &[String]) -> Vec<MatchRange> {
+    let Some((last, rest)) = query_terms.split_last() else {
+        return Vec::new();
+    };
+    let mut ranges = Vec::new();
+    let mut token = String::new();
+    let mut token_start = 0;
+    for (position, c) in text.chars().chain([' ']).enumerate() {
+        if c.is_alphanumeric() {
+            if token.is_empty() {
+                token_start = position;
+            }
+            token.push(c);
+            continue;
+        }
+        if token.is_empty() {
+            continue;
+        }
+        let lowered = token.to_lowercase();
+        let length = if lowered == *last || rest.contains(&lowered) {
+            Some(token.chars().count())
+        } else if lowered.starts_with(last.as_str()) {
+            Some(last.chars().count().min(token.chars().count()))
+        } else {
+            None
+        };
+        if let Some(length) = length {
+            ranges.push(MatchRange {
+                start: token_start,
+                end: token_start + length,
+            });
+        }
+        token.clear();
+    }
+    ranges
+}
+
+fn build_snippet(text: &str, query_terms: &[String]) -> String {
+    let lowered = text.to_lowercase();
+    let first_match = query_terms
+        .iter()
+        .filter_map(|term| lowered.find(term.as_str()))
+        .min();
+
+    // Lowercasing can change byte lengths, so map the match back through chars.
+    let match_char = first_match
+        .map(|byte_index| lowered[..byte_index].chars().count())
+        .unwrap_or(0);
+    snippet_around(text, match_char)
+}
+
+/// About `SNIPPET_RADIUS` characters either side of `match_char`.
+fn snippet_around(text: &str, match_char: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let match_char = match_char.min(chars.len());
+    let start = match_char.saturating_sub(SNIPPET_RADIUS);
+    let end = (match_char + SNIPPET_RADIUS).min(chars.len());
+
+    let mut snippet: String = chars[start..end].iter().collect();
+    snippet = snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: &str, text: &str) -> Note {
+        let mut note = Note::new(text.to_string());
+        note.id = id.to_string();
+        note
+    }
+
+    fn ids(results: &[SearchResult]) -> Vec<&str> {
+        results
+            .iter()
+            .map(|result| result.note.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn matches_every_term_and_the_last_as_a_prefix() {
+        let index = SearchIndex::build(&[
+            note("a", "Groceries: milk and bread"),
+            note("b", "Bread recipe, bread flour, more bread"),
+            note("c", "Call the bank"),
+        ])
+        .unwrap();
+
+        assert_eq!(ids(&index.search("bread", 10)), ["b", "a"]);
+        assert_eq!(ids(&index.search("milk bre", 10)), ["a"]);
+        assert_eq!(ids(&index.search("not bank", 10)), Vec::<&str>::new());
+        assert!(index.search("  ", 10).is_empty());
+
+        let result = &index.search("mil", 10)[0];
+        assert_eq!(result.matches, [MatchRange { start: 11, end: 14 }]);
+    }
+
+    #[test]
+    fn upserts_and_removes_notes() {
+        let mut index = SearchIndex::build(&[note("a", "first draft")]).unwrap();
+        index.upsert(&note("a", "final copy"));
+        index.upsert(&note("b", "draft two"));
+        assert_eq!(ids(&index.search("draft", 10)), ["b"]);
+
+        index.remove("b");
+        assert!(index.search("draft", 10).is_empty());
+        assert_eq!(ids(&index.search("final", 10)), ["a"]);
+    }
+
+    #[test]
+    fn reopening_catches_up_with_the_notes() {
+        let dir = std::env::temp_dir().join(format!("jotin-search-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let notes_path = dir.join("notes.json");
+
+        let index =
+            SearchIndex::open(&notes_path, &[note("a", "apples"), note("b", "pears")]).unwrap();
+        drop(index);
+        assert!(dir.join(INDEX_FILE_NAME).exists());
+
+        let index =
+            SearchIndex::open(&notes_path, &[note("a", "plums"), note("c", "pears")]).unwrap();
+        assert!(index.search("apples", 10).is_empty());
+        assert_eq!(ids(&index.search("plums", 10)), ["a"]);
+        assert_eq!(ids(&index.search("pears", 10)), ["c"]);
+        drop(index);
+
+        fs::write(dir.join(INDEX_FILE_NAME), "not a database").unwrap();
+        let index = SearchIndex::open(&notes_path, &[note("a", "plums")]).unwrap();
+        assert_eq!(ids(&index.search("plums", 10)), ["a"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
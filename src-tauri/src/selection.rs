@@ -0,0 +1,179 @@
+//! Saving the text selected in whatever app has focus as a note, without
+//! copying it first. On Linux the selection is already readable as the
+//! PRIMARY selection, so the clipboard is left alone. Elsewhere the shortcut
+//! simulates the copy keystroke (`osascript` on macOS, which needs the
+//! Accessibility permission, and `SendKeys` on Windows) and then puts back
+//! whatever the clipboard held before.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use tauri::{AppHandle, Manager};
+
+use crate::{append, clipboard_watcher, notifications, StorageState};
+
+/// How long to wait for the focused app to answer the copy keystroke.
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+const COPY_TIMEOUT: Duration = Duration::from_millis(1000);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Lets the user release the shortcut's modifiers, which would otherwise
+/// turn the simulated copy into a different keystroke.
+const MODIFIER_RELEASE_DELAY: Duration = Duration::from_millis(250);
+
+/// Saves the current selection as a note right away, confirming with a
+/// notification like clipboard capture does.
+pub(crate) fn capture_selection(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        thread::sleep(MODIFIER_RELEASE_DELAY);
+        let text = match selected_text() {
+            Ok(text) => text.unwrap_or_default(),
+            Err(error) => {
+                eprintln!("{error}");
+                String::new()
+            }
+        };
+        if text.trim().is_empty() {
+            notifications::show(
+                &app,
+                "Nothing to save",
+                "No text is selected",
+                &[],
+                |_, _| {},
+            );
+            return;
+        }
+
+        // The copy may have briefly put the selection on the clipboard.
+        clipboard_watcher::ignore_text(&text);
+        let state = app.state::<StorageState>();
+        match append::capture_text(&app, &state, &text) {
+            Ok(note) => notifications::show(
+                &app,
+                "Saved selection as a note",
+                note.title(),
+                &[],
+                |_, _| {},
+            ),
+            Err(error) => eprintln!("Failed to save selection as a note: {error}"),
+        }
+    });
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn selected_text() -> Result<Option<String>, String> {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("Failed to access the clipboard: {e}"))?;
+    // Some X11 owners answer the first request late; poll briefly.
+    let deadline = Instant::now() + POLL_INTERVAL * 4;
+    loop {
+        match clipboard
+            .get()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text()
+        {
+            Ok(text) => return Ok(Some(text)),
+            Err(_) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+            Err(error) => return Err(format!("Failed to read the selection: {error}")),
+        }
+    }
+}
+
+/// Clears the clipboard, sends the copy keystroke and waits for text to
+/// land, then restores the previous contents either way.
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn selected_text() -> Result<Option<String>, String> {
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("Failed to access the clipboard: {e}"))?;
+    let previous = SavedClipboard::take(&mut clipboard);
+    clipboard
+        .clear()
+        .map_err(|e| format!("Failed to clear the clipboard: {e}"))?;
+
+    let copied = send_copy_keystroke().map(|()| {
+        let deadline = Instant::now() + COPY_TIMEOUT;
+        loop {
+            match clipboard.get_text() {
+                Ok(text) if !text.is_empty() => break Some(text),
+                _ if Instant::now() >= deadline => break None,
+                _ => thread::sleep(POLL_INTERVAL),
+            }
+        }
+    });
+
+    previous.restore(&mut clipboard);
+    copied
+}
+
+/// What was on the clipboard before the simulated copy replaced it.
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+enum SavedClipboard {
+    Text(String),
+    Image(arboard::ImageData<'static>),
+    Empty,
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+impl SavedClipboard {
+    fn take(clipboard: &mut arboard::Clipboard) -> Self {
+        if let Ok(text) = clipboard.get_text() {
+            return Self::Text(text);
+        }
+        match clipboard.get_image() {
+            Ok(image) => Self::Image(image),
+            Err(_) => Self::Empty,
+        }
+    }
+
+    fn restore(self, clipboard: &mut arboard::Clipboard) {
+        let restored = match self {
+            Self::Text(text) => {
+                clipboard_watcher::ignore_text(&text);
+                clipboard.set_text(text)
+            }
+            Self::Image(image) => clipboard.set_image(image),
+            Self::Empty => clipboard.clear(),
+        };
+        if let Err(error) = restored {
+            eprintln!("Failed to restore the clipboard: {error}");
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn send_copy_keystroke() -> Result<(), String> {
+    run(std::process::Command::new("osascript").args([
+        "-e",
+        r#"tell application "System Events" to keystroke "c" using command down"#,
+    ]))
+}
+
+#[cfg(target_os = "windows")]
+fn send_copy_keystroke() -> Result<(), String> {
+    run(std::process::Command::new("powershell").args([
+        "-NoProfile",
+        "-Command",
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         [System.Windows.Forms.SendKeys]::SendWait('^c')",
+    ]))
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn run(command: &mut std::process::Command) -> Result<(), String> {
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to send the copy keystroke: {e}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Failed to send the copy keystroke: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
@@ -0,0 +1,649 @@
+use std::{fs, path::Path, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+use crate::{
+    ai::DEFAULT_AI_MODEL,
+    append::{CaptureTarget, DEFAULT_APPEND_WINDOW_MINUTES},
+    backup::{self, DEFAULT_BACKUP_RETENTION},
+    clipboard_watcher,
+    dismiss::{self, CaptureDismiss},
+    email::DEFAULT_SMTP_PORT,
+    embeddings::DEFAULT_EMBEDDING_MODEL,
+    git_sync, http_api,
+    http_api::DEFAULT_HTTP_API_PORT,
+    keychain, notifications,
+    notion::DEFAULT_NOTION_TAGS_PROPERTY,
+    placement::{CaptureMode, CaptureMonitor, CapturePlacement, WindowPosition},
+    pomodoro::{
+        DEFAULT_POMODORO_BREAK_MINUTES, DEFAULT_POMODORO_LONG_BREAK_MINUTES,
+        DEFAULT_POMODORO_WORK_MINUTES,
+    },
+    purge_expired_trash, resolve_app_data_file,
+    s3::{DEFAULT_S3_PREFIX, DEFAULT_S3_REGION},
+    saved_searches::SavedSearch,
+    shortcuts,
+    shortcuts::{
+        DEFAULT_CAPTURE_SHORTCUT, DEFAULT_CLIPBOARD_CAPTURE_SHORTCUT, DEFAULT_MAIN_WINDOW_SHORTCUT,
+    },
+    shred, theme, tray,
+    undo::DEFAULT_UNDO_WINDOW_SECS,
+    vault::DEFAULT_VAULT_CAPTURE_FOLDER,
+    write_file_atomically, NoteSort, StorageState, CAPTURE_WINDOW_LABEL,
+};
+
+pub(crate) const SETTINGS_FILE_NAME: &str = "settings.json";
+const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+static SETTINGS_WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+type ShortcutField = (
+    fn(&mut Settings) -> &mut String,
+    fn(&AppHandle, &str) -> Result<(), String>,
+);
+
+/// The global shortcut settings and how each is registered. The first is
+/// the capture shortcut, the only one that cannot be turned off.
+const SHORTCUT_FIELDS: [ShortcutField; 6] = [
+    (
+        |settings| &mut settings.capture_shortcut,
+        shortcuts::apply_capture_shortcut,
+    ),
+    (
+        |settings| &mut settings.clipboard_capture_shortcut,
+        shortcuts::apply_clipboard_capture_shortcut,
+    ),
+    (
+        |settings| &mut settings.main_window_shortcut,
+        shortcuts::apply_main_window_shortcut,
+    ),
+    (
+        |settings| &mut settings.screenshot_capture_shortcut,
+        shortcuts::apply_screenshot_capture_shortcut,
+    ),
+    (
+        |settings| &mut settings.selection_capture_shortcut,
+        shortcuts::apply_selection_capture_shortcut,
+    ),
+    (
+        |settings| &mut settings.voice_capture_shortcut,
+        shortcuts::apply_voice_capture_shortcut,
+    ),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ThemePreference {
+    Light,
+    Dark,
+    #[default]
+    System,
+}
+
+/// What clicking a note in the tray's Recent submenu does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TrayNoteAction {
+    #[default]
+    Open,
+    Copy,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "snake_case")]
+pub(crate) struct Settings {
+    /// Accelerator that opens quick capture, e.g. `Ctrl+Alt+N`.
+    pub capture_shortcut: String,
+    /// Accelerator that saves the clipboard text as a note; empty disables it.
+    pub clipboard_capture_shortcut: String,
+    /// Accelerator that shows the main window, or hides it when already in
+    /// front; empty disables it.
+    pub main_window_shortcut: String,
+    /// Accelerator that screenshots a screen region into a new note; empty
+    /// disables it.
+    pub screenshot_capture_shortcut: String,
+    /// Accelerator that saves the text selected in the focused app as a note;
+    /// empty disables it.
+    pub selection_capture_shortcut: String,
+    /// Push-to-talk accelerator that records a voice note while held; empty
+    /// disables it.
+    pub voice_capture_shortcut: String,
+    /// whisper.cpp model (`ggml-*.bin`) voice notes are transcribed with;
+    /// `None` keeps them as audio only.
+    pub whisper_model_path: Option<String>,
+    pub theme: ThemePreference,
+    /// Keeps the quick-capture window above other apps while it is open.
+    pub capture_always_on_top: bool,
+    /// Adds the tags suggested for a new note instead of only proposing them.
+    pub auto_apply_tag_suggestions: bool,
+    /// Stores the app and window title in front when quick capture opened
+    /// on each note captured there.
+    pub record_capture_source: bool,
+    /// Stores the active tab's address when quick capture opened over a
+    /// browser.
+    pub record_source_url: bool,
+    pub capture_mode: CaptureMode,
+    pub capture_target: CaptureTarget,
+    /// Minutes after its last change that the latest note still takes
+    /// appended captures.
+    pub append_window_minutes: u32,
+    pub capture_placement: CapturePlacement,
+    pub capture_monitor: CaptureMonitor,
+    pub capture_dismiss: CaptureDismiss,
+    /// Where the capture window was last closed, for the remembered placement.
+    pub capture_position: Option<WindowPosition>,
+    /// Order used when a notes list request does not ask for one.
+    pub default_note_sort: NoteSort,
+    pub tray_recent_action: TrayNoteAction,
+    /// Managed through the saved search commands; pinned ones are in the tray.
+    pub saved_searches: Vec<SavedSearch>,
+    /// Directory holding the notes and notebooks files; `None` uses the app data dir.
+    pub data_dir: Option<String>,
+    /// Obsidian vault the notes are stored in, one Markdown file each,
+    /// instead of the notes file; `None` keeps the notes file.
+    pub vault_dir: Option<String>,
+    /// Vault folder new notes are created in, e.g. `Inbox`; empty uses the
+    /// vault's root.
+    pub vault_capture_folder: String,
+    /// Number of rotating backups kept for the notes file; `0` disables them.
+    pub backup_retention: u32,
+    /// Days a trashed note is kept before being purged; `None` keeps it forever.
+    pub trash_retention_days: Option<u32>,
+    /// How long a destructive operation stays undoable.
+    pub undo_window_seconds: u32,
+    /// Turns every new clipboard text entry into a note.
+    pub clipboard_watcher_enabled: bool,
+    /// Silences native notifications such as reminders.
+    pub do_not_disturb: bool,
+    /// Length of a pomodoro focus interval.
+    pub pomodoro_work_minutes: u32,
+    pub pomodoro_break_minutes: u32,
+    /// Break taken after every fourth focus interval.
+    pub pomodoro_long_break_minutes: u32,
+    /// Serves the loopback HTTP API for scripts and launcher integrations.
+    pub http_api_enabled: bool,
+    pub http_api_port: u16,
+    /// WebDAV directory the notes file is synced to, e.g. a Nextcloud folder.
+    pub webdav_url: Option<String>,
+    /// The password is the `webdav_password` secret.
+    pub webdav_username: Option<String>,
+    /// Encrypts notes end to end before they reach the WebDAV server, with
+    /// the `sync_passphrase` secret. S3 sync always does.
+    pub sync_encrypted: bool,
+    /// Keeps the data directory in git, committing every save and syncing
+    /// through `git_remote_url` instead of WebDAV.
+    pub git_sync_enabled: bool,
+    pub git_remote_url: Option<String>,
+    /// S3-compatible endpoint (AWS, MinIO, B2) snapshots are synced
+    /// through, e.g. `https://s3.eu-west-1.amazonaws.com`, when no git sync
+    /// is set up. Snapshots are end-to-end encrypted with the
+    /// `sync_passphrase` secret.
+    pub s3_endpoint: Option<String>,
+    pub s3_bucket: Option<String>,
+    pub s3_region: String,
+    /// Paired with the `s3_secret_access_key` secret.
+    pub s3_access_key_id: Option<String>,
+    /// Folder inside the bucket the snapshots go to; empty uses its root.
+    pub s3_prefix: String,
+    /// Minutes between background syncs; `None` syncs only on demand.
+    pub sync_interval_minutes: Option<u32>,
+    /// Stops the fetches jotin makes on its own, such as link previews and
+    /// exchange rates. Sync and AI keep following their own settings.
+    pub offline_mode: bool,
+    /// Lets `convert` fetch daily exchange rates for currency conversions.
+    pub currency_conversion_enabled: bool,
+    /// Allows `summarize_note` and `tidy_note` to send notes to `ai_endpoint_url`.
+    pub ai_enabled: bool,
+    /// OpenAI-compatible API base, e.g. `https://api.openai.com/v1` or a
+    /// local server's. Its key, if it needs one, is the `ai_api_key` secret.
+    pub ai_endpoint_url: Option<String>,
+    pub ai_model: String,
+    /// Stores finished summaries on their notes.
+    pub ai_save_summaries: bool,
+    /// OpenAI-compatible API base whose `/embeddings` powers
+    /// `semantic_search`, e.g. Ollama's `http://localhost:11434/v1`; `None`
+    /// turns semantic search off.
+    pub embedding_endpoint_url: Option<String>,
+    pub embedding_model: String,
+    /// Database notes are sent to, as its id or URL; it must be shared with
+    /// the integration.
+    pub notion_database_id: Option<String>,
+    /// Multi-select property of that database the tags go to; created when
+    /// missing.
+    pub notion_tags_property: String,
+    /// Moves notes to the trash once they are sent to Todoist.
+    pub todoist_trash_sent_notes: bool,
+    /// SMTP server `email_note` sends through; its password is the
+    /// `smtp_password` secret.
+    pub smtp_host: Option<String>,
+    /// `465` connects over TLS, any other port upgrades with STARTTLS.
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    /// Sender address; `None` uses the username.
+    pub smtp_from: Option<String>,
+    /// Endpoint `publish_note` POSTs notes to, e.g. a self-hosted pastebin.
+    pub publish_url: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            capture_shortcut: DEFAULT_CAPTURE_SHORTCUT.to_string(),
+            clipboard_capture_shortcut: DEFAULT_CLIPBOARD_CAPTURE_SHORTCUT.to_string(),
+            main_window_shortcut: DEFAULT_MAIN_WINDOW_SHORTCUT.to_string(),
+            screenshot_capture_shortcut: String::new(),
+            selection_capture_shortcut: String::new(),
+            voice_capture_shortcut: String::new(),
+            whisper_model_path: None,
+            theme: ThemePreference::default(),
+            capture_always_on_top: true,
+            auto_apply_tag_suggestions: false,
+            record_capture_source: false,
+            record_source_url: false,
+            capture_mode: CaptureMode::default(),
+            capture_target: CaptureTarget::default(),
+            append_window_minutes: DEFAULT_APPEND_WINDOW_MINUTES,
+            capture_placement: CapturePlacement::default(),
+            capture_monitor: CaptureMonitor::default(),
+            capture_dismiss: CaptureDismiss::default(),
+            capture_position: None,
+            default_note_sort: NoteSort::default(),
+            tray_recent_action: TrayNoteAction::default(),
+            saved_searches: Vec::new(),
+            data_dir: None,
+            vault_dir: None,
+            vault_capture_folder: DEFAULT_VAULT_CAPTURE_FOLDER.to_string(),
+            backup_retention: DEFAULT_BACKUP_RETENTION,
+            trash_retention_days: None,
+            undo_window_seconds: DEFAULT_UNDO_WINDOW_SECS,
+            clipboard_watcher_enabled: false,
+            do_not_disturb: false,
+            pomodoro_work_minutes: DEFAULT_POMODORO_WORK_MINUTES,
+            pomodoro_break_minutes: DEFAULT_POMODORO_BREAK_MINUTES,
+            pomodoro_long_break_minutes: DEFAULT_POMODORO_LONG_BREAK_MINUTES,
+            http_api_enabled: false,
+            http_api_port: DEFAULT_HTTP_API_PORT,
+            webdav_url: None,
+            webdav_username: None,
+            sync_encrypted: false,
+            git_sync_enabled: false,
+            git_remote_url: None,
+            s3_endpoint: None,
+            s3_bucket: None,
+            s3_region: DEFAULT_S3_REGION.to_string(),
+            s3_access_key_id: None,
+            s3_prefix: DEFAULT_S3_PREFIX.to_string(),
+            sync_interval_minutes: None,
+            offline_mode: false,
+            currency_conversion_enabled: false,
+            ai_enabled: false,
+            ai_endpoint_url: None,
+            ai_model: DEFAULT_AI_MODEL.to_string(),
+            ai_save_summaries: false,
+            embedding_endpoint_url: None,
+            embedding_model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            notion_database_id: None,
+            notion_tags_property: DEFAULT_NOTION_TAGS_PROPERTY.to_string(),
+            todoist_trash_sent_notes: false,
+            smtp_host: None,
+            smtp_port: DEFAULT_SMTP_PORT,
+            smtp_username: None,
+            smtp_from: None,
+            publish_url: None,
+        }
+    }
+}
+
+/// Credentials kept in the system keychain instead of `settings.json`, so
+/// they never reach the settings events, backups or git sync. Settings only
+/// learn whether each one is set, through `has_secret`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Secret {
+    WebdavPassword,
+    S3SecretAccessKey,
+    /// Sent to `ai_endpoint_url`.
+    AiApiKey,
+    /// Internal integration token `send_note_to_notion` authenticates with.
+    NotionToken,
+    /// Personal API token `send_to_todoist` creates tasks with.
+    TodoistToken,
+    /// Personal access token with the `gist` scope, for `share_as_gist`.
+    GithubToken,
+    /// Bearer token sent to the publish endpoint, if it wants one.
+    PublishToken,
+    /// Bearer token every HTTP API request must present; generated on enable.
+    HttpApiToken,
+    /// Password for `smtp_username` on the SMTP server.
+    SmtpPassword,
+    /// End-to-end encrypted sync derives its key from it; every device
+    /// needs the same one.
+    SyncPassphrase,
+}
+
+impl Secret {
+    const ALL: [Self; 10] = [
+        Self::WebdavPassword,
+        Self::S3SecretAccessKey,
+        Self::AiApiKey,
+        Self::NotionToken,
+        Self::TodoistToken,
+        Self::GithubToken,
+        Self::PublishToken,
+        Self::HttpApiToken,
+        Self::SmtpPassword,
+        Self::SyncPassphrase,
+    ];
+
+    /// The settings field it was stored in before moving to the keychain;
+    /// the SMTP password and sync passphrase always lived in the keychain.
+    fn field(self) -> &'static str {
+        match self {
+            Self::WebdavPassword => "webdav_password",
+            Self::S3SecretAccessKey => "s3_secret_access_key",
+            Self::AiApiKey => "ai_api_key",
+            Self::NotionToken => "notion_token",
+            Self::TodoistToken => "todoist_token",
+            Self::GithubToken => "github_token",
+            Self::PublishToken => "publish_token",
+            Self::HttpApiToken => "http_api_token",
+            Self::SmtpPassword => "smtp_password",
+            Self::SyncPassphrase => "sync_passphrase",
+        }
+    }
+
+    /// `webdav-password`, `smtp-password` and so on.
+    fn account(self) -> String {
+        self.field().replace('_', "-")
+    }
+
+    pub(crate) fn get(self) -> Result<Option<String>, String> {
+        keychain::get_secret(&self.account())
+    }
+
+    fn set(self, value: Option<&str>) -> Result<(), String> {
+        // A password or passphrase may start or end with spaces; keys and
+        // tokens do not.
+        let value = value.map(|value| match self {
+            Self::WebdavPassword | Self::SmtpPassword | Self::SyncPassphrase => value,
+            _ => value.trim(),
+        });
+        match value.filter(|value| !value.is_empty()) {
+            Some(value) => keychain::set_secret(&self.account(), value),
+            None => keychain::delete_secret(&self.account()),
+        }
+    }
+}
+
+#[tauri::command]
+pub(crate) fn get_settings(app: AppHandle) -> Result<Settings, String> {
+    load_settings(&app)
+}
+
+/// Saves a secret to the system keychain; `None` or an empty value removes it.
+#[tauri::command]
+pub(crate) fn set_secret(
+    app: AppHandle,
+    secret: Secret,
+    value: Option<String>,
+) -> Result<(), String> {
+    secret.set(value.as_deref())?;
+    if secret == Secret::HttpApiToken {
+        http_api::apply(&app)?;
+    }
+    Ok(())
+}
+
+/// Whether a secret is stored, since settings only ever show that much.
+#[tauri::command]
+pub(crate) fn has_secret(secret: Secret) -> Result<bool, String> {
+    secret.get().map(|value| value.is_some())
+}
+
+/// The HTTP API token, for settings to show when asked; unlike the other
+/// secrets it is generated by the app and has to be copied into scripts.
+#[tauri::command]
+pub(crate) fn reveal_http_api_token() -> Result<Option<String>, String> {
+    Secret::HttpApiToken.get()
+}
+
+/// Moves secrets earlier versions kept in `settings.json` to the keychain
+/// and removes them from the file. One the keychain refuses stays in the
+/// file, unused, until it can be moved.
+pub(crate) fn migrate_secrets(app: &AppHandle) -> Result<(), String> {
+    let _guard = SETTINGS_WRITE_LOCK
+        .lock()
+        .map_err(|_| "Settings lock was poisoned".to_string())?;
+
+    let path = resolve_app_data_file(app, SETTINGS_FILE_NAME)?;
+    let Ok(raw) = fs::read_to_string(&path) else {
+        return Ok(());
+    };
+    let Ok(serde_json::Value::Object(mut fields)) = serde_json::from_str(&raw) else {
+        return Ok(());
+    };
+
+    let mut moved = false;
+    for secret in Secret::ALL {
+        let Some(value) = fields.get(secret.field()) else {
+            continue;
+        };
+        if let Some(value) = value.as_str() {
+            if let Err(error) = secret.set(Some(value)) {
+                eprintln!("Failed to move {} to the keychain: {error}", secret.field());
+                continue;
+            }
+        }
+        fields.remove(secret.field());
+        moved = true;
+    }
+    if !moved {
+        return Ok(());
+    }
+
+    let payload = serde_json::to_string_pretty(&fields)
+        .map_err(|e| format!("Failed to serialize settings payload: {e}"))?;
+    shred::rewrite_in_place(&path, &payload)
+        .map_err(|e| format!("Failed to write settings file: {e}"))
+}
+
+/// Validates and applies a full settings payload, then persists it.
+#[tauri::command]
+pub(crate) fn update_settings(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    settings: Settings,
+) -> Result<Settings, String> {
+    let mut settings = settings;
+    // Registering waits until the settings are saved, but an accelerator
+    // that does not parse is refused before anything is written.
+    for (position, (field, _)) in SHORTCUT_FIELDS.iter().enumerate() {
+        let accelerator = field(&mut settings);
+        *accelerator = accelerator.trim().to_string();
+        if position == 0 || !accelerator.is_empty() {
+            shortcuts::parse_shortcut(accelerator)?;
+        }
+    }
+    normalize_optional(&mut settings.whisper_model_path);
+    settings.trash_retention_days = settings.trash_retention_days.filter(|days| *days > 0);
+    normalize_optional(&mut settings.data_dir);
+    normalize_optional(&mut settings.vault_dir);
+    settings.vault_capture_folder = settings
+        .vault_capture_folder
+        .trim()
+        .trim_matches(['/', '\\'])
+        .to_string();
+    normalize_optional(&mut settings.webdav_url);
+    normalize_optional(&mut settings.git_remote_url);
+    settings.s3_endpoint = settings
+        .s3_endpoint
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty());
+    normalize_optional(&mut settings.s3_bucket);
+    settings.s3_region = settings.s3_region.trim().to_string();
+    if settings.s3_region.is_empty() {
+        settings.s3_region = DEFAULT_S3_REGION.to_string();
+    }
+    normalize_optional(&mut settings.s3_access_key_id);
+    settings.s3_prefix = settings.s3_prefix.trim().trim_matches('/').to_string();
+    settings.sync_interval_minutes = settings
+        .sync_interval_minutes
+        .filter(|minutes| *minutes > 0);
+    normalize_optional(&mut settings.ai_endpoint_url);
+    settings.ai_model = settings.ai_model.trim().to_string();
+    if settings.ai_model.is_empty() {
+        settings.ai_model = DEFAULT_AI_MODEL.to_string();
+    }
+    normalize_optional(&mut settings.embedding_endpoint_url);
+    settings.embedding_model = settings.embedding_model.trim().to_string();
+    if settings.embedding_model.is_empty() {
+        settings.embedding_model = DEFAULT_EMBEDDING_MODEL.to_string();
+    }
+    normalize_optional(&mut settings.notion_database_id);
+    settings.notion_tags_property = settings.notion_tags_property.trim().to_string();
+    if settings.notion_tags_property.is_empty() {
+        settings.notion_tags_property = DEFAULT_NOTION_TAGS_PROPERTY.to_string();
+    }
+    normalize_optional(&mut settings.smtp_host);
+    if settings.smtp_port == 0 {
+        settings.smtp_port = DEFAULT_SMTP_PORT;
+    }
+    normalize_optional(&mut settings.smtp_username);
+    normalize_optional(&mut settings.smtp_from);
+    normalize_optional(&mut settings.publish_url);
+
+    let mut previous = load_settings(&app)?;
+    let mut updated = modify_settings(&app, |current| {
+        *current = settings;
+        Ok(())
+    })?;
+
+    // A shortcut that cannot be registered, e.g. because another app owns
+    // it, is put back to the one still bound; the other changes stand.
+    let mut shortcut_error = None;
+    for (field, apply) in SHORTCUT_FIELDS {
+        let accelerator = field(&mut updated).clone();
+        let bound = field(&mut previous).clone();
+        if accelerator == bound {
+            continue;
+        }
+        if let Err(error) = apply(&app, &accelerator) {
+            updated = modify_settings(&app, |current| {
+                *field(current) = bound;
+                Ok(())
+            })?;
+            shortcut_error.get_or_insert(error);
+        }
+    }
+
+    if previous.capture_always_on_top != updated.capture_always_on_top {
+        apply_capture_always_on_top(&app, updated.capture_always_on_top);
+    }
+    if previous.theme != updated.theme {
+        theme::apply(&app, updated.theme);
+    }
+    if previous.saved_searches != updated.saved_searches {
+        tray::refresh(&app);
+    }
+    backup::set_retention(updated.backup_retention);
+    if previous.data_dir != updated.data_dir || previous.vault_dir != updated.vault_dir {
+        state.invalidate_search_index();
+    }
+    if previous.clipboard_watcher_enabled != updated.clipboard_watcher_enabled {
+        clipboard_watcher::apply(&app, updated.clipboard_watcher_enabled);
+    }
+    if previous.do_not_disturb != updated.do_not_disturb {
+        notifications::apply(&app, updated.do_not_disturb);
+    }
+    if updated.http_api_enabled && Secret::HttpApiToken.get()?.is_none() {
+        Secret::HttpApiToken.set(Some(&Uuid::new_v4().simple().to_string()))?;
+    }
+    if previous.http_api_enabled != updated.http_api_enabled
+        || previous.http_api_port != updated.http_api_port
+    {
+        http_api::apply(&app)?;
+    }
+    if previous.git_sync_enabled != updated.git_sync_enabled
+        || previous.git_remote_url != updated.git_remote_url
+        || previous.data_dir != updated.data_dir
+        || previous.vault_dir != updated.vault_dir
+    {
+        git_sync::apply(&app)?;
+    }
+    if previous.trash_retention_days != updated.trash_retention_days {
+        purge_expired_trash(&app, &state)?;
+    }
+
+    match shortcut_error {
+        Some(error) => Err(error),
+        None => Ok(updated),
+    }
+}
+
+/// Trims an optional text setting, treating a blank one as unset.
+fn normalize_optional(value: &mut Option<String>) {
+    *value = value
+        .take()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+}
+
+#[tauri::command]
+pub(crate) fn set_capture_always_on_top(app: AppHandle, enabled: bool) -> Result<bool, String> {
+    modify_settings(&app, |settings| {
+        settings.capture_always_on_top = enabled;
+        Ok(())
+    })?;
+
+    apply_capture_always_on_top(&app, enabled);
+    Ok(enabled)
+}
+
+/// Updates an open capture window and the tray check mark without persisting.
+fn apply_capture_always_on_top(app: &AppHandle, enabled: bool) {
+    if let Some(window) = app.get_webview_window(CAPTURE_WINDOW_LABEL) {
+        let _ = window.set_always_on_top(enabled || dismiss::is_pinned());
+    }
+    tray::refresh(app);
+}
+
+pub(crate) fn load_settings(app: &AppHandle) -> Result<Settings, String> {
+    load_settings_from_path(&resolve_app_data_file(app, SETTINGS_FILE_NAME)?)
+}
+
+pub(crate) fn load_settings_from_path(path: &Path) -> Result<Settings, String> {
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+
+    let raw = fs::read_to_string(path).map_err(|e| format!("Failed to read settings file: {e}"))?;
+    if raw.trim().is_empty() {
+        return Ok(Settings::default());
+    }
+
+    serde_json::from_str::<Settings>(&raw)
+        .map_err(|e| format!("Failed to parse settings file: {e}"))
+}
+
+/// Read-modify-write of `settings.json` under a lock, broadcasting the result
+/// to every window through `settings-changed`.
+pub(crate) fn modify_settings(
+    app: &AppHandle,
+    apply: impl FnOnce(&mut Settings) -> Result<(), String>,
+) -> Result<Settings, String> {
+    let _guard = SETTINGS_WRITE_LOCK
+        .lock()
+        .map_err(|_| "Settings lock was poisoned".to_string())?;
+
+    let mut settings = load_settings(app)?;
+    apply(&mut settings)?;
+
+    let path = resolve_app_data_file(app, SETTINGS_FILE_NAME)?;
+    let payload = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize settings payload: {e}"))?;
+    write_file_atomically(&path, &payload)?;
+
+    let _ = app.emit(SETTINGS_CHANGED_EVENT, &settings);
+    Ok(settings)
+}
@@ -0,0 +1,389 @@
+use std::{
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tauri::AppHandle;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::{
+    capture_source, clipboard_watcher, hide_capture_window, screenshot, selection, settings,
+    show_capture_window, show_main_window, templates, toggle_main_window, voice,
+};
+
+pub(crate) const DEFAULT_CAPTURE_SHORTCUT: &str = "Ctrl+Alt+N";
+pub(crate) const DEFAULT_CLIPBOARD_CAPTURE_SHORTCUT: &str = "Ctrl+Alt+V";
+pub(crate) const DEFAULT_MAIN_WINDOW_SHORTCUT: &str = "Ctrl+Alt+J";
+const DOUBLE_SHORTCUT_WINDOW_MS: u64 = 450;
+static LAST_SHORTCUT_PRESS: Mutex<Option<Instant>> = Mutex::new(None);
+static CAPTURE: Binding = Binding {
+    name: "capture",
+    current: Mutex::new(None),
+    on_press: handle_capture_shortcut,
+    on_release: None,
+};
+static CLIPBOARD_CAPTURE: Binding = Binding {
+    name: "clipboard capture",
+    current: Mutex::new(None),
+    on_press: clipboard_watcher::capture_clipboard,
+    on_release: None,
+};
+static MAIN_WINDOW: Binding = Binding {
+    name: "main window",
+    current: Mutex::new(None),
+    on_press: toggle_main_window,
+    on_release: None,
+};
+static SCREENSHOT_CAPTURE: Binding = Binding {
+    name: "screenshot capture",
+    current: Mutex::new(None),
+    on_press: screenshot::capture_region,
+    on_release: None,
+};
+static SELECTION_CAPTURE: Binding = Binding {
+    name: "selection capture",
+    current: Mutex::new(None),
+    on_press: selection::capture_selection,
+    on_release: None,
+};
+/// Push-to-talk: recording lasts as long as the shortcut is held.
+static VOICE_CAPTURE: Binding = Binding {
+    name: "voice capture",
+    current: Mutex::new(None),
+    on_press: voice::start_push_to_talk,
+    on_release: Some(voice::stop_push_to_talk),
+};
+/// Accelerators currently bound to capture templates.
+static TEMPLATE_SHORTCUTS: Mutex<Vec<Shortcut>> = Mutex::new(Vec::new());
+
+/// One of the app's global shortcuts and the accelerator it is bound to.
+struct Binding {
+    name: &'static str,
+    current: Mutex<Option<Shortcut>>,
+    on_press: fn(&AppHandle),
+    on_release: Option<fn(&AppHandle)>,
+}
+
+/// Validates an accelerator such as `Ctrl+Alt+N` or `CmdOrCtrl+Shift+Space`.
+pub(crate) fn parse_shortcut(accelerator: &str) -> Result<Shortcut, String> {
+    Shortcut::from_str(accelerator.trim())
+        .map_err(|e| format!("Invalid shortcut \"{}\": {e}", accelerator.trim()))
+}
+
+/// Swaps the quick-capture shortcut at runtime and persists it.
+#[tauri::command]
+pub(crate) fn set_capture_shortcut(app: AppHandle, accelerator: String) -> Result<String, String> {
+    let accelerator = accelerator.trim().to_string();
+    apply_capture_shortcut(&app, &accelerator)?;
+
+    let settings = settings::modify_settings(&app, |settings| {
+        settings.capture_shortcut = accelerator;
+        Ok(())
+    })?;
+    Ok(settings.capture_shortcut)
+}
+
+/// Swaps the shortcut that saves the clipboard as a note and persists it. An
+/// empty accelerator turns the shortcut off.
+#[tauri::command]
+pub(crate) fn set_clipboard_capture_shortcut(
+    app: AppHandle,
+    accelerator: String,
+) -> Result<String, String> {
+    let accelerator = accelerator.trim().to_string();
+    apply_clipboard_capture_shortcut(&app, &accelerator)?;
+
+    let settings = settings::modify_settings(&app, |settings| {
+        settings.clipboard_capture_shortcut = accelerator;
+        Ok(())
+    })?;
+    Ok(settings.clipboard_capture_shortcut)
+}
+
+/// Swaps the shortcut that shows or hides the main window and persists it.
+/// An empty accelerator turns the shortcut off.
+#[tauri::command]
+pub(crate) fn set_main_window_shortcut(
+    app: AppHandle,
+    accelerator: String,
+) -> Result<String, String> {
+    let accelerator = accelerator.trim().to_string();
+    apply_main_window_shortcut(&app, &accelerator)?;
+
+    let settings = settings::modify_settings(&app, |settings| {
+        settings.main_window_shortcut = accelerator;
+        Ok(())
+    })?;
+    Ok(settings.main_window_shortcut)
+}
+
+/// Swaps the shortcut that captures a screen region as a note and persists
+/// it. An empty accelerator turns the shortcut off.
+#[tauri::command]
+pub(crate) fn set_screenshot_capture_shortcut(
+    app: AppHandle,
+    accelerator: String,
+) -> Result<String, String> {
+    let accelerator = accelerator.trim().to_string();
+    apply_screenshot_capture_shortcut(&app, &accelerator)?;
+
+    let settings = settings::modify_settings(&app, |settings| {
+        settings.screenshot_capture_shortcut = accelerator;
+        Ok(())
+    })?;
+    Ok(settings.screenshot_capture_shortcut)
+}
+
+/// Swaps the shortcut that saves the selected text as a note and persists
+/// it. An empty accelerator turns the shortcut off.
+#[tauri::command]
+pub(crate) fn set_selection_capture_shortcut(
+    app: AppHandle,
+    accelerator: String,
+) -> Result<String, String> {
+    let accelerator = accelerator.trim().to_string();
+    apply_selection_capture_shortcut(&app, &accelerator)?;
+
+    let settings = settings::modify_settings(&app, |settings| {
+        settings.selection_capture_shortcut = accelerator;
+        Ok(())
+    })?;
+    Ok(settings.selection_capture_shortcut)
+}
+
+/// Swaps the push-to-talk voice note shortcut and persists it. An empty
+/// accelerator turns the shortcut off.
+#[tauri::command]
+pub(crate) fn set_voice_capture_shortcut(
+    app: AppHandle,
+    accelerator: String,
+) -> Result<String, String> {
+    let accelerator = accelerator.trim().to_string();
+    apply_voice_capture_shortcut(&app, &accelerator)?;
+
+    let settings = settings::modify_settings(&app, |settings| {
+        settings.voice_capture_shortcut = accelerator;
+        Ok(())
+    })?;
+    Ok(settings.voice_capture_shortcut)
+}
+
+/// Rebinds quick capture without persisting anything.
+pub(crate) fn apply_capture_shortcut(app: &AppHandle, accelerator: &str) -> Result<(), String> {
+    rebind(app, &CAPTURE, Some(parse_shortcut(accelerator)?))
+}
+
+/// Rebinds clipboard capture without persisting anything.
+pub(crate) fn apply_clipboard_capture_shortcut(
+    app: &AppHandle,
+    accelerator: &str,
+) -> Result<(), String> {
+    rebind(
+        app,
+        &CLIPBOARD_CAPTURE,
+        parse_optional_shortcut(accelerator)?,
+    )
+}
+
+/// Rebinds the main window toggle without persisting anything.
+pub(crate) fn apply_main_window_shortcut(app: &AppHandle, accelerator: &str) -> Result<(), String> {
+    rebind(app, &MAIN_WINDOW, parse_optional_shortcut(accelerator)?)
+}
+
+/// Rebinds screenshot capture without persisting anything.
+pub(crate) fn apply_screenshot_capture_shortcut(
+    app: &AppHandle,
+    accelerator: &str,
+) -> Result<(), String> {
+    rebind(
+        app,
+        &SCREENSHOT_CAPTURE,
+        parse_optional_shortcut(accelerator)?,
+    )
+}
+
+/// Rebinds selection capture without persisting anything.
+pub(crate) fn apply_selection_capture_shortcut(
+    app: &AppHandle,
+    accelerator: &str,
+) -> Result<(), String> {
+    rebind(
+        app,
+        &SELECTION_CAPTURE,
+        parse_optional_shortcut(accelerator)?,
+    )
+}
+
+/// Rebinds push-to-talk without persisting anything.
+pub(crate) fn apply_voice_capture_shortcut(
+    app: &AppHandle,
+    accelerator: &str,
+) -> Result<(), String> {
+    rebind(app, &VOICE_CAPTURE, parse_optional_shortcut(accelerator)?)
+}
+
+/// Replaces every template shortcut with `bindings` of template id and
+/// accelerator. One that cannot be registered, e.g. because another app owns
+/// it, is logged and skipped so the rest still work.
+pub(crate) fn apply_template_shortcuts(app: &AppHandle, bindings: &[(String, String)]) {
+    let Ok(mut current) = TEMPLATE_SHORTCUTS.lock() else {
+        return;
+    };
+    for shortcut in current.drain(..) {
+        if let Err(error) = app.global_shortcut().unregister(shortcut) {
+            eprintln!("Failed to unregister template shortcut: {error}");
+        }
+    }
+
+    for (template_id, accelerator) in bindings {
+        let shortcut = match parse_shortcut(accelerator) {
+            Ok(shortcut) => shortcut,
+            Err(error) => {
+                eprintln!("{error}");
+                continue;
+            }
+        };
+        let template_id = template_id.clone();
+        let registered =
+            app.global_shortcut()
+                .on_shortcut(shortcut, move |app, _shortcut, event| {
+                    if event.state() == ShortcutState::Pressed {
+                        templates::capture_from_template(app, &template_id);
+                    }
+                });
+        match registered {
+            Ok(()) => current.push(shortcut),
+            Err(error) => eprintln!("Failed to register template shortcut {accelerator}: {error}"),
+        }
+    }
+}
+
+/// Secondary shortcuts can be turned off with an empty accelerator.
+fn parse_optional_shortcut(accelerator: &str) -> Result<Option<Shortcut>, String> {
+    if accelerator.trim().is_empty() {
+        Ok(None)
+    } else {
+        parse_shortcut(accelerator).map(Some)
+    }
+}
+
+/// The new shortcut is registered before the old one is released, so a
+/// conflicting accelerator leaves the current binding working.
+fn rebind(
+    app: &AppHandle,
+    binding: &'static Binding,
+    shortcut: Option<Shortcut>,
+) -> Result<(), String> {
+    let previous = *binding
+        .current
+        .lock()
+        .map_err(|_| "Shortcut lock was poisoned".to_string())?;
+
+    if previous == shortcut {
+        return Ok(());
+    }
+
+    match shortcut {
+        Some(shortcut) => register(app, binding, shortcut)?,
+        None => {
+            if let Ok(mut current) = binding.current.lock() {
+                *current = None;
+            }
+        }
+    }
+    if let Some(previous) = previous {
+        if let Err(error) = app.global_shortcut().unregister(previous) {
+            eprintln!(
+                "Failed to unregister previous {} shortcut: {error}",
+                binding.name
+            );
+        }
+    }
+    Ok(())
+}
+
+fn register(app: &AppHandle, binding: &'static Binding, shortcut: Shortcut) -> Result<(), String> {
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _shortcut, event| {
+            match (event.state(), binding.on_release) {
+                (ShortcutState::Pressed, _) => (binding.on_press)(app),
+                (ShortcutState::Released, Some(on_release)) => on_release(app),
+                (ShortcutState::Released, None) => {}
+            }
+        })
+        .map_err(|e| format!("Failed to register {} shortcut: {e}", binding.name))?;
+
+    if let Ok(mut current) = binding.current.lock() {
+        *current = Some(shortcut);
+    }
+    Ok(())
+}
+
+fn handle_capture_shortcut(app: &AppHandle) {
+    let now = Instant::now();
+    let is_double_press = LAST_SHORTCUT_PRESS
+        .lock()
+        .map(|mut last_press| {
+            let was_recent = last_press
+                .map(|previous| {
+                    now.duration_since(previous) <= Duration::from_millis(DOUBLE_SHORTCUT_WINDOW_MS)
+                })
+                .unwrap_or(false);
+
+            if was_recent {
+                *last_press = None;
+            } else {
+                *last_press = Some(now);
+            }
+
+            was_recent
+        })
+        .unwrap_or(false);
+
+    if is_double_press {
+        show_main_window(app);
+        let _ = hide_capture_window(app);
+    } else {
+        capture_source::remember(app);
+        if let Err(error) = show_capture_window(app) {
+            eprintln!("{error}");
+        }
+    }
+}
+
+pub(crate) fn setup_global_shortcut(app: &AppHandle) -> tauri::Result<()> {
+    let settings = settings::load_settings(app).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        settings::Settings::default()
+    });
+
+    let shortcut = parse_shortcut(&settings.capture_shortcut).unwrap_or_else(|error| {
+        eprintln!("{error}; falling back to {DEFAULT_CAPTURE_SHORTCUT}");
+        parse_shortcut(DEFAULT_CAPTURE_SHORTCUT).expect("default capture shortcut is valid")
+    });
+    register(app, &CAPTURE, shortcut).map_err(|e| tauri::Error::from(std::io::Error::other(e)))?;
+
+    // Secondary shortcuts often collide with other apps; quick capture must
+    // still work when they do.
+    if let Err(error) = apply_clipboard_capture_shortcut(app, &settings.clipboard_capture_shortcut)
+    {
+        eprintln!("{error}");
+    }
+    if let Err(error) = apply_main_window_shortcut(app, &settings.main_window_shortcut) {
+        eprintln!("{error}");
+    }
+    if let Err(error) =
+        apply_screenshot_capture_shortcut(app, &settings.screenshot_capture_shortcut)
+    {
+        eprintln!("{error}");
+    }
+    if let Err(error) = apply_selection_capture_shortcut(app, &settings.selection_capture_shortcut)
+    {
+        eprintln!("{error}");
+    }
+    if let Err(error) = apply_voice_capture_shortcut(app, &settings.voice_capture_shortcut) {
+        eprintln!("{error}");
+    }
+    Ok(())
+}
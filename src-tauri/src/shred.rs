@@ -0,0 +1,154 @@
+//! Secure deletion of purged notes. Dropping a note from the notes file
+//! leaves its text in the old file's freed disk blocks, in rotating backups,
+//! in its history and sync state, and in attachments only it used. Purging
+//! overwrites each of those in place before removing them.
+//!
+//! This is best effort: SSDs, copy-on-write filesystems (APFS, Btrfs) and
+//! snapshots may still keep old blocks, and copies already synced, exported
+//! or committed by git sync are out of reach.
+
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    attachments, backup, encryption, history, schema, sync, undo, Note, StorageGuard, StorageState,
+    NOTES_CHANGED_EVENT,
+};
+
+const OVERWRITE_CHUNK: usize = 64 * 1024;
+
+/// Permanently and securely deletes a note, trashed or not. Cannot be
+/// undone; see the module docs for what "securely" can promise.
+#[tauri::command]
+pub(crate) fn shred_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<(), String> {
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    let position = notes
+        .iter()
+        .position(|note| note.id == id)
+        .ok_or_else(|| "Note not found".to_string())?;
+    let shredded = notes.remove(position);
+
+    purge(&app, &storage, &notes, &[shredded])?;
+    state.update_search_index(|index| index.remove(&id));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(())
+}
+
+/// Saves `notes`, which no longer contain `purged`, scrubbing every copy of
+/// the purged notes jotin keeps. Everything after the save is logged rather
+/// than returned: the notes are gone either way.
+pub(crate) fn purge(
+    app: &AppHandle,
+    storage: &StorageGuard<'_>,
+    notes: &[Note],
+    purged: &[Note],
+) -> Result<(), String> {
+    save_in_place(storage, notes)?;
+
+    let ids: HashSet<&str> = purged.iter().map(|note| note.id.as_str()).collect();
+    backup::scrub_notes(&storage.notes_path, &ids);
+    sync::forget_notes(app, &ids);
+    undo::forget_notes(&ids);
+    for note in purged {
+        if let Err(error) = history::forget(app, &note.id) {
+            eprintln!("Failed to remove history for {}: {error}", note.id);
+        }
+    }
+    attachments::remove_orphaned_blobs(app, notes);
+    history::remove_orphaned_histories(app, notes);
+    if let Some(vault) = &storage.vault {
+        for path in vault.trashed_files(&ids) {
+            if let Err(error) = destroy_file(&path) {
+                eprintln!("Failed to remove {}: {error}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Saves `notes`, in which `locked` were just sealed, scrubbing their old
+/// text from every copy jotin keeps. Backups keep the sealed notes; the
+/// sync merge base drops them, so the next sync pushes them by timestamp.
+pub(crate) fn scrub_locked(
+    app: &AppHandle,
+    storage: &StorageGuard<'_>,
+    notes: &[Note],
+    locked: &[Note],
+) -> Result<(), String> {
+    save_in_place(storage, notes)?;
+
+    let ids: HashSet<&str> = locked.iter().map(|note| note.id.as_str()).collect();
+    backup::replace_notes(&storage.notes_path, locked);
+    sync::forget_notes(app, &ids);
+    undo::forget_notes(&ids);
+    for note in locked {
+        if let Err(error) = history::forget(app, &note.id) {
+            eprintln!("Failed to remove history for {}: {error}", note.id);
+        }
+    }
+    Ok(())
+}
+
+fn save_in_place(storage: &StorageGuard<'_>, notes: &[Note]) -> Result<(), String> {
+    let payload = encryption::seal(schema::serialize(notes)?)?;
+    // The regular save replaces the file by renaming, which would leave the
+    // old contents behind; overwrite them first.
+    if let Err(error) = rewrite_in_place(&storage.notes_path, &payload) {
+        eprintln!("Failed to overwrite the notes file: {error}");
+    }
+    storage.save_notes(notes)
+}
+
+/// Overwrites a file with zeros, flushes it to disk and removes it.
+pub(crate) fn destroy_file(path: &Path) -> io::Result<()> {
+    let len = fs::metadata(path)?.len();
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    write_filler(&mut file, len, 0)?;
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)
+}
+
+/// Replaces a JSON file's contents within the same file, so its old bytes
+/// are overwritten rather than freed. JSON allows trailing whitespace, so
+/// the file stays readable while the padding is written.
+pub(crate) fn rewrite_in_place(path: &Path, payload: &str) -> io::Result<()> {
+    let mut file = match OpenOptions::new().write(true).open(path) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        file => file?,
+    };
+    let old_len = file.metadata()?.len();
+    file.write_all(payload.as_bytes())?;
+    write_filler(
+        &mut file,
+        old_len.saturating_sub(payload.len() as u64),
+        b' ',
+    )?;
+    file.sync_all()?;
+    file.set_len(payload.len() as u64)?;
+    file.sync_all()
+}
+
+fn write_filler(file: &mut fs::File, len: u64, byte: u8) -> io::Result<()> {
+    let chunk = [byte; OVERWRITE_CHUNK];
+    let mut left = len;
+    while left > 0 {
+        let size = left.min(OVERWRITE_CHUNK as u64) as usize;
+        file.write_all(&chunk[..size])?;
+        left -= size as u64;
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Imports Simplenote's export: the zip it emails, the `notes.json` inside
+//! it, or the unzipped folder. Tags, pins, trash and dates carry over.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use serde::Deserialize;
+use tauri::{AppHandle, State};
+
+use crate::{
+    import::{self, ImportSummary, ImportedNote},
+    normalize_tags, zip, Note, StorageState,
+};
+
+const NOTES_JSON_FILE_NAME: &str = "notes.json";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SimplenoteExport {
+    active_notes: Vec<SimplenoteNote>,
+    trashed_notes: Vec<SimplenoteNote>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SimplenoteNote {
+    id: String,
+    content: String,
+    creation_date: Option<String>,
+    last_modified: Option<String>,
+    tags: Vec<String>,
+    pinned: bool,
+}
+
+/// Imports a Simplenote export at `path`. Trashed notes land in the trash.
+/// Notes already present are skipped, so importing twice is harmless.
+#[tauri::command]
+pub(crate) fn import_simplenote(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    path: String,
+) -> Result<ImportSummary, String> {
+    let raw = read_notes_json(&PathBuf::from(path.trim()))?;
+    let export: SimplenoteExport = serde_json::from_str(&raw)
+        .map_err(|e| format!("Failed to parse Simplenote export: {e}"))?;
+
+    let active = export.active_notes.into_iter().map(|note| (note, false));
+    let trashed = export.trashed_notes.into_iter().map(|note| (note, true));
+    let imported = active
+        .chain(trashed)
+        .map(|(note, trashed)| to_imported(note, trashed))
+        .collect();
+
+    import::store_imported(&app, &state, imported, ImportSummary::default())
+}
+
+fn read_notes_json(path: &Path) -> Result<String, String> {
+    if path.is_dir() {
+        let candidates = [
+            path.join("source").join(NOTES_JSON_FILE_NAME),
+            path.join(NOTES_JSON_FILE_NAME),
+        ];
+        let file = candidates
+            .iter()
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| format!("No {NOTES_JSON_FILE_NAME} in {}", path.display()))?;
+        return fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read {}: {e}", file.display()));
+    }
+
+    let is_zip = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"));
+    if !is_zip {
+        return fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()));
+    }
+
+    let archive = fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let (_, data) = zip::read_entries(&archive)?
+        .into_iter()
+        .find(|(name, _)| name.rsplit('/').next() == Some(NOTES_JSON_FILE_NAME))
+        .ok_or_else(|| format!("No {NOTES_JSON_FILE_NAME} in {}", path.display()))?;
+    String::from_utf8(data).map_err(|e| format!("Failed to read {NOTES_JSON_FILE_NAME}: {e}"))
+}
+
+fn to_imported(simplenote: SimplenoteNote, trashed: bool) -> ImportedNote {
+    let created_at = simplenote
+        .creation_date
+        .as_deref()
+        .and_then(import::normalize_timestamp);
+    let updated_at = simplenote
+        .last_modified
+        .as_deref()
+        .and_then(import::normalize_timestamp);
+
+    let mut note = Note::new(simplenote.content.trim().to_string());
+    if let Some(created_at) = created_at {
+        note.created_at = created_at;
+    }
+    // Simplenote does not record when a note was trashed; its last change
+    // is the closest there is.
+    if trashed {
+        note.deleted_at = Some(
+            updated_at
+                .clone()
+                .unwrap_or_else(|| Utc::now().to_rfc3339()),
+        );
+    }
+    note.updated_at = updated_at.filter(|updated_at| *updated_at != note.created_at);
+    note.tags = normalize_tags(simplenote.tags);
+    note.pinned = simplenote.pinned && !trashed;
+
+    let source = match note.title() {
+        "" => simplenote.id,
+        title => title.to_string(),
+    };
+    ImportedNote { source, note }
+}
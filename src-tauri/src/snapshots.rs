@@ -0,0 +1,307 @@
+//! Offline snapshots of linked pages, for notes that opt in. Each http(s)
+//! link is fetched once, reduced to its readable text (the article or main
+//! content without scripts, navigation and the like) and attached to the
+//! note as a Markdown file, so a captured link stays useful after the page
+//! is gone. Fetches run one at a time from a queue and respect offline mode.
+
+use std::{collections::VecDeque, sync::Mutex, thread};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+
+use crate::{
+    attachments, find_active_note_mut, settings, unfurl, Note, StorageState, NOTES_CHANGED_EVENT,
+};
+
+const MAX_PAGE_BYTES: u64 = 2 * 1024 * 1024;
+const MAX_SNAPSHOT_CHARS: usize = 100_000;
+const MAX_QUEUED: usize = 50;
+/// Elements whose content is never part of the readable text.
+const SKIPPED_ELEMENTS: [&str; 10] = [
+    "script", "style", "noscript", "template", "svg", "nav", "header", "footer", "aside", "form",
+];
+const BLOCK_ELEMENTS: [&str; 14] = [
+    "p",
+    "div",
+    "br",
+    "section",
+    "article",
+    "blockquote",
+    "pre",
+    "table",
+    "tr",
+    "ul",
+    "ol",
+    "dl",
+    "figure",
+    "hr",
+];
+
+static QUEUE: Mutex<Queue> = Mutex::new(Queue {
+    jobs: VecDeque::new(),
+    running: false,
+});
+
+struct Queue {
+    jobs: VecDeque<Job>,
+    /// Whether a worker thread is draining `jobs`.
+    running: bool,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+struct Job {
+    note_id: String,
+    url: String,
+}
+
+/// Which attachment holds the snapshot of which link.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct LinkSnapshot {
+    url: String,
+    hash: String,
+    saved_at: String,
+}
+
+/// Turns snapshots on or off for a note. Turning them off keeps the
+/// snapshots already attached.
+#[tauri::command]
+pub(crate) fn set_link_snapshots(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    enabled: bool,
+) -> Result<Note, String> {
+    let storage = state.lock(&app)?;
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, &id)?;
+    note.snapshot_links = enabled;
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+    queue(&app, &updated);
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(updated)
+}
+
+/// Queues the note's links that have no snapshot yet.
+pub(crate) fn queue(app: &AppHandle, note: &Note) {
+    if !note.snapshot_links {
+        return;
+    }
+    if settings::load_settings(app).is_ok_and(|settings| settings.offline_mode) {
+        return;
+    }
+    let Ok(mut queue) = QUEUE.lock() else {
+        return;
+    };
+
+    for url in unfurl::links(note) {
+        let job = Job {
+            note_id: note.id.clone(),
+            url,
+        };
+        if has_snapshot(note, &job.url) || queue.jobs.contains(&job) {
+            continue;
+        }
+        if queue.jobs.len() >= MAX_QUEUED {
+            eprintln!("Link snapshot queue is full; skipping {}", job.url);
+            continue;
+        }
+        queue.jobs.push_back(job);
+    }
+
+    if !queue.running && !queue.jobs.is_empty() {
+        queue.running = true;
+        let app = app.clone();
+        thread::spawn(move || work(&app));
+    }
+}
+
+fn work(app: &AppHandle) {
+    loop {
+        let job = match QUEUE.lock() {
+            Ok(mut queue) => match queue.jobs.pop_front() {
+                Some(job) => job,
+                None => {
+                    queue.running = false;
+                    return;
+                }
+            },
+            Err(_) => return,
+        };
+        if let Err(error) = snapshot(app, &job) {
+            eprintln!("Failed to snapshot {}: {error}", job.url);
+        }
+    }
+}
+
+fn snapshot(app: &AppHandle, job: &Job) -> Result<(), String> {
+    let page = unfurl::fetch(&job.url, MAX_PAGE_BYTES)
+        .ok_or_else(|| "The page could not be fetched".to_string())?;
+    let html = String::from_utf8_lossy(&page);
+    let title = unfurl::page_title(&html);
+    let text = readable_text(&html);
+    if text.is_empty() {
+        return Err("The page has no readable text".to_string());
+    }
+
+    let saved_at = Utc::now();
+    let markdown = format!(
+        "# {}\n\nSource: <{}>\nSaved: {}\n\n{text}\n",
+        title.as_deref().unwrap_or(&job.url),
+        job.url,
+        saved_at.format("%Y-%m-%d %H:%M UTC"),
+    );
+    let (hash, size) = attachments::store_bytes(app, markdown.as_bytes())?;
+
+    let state = app.state::<StorageState>();
+    let storage = state.lock(app)?;
+    let mut notes = storage.load_notes()?;
+    let Some(note) = notes
+        .iter_mut()
+        .find(|note| note.id == job.note_id && !note.is_trashed())
+    else {
+        return Ok(());
+    };
+    // The note changed while the page was fetched.
+    if !note.snapshot_links
+        || !unfurl::links(note).contains(&job.url)
+        || has_snapshot(note, &job.url)
+    {
+        return Ok(());
+    }
+
+    note.attachments.push(attachments::new_attachment(
+        file_name(&job.url),
+        hash.clone(),
+        size,
+    ));
+    note.link_snapshots
+        .retain(|snapshot| snapshot.url != job.url);
+    note.link_snapshots.push(LinkSnapshot {
+        url: job.url.clone(),
+        hash,
+        saved_at: saved_at.to_rfc3339(),
+    });
+    let updated = note.clone();
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(())
+}
+
+/// A snapshot only counts while its attachment is still on the note.
+fn has_snapshot(note: &Note, url: &str) -> bool {
+    note.link_snapshots.iter().any(|snapshot| {
+        snapshot.url == url
+            && note
+                .attachments
+                .iter()
+                .any(|attachment| attachment.hash() == snapshot.hash)
+    })
+}
+
+/// `snapshot-example.com-some-page.md`
+fn file_name(url: &str) -> String {
+    let slug: String = Url::parse(url)
+        .map(|url| format!("{}{}", url.host_str().unwrap_or_default(), url.path()))
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .take(80)
+        .collect();
+    format!("snapshot-{}.md", slug.trim_matches(['-', '.']))
+}
+
+/// The page's article, main content or body as plain text, with headings,
+/// paragraphs and list items kept on lines of their own.
+fn readable_text(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let (start, end) = ["article", "main", "body"]
+        .iter()
+        .find_map(|name| element_content(&lower, name))
+        .unwrap_or((0, html.len()));
+
+    let mut text = String::new();
+    let mut position = start;
+    while position < end {
+        let Some(offset) = lower[position..end].find('<') else {
+            text.push_str(&html[position..end]);
+            break;
+        };
+        text.push_str(&html[position..position + offset]);
+        let tag_start = position + offset;
+        let Some(tag_len) = lower[tag_start..end].find('>') else {
+            break;
+        };
+        let tag = &lower[tag_start + 1..tag_start + tag_len];
+        position = tag_start + tag_len + 1;
+
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        if tag.starts_with('!') {
+            // Comments may contain `>`; skip to their real end.
+            if tag.starts_with("!--") {
+                position = lower[tag_start..end]
+                    .find("-->")
+                    .map_or(end, |close| tag_start + close + 3);
+            }
+            continue;
+        }
+        if !tag.starts_with('/') && SKIPPED_ELEMENTS.contains(&name.as_str()) {
+            position = lower[position..end]
+                .find(&format!("</{name}"))
+                .map_or(end, |close| position + close);
+            continue;
+        }
+        match name.as_str() {
+            "li" if !tag.starts_with('/') => text.push_str("\n- "),
+            heading @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") if !tag.starts_with('/') => {
+                let level = usize::from(heading.as_bytes()[1] - b'0');
+                text.push_str(&format!("\n\n{} ", "#".repeat(level)));
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => text.push_str("\n\n"),
+            name if BLOCK_ELEMENTS.contains(&name) => text.push_str("\n\n"),
+            _ => {}
+        }
+    }
+
+    let decoded = unfurl::decode_entities(&text);
+    let mut lines: Vec<String> = Vec::new();
+    for line in decoded.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        let blank = line.is_empty() || line == "-";
+        if blank && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(if blank { String::new() } else { line });
+    }
+    let text = lines.join("\n").trim().to_string();
+    match text.char_indices().nth(MAX_SNAPSHOT_CHARS) {
+        Some((cut, _)) => format!("{}\n\n[…]", &text[..cut]),
+        None => text,
+    }
+}
+
+/// Byte range between `<name ...>` and `</name>`, if both are present.
+fn element_content(lower: &str, name: &str) -> Option<(usize, usize)> {
+    let open = lower.find(&format!("<{name}"))?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].rfind(&format!("</{name}"))?;
+    Some((start, end))
+}
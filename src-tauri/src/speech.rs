@@ -0,0 +1,194 @@
+//! Reads notes aloud through the OS speech tools: `say` on macOS,
+//! System.Speech through PowerShell on Windows, and eSpeak (or
+//! speech-dispatcher's `spd-say`) on Linux. One note speaks at a time.
+
+use std::{
+    io::Write,
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{markdown, StorageState};
+
+const SPEECH_CHANGED_EVENT: &str = "speech-changed";
+/// How often a running speaker is checked for having finished on its own.
+const FINISH_POLL: Duration = Duration::from_millis(250);
+static SPEAKING: Mutex<Option<Speech>> = Mutex::new(None);
+/// Tells a finished speaker's watcher apart from the one replacing it.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+struct Speech {
+    speaker: Child,
+    program: &'static str,
+    note_id: String,
+    generation: u64,
+}
+
+/// Payload of `speech-changed`; `note_id` is `None` once speech stops.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct SpeechChanged {
+    note_id: Option<String>,
+}
+
+/// Starts reading a note aloud, cutting off whatever was being read.
+#[tauri::command]
+pub(crate) fn speak_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<(), String> {
+    let text = {
+        let storage = state.lock(&app)?;
+        let notes = storage.notes()?;
+        notes
+            .iter()
+            .find(|note| note.id == id && !note.is_trashed())
+            .map(|note| markdown::to_plain_text(&note.text))
+            .ok_or_else(|| "Note not found".to_string())?
+    };
+    if text.trim().is_empty() {
+        return Err("Note has nothing to read".to_string());
+    }
+
+    let mut speaking = SPEAKING
+        .lock()
+        .map_err(|_| "Speech lock was poisoned".to_string())?;
+    if let Some(previous) = speaking.take() {
+        stop(previous);
+    }
+    let (program, speaker) = spawn_speaker(&text)?;
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    *speaking = Some(Speech {
+        speaker,
+        program,
+        note_id: id.clone(),
+        generation,
+    });
+    drop(speaking);
+
+    let _ = app.emit(SPEECH_CHANGED_EVENT, SpeechChanged { note_id: Some(id) });
+    watch_for_finish(app, generation);
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn stop_speaking(app: AppHandle) -> Result<(), String> {
+    let previous = SPEAKING
+        .lock()
+        .map_err(|_| "Speech lock was poisoned".to_string())?
+        .take();
+    if let Some(previous) = previous {
+        stop(previous);
+        let _ = app.emit(SPEECH_CHANGED_EVENT, SpeechChanged { note_id: None });
+    }
+    Ok(())
+}
+
+/// The note being read aloud, if any.
+#[tauri::command]
+pub(crate) fn get_speaking_note() -> Option<String> {
+    SPEAKING
+        .lock()
+        .ok()?
+        .as_ref()
+        .map(|speech| speech.note_id.clone())
+}
+
+fn watch_for_finish(app: AppHandle, generation: u64) {
+    thread::spawn(move || loop {
+        thread::sleep(FINISH_POLL);
+        let Ok(mut speaking) = SPEAKING.lock() else {
+            return;
+        };
+        let Some(speech) = speaking.as_mut() else {
+            return;
+        };
+        if speech.generation != generation {
+            return;
+        }
+        if !matches!(speech.speaker.try_wait(), Ok(None)) {
+            *speaking = None;
+            drop(speaking);
+            let _ = app.emit(SPEECH_CHANGED_EVENT, SpeechChanged { note_id: None });
+            return;
+        }
+    });
+}
+
+fn stop(mut speech: Speech) {
+    let _ = speech.speaker.kill();
+    let _ = speech.speaker.wait();
+    // spd-say only hands the text to the speech-dispatcher daemon, which
+    // keeps talking unless told to stop.
+    if speech.program == "spd-say" {
+        let _ = Command::new("spd-say").arg("--cancel").status();
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn spawn_speaker(text: &str) -> Result<(&'static str, Child), String> {
+    spawn_with_stdin("say", &[], text)
+        .map(|child| ("say", child))
+        .map_err(|e| format!("Failed to start say: {e}"))
+}
+
+#[cfg(target_os = "windows")]
+fn spawn_speaker(text: &str) -> Result<(&'static str, Child), String> {
+    const SCRIPT: &str = "Add-Type -AssemblyName System.Speech; \
+        (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak([Console]::In.ReadToEnd())";
+    spawn_with_stdin("powershell", &["-NoProfile", "-Command", SCRIPT], text)
+        .map(|child| ("powershell", child))
+        .map_err(|e| format!("Failed to start PowerShell speech: {e}"))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn spawn_speaker(text: &str) -> Result<(&'static str, Child), String> {
+    for program in ["espeak-ng", "espeak"] {
+        match spawn_with_stdin(program, &["--stdin"], text) {
+            Ok(child) => return Ok((program, child)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(format!("Failed to start {program}: {error}")),
+        }
+    }
+    match Command::new("spd-say")
+        .args(["--wait", "--"])
+        .arg(text)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => Ok(("spd-say", child)),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            Err("Reading aloud needs espeak-ng or speech-dispatcher to be installed".to_string())
+        }
+        Err(error) => Err(format!("Failed to start spd-say: {error}")),
+    }
+}
+
+/// Feeds the text on stdin, which sidesteps argument length limits. Some
+/// speakers read as they talk, so the writing happens off this thread.
+fn spawn_with_stdin(program: &str, args: &[&str], text: &str) -> std::io::Result<Child> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let text = text.to_string();
+        // Fails with a broken pipe when speech is stopped early.
+        thread::spawn(move || {
+            let _ = stdin.write_all(text.as_bytes());
+        });
+    }
+    Ok(child)
+}
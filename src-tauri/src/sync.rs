@@ -0,0 +1,1019 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use zeroize::Zeroizing;
+
+use crate::{
+    conflicts::{self, NoteConflict},
+    e2e, encryption, git_sync, http, merge, resolve_data_file, s3, schema,
+    settings::{self, Secret, Settings},
+    shred, write_file_atomically, Note, StorageGuard, StorageState, NOTES_CHANGED_EVENT,
+};
+
+const SYNC_STATE_FILE_NAME: &str = "sync.json";
+pub(crate) const SYNC_STATUS_EVENT: &str = "sync-status";
+const REMOTE_NOTES_FILE_NAME: &str = "notes.json";
+const CONFLICT_TAG: &str = "conflict";
+const REQUEST_TIMEOUT_SECS: u32 = 30;
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+/// Times an S3 sync merges snapshots other devices uploaded meanwhile
+/// before leaving the rest to the next sync.
+const MAX_S3_ROUNDS: usize = 3;
+/// Snapshots kept in the bucket; older ones are deleted after an upload.
+const MAX_S3_SNAPSHOTS: usize = 30;
+
+static SYNC_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static LAST_STATUS: Mutex<Option<SyncStatus>> = Mutex::new(None);
+
+/// What the previous successful sync agreed on, used to tell edits and
+/// purges apart from notes the other side has simply never seen. It only
+/// holds for the remote it was made with; see `load_sync_state_for`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "snake_case")]
+struct SyncState {
+    /// Backend and location of the remote, such as `webdav <file URL>`.
+    target: Option<String>,
+    last_synced_at: Option<String>,
+    synced_ids: Vec<String>,
+    /// Every note as both sides had it, the base of three-way merges.
+    base_notes: Vec<Note>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SyncPhase {
+    Idle,
+    Syncing,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct SyncStatus {
+    phase: SyncPhase,
+    last_synced_at: Option<String>,
+    message: Option<String>,
+}
+
+impl SyncStatus {
+    pub(crate) fn phase(&self) -> SyncPhase {
+        self.phase
+    }
+
+    pub(crate) fn last_synced_at(&self) -> Option<DateTime<Utc>> {
+        self.last_synced_at.as_deref().and_then(parse_timestamp)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct SyncReport {
+    pulled: usize,
+    pushed: usize,
+    conflicts: usize,
+    synced_at: String,
+}
+
+struct RemoteNotes {
+    notes: Vec<Note>,
+    etag: Option<String>,
+    exists: bool,
+    /// Notes file format the remote is in, and is written back in.
+    version: u32,
+    encrypted: bool,
+}
+
+/// How notes are written on a remote: as the plain notes file, or end to
+/// end encrypted (see `e2e`).
+enum RemoteFormat {
+    Plain,
+    Encrypted(Box<e2e::Codec>),
+}
+
+impl RemoteFormat {
+    fn encrypted() -> Result<Self, String> {
+        let passphrase = Secret::SyncPassphrase
+            .get()?
+            .ok_or("Set a sync passphrase first: encrypted sync derives its key from it")?;
+        Ok(Self::Encrypted(Box::new(e2e::Codec::new(Zeroizing::new(
+            passphrase,
+        )))))
+    }
+
+    /// The remote's notes, and whether they were encrypted. A plain remote
+    /// still reads when encrypting, so turning encryption on migrates it;
+    /// an encrypted one is refused rather than overwritten in plain.
+    fn decode(&mut self, raw: &[u8]) -> Result<(schema::NotesFile, bool), String> {
+        if !e2e::is_encrypted(raw) {
+            return Ok((parse_remote_file(&String::from_utf8_lossy(raw))?, false));
+        }
+        match self {
+            Self::Plain => Err(
+                "Remote notes are end-to-end encrypted; turn on encrypted sync to read them"
+                    .to_string(),
+            ),
+            Self::Encrypted(codec) => {
+                let file = schema::NotesFile {
+                    notes: codec.decode(raw)?,
+                    version: schema::CURRENT_VERSION,
+                };
+                Ok((file, true))
+            }
+        }
+    }
+
+    /// A plain remote still in the bare-array format (`version` 1) stays in
+    /// it, for devices running releases from before the versioned format.
+    fn encode(&mut self, notes: &[Note], version: u32) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Plain => schema::serialize_as(notes, version).map(String::into_bytes),
+            Self::Encrypted(codec) => codec.encode(notes),
+        }
+    }
+
+    fn is_encrypted(&self) -> bool {
+        matches!(self, Self::Encrypted(_))
+    }
+}
+
+#[tauri::command]
+pub(crate) fn sync_now(app: AppHandle) -> Result<SyncReport, String> {
+    run_sync(&app)
+}
+
+#[tauri::command]
+pub(crate) fn get_sync_status(app: AppHandle) -> Result<SyncStatus, String> {
+    if let Some(status) = LAST_STATUS.lock().ok().and_then(|status| status.clone()) {
+        return Ok(status);
+    }
+
+    Ok(SyncStatus {
+        phase: SyncPhase::Idle,
+        last_synced_at: load_sync_state(&app)?.last_synced_at,
+        message: None,
+    })
+}
+
+/// Spawns the background scheduler; it re-reads the interval every tick so
+/// settings changes apply without a restart.
+pub(crate) fn start(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let mut elapsed_minutes = 0u32;
+        loop {
+            thread::sleep(SCHEDULER_TICK);
+            elapsed_minutes += 1;
+
+            let Ok(settings) = settings::load_settings(&app) else {
+                continue;
+            };
+            let Some(interval) = settings
+                .sync_interval_minutes
+                .filter(|minutes| *minutes > 0)
+            else {
+                continue;
+            };
+            let configured = settings.git_sync_enabled
+                || settings.webdav_url.is_some()
+                || s3::Bucket::is_configured(&settings);
+            if !configured || elapsed_minutes < interval {
+                continue;
+            }
+
+            elapsed_minutes = 0;
+            if let Err(error) = run_sync(&app) {
+                eprintln!("Background sync failed: {error}");
+            }
+        }
+    });
+}
+
+fn run_sync(app: &AppHandle) -> Result<SyncReport, String> {
+    if SYNC_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+        return Err("A sync is already running".to_string());
+    }
+
+    let previous = load_sync_state(app).unwrap_or_default();
+    publish_status(
+        app,
+        SyncPhase::Syncing,
+        previous.last_synced_at.clone(),
+        None,
+    );
+
+    let result = settings::load_settings(app).and_then(|settings| {
+        if settings.git_sync_enabled {
+            sync_with_git(app, &settings)
+        } else if let Some(bucket) = s3::Bucket::configured(&settings)? {
+            sync_with_s3(app, &bucket)
+        } else {
+            sync_with_webdav(app, &settings)
+        }
+    });
+    match &result {
+        Ok(report) => publish_status(app, SyncPhase::Idle, Some(report.synced_at.clone()), None),
+        Err(error) => publish_status(
+            app,
+            SyncPhase::Error,
+            previous.last_synced_at,
+            Some(error.clone()),
+        ),
+    }
+
+    SYNC_IN_PROGRESS.store(false, Ordering::SeqCst);
+    result
+}
+
+fn sync_with_webdav(app: &AppHandle, settings: &Settings) -> Result<SyncReport, String> {
+    let Some(base_url) = settings.webdav_url.as_deref() else {
+        return Err("WebDAV sync is not configured".to_string());
+    };
+    let remote_url = format!(
+        "{}/{REMOTE_NOTES_FILE_NAME}",
+        base_url.trim_end_matches('/')
+    );
+    let target = format!("webdav {remote_url}");
+    let mut format = if settings.sync_encrypted {
+        RemoteFormat::encrypted()?
+    } else {
+        RemoteFormat::Plain
+    };
+
+    let mut remote = fetch_remote(settings, &remote_url, &mut format)?;
+    let (report, merged) = {
+        let state = app.state::<StorageState>();
+        let storage = state.lock(app)?;
+
+        let local = storage.load_notes()?;
+        merge_into_local(
+            app,
+            &storage,
+            &target,
+            local,
+            std::mem::take(&mut remote.notes),
+            false,
+        )?
+    };
+
+    if report.pushed > 0
+        || report.conflicts > 0
+        || !remote.exists
+        || remote.encrypted != format.is_encrypted()
+    {
+        let payload = format.encode(&merged, remote.version)?;
+        push_remote(settings, &remote_url, &payload, &remote)?;
+    }
+
+    save_synced_state(app, &target, &report, &merged)?;
+    Ok(report)
+}
+
+/// Commits pending edits, merges the remote branch and pushes the result.
+fn sync_with_git(app: &AppHandle, settings: &Settings) -> Result<SyncReport, String> {
+    let dir = git_sync::data_dir(app)?;
+    git_sync::commit_all(&dir, "Update notes")?;
+    let Some(remote_url) = settings.git_remote_url.as_deref() else {
+        return Err("Git sync has no remote configured".to_string());
+    };
+    let target = format!("git {remote_url}");
+    let remote_raw = git_sync::fetch_remote_notes(&dir)?;
+
+    let (report, merged) = {
+        let state = app.state::<StorageState>();
+        let storage = state.lock(app)?;
+
+        let local = storage.load_notes()?;
+        let remote = match remote_raw {
+            Some(raw) => {
+                // The notes file as committed, sealed when storage is encrypted.
+                let remote = parse_remote_notes(&encryption::open(raw)?)?;
+                git_sync::merge_fetched(&dir)?;
+                remote
+            }
+            None => Vec::new(),
+        };
+
+        // The git merge may have touched the notes file textually; always
+        // write the note-level merge back over it.
+        let merged = merge_into_local(app, &storage, &target, local, remote, true)?;
+        git_sync::commit_all(&dir, "Merge notes")?;
+        merged
+    };
+
+    git_sync::push(&dir)?;
+    save_synced_state(app, &target, &report, &merged)?;
+    Ok(report)
+}
+
+/// Pulls the newest snapshot, merges it and uploads a new one.
+/// Snapshots are never overwritten, so two devices syncing at once both
+/// upload; the one that then sees the other's snapshot merges it in too.
+/// Snapshots are always end-to-end encrypted.
+fn sync_with_s3(app: &AppHandle, bucket: &s3::Bucket) -> Result<SyncReport, String> {
+    let mut format = RemoteFormat::encrypted()?;
+    let target = format!("s3 {}", bucket.location());
+
+    let listed = bucket.list_snapshots()?;
+    let mut seen: HashSet<String> = listed.iter().cloned().collect();
+    let mut incoming: Vec<String> = listed.last().cloned().into_iter().collect();
+    let mut report = SyncReport {
+        synced_at: Utc::now().to_rfc3339(),
+        ..SyncReport::default()
+    };
+    let mut merged = Vec::new();
+
+    for round in 0..MAX_S3_ROUNDS {
+        let mut remotes = Vec::new();
+        let mut plain = Vec::new();
+        for name in &incoming {
+            let (file, encrypted) = format.decode(&bucket.download(name)?)?;
+            if !encrypted {
+                plain.push(name.clone());
+            }
+            remotes.push(file.notes);
+        }
+
+        // Plain snapshots are replaced by an encrypted one right away.
+        let mut needs_upload = seen.is_empty() || !plain.is_empty();
+        {
+            let state = app.state::<StorageState>();
+            let storage = state.lock(app)?;
+            // An empty bucket has nothing to merge or to have purged notes:
+            // the local ones become its first snapshot as they are.
+            if remotes.is_empty() {
+                merged = storage.load_notes()?;
+                report.pushed += merged.len();
+            }
+            for remote in remotes {
+                let local = storage.load_notes()?;
+                let (merge, notes) =
+                    merge_into_local(app, &storage, &target, local, remote, false)?;
+                needs_upload |= merge.pushed > 0 || merge.conflicts > 0;
+                report.pulled += merge.pulled;
+                report.pushed += merge.pushed;
+                report.conflicts += merge.conflicts;
+                merged = notes;
+            }
+        }
+
+        let mut uploaded = None;
+        if needs_upload {
+            let name = bucket.new_snapshot_key();
+            bucket.upload(&name, &format.encode(&merged, schema::CURRENT_VERSION)?)?;
+            seen.insert(name.clone());
+            uploaded = Some(name);
+            for name in plain {
+                if let Err(error) = bucket.delete(&name) {
+                    eprintln!("Failed to delete plain S3 snapshot {name}: {error}");
+                }
+            }
+        }
+
+        let listed = bucket.list_snapshots()?;
+        incoming = listed
+            .iter()
+            .filter(|key| seen.insert((*key).clone()))
+            .cloned()
+            .collect();
+        if incoming.is_empty() || round + 1 == MAX_S3_ROUNDS {
+            if uploaded.is_some() {
+                prune_snapshots(bucket, &listed);
+            }
+            break;
+        }
+    }
+
+    save_synced_state(app, &target, &report, &merged)?;
+    Ok(report)
+}
+
+fn prune_snapshots(bucket: &s3::Bucket, keys: &[String]) {
+    let excess = keys.len().saturating_sub(MAX_S3_SNAPSHOTS);
+    for key in &keys[..excess] {
+        if let Err(error) = bucket.delete(key) {
+            eprintln!("Failed to prune S3 snapshot {key}: {error}");
+        }
+    }
+}
+
+/// Merges remote notes into the locally loaded ones and saves the result.
+fn merge_into_local(
+    app: &AppHandle,
+    storage: &StorageGuard<'_>,
+    target: &str,
+    local: Vec<Note>,
+    remote: Vec<Note>,
+    always_save: bool,
+) -> Result<(SyncReport, Vec<Note>), String> {
+    let sync_state = load_sync_state_for(app, target)?;
+    let outcome = merge_notes(local, remote, &sync_state);
+
+    if outcome.local_changed || always_save {
+        storage.save_notes(&outcome.notes)?;
+        app.state::<StorageState>().invalidate_search_index();
+        let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    }
+
+    let report = SyncReport {
+        pulled: outcome.pulled,
+        pushed: outcome.pushed,
+        conflicts: outcome.conflicts,
+        synced_at: Utc::now().to_rfc3339(),
+    };
+    Ok((report, outcome.notes))
+}
+
+fn save_synced_state(
+    app: &AppHandle,
+    target: &str,
+    report: &SyncReport,
+    merged: &[Note],
+) -> Result<(), String> {
+    save_sync_state(
+        app,
+        &SyncState {
+            target: Some(target.to_string()),
+            last_synced_at: Some(report.synced_at.clone()),
+            synced_ids: merged.iter().map(|note| note.id.clone()).collect(),
+            base_notes: merged.to_vec(),
+        },
+    )
+}
+
+fn parse_remote_notes(raw: &str) -> Result<Vec<Note>, String> {
+    parse_remote_file(raw).map(|file| file.notes)
+}
+
+fn parse_remote_file(raw: &str) -> Result<schema::NotesFile, String> {
+    schema::parse(raw).map_err(|e| format!("Failed to parse remote notes: {e}"))
+}
+
+struct MergeOutcome {
+    notes: Vec<Note>,
+    pulled: usize,
+    pushed: usize,
+    conflicts: usize,
+    local_changed: bool,
+}
+
+/// Merges both sides note by note. A note changed on one side since the
+/// last sync takes that side's revision, whatever the timestamps say. One changed on both is merged against its version at
+/// the last sync; when that fails (the same lines were edited) the newer
+/// revision wins and the note keeps the losing version as a conflict.
+fn merge_notes(local: Vec<Note>, remote: Vec<Note>, state: &SyncState) -> MergeOutcome {
+    // A remote without any notes is new, was wiped or could not be written
+    // yet; none of that means the notes synced before were purged.
+    let synced_ids: HashSet<&str> = if remote.is_empty() {
+        HashSet::new()
+    } else {
+        state.synced_ids.iter().map(String::as_str).collect()
+    };
+    let base_by_id: HashMap<&str, &Note> = state
+        .base_notes
+        .iter()
+        .map(|note| (note.id.as_str(), note))
+        .collect();
+    let last_synced_at = state.last_synced_at.as_deref().and_then(parse_timestamp);
+    let changed_since_sync = |note: &Note| {
+        last_synced_at.is_none_or(|synced| note.revised_at().is_none_or(|rev| rev > synced))
+    };
+
+    let mut remote_by_id: HashMap<String, Note> = remote
+        .into_iter()
+        .map(|note| (note.id.clone(), note))
+        .collect();
+    let mut outcome = MergeOutcome {
+        notes: Vec::new(),
+        pulled: 0,
+        pushed: 0,
+        conflicts: 0,
+        local_changed: false,
+    };
+
+    for mut local_note in local {
+        let Some(remote_note) = remote_by_id.remove(&local_note.id) else {
+            if synced_ids.contains(local_note.id.as_str()) {
+                // Known to both sides last time, so the remote purged it.
+                // Only a note already in the trash goes for good; any other
+                // is moved there, and back to the remote with the next push.
+                outcome.local_changed = true;
+                if !local_note.is_trashed() {
+                    local_note.deleted_at = Some(Utc::now().to_rfc3339());
+                    outcome.pushed += 1;
+                    outcome.notes.push(local_note);
+                }
+            } else {
+                outcome.pushed += 1;
+                outcome.notes.push(local_note);
+            }
+            continue;
+        };
+
+        if same_content(&local_note, &remote_note) {
+            outcome.notes.push(local_note);
+            continue;
+        }
+
+        // The version from the last sync tells which side actually changed;
+        // timestamps are only a fallback, as they can tie or be skewed.
+        let base = base_by_id.get(local_note.id.as_str()).copied();
+        let (local_edited, remote_edited) = match base {
+            Some(base) => (
+                !same_content(base, &local_note),
+                !same_content(base, &remote_note),
+            ),
+            None => (
+                changed_since_sync(&local_note),
+                changed_since_sync(&remote_note),
+            ),
+        };
+        let both_changed = local_edited && remote_edited;
+        let remote_wins = match (local_note.locked.is_some(), remote_note.locked.is_some()) {
+            // Syncing never undoes a lock: the plain side predates it or
+            // comes from a device that dropped it.
+            (true, false) => false,
+            (false, true) => true,
+            _ if local_edited != remote_edited => remote_edited,
+            _ => remote_note.revised_at() > local_note.revised_at(),
+        };
+        let merged = both_changed
+            .then_some(base)
+            .flatten()
+            .and_then(|base| merge::merge_note(base, &local_note, &remote_note));
+        if let Some(merged) = merged {
+            // Both sides end up with edits they did not have.
+            outcome.pulled += 1;
+            outcome.pushed += 1;
+            outcome.local_changed = true;
+            outcome.notes.push(merged);
+            continue;
+        }
+
+        let (mut winner, loser) = if remote_wins {
+            outcome.pulled += 1;
+            outcome.local_changed = true;
+            (remote_note, local_note)
+        } else {
+            outcome.pushed += 1;
+            (local_note, remote_note)
+        };
+
+        let already_kept = winner
+            .conflict
+            .as_ref()
+            .is_some_and(|conflict| conflict.holds(&loser));
+        let differs = winner.text != loser.text || winner.locked != loser.locked;
+        let keepable = match (&winner.locked, &loser.locked) {
+            // Kept beside a locked version, a plain one leaves the text readable.
+            (Some(_), None) => false,
+            // A sealed version only opens in its own note, not in a copy.
+            (_, Some(_)) => winner.conflict.is_none(),
+            (None, None) => true,
+        };
+        if both_changed && differs && keepable && !already_kept {
+            outcome.conflicts += 1;
+            outcome.local_changed = true;
+            match winner.conflict {
+                None => winner.conflict = Some(NoteConflict::new(&loser, base)),
+                // One unresolved conflict per note; a further version
+                // becomes a tagged note of its own.
+                Some(_) => outcome.notes.push(conflict_copy(&loser)),
+            }
+        }
+        outcome.notes.push(winner);
+    }
+
+    for (id, remote_note) in remote_by_id {
+        if synced_ids.contains(id.as_str()) {
+            // Known to both sides last time, so it was purged locally.
+            outcome.pushed += 1;
+            continue;
+        }
+        outcome.pulled += 1;
+        outcome.local_changed = true;
+        outcome.notes.push(remote_note);
+    }
+
+    outcome
+        .notes
+        .sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    outcome
+}
+
+fn conflict_copy(note: &Note) -> Note {
+    let mut tags = note.tags.clone();
+    if !tags.iter().any(|tag| tag == CONFLICT_TAG) {
+        tags.push(CONFLICT_TAG.to_string());
+    }
+    conflicts::copy_of(note, note.text.clone(), tags)
+}
+
+fn same_content(a: &Note, b: &Note) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+fn fetch_remote(
+    settings: &Settings,
+    url: &str,
+    format: &mut RemoteFormat,
+) -> Result<RemoteNotes, String> {
+    let body_file = http::StagedFile::new("sync-download")?;
+    let headers_file = http::StagedFile::new("sync-headers")?;
+    let body_arg = body_file.path().to_string_lossy();
+    let headers_arg = headers_file.path().to_string_lossy();
+    let status = curl(
+        settings,
+        url,
+        &["--output", &body_arg, "--dump-header", &headers_arg],
+    );
+    let body = body_file.read();
+    let headers = String::from_utf8_lossy(&headers_file.read()).into_owned();
+
+    match status? {
+        200 => {
+            let (file, encrypted) = format.decode(&body)?;
+            Ok(RemoteNotes {
+                notes: file.notes,
+                etag: header_value(&headers, "etag"),
+                exists: true,
+                version: file.version,
+                encrypted,
+            })
+        }
+        404 => Ok(RemoteNotes {
+            notes: Vec::new(),
+            etag: None,
+            exists: false,
+            version: schema::CURRENT_VERSION,
+            encrypted: false,
+        }),
+        401 | 403 => Err("WebDAV server rejected the credentials".to_string()),
+        status => Err(format!("WebDAV download failed with HTTP {status}")),
+    }
+}
+
+/// Uploads with `If-Match` (or `If-None-Match` for a first push) so a write
+/// from another device between our download and upload is never clobbered.
+fn push_remote(
+    settings: &Settings,
+    url: &str,
+    payload: &[u8],
+    remote: &RemoteNotes,
+) -> Result<(), String> {
+    let upload_file = http::StagedFile::with_contents("sync-upload", payload)?;
+
+    let precondition = match (&remote.etag, remote.exists) {
+        (Some(etag), _) => format!("If-Match: {etag}"),
+        (None, false) => "If-None-Match: *".to_string(),
+        (None, true) => String::new(),
+    };
+    let upload_arg = upload_file.path().to_string_lossy();
+    let mut args = vec!["--upload-file", upload_arg.as_ref()];
+    if !precondition.is_empty() {
+        args.extend(["--header", precondition.as_str()]);
+    }
+
+    match curl(settings, url, &args)? {
+        200..=299 => Ok(()),
+        412 => Err("Notes changed on the server during sync; try again".to_string()),
+        401 | 403 => Err("WebDAV server rejected the credentials".to_string()),
+        status => Err(format!("WebDAV upload failed with HTTP {status}")),
+    }
+}
+
+/// Runs `curl` and returns the HTTP status. Credentials go through a config
+/// on stdin so they never show up in the process list.
+fn curl(settings: &Settings, url: &str, args: &[&str]) -> Result<u16, String> {
+    let mut config = format!("url = {}\n", http::quote_curl_value(url)?);
+    if let Some(username) = settings.webdav_username.as_deref() {
+        let password = Secret::WebdavPassword.get()?.unwrap_or_default();
+        config.push_str(&format!(
+            "user = {}\n",
+            http::quote_curl_value(&format!("{username}:{password}"))?
+        ));
+    }
+
+    let mut curl_args = vec!["--location", "--write-out", "%{http_code}"];
+    curl_args.extend(args);
+    let output = http::run_curl(
+        http::WEB_PROTOCOLS,
+        REQUEST_TIMEOUT_SECS,
+        &config,
+        &curl_args,
+    )
+    .map_err(|e| format!("WebDAV request failed: {e}"))?;
+
+    let code = String::from_utf8_lossy(&output);
+    code.trim()
+        .parse::<u16>()
+        .map_err(|_| format!("Unexpected curl status output: {code}"))
+}
+
+fn header_value(headers: &str, name: &str) -> Option<String> {
+    // With redirects the dump holds several responses; the last one counts.
+    headers
+        .lines()
+        .rev()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim().to_string())
+}
+
+fn publish_status(
+    app: &AppHandle,
+    phase: SyncPhase,
+    last_synced_at: Option<String>,
+    message: Option<String>,
+) {
+    let status = SyncStatus {
+        phase,
+        last_synced_at,
+        message,
+    };
+    if let Ok(mut last) = LAST_STATUS.lock() {
+        *last = Some(status.clone());
+    }
+    let _ = app.emit(SYNC_STATUS_EVENT, status);
+}
+
+/// Drops notes from the last sync's merge base, overwriting the old state
+/// in place. Their ids stay, so the next sync still purges purged notes on
+/// the other side, and pushes locked ones as the newer version.
+pub(crate) fn forget_notes(app: &AppHandle, ids: &HashSet<&str>) {
+    let scrubbed = state_file(app).and_then(|path| {
+        let mut state = load_sync_state_from_path(&path)?;
+        let before = state.base_notes.len();
+        state
+            .base_notes
+            .retain(|note| !ids.contains(note.id.as_str()));
+        if state.base_notes.len() == before {
+            return Ok(());
+        }
+        let payload = serde_json::to_string_pretty(&state)
+            .map_err(|e| format!("Failed to serialize sync state: {e}"))
+            .and_then(encryption::seal)?;
+        shred::rewrite_in_place(&path, &payload)
+            .map_err(|e| format!("Failed to write sync state: {e}"))
+    });
+    if let Err(error) = scrubbed {
+        eprintln!("Failed to scrub sync state: {error}");
+    }
+}
+
+/// The sync state if it was made with `target`; a different or unknown
+/// remote starts over, so nothing its notes lack counts as purged.
+fn load_sync_state_for(app: &AppHandle, target: &str) -> Result<SyncState, String> {
+    let state = load_sync_state(app)?;
+    if state.target.as_deref() == Some(target) {
+        return Ok(state);
+    }
+    Ok(SyncState::default())
+}
+
+fn load_sync_state(app: &AppHandle) -> Result<SyncState, String> {
+    load_sync_state_from_path(&state_file(app)?)
+}
+
+pub(crate) fn state_file(app: &AppHandle) -> Result<PathBuf, String> {
+    resolve_data_file(app, SYNC_STATE_FILE_NAME)
+}
+
+fn load_sync_state_from_path(path: &Path) -> Result<SyncState, String> {
+    if !path.exists() {
+        return Ok(SyncState::default());
+    }
+
+    let raw =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read sync state file: {e}"))?;
+    let raw = encryption::open(raw)?;
+    serde_json::from_str::<SyncState>(&raw)
+        .map_err(|e| format!("Failed to parse sync state file: {e}"))
+}
+
+fn save_sync_state(app: &AppHandle, state: &SyncState) -> Result<(), String> {
+    let path = state_file(app)?;
+    let payload = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize sync state: {e}"))?;
+    let payload = encryption::seal(payload)?;
+
+    write_file_atomically(&path, &payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{Envelope, Key};
+
+    const SYNCED_AT: &str = "2025-01-02T00:00:00+00:00";
+
+    fn note(id: &str, text: &str, updated_at: Option<&str>) -> Note {
+        let mut note = Note::new(text.to_string());
+        note.id = id.to_string();
+        note.created_at = "2025-01-01T00:00:00+00:00".to_string();
+        note.updated_at = updated_at.map(str::to_string);
+        note
+    }
+
+    fn synced(base_notes: Vec<Note>) -> SyncState {
+        SyncState {
+            target: Some("webdav https://example.com/notes.json".to_string()),
+            last_synced_at: Some(SYNCED_AT.to_string()),
+            synced_ids: base_notes.iter().map(|note| note.id.clone()).collect(),
+            base_notes,
+        }
+    }
+
+    #[test]
+    fn first_sync_keeps_both_sides() {
+        let outcome = merge_notes(
+            vec![note("a", "local", None)],
+            vec![note("b", "remote", None)],
+            &SyncState::default(),
+        );
+        assert_eq!(outcome.notes.len(), 2);
+        assert_eq!((outcome.pulled, outcome.pushed), (1, 1));
+        assert!(outcome.local_changed);
+    }
+
+    #[test]
+    fn note_purged_remotely_moves_to_trash() {
+        let state = synced(vec![note("a", "text", None), note("keep", "other", None)]);
+        let outcome = merge_notes(
+            vec![note("a", "text", None), note("keep", "other", None)],
+            vec![note("keep", "other", None)],
+            &state,
+        );
+        let purged = outcome.notes.iter().find(|note| note.id == "a").unwrap();
+        assert!(purged.is_trashed());
+        assert!(outcome.local_changed);
+    }
+
+    #[test]
+    fn trashed_note_purged_remotely_is_dropped() {
+        let mut trashed = note("a", "text", None);
+        trashed.deleted_at = Some(SYNCED_AT.to_string());
+        let state = synced(vec![trashed.clone(), note("keep", "other", None)]);
+        let outcome = merge_notes(vec![trashed], vec![note("keep", "other", None)], &state);
+        assert!(outcome.notes.iter().all(|note| note.id != "a"));
+    }
+
+    #[test]
+    fn empty_remote_purges_nothing() {
+        let state = synced(vec![note("a", "text", None)]);
+        let outcome = merge_notes(vec![note("a", "text", None)], Vec::new(), &state);
+        assert_eq!(outcome.notes.len(), 1);
+        assert!(!outcome.notes[0].is_trashed());
+        assert_eq!(outcome.pushed, 1);
+    }
+
+    #[test]
+    fn note_purged_locally_is_not_pulled_back() {
+        let state = synced(vec![note("a", "text", None)]);
+        let outcome = merge_notes(Vec::new(), vec![note("a", "text", None)], &state);
+        assert!(outcome.notes.is_empty());
+        assert_eq!(outcome.pushed, 1);
+    }
+
+    #[test]
+    fn edit_on_one_side_wins() {
+        let state = synced(vec![note("a", "before", None)]);
+        let outcome = merge_notes(
+            vec![note("a", "before", None)],
+            vec![note("a", "after", Some("2025-01-03T00:00:00+00:00"))],
+            &state,
+        );
+        assert_eq!(outcome.notes[0].text, "after");
+        assert_eq!((outcome.pulled, outcome.conflicts), (1, 0));
+    }
+
+    #[test]
+    fn edit_with_a_tied_timestamp_is_pulled() {
+        let at = Some("2025-01-03T00:00:00+00:00");
+        let state = synced(vec![note("a", "before", at)]);
+        let outcome = merge_notes(
+            vec![note("a", "before", at)],
+            vec![note("a", "after", at)],
+            &state,
+        );
+        assert_eq!(outcome.notes[0].text, "after");
+        assert_eq!((outcome.pulled, outcome.pushed), (1, 0));
+        assert!(outcome.local_changed);
+    }
+
+    #[test]
+    fn edits_to_different_lines_merge() {
+        let state = synced(vec![note("a", "one\ntwo\nthree", None)]);
+        let outcome = merge_notes(
+            vec![note(
+                "a",
+                "ONE\ntwo\nthree",
+                Some("2025-01-03T00:00:00+00:00"),
+            )],
+            vec![note(
+                "a",
+                "one\ntwo\nTHREE",
+                Some("2025-01-04T00:00:00+00:00"),
+            )],
+            &state,
+        );
+        assert_eq!(outcome.notes[0].text, "ONE\ntwo\nTHREE");
+        assert_eq!((outcome.pulled, outcome.pushed), (1, 1));
+        assert_eq!(outcome.conflicts, 0);
+        assert!(outcome.notes[0].conflict.is_none());
+    }
+
+    #[test]
+    fn edits_to_the_same_line_merge() {
+        let state = synced(vec![note("a", "buy milk\ntwo", None)]);
+        let outcome = merge_notes(
+            vec![note(
+                "a",
+                "buy oat milk\ntwo",
+                Some("2025-01-03T00:00:00+00:00"),
+            )],
+            vec![note(
+                "a",
+                "buy milk, eggs\ntwo",
+                Some("2025-01-04T00:00:00+00:00"),
+            )],
+            &state,
+        );
+        assert_eq!(outcome.conflicts, 0);
+        let merged = &outcome.notes[0];
+        assert_eq!(merged.text, "buy oat milk, eggs\ntwo");
+        assert!(merged.crdt.is_some());
+    }
+
+    fn locked(id: &str, text: &str, updated_at: &str) -> Note {
+        let mut locked = note(id, "🔒", Some(updated_at));
+        let key = Key::for_tests("correct horse", 1);
+        locked.locked = Some(Envelope::seal(&key, text.as_bytes(), id).unwrap());
+        locked
+    }
+
+    #[test]
+    fn locked_edits_conflict_without_their_text() {
+        let state = synced(vec![note("a", "secret", None)]);
+        let local = locked("a", "secret, local", "2025-01-03T00:00:00+00:00");
+        let outcome = merge_notes(
+            vec![local.clone()],
+            vec![locked("a", "secret, remote", "2025-01-04T00:00:00+00:00")],
+            &state,
+        );
+        assert_eq!(outcome.conflicts, 1);
+        let conflict = outcome.notes[0].conflict.as_ref().unwrap();
+        assert!(conflict.holds(&local));
+        let stored = serde_json::to_string(conflict).unwrap();
+        assert!(!stored.contains("secret"));
+    }
+
+    #[test]
+    fn lock_outlasts_a_newer_plain_edit() {
+        let state = synced(vec![note("a", "secret", None)]);
+        let local = locked("a", "secret", "2025-01-03T00:00:00+00:00");
+        let outcome = merge_notes(
+            vec![local.clone()],
+            vec![note(
+                "a",
+                "secret, edited",
+                Some("2025-01-04T00:00:00+00:00"),
+            )],
+            &state,
+        );
+        assert_eq!(outcome.notes[0].locked, local.locked);
+        assert!(outcome.notes[0].conflict.is_none());
+        assert_eq!(outcome.conflicts, 0);
+    }
+
+    #[test]
+    fn plain_sync_refuses_an_encrypted_remote() {
+        let mut codec = e2e::Codec::new(Zeroizing::new("correct horse".to_string()));
+        let encrypted = codec.encode(&[note("a", "secret", None)]).unwrap();
+        assert!(RemoteFormat::Plain.decode(&encrypted).is_err());
+
+        let plain = schema::serialize(&[note("a", "text", None)]).unwrap();
+        let (file, encrypted) = RemoteFormat::Encrypted(Box::new(codec))
+            .decode(plain.as_bytes())
+            .unwrap();
+        assert_eq!(file.notes[0].text, "text");
+        assert!(!encrypted);
+    }
+}
@@ -0,0 +1,312 @@
+//! Named capture templates. Placeholders such as `{{date}}` are expanded on
+//! the backend when a note is created from a template, and a template can
+//! have a global shortcut that creates such a note directly.
+
+use std::{collections::HashMap, fs, path::Path, thread};
+
+use chrono::{Local, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+use crate::{
+    insert_note, notifications, resolve_data_file, shortcuts, write_file_atomically, Note,
+    StorageState,
+};
+
+const TEMPLATES_FILE_NAME: &str = "templates.json";
+const TEMPLATES_CHANGED_EVENT: &str = "templates-changed";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct Template {
+    id: String,
+    name: String,
+    /// Note text with `{{placeholder}}` markers, expanded on use.
+    body: String,
+    /// Global accelerator that creates a note from this template.
+    #[serde(default)]
+    shortcut: Option<String>,
+    created_at: String,
+}
+
+#[tauri::command]
+pub(crate) fn list_templates(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+) -> Result<Vec<Template>, String> {
+    let _guard = state.lock(&app)?;
+
+    load_templates(&app)
+}
+
+#[tauri::command]
+pub(crate) fn create_template(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    name: String,
+    body: String,
+    shortcut: Option<String>,
+) -> Result<Template, String> {
+    let name = validate_template_name(&name)?;
+    let shortcut = normalize_shortcut(shortcut)?;
+
+    let _guard = state.lock(&app)?;
+
+    let mut templates = load_templates(&app)?;
+    ensure_unique(&templates, &name, shortcut.as_deref(), None)?;
+
+    let template = Template {
+        id: Uuid::new_v4().to_string(),
+        name,
+        body,
+        shortcut,
+        created_at: Utc::now().to_rfc3339(),
+    };
+
+    templates.push(template.clone());
+    save_templates(&app, &templates)?;
+    apply_shortcuts(&app, &templates);
+
+    let _ = app.emit(TEMPLATES_CHANGED_EVENT, ());
+    Ok(template)
+}
+
+#[tauri::command]
+pub(crate) fn update_template(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+    name: String,
+    body: String,
+    shortcut: Option<String>,
+) -> Result<Template, String> {
+    let name = validate_template_name(&name)?;
+    let shortcut = normalize_shortcut(shortcut)?;
+
+    let _guard = state.lock(&app)?;
+
+    let mut templates = load_templates(&app)?;
+    ensure_unique(&templates, &name, shortcut.as_deref(), Some(&id))?;
+    let template = templates
+        .iter_mut()
+        .find(|template| template.id == id)
+        .ok_or_else(|| "Template not found".to_string())?;
+    template.name = name;
+    template.body = body;
+    template.shortcut = shortcut;
+    let updated = template.clone();
+
+    save_templates(&app, &templates)?;
+    apply_shortcuts(&app, &templates);
+
+    let _ = app.emit(TEMPLATES_CHANGED_EVENT, ());
+    Ok(updated)
+}
+
+#[tauri::command]
+pub(crate) fn delete_template(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    id: String,
+) -> Result<(), String> {
+    let _guard = state.lock(&app)?;
+
+    let mut templates = load_templates(&app)?;
+    let before = templates.len();
+    templates.retain(|template| template.id != id);
+    if templates.len() == before {
+        return Err("Template not found".to_string());
+    }
+
+    save_templates(&app, &templates)?;
+    apply_shortcuts(&app, &templates);
+
+    let _ = app.emit(TEMPLATES_CHANGED_EVENT, ());
+    Ok(())
+}
+
+/// Creates a note from a template. `{{date}}`, `{{time}}` and
+/// `{{clipboard}}` are filled in automatically; `fill` supplies any other
+/// placeholder and can override those. Unknown placeholders are kept as is.
+#[tauri::command]
+pub(crate) fn create_note_from_template(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    template_id: String,
+    fill: Option<HashMap<String, String>>,
+) -> Result<Note, String> {
+    let template = {
+        let _guard = state.lock(&app)?;
+        find_template(&app, &template_id)?
+    };
+
+    let text = expand(&template.body, &fill.unwrap_or_default());
+    insert_note(&app, &state, &text)
+}
+
+/// Binds the saved template shortcuts; called once at startup.
+pub(crate) fn setup_shortcuts(app: &AppHandle) {
+    match load_templates(app) {
+        Ok(templates) => apply_shortcuts(app, &templates),
+        Err(error) => eprintln!("{error}"),
+    }
+}
+
+/// Runs on a template's global shortcut.
+pub(crate) fn capture_from_template(app: &AppHandle, template_id: &str) {
+    let app = app.clone();
+    let template_id = template_id.to_string();
+    thread::spawn(move || {
+        let state = app.state::<StorageState>();
+        let template = match state
+            .lock(&app)
+            .and_then(|_guard| find_template(&app, &template_id))
+        {
+            Ok(template) => template,
+            Err(error) => {
+                eprintln!("Failed to load template: {error}");
+                return;
+            }
+        };
+
+        let text = expand(&template.body, &HashMap::new());
+        match insert_note(&app, &state, &text) {
+            Ok(note) => notifications::show(
+                &app,
+                &format!("Created a note from \"{}\"", template.name),
+                note.title(),
+                &[],
+                |_, _| {},
+            ),
+            Err(error) => eprintln!("Failed to create note from template: {error}"),
+        }
+    });
+}
+
+/// Replaces every `{{name}}` marker, ignoring spaces inside the braces.
+fn expand(body: &str, fill: &HashMap<String, String>) -> String {
+    let now = Local::now();
+    let mut expanded = String::with_capacity(body.len());
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + length].trim();
+        expanded.push_str(&rest[..start]);
+
+        let value = match fill.get(name) {
+            Some(value) => Some(value.clone()),
+            None => match name {
+                "date" => Some(now.format("%Y-%m-%d").to_string()),
+                "time" => Some(now.format("%H:%M").to_string()),
+                "clipboard" => Some(clipboard_text()),
+                _ => None,
+            },
+        };
+        let marker = &rest[start..start + 2 + length + 2];
+        expanded.push_str(value.as_deref().unwrap_or(marker));
+        rest = &rest[start + marker.len()..];
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// An empty or non-text clipboard expands to nothing.
+fn clipboard_text() -> String {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map(|text| text.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn apply_shortcuts(app: &AppHandle, templates: &[Template]) {
+    let bindings: Vec<(String, String)> = templates
+        .iter()
+        .filter_map(|template| Some((template.id.clone(), template.shortcut.clone()?)))
+        .collect();
+    shortcuts::apply_template_shortcuts(app, &bindings);
+}
+
+fn validate_template_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Template name cannot be empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// An empty accelerator means no shortcut.
+fn normalize_shortcut(shortcut: Option<String>) -> Result<Option<String>, String> {
+    let Some(shortcut) = shortcut.map(|shortcut| shortcut.trim().to_string()) else {
+        return Ok(None);
+    };
+    if shortcut.is_empty() {
+        return Ok(None);
+    }
+    shortcuts::parse_shortcut(&shortcut)?;
+    Ok(Some(shortcut))
+}
+
+fn ensure_unique(
+    templates: &[Template],
+    name: &str,
+    shortcut: Option<&str>,
+    except_id: Option<&str>,
+) -> Result<(), String> {
+    for template in templates {
+        if Some(template.id.as_str()) == except_id {
+            continue;
+        }
+        if template.name.eq_ignore_ascii_case(name) {
+            return Err(format!("A template named \"{name}\" already exists"));
+        }
+        if let (Some(shortcut), Some(taken)) = (shortcut, template.shortcut.as_deref()) {
+            if taken.eq_ignore_ascii_case(shortcut) {
+                return Err(format!(
+                    "{shortcut} is already used by the \"{}\" template",
+                    template.name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn find_template(app: &AppHandle, id: &str) -> Result<Template, String> {
+    load_templates(app)?
+        .into_iter()
+        .find(|template| template.id == id)
+        .ok_or_else(|| "Template not found".to_string())
+}
+
+fn load_templates(app: &AppHandle) -> Result<Vec<Template>, String> {
+    let path = resolve_data_file(app, TEMPLATES_FILE_NAME)?;
+    load_templates_from_path(&path)
+}
+
+fn load_templates_from_path(path: &Path) -> Result<Vec<Template>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let raw =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read templates file: {e}"))?;
+    if raw.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    serde_json::from_str::<Vec<Template>>(&raw)
+        .map_err(|e| format!("Failed to parse templates file: {e}"))
+}
+
+fn save_templates(app: &AppHandle, templates: &[Template]) -> Result<(), String> {
+    let path = resolve_data_file(app, TEMPLATES_FILE_NAME)?;
+    let payload = serde_json::to_string_pretty(templates)
+        .map_err(|e| format!("Failed to serialize templates payload: {e}"))?;
+
+    write_file_atomically(&path, &payload)
+}
@@ -0,0 +1,115 @@
+//! The light/dark theme, decided here so the capture and main windows always
+//! agree. Native window chrome follows the preference too; with the system
+//! preference both track OS theme changes.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Theme};
+
+use crate::{
+    settings::{self, ThemePreference},
+    CAPTURE_WINDOW_LABEL, MAIN_WINDOW_LABEL,
+};
+
+pub(crate) const THEME_CHANGED_EVENT: &str = "theme-changed";
+
+/// The theme windows actually render, the payload of `theme-changed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ThemeMode {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct ThemeState {
+    preference: ThemePreference,
+    resolved: ThemeMode,
+}
+
+#[tauri::command]
+pub(crate) fn get_theme(app: AppHandle) -> Result<ThemeState, String> {
+    let preference = settings::load_settings(&app)?.theme;
+    Ok(ThemeState {
+        preference,
+        resolved: resolve(&app, preference),
+    })
+}
+
+/// Saves the preference and switches every window to it.
+#[tauri::command]
+pub(crate) fn set_theme(app: AppHandle, theme: ThemePreference) -> Result<ThemeState, String> {
+    settings::modify_settings(&app, |settings| {
+        settings.theme = theme;
+        Ok(())
+    })?;
+
+    Ok(apply(&app, theme))
+}
+
+/// Applies a preference to the native windows and tells the webviews,
+/// without persisting it.
+pub(crate) fn apply(app: &AppHandle, preference: ThemePreference) -> ThemeState {
+    app.set_theme(native_theme(preference));
+
+    let state = ThemeState {
+        preference,
+        resolved: resolve(app, preference),
+    };
+    let _ = app.emit(THEME_CHANGED_EVENT, state.resolved);
+    state
+}
+
+/// Picks up the saved preference at startup.
+pub(crate) fn load(app: &AppHandle) {
+    let preference = settings::load_settings(app)
+        .map(|settings| settings.theme)
+        .unwrap_or_default();
+    apply(app, preference);
+}
+
+/// Runs on a window's `ThemeChanged` event, i.e. when the OS switches
+/// between light and dark.
+pub(crate) fn handle_os_theme_changed(app: &AppHandle, theme: Theme) {
+    let follows_system = settings::load_settings(app)
+        .map(|settings| settings.theme == ThemePreference::System)
+        .unwrap_or(true);
+    if follows_system {
+        let _ = app.emit(THEME_CHANGED_EVENT, mode_of(theme));
+    }
+}
+
+/// The theme a newly built window should be forced to, if any.
+pub(crate) fn window_theme(app: &AppHandle) -> Option<Theme> {
+    let preference = settings::load_settings(app)
+        .map(|settings| settings.theme)
+        .unwrap_or_default();
+    native_theme(preference)
+}
+
+fn native_theme(preference: ThemePreference) -> Option<Theme> {
+    match preference {
+        ThemePreference::Light => Some(Theme::Light),
+        ThemePreference::Dark => Some(Theme::Dark),
+        ThemePreference::System => None,
+    }
+}
+
+fn resolve(app: &AppHandle, preference: ThemePreference) -> ThemeMode {
+    match preference {
+        ThemePreference::Light => ThemeMode::Light,
+        ThemePreference::Dark => ThemeMode::Dark,
+        ThemePreference::System => [MAIN_WINDOW_LABEL, CAPTURE_WINDOW_LABEL]
+            .into_iter()
+            .filter_map(|label| app.get_webview_window(label))
+            .find_map(|window| window.theme().ok())
+            .map_or(ThemeMode::Light, mode_of),
+    }
+}
+
+fn mode_of(theme: Theme) -> ThemeMode {
+    match theme {
+        Theme::Dark => ThemeMode::Dark,
+        _ => ThemeMode::Light,
+    }
+}
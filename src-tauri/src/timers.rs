@@ -0,0 +1,82 @@
+//! Time tracking on notes. A running timer is just its start time on the
+//! note; stopping it appends a timestamped entry with the elapsed seconds,
+//! so a note such as "started the report" collects how long it took. The
+//! tray shows running timers.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{find_active_note_mut, Note, StorageState, NOTES_CHANGED_EVENT};
+
+/// One stretch of tracked time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct TimeEntry {
+    started_at: String,
+    stopped_at: String,
+    seconds: i64,
+}
+
+#[tauri::command]
+pub(crate) fn start_timer(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    note_id: String,
+) -> Result<Note, String> {
+    update(&app, &state, &note_id, |note, now| {
+        if note.timer_started_at.is_some() {
+            return Err("The timer is already running".to_string());
+        }
+        note.timer_started_at = Some(now.to_rfc3339());
+        Ok(())
+    })
+}
+
+/// Stops the note's timer and records the time since it started.
+#[tauri::command]
+pub(crate) fn stop_timer(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    note_id: String,
+) -> Result<Note, String> {
+    update(&app, &state, &note_id, |note, now| {
+        let started_at = running_since(note).ok_or_else(|| "No timer is running".to_string())?;
+        note.time_entries.push(TimeEntry {
+            started_at: started_at.to_rfc3339(),
+            stopped_at: now.to_rfc3339(),
+            seconds: (now - started_at).num_seconds().max(0),
+        });
+        note.timer_started_at = None;
+        Ok(())
+    })
+}
+
+/// When the note's timer was started, if it is running.
+pub(crate) fn running_since(note: &Note) -> Option<DateTime<Utc>> {
+    note.timer_started_at
+        .as_deref()
+        .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+        .map(|at| at.with_timezone(&Utc))
+}
+
+fn update(
+    app: &AppHandle,
+    state: &StorageState,
+    note_id: &str,
+    change: impl FnOnce(&mut Note, DateTime<Utc>) -> Result<(), String>,
+) -> Result<Note, String> {
+    let storage = state.lock(app)?;
+    let mut notes = storage.load_notes()?;
+    let note = find_active_note_mut(&mut notes, note_id)?;
+    let now = Utc::now();
+    change(note, now)?;
+    note.updated_at = Some(now.to_rfc3339());
+    let updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(updated)
+}
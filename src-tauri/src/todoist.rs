@@ -0,0 +1,135 @@
+//! Turns a note into a Todoist task through the REST API, since many
+//! captures are really to-dos. The note's title becomes the task, the rest
+//! of its text the description and its tags the task's labels.
+
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{
+    find_active_note_mut, http,
+    settings::{self, Secret},
+    undo, StorageState, NOTES_CHANGED_EVENT,
+};
+
+const TODOIST_API_URL: &str = "https://api.todoist.com/rest/v2";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct TodoistTask {
+    task_id: String,
+    url: String,
+    /// Whether the note was moved to the trash after being sent.
+    trashed: bool,
+}
+
+/// Creates a task from the note in `project`, a project name or id, or in
+/// the Inbox when `None`. With `todoist_trash_sent_notes` on, the note then
+/// goes to the trash, undoably.
+#[tauri::command]
+pub(crate) fn send_to_todoist(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+    note_id: String,
+    project: Option<String>,
+) -> Result<TodoistTask, String> {
+    let note = {
+        let storage = state.lock(&app)?;
+        let notes = storage.notes()?;
+        notes
+            .iter()
+            .find(|note| note.id == note_id && !note.is_trashed())
+            .cloned()
+            .ok_or_else(|| "Note not found".to_string())?
+    };
+
+    let settings = settings::load_settings(&app)?;
+    let token = Secret::TodoistToken
+        .get()?
+        .ok_or_else(|| "Set a Todoist API token in settings first".to_string())?;
+
+    let content = match note.title() {
+        "" => "Untitled",
+        title => title,
+    };
+    let mut body = json!({
+        "content": content,
+        "description": description(&note.text),
+        "labels": note.tags,
+    });
+    if let Some(project) = project.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        body["project_id"] = json!(project_id(&token, project)?);
+    }
+    let task = request(&token, "POST", "tasks", Some(&body))?;
+    let task_id = task["id"]
+        .as_str()
+        .ok_or_else(|| "Todoist did not return the new task".to_string())?
+        .to_string();
+    let url = task["url"].as_str().unwrap_or_default().to_string();
+
+    let trashed = settings.todoist_trash_sent_notes;
+    if trashed {
+        let storage = state.lock(&app)?;
+        let mut notes = storage.load_notes()?;
+        let note = find_active_note_mut(&mut notes, &note_id)?;
+        let before = note.clone();
+        note.deleted_at = Some(Utc::now().to_rfc3339());
+
+        storage.save_notes(&notes)?;
+        state.update_search_index(|index| index.remove(&note_id));
+        undo::record("Send to Todoist", vec![before]);
+        let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    }
+
+    Ok(TodoistTask {
+        task_id,
+        url,
+        trashed,
+    })
+}
+
+/// Everything after the title line.
+fn description(text: &str) -> String {
+    let mut lines = text.lines().skip_while(|line| line.trim().is_empty());
+    lines.next();
+    lines.collect::<Vec<_>>().join("\n").trim().to_string()
+}
+
+/// Accepts an id as well as a project name, matched case-insensitively.
+fn project_id(token: &str, project: &str) -> Result<String, String> {
+    let projects = request(token, "GET", "projects", None)?;
+    projects
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|candidate| {
+            candidate["id"].as_str() == Some(project)
+                || candidate["name"]
+                    .as_str()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(project))
+        })
+        .and_then(|candidate| candidate["id"].as_str())
+        .map(str::to_string)
+        .ok_or_else(|| format!("No Todoist project named \"{project}\""))
+}
+
+fn request(token: &str, method: &str, path: &str, body: Option<&Value>) -> Result<Value, String> {
+    let headers = [format!("Authorization: Bearer {token}")];
+    let response = http::send_json(method, &format!("{TODOIST_API_URL}/{path}"), &headers, body)?;
+    if response.is_success() {
+        return Ok(response.body);
+    }
+    match response
+        .body
+        .as_str()
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+    {
+        Some(message) => Err(format!("Todoist returned an error: {message}")),
+        None => Err(format!(
+            "Todoist request failed with HTTP {}",
+            response.status
+        )),
+    }
+}
@@ -0,0 +1,393 @@
+//! The tray icon, its menu and its tooltip. The menu is rebuilt from current
+//! state whenever notes or toggles change, so it never shows stale entries.
+
+use std::{sync::Mutex, thread, time::Duration};
+
+use chrono::{DateTime, Utc};
+use tauri::{
+    menu::{CheckMenuItemBuilder, Menu, MenuBuilder, MenuEvent, MenuItemBuilder, SubmenuBuilder},
+    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
+    AppHandle, Listener, Manager, Wry,
+};
+
+use crate::{
+    app_icon_image, autostart, clipboard_watcher, copy_note, deep_link, notifications, pomodoro,
+    settings::{self, TrayNoteAction},
+    show_capture_window, show_main_window,
+    sync::{self, SyncPhase, SyncStatus, SYNC_STATUS_EVENT},
+    timers, CopyFormat, Note, StorageState, NOTES_CHANGED_EVENT,
+};
+
+const TRAY_ID: &str = "jotin-tray";
+const RECENT_NOTES_LIMIT: usize = 8;
+const RECENT_LABEL_CHARS: usize = 40;
+const RECENT_ITEM_PREFIX: &str = "recent:";
+const SAVED_SEARCH_ITEM_PREFIX: &str = "search:";
+/// Keeps "synced 2m ago" current between events.
+const STATUS_TICK: Duration = Duration::from_secs(60);
+static REFRESH_LOCK: Mutex<()> = Mutex::new(());
+
+/// What the tooltip summarizes, updated piecemeal by the event that changed it.
+#[derive(Default)]
+pub(crate) struct TrayStatus(Mutex<StatusSnapshot>);
+
+#[derive(Default)]
+struct StatusSnapshot {
+    note_count: usize,
+    sync: Option<SyncStatus>,
+    timers: Vec<RunningTimer>,
+}
+
+/// Title and start time of a note whose timer is running.
+type RunningTimer = (String, DateTime<Utc>);
+
+pub(crate) fn setup(app: &AppHandle) -> Result<(), tauri::Error> {
+    let (note_count, recent, timers) = recent_notes(app);
+    app.manage(TrayStatus(Mutex::new(StatusSnapshot {
+        note_count,
+        sync: sync::get_sync_status(app.clone()).ok(),
+        timers,
+    })));
+    let menu = build_menu(app, &recent)?;
+
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(handle_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        });
+
+    if let Some(icon) = app_icon_image() {
+        tray = tray.icon(icon);
+    }
+
+    tray.build(app)?;
+    update_tooltip(app);
+
+    let handle = app.clone();
+    app.listen_any(NOTES_CHANGED_EVENT, move |_| refresh(&handle));
+    let handle = app.clone();
+    app.listen_any(SYNC_STATUS_EVENT, move |_| {
+        if let Some(status) = handle.try_state::<TrayStatus>() {
+            if let Ok(mut snapshot) = status.0.lock() {
+                snapshot.sync = sync::get_sync_status(handle.clone()).ok();
+            }
+        }
+        update_tooltip(&handle);
+    });
+    let handle = app.clone();
+    thread::spawn(move || loop {
+        thread::sleep(STATUS_TICK);
+        update_tooltip(&handle);
+    });
+    Ok(())
+}
+
+/// Rebuilds the menu on a background thread: `notes-changed` is emitted
+/// while the emitting command may still hold the storage lock.
+pub(crate) fn refresh(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let Ok(_guard) = REFRESH_LOCK.lock() else {
+            return;
+        };
+        let Some(tray) = app.tray_by_id(TRAY_ID) else {
+            return;
+        };
+
+        let (note_count, recent, timers) = recent_notes(&app);
+        if let Some(status) = app.try_state::<TrayStatus>() {
+            if let Ok(mut snapshot) = status.0.lock() {
+                snapshot.note_count = note_count;
+                snapshot.timers = timers;
+            }
+        }
+        if let Err(error) = build_menu(&app, &recent).and_then(|menu| tray.set_menu(Some(menu))) {
+            eprintln!("Failed to refresh tray menu: {error}");
+        }
+        update_tooltip(&app);
+    });
+}
+
+/// `Jotin — 142 notes, synced 2m ago`; on macOS the menu bar title shows
+/// just the count, or the pomodoro countdown or a timer's elapsed time
+/// while one runs.
+pub(crate) fn update_tooltip(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let Some(status) = app.try_state::<TrayStatus>() else {
+        return;
+    };
+    let (note_count, sync, timers) = match status.0.lock() {
+        Ok(snapshot) => (
+            snapshot.note_count,
+            snapshot.sync.clone(),
+            snapshot.timers.clone(),
+        ),
+        Err(_) => return,
+    };
+    let now = Utc::now();
+
+    let noun = if note_count == 1 { "note" } else { "notes" };
+    let mut tooltip = format!("Jotin \u{2014} {note_count} {noun}");
+    if let Some(sync) = sync.filter(|_| sync_configured(app)) {
+        tooltip.push_str(", ");
+        tooltip.push_str(&describe_sync(&sync, now));
+    }
+    let pomodoro = pomodoro::tray_label(app);
+    if let Some(pomodoro) = &pomodoro {
+        tooltip.push_str(", ");
+        tooltip.push_str(pomodoro);
+    }
+    for (title, started_at) in &timers {
+        tooltip.push_str(&format!(", \u{23F1} {title} {}", elapsed(*started_at, now)));
+    }
+    let _ = tray.set_tooltip(Some(tooltip));
+    #[cfg(target_os = "macos")]
+    let _ = tray.set_title(Some(match (pomodoro, timers.first()) {
+        (Some(pomodoro), _) => pomodoro,
+        (None, Some((_, started_at))) => format!("\u{23F1} {}", elapsed(*started_at, now)),
+        (None, None) => note_count.to_string(),
+    }));
+}
+
+/// `1h 05m`, or `12m` under an hour; minutes match the tooltip's tick.
+fn elapsed(since: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (now - since).num_minutes().max(0);
+    match minutes {
+        0..=59 => format!("{minutes}m"),
+        _ => format!("{}h {:02}m", minutes / 60, minutes % 60),
+    }
+}
+
+fn sync_configured(app: &AppHandle) -> bool {
+    settings::load_settings(app)
+        .map(|settings| settings.webdav_url.is_some() || settings.git_sync_enabled)
+        .unwrap_or(false)
+}
+
+fn describe_sync(sync: &SyncStatus, now: DateTime<Utc>) -> String {
+    match sync.phase() {
+        SyncPhase::Syncing => "syncing\u{2026}".to_string(),
+        SyncPhase::Error => "sync failed".to_string(),
+        SyncPhase::Idle => match sync.last_synced_at() {
+            Some(synced_at) => format!("synced {}", time_ago(synced_at, now)),
+            None => "not synced yet".to_string(),
+        },
+    }
+}
+
+fn time_ago(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (now - at).num_minutes().max(0);
+    match minutes {
+        0 => "just now".to_string(),
+        1..=59 => format!("{minutes}m ago"),
+        60..=1439 => format!("{}h ago", minutes / 60),
+        _ => format!("{}d ago", minutes / 1440),
+    }
+}
+
+fn build_menu(app: &AppHandle, recent: &[Note]) -> Result<Menu<Wry>, tauri::Error> {
+    let new_note = MenuItemBuilder::with_id("new_note", "New Note").build(app)?;
+    let open_notes = MenuItemBuilder::with_id("open_notes", "Open Notes").build(app)?;
+    let recent = build_recent_submenu(app, recent)?;
+    let saved_searches = build_saved_searches_submenu(app)?;
+    let launch_at_login = CheckMenuItemBuilder::with_id("launch_at_login", "Launch at Login")
+        .checked(autostart::is_autostart_enabled(app.clone()).unwrap_or(false))
+        .build(app)?;
+    let clipboard_watcher = CheckMenuItemBuilder::with_id("clipboard_watcher", "Watch Clipboard")
+        .checked(clipboard_watcher::is_enabled())
+        .build(app)?;
+    let do_not_disturb = CheckMenuItemBuilder::with_id("do_not_disturb", "Do Not Disturb")
+        .checked(notifications::is_do_not_disturb())
+        .build(app)?;
+    let capture_on_top =
+        CheckMenuItemBuilder::with_id("capture_always_on_top", "Capture Always on Top")
+            .checked(
+                settings::load_settings(app)
+                    .map(|settings| settings.capture_always_on_top)
+                    .unwrap_or(true),
+            )
+            .build(app)?;
+    let quit = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
+
+    let mut menu = MenuBuilder::new(app)
+        .item(&new_note)
+        .item(&open_notes)
+        .item(&recent);
+    if let Some(saved_searches) = &saved_searches {
+        menu = menu.item(saved_searches);
+    }
+    menu.separator()
+        .item(&launch_at_login)
+        .item(&clipboard_watcher)
+        .item(&do_not_disturb)
+        .item(&capture_on_top)
+        .separator()
+        .item(&quit)
+        .build()
+}
+
+fn build_recent_submenu(
+    app: &AppHandle,
+    notes: &[Note],
+) -> Result<tauri::menu::Submenu<Wry>, tauri::Error> {
+    let mut submenu = SubmenuBuilder::new(app, "Recent");
+    if notes.is_empty() {
+        let empty = MenuItemBuilder::with_id("recent_empty", "No Notes Yet")
+            .enabled(false)
+            .build(app)?;
+        submenu = submenu.item(&empty);
+    }
+    for note in notes {
+        let item =
+            MenuItemBuilder::with_id(format!("{RECENT_ITEM_PREFIX}{}", note.id), menu_label(note))
+                .build(app)?;
+        submenu = submenu.item(&item);
+    }
+    submenu.build()
+}
+
+/// The pinned saved searches, or `None` when there are none.
+fn build_saved_searches_submenu(
+    app: &AppHandle,
+) -> Result<Option<tauri::menu::Submenu<Wry>>, tauri::Error> {
+    let settings = settings::load_settings(app).unwrap_or_default();
+    let pinned: Vec<_> = settings
+        .saved_searches
+        .iter()
+        .filter(|saved| saved.is_pinned())
+        .collect();
+    if pinned.is_empty() {
+        return Ok(None);
+    }
+
+    let mut submenu = SubmenuBuilder::new(app, "Saved Searches");
+    for saved in pinned {
+        let item = MenuItemBuilder::with_id(
+            format!("{SAVED_SEARCH_ITEM_PREFIX}{}", saved.id()),
+            saved.name().replace('&', "&&"),
+        )
+        .build(app)?;
+        submenu = submenu.item(&item);
+    }
+    submenu.build().map(Some)
+}
+
+/// How many notes are active, the latest few, most recently created or
+/// edited first, and the running timers.
+fn recent_notes(app: &AppHandle) -> (usize, Vec<Note>, Vec<RunningTimer>) {
+    let state = app.state::<StorageState>();
+    let notes = match state.lock(app).and_then(|storage| storage.notes()) {
+        Ok(notes) => notes,
+        Err(error) => {
+            eprintln!("Failed to load notes for the tray: {error}");
+            return (0, Vec::new(), Vec::new());
+        }
+    };
+
+    let mut active: Vec<&Note> = notes.iter().filter(|note| !note.is_trashed()).collect();
+    active.sort_by_key(|note| std::cmp::Reverse(note.revised_at()));
+    let recent = active
+        .iter()
+        .take(RECENT_NOTES_LIMIT)
+        .map(|note| (*note).clone())
+        .collect();
+    let timers = active
+        .iter()
+        .filter_map(|note| Some((note.title().to_string(), timers::running_since(note)?)))
+        .collect();
+    (active.len(), recent, timers)
+}
+
+/// The note title, shortened; `&` is doubled so it is not read as a mnemonic.
+fn menu_label(note: &Note) -> String {
+    let title = note.title();
+    let mut label: String = title.chars().take(RECENT_LABEL_CHARS).collect();
+    if title.chars().count() > RECENT_LABEL_CHARS {
+        label = format!("{}…", label.trim_end());
+    }
+    if label.is_empty() {
+        label = "Untitled".to_string();
+    }
+    label.replace('&', "&&")
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    if let Some(note_id) = event.id.as_ref().strip_prefix(RECENT_ITEM_PREFIX) {
+        open_recent_note(app, note_id);
+        return;
+    }
+    if let Some(id) = event.id.as_ref().strip_prefix(SAVED_SEARCH_ITEM_PREFIX) {
+        deep_link::open_saved_search(app, id);
+        return;
+    }
+
+    match event.id.as_ref() {
+        "new_note" => {
+            if let Err(error) = show_capture_window(app) {
+                eprintln!("{error}");
+            }
+        }
+        "open_notes" => show_main_window(app),
+        "launch_at_login" => {
+            let enable = !autostart::is_autostart_enabled(app.clone()).unwrap_or(false);
+            if let Err(error) = autostart::set_autostart(app, enable) {
+                eprintln!("{error}");
+            }
+        }
+        "clipboard_watcher" => {
+            let enable = !clipboard_watcher::is_enabled();
+            if let Err(error) = clipboard_watcher::set_clipboard_watcher(app.clone(), enable) {
+                eprintln!("{error}");
+            }
+        }
+        "do_not_disturb" => {
+            let enable = !notifications::is_do_not_disturb();
+            if let Err(error) = notifications::set_do_not_disturb(app.clone(), enable) {
+                eprintln!("{error}");
+            }
+        }
+        "capture_always_on_top" => {
+            let enable = !settings::load_settings(app)
+                .map(|settings| settings.capture_always_on_top)
+                .unwrap_or(true);
+            if let Err(error) = settings::set_capture_always_on_top(app.clone(), enable) {
+                eprintln!("{error}");
+            }
+        }
+        "quit" => app.exit(0),
+        _ => {}
+    }
+}
+
+fn open_recent_note(app: &AppHandle, note_id: &str) {
+    let action = settings::load_settings(app)
+        .map(|settings| settings.tray_recent_action)
+        .unwrap_or_default();
+
+    match action {
+        TrayNoteAction::Open => deep_link::open_note(app, note_id),
+        TrayNoteAction::Copy => {
+            let state = app.state::<StorageState>();
+            let copied = copy_note(
+                app.clone(),
+                state,
+                note_id.to_string(),
+                Some(CopyFormat::Markdown),
+            );
+            if let Err(error) = copied {
+                eprintln!("{error}");
+            }
+        }
+    }
+}
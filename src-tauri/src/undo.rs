@@ -0,0 +1,101 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Mutex,
+    time::Instant,
+};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{settings, Note, StorageState, NOTES_CHANGED_EVENT};
+
+pub(crate) const DEFAULT_UNDO_WINDOW_SECS: u32 = 30;
+const MAX_UNDO_ENTRIES: usize = 20;
+
+static UNDO_STACK: Mutex<VecDeque<UndoEntry>> = Mutex::new(VecDeque::new());
+
+/// How to reverse one operation: the affected notes as they were before it.
+/// Undoing writes them back by id, re-adding any that no longer exist.
+struct UndoEntry {
+    label: String,
+    recorded_at: Instant,
+    notes_before: Vec<Note>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct UndoSummary {
+    label: String,
+    restored: usize,
+}
+
+/// Reverses the most recent operation if it is still inside the undo window.
+/// Returns `None` when there is nothing left to undo.
+#[tauri::command]
+pub(crate) fn undo_last_operation(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+) -> Result<Option<UndoSummary>, String> {
+    let window_secs = settings::load_settings(&app)?.undo_window_seconds;
+    let entry = {
+        let mut stack = UNDO_STACK
+            .lock()
+            .map_err(|_| "Undo stack was poisoned".to_string())?;
+        stack.retain(|entry| entry.recorded_at.elapsed().as_secs() < u64::from(window_secs));
+        stack.pop_back()
+    };
+    let Some(entry) = entry else {
+        return Ok(None);
+    };
+
+    let storage = state.lock(&app)?;
+
+    let mut notes = storage.load_notes()?;
+    for before in &entry.notes_before {
+        match notes.iter_mut().find(|note| note.id == before.id) {
+            Some(note) => *note = before.clone(),
+            None => notes.push(before.clone()),
+        }
+    }
+
+    storage.save_notes(&notes)?;
+    state.invalidate_search_index();
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(Some(UndoSummary {
+        label: entry.label,
+        restored: entry.notes_before.len(),
+    }))
+}
+
+/// Drops purged notes from the undo stack so undoing cannot bring them back.
+pub(crate) fn forget_notes(ids: &HashSet<&str>) {
+    let Ok(mut stack) = UNDO_STACK.lock() else {
+        return;
+    };
+    for entry in stack.iter_mut() {
+        entry
+            .notes_before
+            .retain(|note| !ids.contains(note.id.as_str()));
+    }
+    stack.retain(|entry| !entry.notes_before.is_empty());
+}
+
+/// Remembers the notes an operation is about to change so it can be undone.
+pub(crate) fn record(label: &str, notes_before: Vec<Note>) {
+    if notes_before.is_empty() {
+        return;
+    }
+    let Ok(mut stack) = UNDO_STACK.lock() else {
+        return;
+    };
+
+    stack.push_back(UndoEntry {
+        label: label.to_string(),
+        recorded_at: Instant::now(),
+        notes_before,
+    });
+    while stack.len() > MAX_UNDO_ENTRIES {
+        stack.pop_front();
+    }
+}
@@ -0,0 +1,340 @@
+//! Link previews: the page title and favicon of each http(s) link in a note,
+//! so lists can show "GitHub – tauri-apps/tauri" instead of a raw URL.
+//! Fetched in the background through `curl` after a note is saved, cached
+//! on disk by URL, and stored on the note as `link_previews`. Nothing is
+//! fetched in offline mode.
+
+use std::{collections::HashMap, fs, io::Read, sync::Mutex, thread};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Url};
+
+use crate::{
+    http, resolve_app_data_file, settings, write_file_atomically, Note, StorageState,
+    NOTES_CHANGED_EVENT,
+};
+
+const CACHE_FILE_NAME: &str = "link-previews.json";
+/// Cached previews are fetched again after this long.
+const CACHE_MAX_AGE_DAYS: i64 = 7;
+const MAX_LINKS_PER_NOTE: usize = 10;
+/// The title sits in `<head>`, so the rest of a page is never read.
+const MAX_PAGE_BYTES: u64 = 256 * 1024;
+const MAX_FAVICON_BYTES: u64 = 64 * 1024;
+const MAX_TITLE_CHARS: usize = 200;
+const FETCH_TIMEOUT_SECS: u32 = 10;
+const USER_AGENT: &str = "Mozilla/5.0 (compatible; jotin link preview)";
+static CACHE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) struct LinkPreview {
+    url: String,
+    title: Option<String>,
+    /// The favicon as a `data:` URL, so showing it needs no request.
+    favicon: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+struct CachedPreview {
+    #[serde(flatten)]
+    preview: LinkPreview,
+    fetched_at: String,
+}
+
+/// Previews the note's links in the background and stores them on it.
+pub(crate) fn refresh(app: &AppHandle, note: &Note) {
+    let urls = links(note);
+    let has_previews = !note.link_previews.is_empty();
+    if urls.is_empty() && !has_previews {
+        return;
+    }
+    if settings::load_settings(app).is_ok_and(|settings| settings.offline_mode) {
+        return;
+    }
+
+    let app = app.clone();
+    let note_id = note.id.clone();
+    thread::spawn(move || {
+        let previews: Vec<LinkPreview> = urls.iter().map(|url| preview(&app, url)).collect();
+        if let Err(error) = store(&app, &note_id, &urls, previews) {
+            eprintln!("Failed to store link previews: {error}");
+        }
+    });
+}
+
+pub(crate) fn links(note: &Note) -> Vec<String> {
+    note.metadata
+        .links
+        .iter()
+        .filter(|link| link.starts_with("http://") || link.starts_with("https://"))
+        .take(MAX_LINKS_PER_NOTE)
+        .cloned()
+        .collect()
+}
+
+/// Saves the previews unless the note's links changed meanwhile; the save
+/// that changed them started its own refresh.
+fn store(
+    app: &AppHandle,
+    note_id: &str,
+    urls: &[String],
+    previews: Vec<LinkPreview>,
+) -> Result<(), String> {
+    let state = app.state::<StorageState>();
+    let storage = state.lock(app)?;
+    let mut notes = storage.load_notes()?;
+    let Some(note) = notes.iter_mut().find(|note| note.id == note_id) else {
+        return Ok(());
+    };
+    if links(note) != urls || note.link_previews == previews {
+        return Ok(());
+    }
+    note.link_previews = previews;
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let updated = note.clone();
+    storage.save_notes(&notes)?;
+    if !updated.is_trashed() {
+        state.update_search_index(|index| index.upsert(&updated));
+    }
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(())
+}
+
+/// The cached preview when fresh, otherwise a new one. A page that cannot
+/// be fetched still gets a preview without a title, cached like the rest so
+/// it is not retried on every save.
+fn preview(app: &AppHandle, url: &str) -> LinkPreview {
+    let cutoff = Utc::now() - Duration::days(CACHE_MAX_AGE_DAYS);
+    let cached = load_cache(app).remove(url).filter(|cached| {
+        DateTime::parse_from_rfc3339(&cached.fetched_at).is_ok_and(|at| at > cutoff)
+    });
+    if let Some(cached) = cached {
+        return cached.preview;
+    }
+
+    let preview = fetch_preview(url);
+    if let Err(error) = save_to_cache(app, &preview) {
+        eprintln!("Failed to cache link preview: {error}");
+    }
+    preview
+}
+
+fn fetch_preview(url: &str) -> LinkPreview {
+    let page = fetch(url, MAX_PAGE_BYTES).unwrap_or_default();
+    let html = String::from_utf8_lossy(&page);
+    // The href comes from the page, which may point anywhere.
+    let favicon = Url::parse(url)
+        .ok()
+        .and_then(|base| base.join(&favicon_href(&html)).ok())
+        .filter(|icon_url| matches!(icon_url.scheme(), "http" | "https"))
+        .and_then(|icon_url| fetch(icon_url.as_str(), MAX_FAVICON_BYTES))
+        .and_then(|icon| data_url(&icon));
+    LinkPreview {
+        url: url.to_string(),
+        title: page_title(&html),
+        favicon,
+    }
+}
+
+/// Reads at most `limit` bytes of the response body; `None` when the
+/// request fails or returns an HTTP error.
+pub(crate) fn fetch(url: &str, limit: u64) -> Option<Vec<u8>> {
+    let config = format!(
+        "url = {}\nuser-agent = {}\n",
+        http::quote_curl_value(url).ok()?,
+        http::quote_curl_value(USER_AGENT).ok()?
+    );
+    let mut child = http::spawn_curl(
+        http::WEB_PROTOCOLS,
+        FETCH_TIMEOUT_SECS,
+        &config,
+        &["--fail", "--location", "--compressed"],
+    )
+    .ok()?;
+    let mut body = Vec::new();
+    let read = child
+        .stdout
+        .take()
+        .map(|stdout| stdout.take(limit).read_to_end(&mut body));
+    // Stop downloading the rest of a large page.
+    let _ = child.kill();
+    let status = child.wait().ok()?;
+    if !matches!(read, Some(Ok(_))) || body.is_empty() {
+        return None;
+    }
+    // Killed once the limit was reached, which is not a failure.
+    (status.success() || body.len() as u64 == limit).then_some(body)
+}
+
+/// `<title>`, falling back to `og:title`.
+pub(crate) fn page_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let title = lower
+        .find("<title")
+        .and_then(|start| Some(start + lower[start..].find('>')? + 1))
+        .and_then(|start| {
+            let end = start + lower[start..].find("</title")?;
+            Some(decode_entities(&html[start..end]))
+        })
+        .or_else(|| {
+            tags(html, "meta")
+                .find(|tag| attribute(tag, "property").as_deref() == Some("og:title"))
+                .and_then(|tag| attribute(tag, "content"))
+        })?;
+
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    let title: String = title.chars().take(MAX_TITLE_CHARS).collect();
+    (!title.is_empty()).then_some(title)
+}
+
+/// The first `<link rel="icon">` (or `shortcut icon`, `apple-touch-icon`),
+/// else the conventional `/favicon.ico`.
+fn favicon_href(html: &str) -> String {
+    tags(html, "link")
+        .find(|tag| {
+            attribute(tag, "rel").is_some_and(|rel| {
+                rel.to_ascii_lowercase()
+                    .split_whitespace()
+                    .any(|rel| rel == "icon" || rel == "apple-touch-icon")
+            })
+        })
+        .and_then(|tag| attribute(tag, "href"))
+        .unwrap_or_else(|| "/favicon.ico".to_string())
+}
+
+/// The text of each `<name ...>` tag, from its name to the closing `>`.
+fn tags<'a>(html: &'a str, name: &str) -> impl Iterator<Item = &'a str> {
+    let lower = html.to_ascii_lowercase();
+    let opening = format!("<{name}");
+    let mut starts = Vec::new();
+    let mut offset = 0;
+    while let Some(found) = lower[offset..].find(&opening) {
+        let start = offset + found + opening.len();
+        let Some(end) = lower[start..].find('>') else {
+            break;
+        };
+        starts.push((start, start + end));
+        offset = start + end;
+    }
+    starts
+        .into_iter()
+        .map(move |(start, end)| &html[start..end])
+}
+
+/// The value of `name="..."`, `name='...'` or `name=...` in a tag.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut offset = 0;
+    while let Some(found) = lower[offset..].find(name) {
+        let start = offset + found;
+        offset = start + name.len();
+        let preceded = lower[..start].ends_with(|c: char| c.is_whitespace());
+        let rest = lower[offset..].trim_start();
+        if !preceded || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - rest.len() + 1;
+        let value = tag[value_start..].trim_start();
+        let value = match value.chars().next()? {
+            quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+            _ => value
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()?,
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..end]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Sniffed rather than trusted from the URL; anything that is not a known
+/// image format is dropped.
+fn data_url(icon: &[u8]) -> Option<String> {
+    let mime = match icon {
+        [0x89, b'P', b'N', b'G', ..] => "image/png",
+        [0x00, 0x00, 0x01, 0x00, ..] => "image/x-icon",
+        [b'G', b'I', b'F', b'8', ..] => "image/gif",
+        [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        _ if String::from_utf8_lossy(&icon[..icon.len().min(512)]).contains("<svg") => {
+            "image/svg+xml"
+        }
+        _ => return None,
+    };
+    Some(format!("data:{mime};base64,{}", STANDARD.encode(icon)))
+}
+
+fn load_cache(app: &AppHandle) -> HashMap<String, CachedPreview> {
+    resolve_app_data_file(app, CACHE_FILE_NAME)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn save_to_cache(app: &AppHandle, preview: &LinkPreview) -> Result<(), String> {
+    let _guard = CACHE_LOCK
+        .lock()
+        .map_err(|_| "Link preview cache lock was poisoned".to_string())?;
+    let mut cache = load_cache(app);
+    let cutoff = Utc::now() - Duration::days(CACHE_MAX_AGE_DAYS);
+    cache.retain(|_, cached| {
+        DateTime::parse_from_rfc3339(&cached.fetched_at).is_ok_and(|at| at > cutoff)
+    });
+    cache.insert(
+        preview.url.clone(),
+        CachedPreview {
+            preview: preview.clone(),
+            fetched_at: Utc::now().to_rfc3339(),
+        },
+    );
+
+    let payload = serde_json::to_string_pretty(&cache)
+        .map_err(|e| format!("Failed to serialize link previews: {e}"))?;
+    write_file_atomically(&resolve_app_data_file(app, CACHE_FILE_NAME)?, &payload)
+}
@@ -0,0 +1,480 @@
+//! An Obsidian vault as the notes storage. Each note is a Markdown file in
+//! the vault, edited by jotin and Obsidian alike, and new captures land as
+//! files in the capture folder. What Markdown cannot hold (pins, colors,
+//! notebooks, the trash, where each file is) stays in `.jotin/notes.json`
+//! inside the vault, so it travels with it. The notes watcher picks up edits
+//! made outside jotin through the vault's `stamp`.
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    export, frontmatter, import, normalize_tags, notes_stamp, save_notes_to_path, schema,
+    settings::Settings, FileStamp, Note, NOTES_FILE_NAME,
+};
+
+/// Hidden from Obsidian, like its own `.obsidian` folder.
+const SIDECAR_DIR_NAME: &str = ".jotin";
+/// Where Obsidian's "move to .trash folder" option puts deleted files.
+const TRASH_DIR_NAME: &str = ".trash";
+pub(crate) const DEFAULT_VAULT_CAPTURE_FOLDER: &str = "Inbox";
+/// A folder changed more recently than this is listed again next time:
+/// some filesystems keep modification times in whole seconds or two, so an
+/// entry added right after the listing may leave its time unchanged.
+const LISTING_SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// The vault's folders as last listed. `stamp` runs on every storage lock
+/// and every watcher tick, so folders whose modification time is unchanged
+/// (nothing added, removed or renamed in them) are not read again; the
+/// Markdown files are still checked one by one for edits.
+static LISTINGS: Mutex<BTreeMap<PathBuf, Listing>> = Mutex::new(BTreeMap::new());
+
+struct Listing {
+    modified: SystemTime,
+    dirs: Vec<PathBuf>,
+    markdown_files: Vec<PathBuf>,
+}
+
+pub(crate) struct Vault {
+    root: PathBuf,
+    capture_folder: String,
+}
+
+impl Vault {
+    /// The vault the settings point at, if vault storage is on.
+    pub(crate) fn configured(settings: &Settings) -> Option<Self> {
+        let root = settings.vault_dir.as_deref()?;
+        Some(Self {
+            root: PathBuf::from(root),
+            capture_folder: settings.vault_capture_folder.clone(),
+        })
+    }
+
+    /// The notes file that keeps everything the Markdown files do not.
+    pub(crate) fn sidecar_path(&self) -> Result<PathBuf, String> {
+        let dir = self.root.join(SIDECAR_DIR_NAME);
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create vault data folder: {e}"))?;
+        Ok(dir.join(NOTES_FILE_NAME))
+    }
+
+    /// Fingerprint of the sidecar and every Markdown file, which changes
+    /// whenever a file is added, removed, renamed or edited.
+    pub(crate) fn stamp(&self, sidecar: &Path) -> Option<FileStamp> {
+        let (mut latest, sidecar_len) = notes_stamp(sidecar).unwrap_or((SystemTime::UNIX_EPOCH, 0));
+        let mut hasher = DefaultHasher::new();
+        sidecar_len.hash(&mut hasher);
+        for (relative, path) in self.markdown_files() {
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            latest = latest.max(modified);
+            (relative, metadata.len(), modified).hash(&mut hasher);
+        }
+        Some((latest, hasher.finish()))
+    }
+
+    /// The notes as the vault files have them, completed from the sidecar.
+    pub(crate) fn load(&self, sidecar: &Path) -> Result<Vec<Note>, String> {
+        let known = schema::read_upgrading(sidecar)?;
+        let by_path: HashMap<&str, usize> = known
+            .iter()
+            .enumerate()
+            .filter_map(|(position, note)| Some((note.vault_path.as_deref()?, position)))
+            .collect();
+        let by_id: HashMap<&str, usize> = known
+            .iter()
+            .enumerate()
+            .map(|(position, note)| (note.id.as_str(), position))
+            .collect();
+
+        let mut matched = HashSet::new();
+        let mut ids: HashSet<String> = known.iter().map(|note| note.id.clone()).collect();
+        let mut notes = Vec::new();
+        for (relative, path) in self.markdown_files() {
+            let raw = match fs::read_to_string(&path) {
+                Ok(raw) => raw,
+                Err(error) => {
+                    eprintln!("Failed to read {}: {error}", path.display());
+                    continue;
+                }
+            };
+            let document = frontmatter::parse_markdown(&raw);
+            let modified_at = import::file_modified_at(&path);
+
+            // Moved or renamed in Obsidian: the path is new but the id is not.
+            let known_position = by_path
+                .get(relative.as_str())
+                .or_else(|| document.id.as_deref().and_then(|id| by_id.get(id)))
+                .copied()
+                .filter(|position| matched.insert(*position));
+            let mut note = match known_position {
+                Some(position) => known[position].clone(),
+                None => {
+                    let mut note = Note::new(document.body.clone());
+                    note.id = document
+                        .id
+                        .clone()
+                        .filter(|id| Uuid::parse_str(id).is_ok() && !ids.contains(id))
+                        .unwrap_or(note.id);
+                    ids.insert(note.id.clone());
+                    note.created_at = document
+                        .created_at
+                        .as_deref()
+                        .and_then(import::normalize_timestamp)
+                        .or_else(|| modified_at.clone())
+                        .unwrap_or(note.created_at);
+                    note
+                }
+            };
+
+            let tags = normalize_tags(document.tags);
+            if note.text != document.body || note.tags != tags {
+                note.set_text(document.body);
+                note.tags = tags;
+                note.updated_at = document
+                    .updated_at
+                    .as_deref()
+                    .and_then(import::normalize_timestamp)
+                    .or(modified_at);
+            }
+            note.vault_path = Some(relative);
+            note.deleted_at = None;
+            notes.push(note);
+        }
+
+        // Trashed notes have no file; neither do captures not written yet,
+        // e.g. from the CLI. Any other note's file was deleted in the vault.
+        for (position, note) in known.into_iter().enumerate() {
+            if !matched.contains(&position) && (note.is_trashed() || note.vault_path.is_none()) {
+                notes.push(note);
+            }
+        }
+        Ok(notes)
+    }
+
+    /// Writes the notes that changed since `previous` to their files, gives
+    /// new notes a file in the capture folder and moves the files of trashed
+    /// or deleted notes to the vault's trash. Returns the notes as saved.
+    pub(crate) fn save(
+        &self,
+        sidecar: &Path,
+        notes: &[Note],
+        previous: &[Note],
+    ) -> Result<Vec<Note>, String> {
+        let previous: HashMap<&str, &Note> = previous
+            .iter()
+            .map(|note| (note.id.as_str(), note))
+            .collect();
+        let mut notes = notes.to_vec();
+        let mut used_paths: HashSet<String> = notes
+            .iter()
+            .filter_map(|note| note.vault_path.as_ref())
+            .map(|path| path.to_lowercase())
+            .collect();
+
+        for note in notes.iter_mut().filter(|note| !note.is_trashed()) {
+            let before = previous.get(note.id.as_str()).copied();
+            let unchanged = before.is_some_and(|before| {
+                !before.is_trashed()
+                    && before.text == note.text
+                    && before.tags == note.tags
+                    && before.vault_path == note.vault_path
+            });
+            if unchanged && note.vault_path.is_some() {
+                continue;
+            }
+            if note.vault_path.is_none() {
+                note.vault_path = Some(self.new_file_path(note, &mut used_paths));
+            }
+            self.write_note(note)?;
+        }
+
+        let kept: HashMap<&str, &Note> = notes
+            .iter()
+            .filter(|note| !note.is_trashed())
+            .map(|note| (note.id.as_str(), note))
+            .collect();
+        for before in previous.values().filter(|note| !note.is_trashed()) {
+            let Some(path) = before.vault_path.as_deref() else {
+                continue;
+            };
+            let still_there = kept
+                .get(before.id.as_str())
+                .is_some_and(|note| note.vault_path.as_deref() == Some(path));
+            if !still_there {
+                self.move_to_trash(path)?;
+            }
+        }
+
+        save_notes_to_path(sidecar, &notes)?;
+        Ok(notes)
+    }
+
+    /// Rewrites a note's file, keeping whatever frontmatter Obsidian or the
+    /// user put there and updating only the keys jotin owns.
+    fn write_note(&self, note: &Note) -> Result<(), String> {
+        let Some(relative) = note.vault_path.as_deref() else {
+            return Ok(());
+        };
+        let path = self.root.join(relative);
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        let header = frontmatter::split_frontmatter(&existing)
+            .map(|(header, _)| header.to_string())
+            .unwrap_or_else(|| format!("created_at: {}\n", frontmatter::quote(&note.created_at)));
+
+        let tags: Vec<String> = note
+            .tags
+            .iter()
+            .map(|tag| frontmatter::quote(tag))
+            .collect();
+        let header = frontmatter::upsert_fields(
+            &header,
+            &[
+                ("id", frontmatter::quote(&note.id)),
+                ("tags", format!("[{}]", tags.join(", "))),
+            ],
+        );
+        let contents = format!("---\n{header}---\n\n{}\n", note.text.trim_end());
+        if contents == existing {
+            return Ok(());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+        }
+        fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    fn new_file_path(&self, note: &Note, used_paths: &mut HashSet<String>) -> String {
+        let stem = export::sanitize_file_stem(note.title()).unwrap_or_else(|| note.id.clone());
+        let folder = self.capture_folder.trim_matches('/');
+        let prefix = if folder.is_empty() {
+            String::new()
+        } else {
+            format!("{folder}/")
+        };
+
+        let mut candidate = format!("{prefix}{stem}.md");
+        let mut counter = 2;
+        while self.root.join(&candidate).exists() || !used_paths.insert(candidate.to_lowercase()) {
+            candidate = format!("{prefix}{stem} {counter}.md");
+            counter += 1;
+        }
+        candidate
+    }
+
+    fn move_to_trash(&self, relative: &str) -> Result<(), String> {
+        let path = self.root.join(relative);
+        if !path.exists() {
+            return Ok(());
+        }
+        let trash = self.root.join(TRASH_DIR_NAME);
+        fs::create_dir_all(&trash).map_err(|e| format!("Failed to create vault trash: {e}"))?;
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("{}.md", Uuid::new_v4()));
+        let mut target = trash.join(&name);
+        if target.exists() {
+            let stamp = DateTime::<Utc>::from(SystemTime::now()).format("%Y%m%d%H%M%S");
+            target = trash.join(format!("{stamp} {name}"));
+        }
+        fs::rename(&path, &target)
+            .map_err(|e| format!("Failed to move {} to the vault trash: {e}", path.display()))
+    }
+
+    /// Files in the vault trash that belong to the given notes, recognized by
+    /// the id in their frontmatter.
+    pub(crate) fn trashed_files(&self, ids: &HashSet<&str>) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(self.root.join(TRASH_DIR_NAME)) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                fs::read_to_string(path).is_ok_and(|raw| {
+                    frontmatter::parse_markdown(&raw)
+                        .id
+                        .is_some_and(|id| ids.contains(id.as_str()))
+                })
+            })
+            .collect()
+    }
+
+    /// Markdown files under the root with their `/`-separated relative paths,
+    /// skipping hidden folders such as `.obsidian`, `.trash` and `.jotin`.
+    fn markdown_files(&self) -> Vec<(String, PathBuf)> {
+        let mut previous = LISTINGS
+            .lock()
+            .map(|mut listings| std::mem::take(&mut *listings))
+            .unwrap_or_default();
+        let mut listings = BTreeMap::new();
+        let mut files = Vec::new();
+        collect_markdown_files(
+            &self.root,
+            &self.root,
+            &mut previous,
+            &mut listings,
+            &mut files,
+        );
+        if let Ok(mut cached) = LISTINGS.lock() {
+            *cached = listings;
+        }
+        files.sort();
+        files
+    }
+}
+
+/// Walks `dir`, reusing the listings in `previous` that are still current
+/// and keeping the ones worth reusing next time in `listings`.
+fn collect_markdown_files(
+    root: &Path,
+    dir: &Path,
+    previous: &mut BTreeMap<PathBuf, Listing>,
+    listings: &mut BTreeMap<PathBuf, Listing>,
+    files: &mut Vec<(String, PathBuf)>,
+) {
+    let Ok(modified) = fs::metadata(dir).and_then(|metadata| metadata.modified()) else {
+        return;
+    };
+    let listing = match previous.remove(dir) {
+        Some(listing) if listing.modified == modified => listing,
+        _ => match list_dir(dir, modified) {
+            Some(listing) => listing,
+            None => return,
+        },
+    };
+
+    for path in &listing.markdown_files {
+        let relative = path
+            .strip_prefix(root)
+            .unwrap_or(path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.push((relative, path.clone()));
+    }
+    for subdir in &listing.dirs {
+        collect_markdown_files(root, subdir, previous, listings, files);
+    }
+
+    let settled = SystemTime::now()
+        .duration_since(modified)
+        .is_ok_and(|age| age >= LISTING_SETTLE_TIME);
+    if settled {
+        listings.insert(dir.to_path_buf(), listing);
+    }
+}
+
+/// The folders and Markdown files directly in `dir`, skipping hidden
+/// entries such as `.obsidian`, `.trash` and `.jotin`.
+fn list_dir(dir: &Path, modified: SystemTime) -> Option<Listing> {
+    let entries = fs::read_dir(dir).ok()?;
+    let mut listing = Listing {
+        modified,
+        dirs: Vec::new(),
+        markdown_files: Vec::new(),
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        // Symlinks are skipped rather than followed, as on import, so a link
+        // back up the vault cannot make the walk loop.
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_symlink() {
+            continue;
+        }
+
+        let path = entry.path();
+        if file_type.is_dir() {
+            listing.dirs.push(path);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("md"))
+        {
+            listing.markdown_files.push(path);
+        }
+    }
+    Some(listing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_vault() -> Vault {
+        let root = std::env::temp_dir().join(format!("jotin-vault-{}", Uuid::new_v4()));
+        fs::create_dir_all(root.join("Inbox")).unwrap();
+        Vault {
+            root,
+            capture_folder: DEFAULT_VAULT_CAPTURE_FOLDER.to_string(),
+        }
+    }
+
+    fn relative_paths(vault: &Vault) -> Vec<String> {
+        vault
+            .markdown_files()
+            .into_iter()
+            .map(|(relative, _)| relative)
+            .collect()
+    }
+
+    /// Ages a folder past `LISTING_SETTLE_TIME`, so its listing is reused.
+    fn backdate(dir: &Path) {
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::open(dir)
+            .and_then(|dir| dir.set_modified(an_hour_ago))
+            .unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_symlinks_and_hidden_folders() {
+        let vault = temp_vault();
+        fs::write(vault.root.join("Inbox/a.md"), "a").unwrap();
+        fs::create_dir_all(vault.root.join(".obsidian")).unwrap();
+        fs::write(vault.root.join(".obsidian/b.md"), "b").unwrap();
+        std::os::unix::fs::symlink(&vault.root, vault.root.join("Inbox/loop")).unwrap();
+        std::os::unix::fs::symlink(vault.root.join("Inbox/a.md"), vault.root.join("c.md")).unwrap();
+
+        assert_eq!(relative_paths(&vault), vec!["Inbox/a.md"]);
+        fs::remove_dir_all(&vault.root).unwrap();
+    }
+
+    #[test]
+    fn stamp_sees_files_added_to_and_edited_in_listed_folders() {
+        let vault = temp_vault();
+        let sidecar = vault.sidecar_path().unwrap();
+        fs::write(vault.root.join("Inbox/a.md"), "a").unwrap();
+        backdate(&vault.root.join("Inbox"));
+        backdate(&vault.root);
+        let first = vault.stamp(&sidecar);
+        assert_eq!(vault.stamp(&sidecar), first);
+
+        fs::write(vault.root.join("Inbox/b.md"), "b").unwrap();
+        assert_eq!(relative_paths(&vault), vec!["Inbox/a.md", "Inbox/b.md"]);
+        let added = vault.stamp(&sidecar);
+        assert_ne!(added, first);
+
+        fs::write(vault.root.join("Inbox/a.md"), "edited").unwrap();
+        assert_ne!(vault.stamp(&sidecar), added);
+        fs::remove_dir_all(&vault.root).unwrap();
+    }
+}
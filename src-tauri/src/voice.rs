@@ -0,0 +1,351 @@
+//! Voice notes: audio from the default microphone is recorded through `sox`
+//! (`arecord` on Linux), saved as a WAV attachment and transcribed in the
+//! background with the whisper.cpp command line tool.
+
+use std::{
+    fs,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+use crate::{
+    attachments, insert_prepared_note, notifications, settings, Note, StorageState,
+    NOTES_CHANGED_EVENT,
+};
+
+const RECORDING_CHANGED_EVENT: &str = "voice-recording-changed";
+const TRANSCRIPTION_FINISHED_EVENT: &str = "transcription-finished";
+/// Text of a voice note until its transcript arrives.
+const VOICE_NOTE_PLACEHOLDER: &str = "Voice note";
+/// Releasing push-to-talk sooner than this counts as an accidental tap.
+const MIN_RECORDING: Duration = Duration::from_millis(400);
+/// whisper.cpp's command line tool, under the names it is packaged as.
+const WHISPER_BINARIES: [&str; 3] = ["whisper-cli", "whisper-cpp", "whisper"];
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+
+struct Recording {
+    recorder: Child,
+    path: PathBuf,
+    started_at: Instant,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+struct TranscriptionFinished {
+    note_id: String,
+    transcribed: bool,
+}
+
+/// Starts recording from the default microphone.
+#[tauri::command]
+pub(crate) fn start_voice_note(app: AppHandle) -> Result<(), String> {
+    let mut recording = RECORDING
+        .lock()
+        .map_err(|_| "Recording lock was poisoned".to_string())?;
+    if recording.is_some() {
+        return Err("Already recording".to_string());
+    }
+
+    let path = std::env::temp_dir().join(format!("jotin-voice-{}.wav", Uuid::new_v4()));
+    let recorder = spawn_recorder(&path)?;
+    *recording = Some(Recording {
+        recorder,
+        path,
+        started_at: Instant::now(),
+    });
+
+    let _ = app.emit(RECORDING_CHANGED_EVENT, true);
+    Ok(())
+}
+
+/// Stops recording and saves a note with the audio attached. The transcript
+/// replaces the placeholder text once whisper.cpp is done.
+#[tauri::command]
+pub(crate) fn stop_voice_note(
+    app: AppHandle,
+    state: State<'_, StorageState>,
+) -> Result<Note, String> {
+    let recording = take_recording(&app)?;
+    let stored = attachments::store_file(&app, &recording.path);
+    let _ = fs::remove_file(&recording.path);
+    let (hash, size) = stored?;
+
+    let file_name = format!("voice-{}.wav", Utc::now().format("%Y%m%d-%H%M%S"));
+    let mut note = Note::new(VOICE_NOTE_PLACEHOLDER.to_string());
+    note.attachments
+        .push(attachments::new_attachment(file_name, hash.clone(), size));
+    let note = insert_prepared_note(&app, &state, note)?;
+
+    transcribe_in_background(&app, note.id.clone(), hash);
+    Ok(note)
+}
+
+/// Stops recording and throws the audio away.
+#[tauri::command]
+pub(crate) fn cancel_voice_note(app: AppHandle) -> Result<(), String> {
+    let recording = take_recording(&app)?;
+    let _ = fs::remove_file(&recording.path);
+    Ok(())
+}
+
+/// Push-to-talk: the voice shortcut went down.
+pub(crate) fn start_push_to_talk(app: &AppHandle) {
+    if let Err(error) = start_voice_note(app.clone()) {
+        eprintln!("Failed to start voice note: {error}");
+    }
+}
+
+/// Push-to-talk: the voice shortcut was released.
+pub(crate) fn stop_push_to_talk(app: &AppHandle) {
+    let tapped = RECORDING
+        .lock()
+        .map(|recording| {
+            recording
+                .as_ref()
+                .is_some_and(|recording| recording.started_at.elapsed() < MIN_RECORDING)
+        })
+        .unwrap_or(false);
+    if tapped {
+        let _ = cancel_voice_note(app.clone());
+        return;
+    }
+
+    let app = app.clone();
+    thread::spawn(move || {
+        let state = app.state::<StorageState>();
+        match stop_voice_note(app.clone(), state) {
+            Ok(_) => notifications::show(
+                &app,
+                "Saved voice note",
+                "Transcribing\u{2026}",
+                &[],
+                |_, _| {},
+            ),
+            Err(error) => eprintln!("Failed to save voice note: {error}"),
+        }
+    });
+}
+
+fn take_recording(app: &AppHandle) -> Result<Recording, String> {
+    let mut recording = RECORDING
+        .lock()
+        .map_err(|_| "Recording lock was poisoned".to_string())?
+        .take()
+        .ok_or_else(|| "Not recording".to_string())?;
+    let _ = app.emit(RECORDING_CHANGED_EVENT, false);
+
+    // Killing the recorder leaves the WAV header claiming a bogus length;
+    // it is fixed up from the actual file size below.
+    let _ = recording.recorder.kill();
+    let _ = recording.recorder.wait();
+    repair_wav_header(&recording.path)?;
+    Ok(recording)
+}
+
+#[cfg(target_os = "linux")]
+fn spawn_recorder(path: &Path) -> Result<Child, String> {
+    let path = path.to_string_lossy();
+    let sox = [
+        "-q",
+        "-d",
+        "-r",
+        "16000",
+        "-c",
+        "1",
+        "-b",
+        "16",
+        path.as_ref(),
+    ];
+    let arecord = [
+        "-q",
+        "-f",
+        "S16_LE",
+        "-r",
+        "16000",
+        "-c",
+        "1",
+        "-t",
+        "wav",
+        path.as_ref(),
+    ];
+    spawn_first(&[("sox", &sox[..]), ("arecord", &arecord[..])])
+}
+
+#[cfg(not(target_os = "linux"))]
+fn spawn_recorder(path: &Path) -> Result<Child, String> {
+    let path = path.to_string_lossy();
+    let sox = [
+        "-q",
+        "-d",
+        "-r",
+        "16000",
+        "-c",
+        "1",
+        "-b",
+        "16",
+        path.as_ref(),
+    ];
+    spawn_first(&[("sox", &sox[..])])
+}
+
+/// Starts the first recorder that is installed.
+fn spawn_first(recorders: &[(&str, &[&str])]) -> Result<Child, String> {
+    for (program, args) in recorders {
+        match Command::new(program)
+            .args(*args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => return Ok(child),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(format!("Failed to start {program}: {error}")),
+        }
+    }
+    let names: Vec<&str> = recorders.iter().map(|(program, _)| *program).collect();
+    Err(format!(
+        "Recording needs {} to be installed",
+        names.join(" or ")
+    ))
+}
+
+/// Rewrites the RIFF and `data` chunk sizes to match the file's length.
+fn repair_wav_header(path: &Path) -> Result<(), String> {
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open recording: {e}"))?;
+    let length = file
+        .metadata()
+        .map_err(|e| format!("Failed to read recording: {e}"))?
+        .len();
+
+    let mut header = [0u8; 12];
+    if file.read_exact(&mut header).is_err() || &header[..4] != b"RIFF" || &header[8..] != b"WAVE" {
+        return Err("Recording is empty or not a WAV file".to_string());
+    }
+
+    let mut offset = 12u64;
+    while offset + 8 <= length {
+        let mut chunk = [0u8; 8];
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut chunk))
+            .map_err(|e| format!("Failed to read recording: {e}"))?;
+        if &chunk[..4] == b"data" {
+            let data_size = u32::try_from(length - offset - 8).unwrap_or(u32::MAX);
+            let riff_size = u32::try_from(length - 8).unwrap_or(u32::MAX);
+            file.seek(SeekFrom::Start(offset + 4))
+                .and_then(|_| file.write_all(&data_size.to_le_bytes()))
+                .and_then(|_| file.seek(SeekFrom::Start(4)))
+                .and_then(|_| file.write_all(&riff_size.to_le_bytes()))
+                .map_err(|e| format!("Failed to finish recording: {e}"))?;
+            return Ok(());
+        }
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        offset += 8 + u64::from(size) + u64::from(size % 2);
+    }
+    Err("Recording has no audio".to_string())
+}
+
+fn transcribe_in_background(app: &AppHandle, note_id: String, hash: String) {
+    let Some(model) = settings::load_settings(app)
+        .ok()
+        .and_then(|settings| settings.whisper_model_path)
+    else {
+        return;
+    };
+
+    let app = app.clone();
+    thread::spawn(move || {
+        let transcript = attachments::blob_path(&app, &hash)
+            .and_then(|audio| transcribe(Path::new(&model), &audio));
+        let transcribed = match transcript {
+            Ok(transcript) if !transcript.is_empty() => {
+                match fill_transcript(&app, &note_id, &transcript) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        eprintln!("Failed to save transcript: {error}");
+                        false
+                    }
+                }
+            }
+            Ok(_) => false,
+            Err(error) => {
+                eprintln!("Failed to transcribe voice note: {error}");
+                false
+            }
+        };
+        let _ = app.emit(
+            TRANSCRIPTION_FINISHED_EVENT,
+            TranscriptionFinished {
+                note_id,
+                transcribed,
+            },
+        );
+    });
+}
+
+fn transcribe(model: &Path, audio: &Path) -> Result<String, String> {
+    for binary in WHISPER_BINARIES {
+        let output = match Command::new(binary)
+            .arg("-m")
+            .arg(model)
+            .arg("-f")
+            .arg(audio)
+            .args(["--no-timestamps", "--no-prints"])
+            .output()
+        {
+            Ok(output) => output,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(error) => return Err(format!("Failed to run {binary}: {error}")),
+        };
+        if !output.status.success() {
+            return Err(format!(
+                "{binary} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        return Ok(String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "));
+    }
+    Err("Transcription needs whisper.cpp to be installed".to_string())
+}
+
+/// Replaces the placeholder with the transcript, or adds the transcript
+/// below whatever was typed into the note meanwhile.
+fn fill_transcript(app: &AppHandle, note_id: &str, transcript: &str) -> Result<(), String> {
+    let state = app.state::<StorageState>();
+    let storage = state.lock(app)?;
+
+    let mut notes = storage.load_notes()?;
+    let Some(note) = notes.iter_mut().find(|note| note.id == note_id) else {
+        return Ok(());
+    };
+    let text = if note.text == VOICE_NOTE_PLACEHOLDER {
+        transcript.to_string()
+    } else {
+        format!("{}\n\n{transcript}", note.text.trim_end())
+    };
+    note.set_text(text);
+    note.updated_at = Some(Utc::now().to_rfc3339());
+    let updated = note.clone();
+
+    storage.save_notes(&notes)?;
+    state.update_search_index(|index| index.upsert(&updated));
+
+    let _ = app.emit(NOTES_CHANGED_EVENT, ());
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! Remembers where the main window was and how big, so it reopens the same
+//! way after a restart. Kept next to the settings file rather than in the
+//! data directory, since geometry belongs to this machine's screens.
+
+use std::{
+    fs,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, PhysicalPosition, PhysicalSize, WebviewWindow};
+
+use crate::{placement, resolve_app_data_file, write_file_atomically};
+
+const WINDOW_STATE_FILE_NAME: &str = "window_state.json";
+/// Moves and resizes arrive in bursts while dragging; they are saved at most
+/// this often.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+static SAVE_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Physical pixels. While maximized the rest keeps the size to restore to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+struct MainWindowState {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    maximized: bool,
+}
+
+/// Puts the main window back where it was saved. A position on a monitor
+/// that is no longer connected is dropped, keeping only the size.
+pub(crate) fn restore(window: &WebviewWindow) {
+    let Some(state) = load(window) else {
+        return;
+    };
+
+    let _ = window.set_size(PhysicalSize::new(state.width, state.height));
+    let position = (f64::from(state.x), f64::from(state.y));
+    if let Some(monitor) = placement::monitor_at(window.app_handle(), position) {
+        let size = (f64::from(state.width), f64::from(state.height));
+        let (x, y) = placement::clamp_to(monitor.work_area(), position, size);
+        let _ = window.set_position(PhysicalPosition::new(x, y));
+    }
+    if state.maximized {
+        let _ = window.maximize();
+    }
+}
+
+/// Runs on the main window's `Moved` and `Resized` events.
+pub(crate) fn schedule_save(window: &WebviewWindow) {
+    if SAVE_PENDING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let window = window.clone();
+    thread::spawn(move || {
+        thread::sleep(SAVE_DELAY);
+        SAVE_PENDING.store(false, Ordering::SeqCst);
+        if let Err(error) = save(&window) {
+            eprintln!("{error}");
+        }
+    });
+}
+
+fn save(window: &WebviewWindow) -> Result<(), String> {
+    // Minimized windows report placeholder geometry on some platforms.
+    if window.is_minimized().unwrap_or(false) {
+        return Ok(());
+    }
+
+    let maximized = window.is_maximized().unwrap_or(false);
+    let state = match load(window).filter(|_| maximized) {
+        Some(previous) => MainWindowState {
+            maximized,
+            ..previous
+        },
+        None => {
+            let position = window
+                .outer_position()
+                .map_err(|e| format!("Failed to read main window position: {e}"))?;
+            let size = window
+                .inner_size()
+                .map_err(|e| format!("Failed to read main window size: {e}"))?;
+            MainWindowState {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                maximized,
+            }
+        }
+    };
+
+    let path = resolve_app_data_file(window.app_handle(), WINDOW_STATE_FILE_NAME)?;
+    let payload = serde_json::to_string_pretty(&state)
+        .map_err(|e| format!("Failed to serialize window state: {e}"))?;
+    write_file_atomically(&path, &payload)
+}
+
+fn load(window: &WebviewWindow) -> Option<MainWindowState> {
+    let path = resolve_app_data_file(window.app_handle(), WINDOW_STATE_FILE_NAME).ok()?;
+    let raw = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&raw)
+        .inspect_err(|error| eprintln!("Failed to parse window state: {error}"))
+        .ok()
+}
@@ -0,0 +1,287 @@
+//! Just enough of the ZIP format for backup archives: deflated entries, no
+//! ZIP64, no encryption. Archives open in Finder, Explorer and `unzip`.
+
+use std::io::{Read, Write};
+
+use chrono::{Datelike, Local, Timelike};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const LOCAL_HEADER_LEN: usize = 30;
+const CENTRAL_HEADER_LEN: usize = 46;
+const END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+const VERSION: u16 = 20;
+const UTF8_NAMES_FLAG: u16 = 1 << 11;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+/// Limits on what `read_entries` inflates, so a small crafted archive
+/// cannot exhaust memory.
+const MAX_ENTRY_SIZE: usize = 512 * 1024 * 1024;
+const MAX_TOTAL_SIZE: usize = 2 * 1024 * 1024 * 1024;
+
+struct CentralEntry {
+    name: String,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+pub(crate) struct ZipWriter {
+    buffer: Vec<u8>,
+    entries: Vec<CentralEntry>,
+    dos_time: u16,
+    dos_date: u16,
+}
+
+impl ZipWriter {
+    pub(crate) fn new() -> Self {
+        let now = Local::now();
+        Self {
+            buffer: Vec::new(),
+            entries: Vec::new(),
+            dos_time: ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16,
+            dos_date: (((now.year() - 1980).max(0) as u32) << 9 | (now.month() << 5) | now.day())
+                as u16,
+        }
+    }
+
+    pub(crate) fn add_file(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(data)
+            .and_then(|()| encoder.flush())
+            .map_err(|e| format!("Failed to compress {name}: {e}"))?;
+        let compressed = encoder
+            .finish()
+            .map_err(|e| format!("Failed to compress {name}: {e}"))?;
+
+        let entry = CentralEntry {
+            name: name.to_string(),
+            crc: crc32fast::hash(data),
+            compressed_size: to_u32(compressed.len(), name)?,
+            size: to_u32(data.len(), name)?,
+            offset: to_u32(self.buffer.len(), name)?,
+        };
+
+        put_u32(&mut self.buffer, LOCAL_HEADER_SIGNATURE);
+        put_u16(&mut self.buffer, VERSION);
+        self.put_entry_fields(&entry);
+        put_u16(&mut self.buffer, 0); // extra field length
+        self.buffer.extend_from_slice(entry.name.as_bytes());
+        self.buffer.extend_from_slice(&compressed);
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Result<Vec<u8>, String> {
+        let directory_offset = to_u32(self.buffer.len(), "archive")?;
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            put_u32(&mut self.buffer, CENTRAL_HEADER_SIGNATURE);
+            put_u16(&mut self.buffer, VERSION); // made by
+            put_u16(&mut self.buffer, VERSION); // needed to extract
+            self.put_entry_fields(entry);
+            put_u16(&mut self.buffer, 0); // extra field length
+            put_u16(&mut self.buffer, 0); // comment length
+            put_u16(&mut self.buffer, 0); // disk number
+            put_u16(&mut self.buffer, 0); // internal attributes
+            put_u32(&mut self.buffer, 0); // external attributes
+            put_u32(&mut self.buffer, entry.offset);
+            self.buffer.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = to_u32(self.buffer.len(), "archive")? - directory_offset;
+        let count = u16::try_from(entries.len())
+            .map_err(|_| "Too many files for a backup archive".to_string())?;
+
+        put_u32(&mut self.buffer, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut self.buffer, 0); // this disk
+        put_u16(&mut self.buffer, 0); // disk with the directory
+        put_u16(&mut self.buffer, count);
+        put_u16(&mut self.buffer, count);
+        put_u32(&mut self.buffer, directory_size);
+        put_u32(&mut self.buffer, directory_offset);
+        put_u16(&mut self.buffer, 0); // comment length
+        Ok(self.buffer)
+    }
+
+    /// Fields shared by local and central headers, from the flags onwards.
+    fn put_entry_fields(&mut self, entry: &CentralEntry) {
+        let name_len = entry.name.len() as u16;
+        put_u16(&mut self.buffer, UTF8_NAMES_FLAG);
+        put_u16(&mut self.buffer, METHOD_DEFLATED);
+        put_u16(&mut self.buffer, self.dos_time);
+        put_u16(&mut self.buffer, self.dos_date);
+        put_u32(&mut self.buffer, entry.crc);
+        put_u32(&mut self.buffer, entry.compressed_size);
+        put_u32(&mut self.buffer, entry.size);
+        put_u16(&mut self.buffer, name_len);
+    }
+}
+
+/// Reads every file entry of an archive, verifying checksums. Entries over
+/// `MAX_ENTRY_SIZE`, or archives over `MAX_TOTAL_SIZE` in all, are refused.
+pub(crate) fn read_entries(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let end = find_end_of_central_directory(archive)?;
+    let count = get_u16(archive, end + 10)? as usize;
+    let mut cursor = get_u32(archive, end + 16)? as usize;
+
+    let mut files = Vec::new();
+    let mut total_size = 0usize;
+    for _ in 0..count {
+        if get_u32(archive, cursor)? != CENTRAL_HEADER_SIGNATURE {
+            return Err("Corrupt archive: bad central directory".to_string());
+        }
+        let method = get_u16(archive, cursor + 10)?;
+        let crc = get_u32(archive, cursor + 16)?;
+        let compressed_size = get_u32(archive, cursor + 20)? as usize;
+        let size = get_u32(archive, cursor + 24)? as usize;
+        let name_len = get_u16(archive, cursor + 28)? as usize;
+        let extra_len = get_u16(archive, cursor + 30)? as usize;
+        let comment_len = get_u16(archive, cursor + 32)? as usize;
+        let offset = get_u32(archive, cursor + 42)? as usize;
+        let name = slice(archive, cursor + CENTRAL_HEADER_LEN, name_len)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        cursor += CENTRAL_HEADER_LEN + name_len + extra_len + comment_len;
+
+        if name.ends_with('/') {
+            continue;
+        }
+        total_size = total_size.saturating_add(size);
+        if size > MAX_ENTRY_SIZE || total_size > MAX_TOTAL_SIZE {
+            return Err(format!("{name} is too large to read from an archive"));
+        }
+
+        if get_u32(archive, offset)? != LOCAL_HEADER_SIGNATURE {
+            return Err(format!("Corrupt archive: bad header for {name}"));
+        }
+        let local_name_len = get_u16(archive, offset + 26)? as usize;
+        let local_extra_len = get_u16(archive, offset + 28)? as usize;
+        let data_start = offset + LOCAL_HEADER_LEN + local_name_len + local_extra_len;
+        let raw = slice(archive, data_start, compressed_size)?;
+
+        let data = match method {
+            METHOD_STORED => raw.to_vec(),
+            METHOD_DEFLATED => {
+                // One byte past the declared size is enough to tell that the
+                // header lied; the check below rejects it.
+                let mut data = Vec::new();
+                DeflateDecoder::new(raw)
+                    .take(size as u64 + 1)
+                    .read_to_end(&mut data)
+                    .map_err(|e| format!("Failed to decompress {name}: {e}"))?;
+                data
+            }
+            other => return Err(format!("Unsupported compression method {other} for {name}")),
+        };
+        if data.len() != size || crc32fast::hash(&data) != crc {
+            return Err(format!("Corrupt archive: checksum mismatch for {name}"));
+        }
+
+        files.push((name, data));
+    }
+    Ok(files)
+}
+
+fn find_end_of_central_directory(archive: &[u8]) -> Result<usize, String> {
+    if archive.len() < END_OF_CENTRAL_DIRECTORY_LEN {
+        return Err("Not a zip archive".to_string());
+    }
+    (0..=archive.len() - END_OF_CENTRAL_DIRECTORY_LEN)
+        .rev()
+        .find(|&index| get_u32(archive, index).ok() == Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE))
+        .ok_or_else(|| "Not a zip archive".to_string())
+}
+
+fn slice(bytes: &[u8], start: usize, len: usize) -> Result<&[u8], String> {
+    start
+        .checked_add(len)
+        .and_then(|end| bytes.get(start..end))
+        .ok_or_else(|| "Corrupt archive: unexpected end of file".to_string())
+}
+
+fn get_u16(bytes: &[u8], at: usize) -> Result<u16, String> {
+    let raw = slice(bytes, at, 2)?;
+    Ok(u16::from_le_bytes([raw[0], raw[1]]))
+}
+
+fn get_u32(bytes: &[u8], at: usize) -> Result<u32, String> {
+    let raw = slice(bytes, at, 4)?;
+    Ok(u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]))
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn to_u32(value: usize, name: &str) -> Result<u32, String> {
+    u32::try_from(value).map_err(|_| format!("{name} is too large for a backup archive"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive() -> Vec<u8> {
+        let mut writer = ZipWriter::new();
+        writer.add_file("notes.json", b"[]").unwrap();
+        writer.add_file("attachments/abc", &[7; 1000]).unwrap();
+        writer.finish().unwrap()
+    }
+
+    /// Offset of the first central directory header.
+    fn central_directory(archive: &[u8]) -> usize {
+        let end = find_end_of_central_directory(archive).unwrap();
+        get_u32(archive, end + 16).unwrap() as usize
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let entries = read_entries(&archive()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], ("notes.json".to_string(), b"[]".to_vec()));
+        assert_eq!(entries[1].0, "attachments/abc");
+        assert_eq!(entries[1].1, vec![7; 1000]);
+    }
+
+    #[test]
+    fn rejects_what_is_not_an_archive() {
+        assert!(read_entries(b"not a zip").is_err());
+        assert!(read_entries(&[]).is_err());
+    }
+
+    #[test]
+    fn rejects_a_wrong_checksum() {
+        let mut archive = archive();
+        let crc_at = central_directory(&archive) + 16;
+        archive[crc_at] ^= 0xff;
+        let error = read_entries(&archive).unwrap_err();
+        assert!(error.contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn rejects_data_longer_than_its_header_says() {
+        let mut archive = archive();
+        let size_at = central_directory(&archive) + 24;
+        archive[size_at..size_at + 4].copy_from_slice(&1u32.to_le_bytes());
+        let error = read_entries(&archive).unwrap_err();
+        assert!(error.contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn rejects_entries_over_the_size_limit() {
+        let mut archive = archive();
+        let size_at = central_directory(&archive) + 24;
+        let size = (MAX_ENTRY_SIZE + 1) as u32;
+        archive[size_at..size_at + 4].copy_from_slice(&size.to_le_bytes());
+        let error = read_entries(&archive).unwrap_err();
+        assert!(error.contains("too large"));
+    }
+}
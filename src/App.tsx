@@ -1,7 +1,18 @@
 import { invoke } from "@tauri-apps/api/core";
-import { emit, listen } from "@tauri-apps/api/event";
+import { listen } from "@tauri-apps/api/event";
 import { getCurrentWindow } from "@tauri-apps/api/window";
-import { Check, Copy, Loader2, Moon, Plus, Sun, Trash2 } from "lucide-react";
+import {
+	Check,
+	Copy,
+	Loader2,
+	Moon,
+	Pin,
+	PinOff,
+	Plus,
+	Sun,
+	Trash2,
+	X,
+} from "lucide-react";
 import { useCallback, useEffect, useMemo, useRef, useState } from "react";
 
 import {
@@ -30,6 +41,32 @@ type Note = {
 	updated_at?: string | null;
 };
 
+type CaptureConfig = {
+	mode: "single_line" | "multi_line";
+	target: keyof typeof CAPTURE_COMMANDS;
+	enter_submits: boolean;
+	submit_shortcut: string;
+	dismiss: "focus_loss" | "escape" | "never";
+	pinned: boolean;
+};
+
+const CAPTURE_COMMANDS = {
+	new_note: "create_note",
+	append_to_latest: "append_to_latest_note",
+	append_to_daily: "append_to_daily_note",
+} as const;
+
+type CloseReason = "explicit" | "submitted" | "escape";
+
+const DEFAULT_CAPTURE_CONFIG: CaptureConfig = {
+	mode: "single_line",
+	target: "new_note",
+	enter_submits: true,
+	submit_shortcut: "Enter",
+	dismiss: "escape",
+	pinned: false,
+};
+
 const NOTES_CHANGED_EVENT = "notes-changed";
 const CAPTURE_OPENED_EVENT = "capture-opened";
 const THEME_STORAGE_KEY = "jotin-theme";
@@ -40,8 +77,19 @@ const FOCUS_INPUT_RETRY_DELAY_MS = 35;
 const CAPTURE_REFOCUS_DELAY_MS = 120;
 const CAPTURE_REFOCUS_DELAY_SECONDARY_MS = 240;
 const COPY_FEEDBACK_DELAY_MS = 1200;
+const DRAFT_SAVE_DELAY_MS = 300;
 const windowHandle = getCurrentWindow();
 type ThemeMode = "light" | "dark";
+type ThemeState = {
+	preference: "light" | "dark" | "system";
+	resolved: ThemeMode;
+};
+
+function loadTheme(onLoaded: (theme: ThemeMode) => void) {
+	void invoke<ThemeState>("get_theme")
+		.then((state) => onLoaded(state.resolved))
+		.catch(() => undefined);
+}
 
 function detectInitialTheme(): ThemeMode {
 	const stored = localStorage.getItem(THEME_STORAGE_KEY);
@@ -72,7 +120,32 @@ function formatTimestamp(timestamp: string) {
 function CaptureWindow() {
 	const [draft, setDraft] = useState("");
 	const [error, setError] = useState<string | null>(null);
+	const [config, setConfig] = useState<CaptureConfig>(DEFAULT_CAPTURE_CONFIG);
 	const textareaRef = useRef<HTMLTextAreaElement | null>(null);
+	const draftTimerRef = useRef<number | null>(null);
+
+	const scheduleDraftSave = useCallback((text: string) => {
+		if (draftTimerRef.current !== null) {
+			window.clearTimeout(draftTimerRef.current);
+		}
+		draftTimerRef.current = window.setTimeout(() => {
+			draftTimerRef.current = null;
+			void invoke("save_capture_draft", { text }).catch(() => undefined);
+		}, DRAFT_SAVE_DELAY_MS);
+	}, []);
+
+	const restoreDraft = useCallback(() => {
+		void invoke<string | null>("load_capture_draft")
+			.then((saved) => {
+				setDraft(saved ?? "");
+				requestAnimationFrame(() => {
+					const input = textareaRef.current;
+					const end = saved?.length ?? 0;
+					input?.setSelectionRange(end, end);
+				});
+			})
+			.catch(() => setDraft(""));
+	}, []);
 
 	const focusInput = useCallback(() => {
 		const attemptFocus = (remainingAttempts: number) => {
@@ -103,26 +176,46 @@ function CaptureWindow() {
 		});
 	}, []);
 
-	const closeCapture = useCallback(async () => {
-		setError(null);
-		setDraft("");
+	const closeCapture = useCallback(async (reason: CloseReason) => {
 		try {
-			await invoke("close_quick_capture");
+			const hidden = await invoke<boolean>("close_quick_capture", { reason });
+			if (hidden || reason === "submitted") {
+				setError(null);
+				setDraft("");
+			}
 		} catch {
 			void windowHandle.hide();
 		}
 	}, []);
 
+	const togglePin = useCallback(async () => {
+		try {
+			setConfig(
+				await invoke<CaptureConfig>("pin_capture_window", {
+					pinned: !config.pinned,
+				}),
+			);
+		} catch {
+			// Keep the current pin state.
+		}
+		focusInput();
+	}, [config.pinned, focusInput]);
+
 	const submitNote = useCallback(async () => {
 		const trimmed = draft.trim();
 		if (!trimmed) {
-			await closeCapture();
+			await closeCapture("submitted");
 			return;
 		}
 
 		try {
-			await invoke("create_note", { text: trimmed });
-			await closeCapture();
+			await invoke(CAPTURE_COMMANDS[config.target], { text: trimmed });
+			if (draftTimerRef.current !== null) {
+				window.clearTimeout(draftTimerRef.current);
+				draftTimerRef.current = null;
+			}
+			await invoke("save_capture_draft", { text: "" }).catch(() => undefined);
+			await closeCapture("submitted");
 		} catch (submitError) {
 			setError(
 				submitError instanceof Error
@@ -130,15 +223,24 @@ function CaptureWindow() {
 					: String(submitError),
 			);
 		}
-	}, [closeCapture, draft]);
+	}, [closeCapture, config.target, draft]);
 
 	useEffect(() => {
 		focusInput();
+		restoreDraft();
 
 		let disposed = false;
 		let unlistenEvent: (() => void) | undefined;
-		void listen(CAPTURE_OPENED_EVENT, () => {
-			setDraft("");
+		void invoke<CaptureConfig>("get_capture_config")
+			.then((loaded) => {
+				if (!disposed) {
+					setConfig(loaded);
+				}
+			})
+			.catch(() => undefined);
+		void listen<CaptureConfig>(CAPTURE_OPENED_EVENT, (event) => {
+			setConfig(event.payload);
+			restoreDraft();
 			setError(null);
 			focusInput();
 			setTimeout(() => focusInput(), CAPTURE_REFOCUS_DELAY_MS);
@@ -154,7 +256,7 @@ function CaptureWindow() {
 		const onKeyDown = (event: KeyboardEvent) => {
 			if (event.key === "Escape" || event.key === "Esc") {
 				event.preventDefault();
-				void closeCapture();
+				void closeCapture("escape");
 			}
 		};
 
@@ -170,33 +272,41 @@ function CaptureWindow() {
 			window.removeEventListener("focus", onWindowFocus);
 			unlistenEvent?.();
 		};
-	}, [closeCapture, focusInput]);
+	}, [closeCapture, focusInput, restoreDraft]);
+
+	const multiLine = config.mode === "multi_line";
 
 	return (
 		<main className="h-screen bg-transparent">
 			<section className="flex h-full w-full items-center gap-2 rounded-[12px] bg-card px-3 py-2 text-card-foreground">
 				<Textarea
 					ref={textareaRef}
-					className="min-h-0 h-8 flex-1 resize-none border-0 bg-transparent p-0 text-2xl font-semibold shadow-none focus-visible:ring-0"
-					placeholder="Type a note, then press Enter"
-					rows={1}
+					className={
+						multiLine
+							? "min-h-0 h-full flex-1 resize-none border-0 bg-transparent p-0 text-lg shadow-none focus-visible:ring-0"
+							: "min-h-0 h-8 flex-1 resize-none border-0 bg-transparent p-0 text-2xl font-semibold shadow-none focus-visible:ring-0"
+					}
+					placeholder={`Type a note, then press ${config.submit_shortcut}`}
+					rows={multiLine ? 5 : 1}
 					value={draft}
-					onChange={(event) => setDraft(event.currentTarget.value)}
+					onChange={(event) => {
+						setDraft(event.currentTarget.value);
+						scheduleDraftSave(event.currentTarget.value);
+					}}
 					onKeyDown={(event) => {
 						if (event.key === "Escape" || event.key === "Esc") {
 							event.preventDefault();
 							event.stopPropagation();
-							void closeCapture();
+							void closeCapture("escape");
 							return;
 						}
 
-						if (
-							event.key === "Enter" &&
-							!event.metaKey &&
-							!event.shiftKey &&
-							!event.ctrlKey &&
-							!event.altKey
-						) {
+						if (event.key !== "Enter" || event.shiftKey || event.altKey) {
+							return;
+						}
+
+						const modified = event.metaKey || event.ctrlKey;
+						if (config.enter_submits ? !modified : modified) {
 							event.preventDefault();
 							void submitNote();
 						}
@@ -204,13 +314,43 @@ function CaptureWindow() {
 				/>
 				<div className="flex shrink-0 items-center gap-3 text-xs text-muted-foreground">
 					<KbdGroup>
-						<Kbd className="h-6 rounded-md px-2 text-[11px]">enter</Kbd>
+						<Kbd className="h-6 rounded-md px-2 text-[11px]">
+							{config.submit_shortcut.toLowerCase()}
+						</Kbd>
 						<span>save</span>
 					</KbdGroup>
-					<KbdGroup>
-						<Kbd className="h-6 rounded-md px-2 text-[11px]">esc</Kbd>
-						<span>close</span>
-					</KbdGroup>
+					{config.dismiss === "never" ? null : (
+						<KbdGroup>
+							<Kbd className="h-6 rounded-md px-2 text-[11px]">esc</Kbd>
+							<span>close</span>
+						</KbdGroup>
+					)}
+					<Button
+						type="button"
+						variant="ghost"
+						size="icon-sm"
+						onClick={() => void togglePin()}
+						aria-label={config.pinned ? "Unpin capture" : "Pin capture"}
+						title={config.pinned ? "Unpin capture" : "Keep capture open"}
+					>
+						{config.pinned ? (
+							<PinOff className="size-4" />
+						) : (
+							<Pin className="size-4" />
+						)}
+					</Button>
+					{config.dismiss === "never" ? (
+						<Button
+							type="button"
+							variant="ghost"
+							size="icon-sm"
+							onClick={() => void closeCapture("explicit")}
+							aria-label="Close capture"
+							title="Close capture"
+						>
+							<X className="size-4" />
+						</Button>
+					) : null}
 				</div>
 			</section>
 			{error ? (
@@ -314,7 +454,7 @@ function NotesWindow({
 	const onCopy = useCallback(
 		async (id: string, text: string) => {
 			try {
-				await invoke("copy_note_text", { text });
+				await invoke("copy_note", { id, format: "markdown" });
 				try {
 					await navigator.clipboard.writeText(text);
 				} catch {
@@ -501,8 +641,12 @@ function App() {
 	const [theme, setTheme] = useState<ThemeMode>(detectInitialTheme);
 
 	const toggleTheme = useCallback(() => {
-		setTheme((previous) => (previous === "dark" ? "light" : "dark"));
-	}, []);
+		const next: ThemeMode = theme === "dark" ? "light" : "dark";
+		setTheme(next);
+		void invoke<ThemeState>("set_theme", { theme: next })
+			.then((state) => setTheme(state.resolved))
+			.catch(() => undefined);
+	}, [theme]);
 
 	useEffect(() => {
 		document.body.classList.toggle("capture-window", isCaptureWindow);
@@ -514,6 +658,11 @@ function App() {
 	useEffect(() => {
 		let disposed = false;
 		let unlistenTheme: (() => void) | undefined;
+		loadTheme((loaded) => {
+			if (!disposed) {
+				setTheme(loaded);
+			}
+		});
 		void listen<ThemeMode>(THEME_CHANGED_EVENT, (event) => {
 			if (event.payload === "light" || event.payload === "dark") {
 				setTheme(event.payload);
@@ -551,7 +700,7 @@ function App() {
 		let unlistenCaptureOpen: (() => void) | undefined;
 		let disposed = false;
 		void listen(CAPTURE_OPENED_EVENT, () => {
-			setTheme(detectInitialTheme());
+			loadTheme(setTheme);
 		}).then((unlisten) => {
 			if (disposed) {
 				unlisten();
@@ -569,7 +718,6 @@ function App() {
 	useEffect(() => {
 		document.documentElement.classList.toggle("dark", theme === "dark");
 		localStorage.setItem(THEME_STORAGE_KEY, theme);
-		void emit(THEME_CHANGED_EVENT, theme);
 	}, [theme]);
 
 	return isCaptureWindow ? (